@@ -0,0 +1,79 @@
+//! Throughput of the CLI output parsing hot paths.
+//!
+//! Run with `cargo bench --bench parser`; compare against a saved baseline
+//! with `cargo bench --bench parser -- --baseline main`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use xiq_cli_tool::api::parse_cli_results;
+use xiq_cli_tool::cli_status::{CliResult, CliStatus};
+use xiq_cli_tool::parser::{extract_bssid_lines, extract_interfaces, InterfaceParser};
+use xiq_cli_tool::parser_registry::ParserRegistry;
+
+/// `show interface` output of one AP with `ssids` SSIDs on each of two radios
+fn ap_output(ap: usize, ssids: usize) -> String {
+    let mut output = String::from(
+        "Name     MAC addr           Mode     State  Chan(Width) VLAN  Radio Hive  SSID\n\
+         -------- -----------------  -------  -----  ----------- ----  ----- ----- ----\n\
+         mgt0     0019.7700.0001     -        up     -           1     -     hive0 -\n",
+    );
+    for radio in 0..2 {
+        for ssid in 0..ssids {
+            output.push_str(&format!(
+                "wifi{radio}.{n}  {:02x}:{:02x}:{:02x}:00:{radio:02x}:{ssid:02x}  access   up     {}(20)      10    wifi{radio} hive0 Acme Guest WiFi {ssid}\n",
+                (ap >> 16) & 0xff,
+                (ap >> 8) & 0xff,
+                ap & 0xff,
+                if radio == 0 { 11 } else { 36 },
+                n = ssid + 1,
+            ));
+        }
+    }
+    output.push_str(&format!("BSSID for neighbor scan: 02:00:00:{:02x}:{:02x}:ff\n", (ap >> 8) & 0xff, ap & 0xff));
+    output
+}
+
+fn bench_single_output(c: &mut Criterion) {
+    let output = ap_output(1, 8);
+    let mut group = c.benchmark_group("single_ap");
+    group.throughput(Throughput::Bytes(output.len() as u64));
+
+    group.bench_function("interface_table", |b| {
+        let parser = InterfaceParser::new();
+        b.iter(|| parser.parse(black_box(&output)))
+    });
+    group.bench_function("bssid_lines", |b| b.iter(|| extract_bssid_lines(black_box(&output))));
+    group.bench_function("extract_interfaces", |b| b.iter(|| extract_interfaces(black_box(&output))));
+    group.finish();
+}
+
+fn bench_fleet(c: &mut Criterion) {
+    let parsers = ParserRegistry::with_defaults();
+    let mut group = c.benchmark_group("fleet");
+    group.sample_size(10);
+
+    for aps in [1_000, 10_000] {
+        let devices: Vec<_> = (0..aps)
+            .map(|id| {
+                serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": true })
+            })
+            .collect();
+        let outputs: Vec<_> = (0..aps)
+            .map(|id| CliResult { device_id: id as i64, status: CliStatus::Success, output: ap_output(id, 8) })
+            .collect();
+        let bytes: usize = outputs.iter().map(|r| r.output.len()).sum();
+
+        group.throughput(Throughput::Bytes(bytes as u64));
+        group.bench_with_input(BenchmarkId::new("parse_cli_results", aps), &outputs, |b, outputs| {
+            b.iter_batched(
+                || outputs.clone(),
+                |outputs| parse_cli_results(&devices, outputs, "show interface", &parsers),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_single_output, bench_fleet);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+/* Generated by cbindgen from bindings/c/src/lib.rs; do not edit. */
+
+#ifndef XIQ_H
+#define XIQ_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Normalize a MAC address to colon-separated uppercase
+//
+// # Safety
+// `mac` must be NULL or point to a NUL-terminated string.
+char *xiq_normalize_mac(const char *mac);
+
+// Parse HiveOS `show interface` output into a JSON array of interfaces
+// (`name`, `mac`, `mode`, `state`, `channel`, `vlan`, `radio`, `hive`, `ssid`,
+// `tx_power`, `phy_mode`, `security`, `encryption`, `broadcast`)
+//
+// # Safety
+// `cli_output` must be NULL or point to a NUL-terminated string.
+char *xiq_extract_interfaces(const char *cli_output);
+
+// Free a string returned by this library. NULL is ignored.
+//
+// # Safety
+// `s` must be NULL or a pointer returned by this library that has not
+// already been freed.
+void xiq_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* XIQ_H */
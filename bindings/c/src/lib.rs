@@ -0,0 +1,93 @@
+//! C ABI for the HiveOS parser, for reuse from C and C++ programs.
+//!
+//! Strings returned by these functions are allocated by Rust and must be
+//! released with `xiq_string_free`. Functions return NULL when the input is
+//! NULL or not valid UTF-8.
+
+use std::ffi::{c_char, CStr, CString};
+use xiq_cli_tool::parser;
+
+/// Borrow a NUL-terminated C string as UTF-8
+///
+/// # Safety
+/// `ptr` must be NULL or point to a NUL-terminated string.
+unsafe fn input<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+fn output(value: String) -> *mut c_char {
+    CString::new(value).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Normalize a MAC address to colon-separated uppercase
+///
+/// # Safety
+/// `mac` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xiq_normalize_mac(mac: *const c_char) -> *mut c_char {
+    match input(mac) {
+        Some(mac) => output(parser::normalize_mac(mac)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Parse HiveOS `show interface` output into a JSON array of interfaces
+/// (`name`, `mac`, `mode`, `state`, `channel`, `vlan`, `radio`, `hive`, `ssid`,
+/// `tx_power`, `phy_mode`, `security`, `encryption`, `broadcast`)
+///
+/// # Safety
+/// `cli_output` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xiq_extract_interfaces(cli_output: *const c_char) -> *mut c_char {
+    let Some(cli_output) = input(cli_output) else {
+        return std::ptr::null_mut();
+    };
+    match serde_json::to_string(&parser::extract_interfaces(cli_output)) {
+        Ok(json) => output(json),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Free a string returned by this library. NULL is ignored.
+///
+/// # Safety
+/// `s` must be NULL or a pointer returned by this library that has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn xiq_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        unsafe {
+            let mac = CString::new("0011.2233.4455").unwrap();
+            let normalized = xiq_normalize_mac(mac.as_ptr());
+            assert_eq!(CStr::from_ptr(normalized).to_str().unwrap(), "00:11:22:33:44:55");
+            xiq_string_free(normalized);
+
+            let output = CString::new(
+                "Name      MAC addr        Mode    State Chan(Width) VLAN Radio  Hive      SSID\n\
+                 --------- --------------- ------- ----- ----------- ---- ------ --------- ------\n\
+                 Wifi0.1   0011.2233.4455  access  U     36(80)      1    wifi0  hive0     Corp\n",
+            )
+            .unwrap();
+            let json = xiq_extract_interfaces(output.as_ptr());
+            let entries: serde_json::Value = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            xiq_string_free(json);
+            assert_eq!(entries[0]["mac"], "00:11:22:33:44:55");
+
+            assert!(xiq_extract_interfaces(std::ptr::null()).is_null());
+            xiq_string_free(std::ptr::null_mut());
+        }
+    }
+}
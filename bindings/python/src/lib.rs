@@ -0,0 +1,158 @@
+//! Python bindings for the collection pipeline: the CloudIQ client, the
+//! HiveOS parser and the file exports. Values cross the boundary as JSON,
+//! so devices and results are plain Python lists and dicts.
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use xiq_cli_tool::api::XiqApi;
+use xiq_cli_tool::client::CloudIQClient;
+use xiq_cli_tool::config::Config;
+use xiq_cli_tool::output::LineEnding;
+use xiq_cli_tool::parser;
+use xiq_cli_tool::parser_registry::ParserRegistry;
+use xiq_cli_tool::report::DeviceInterfaces;
+use xiq_cli_tool::sink::{self, ExportContext, TxtLayout};
+
+const DEFAULT_BASE_URL: &str = "https://api.extremecloudiq.com";
+
+fn py_err(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", e))
+}
+
+fn to_py<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| py_err(e.into()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn from_py<T: DeserializeOwned>(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json: String = py.import("json")?.call_method1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&json).map_err(|e| py_err(e.into()))
+}
+
+/// CloudIQ API client. Calls block until the request finishes and release
+/// the GIL while waiting.
+#[pyclass]
+struct Client {
+    inner: CloudIQClient,
+    runtime: tokio::runtime::Runtime,
+}
+
+#[pymethods]
+impl Client {
+    #[new]
+    #[pyo3(signature = (base_url = None))]
+    fn new(base_url: Option<String>) -> PyResult<Self> {
+        let runtime = tokio::runtime::Runtime::new().map_err(|e| py_err(e.into()))?;
+        Ok(Self {
+            inner: CloudIQClient::new(base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string())),
+            runtime,
+        })
+    }
+
+    fn login(&mut self, py: Python<'_>, username: &str, password: &str) -> PyResult<()> {
+        let Self { inner, runtime } = self;
+        py.allow_threads(|| runtime.block_on(inner.login(username, password)))
+            .map_err(|e| py_err(e.into()))
+    }
+
+    /// Revoke the access token from `login`
+    fn logout(&mut self, py: Python<'_>) -> PyResult<()> {
+        let Self { inner, runtime } = self;
+        py.allow_threads(|| runtime.block_on(inner.logout()))
+            .map_err(|e| py_err(e.into()))
+    }
+
+    /// Every managed device, as returned by the API
+    fn get_devices(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let devices = py
+            .allow_threads(|| self.runtime.block_on(self.inner.get_devices()))
+            .map_err(|e| py_err(e.into()))?;
+        to_py(py, &devices)
+    }
+
+    /// Run a CLI command on the connected APs in `devices` and parse the output
+    fn run_command(&self, py: Python<'_>, command: &str, devices: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let devices: Vec<serde_json::Value> = from_py(py, devices)?;
+        let parsers = ParserRegistry::with_defaults();
+        let results = py
+            .allow_threads(|| {
+                self.runtime
+                    .block_on(self.inner.run_command_on_connected_aps(&devices, command, &parsers))
+            })
+            .map_err(py_err)?;
+        to_py(py, &results)
+    }
+}
+
+/// Normalize a MAC address to colon-separated uppercase
+#[pyfunction]
+fn normalize_mac(mac: &str) -> String {
+    parser::normalize_mac(mac)
+}
+
+/// Parse HiveOS `show interface` output into a list of interface dicts
+#[pyfunction]
+fn extract_interfaces(py: Python<'_>, output: &str) -> PyResult<PyObject> {
+    to_py(py, &parser::extract_interfaces(output))
+}
+
+/// Write the output files for a run's results to the working directory with
+/// the named sinks (default: the default sinks without `db`). `flat_txt`
+/// writes wifi-bssids.txt with one line per BSSID instead of grouped by radio.
+#[pyfunction]
+#[pyo3(signature = (command, devices, results, sinks = None, crlf = false, flat_txt = false))]
+fn export(
+    py: Python<'_>,
+    command: &str,
+    devices: &Bound<'_, PyAny>,
+    results: &Bound<'_, PyAny>,
+    sinks: Option<Vec<String>>,
+    crlf: bool,
+    flat_txt: bool,
+) -> PyResult<()> {
+    let devices: Vec<serde_json::Value> = from_py(py, devices)?;
+    let results: Vec<DeviceInterfaces> = from_py(py, results)?;
+
+    let config = Config::default();
+    let names = sinks.unwrap_or_else(|| {
+        config.output.sinks.iter().filter(|s| s.as_str() != "db").cloned().collect()
+    });
+    let sinks = sink::build_sinks(&names, &config, None).map_err(py_err)?;
+
+    let ctx = ExportContext {
+        command,
+        devices: &devices,
+        results: &results,
+        line_ending: if crlf { LineEnding::Crlf } else { LineEnding::Lf },
+        csv_profile: None,
+        split_by: None,
+        incremental: false,
+        txt_layout: if flat_txt { TxtLayout::Flat } else { TxtLayout::Grouped },
+        collected_at: chrono::Utc::now(),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| py_err(e.into()))?;
+    py.allow_threads(|| {
+        runtime.block_on(async {
+            for sink in &sinks {
+                sink.export(&ctx).await?;
+            }
+            anyhow::Ok(())
+        })
+    })
+    .map_err(py_err)
+}
+
+#[pymodule]
+fn xiq(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Client>()?;
+    m.add_function(wrap_pyfunction!(normalize_mac, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_interfaces, m)?)?;
+    m.add_function(wrap_pyfunction!(export, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Arbitrary device output through the parsers and the MAC normalizer.
+//! Every MAC that comes out must be canonical or flagged as malformed, so
+//! nothing unusable reaches the exports.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xiq_cli_tool::mac_check::{mac_issue, MacIssue};
+use xiq_cli_tool::parser::{extract_bssid_lines, extract_interfaces, normalize_mac, InterfaceParser};
+
+/// `XX:XX:XX:XX:XX:XX` with uppercase hex digits
+fn is_canonical(mac: &str) -> bool {
+    mac.len() == 17
+        && mac.split(':').all(|octet| {
+            octet.len() == 2 && octet.chars().all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c))
+        })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let output = String::from_utf8_lossy(data);
+
+    for entry in InterfaceParser::new().parse(&output).iter().chain(&extract_interfaces(&output)) {
+        assert!(is_canonical(&entry.mac) || mac_issue(&entry.mac) == Some(MacIssue::Malformed), "{:?}", entry.mac);
+    }
+    for entry in extract_bssid_lines(&output) {
+        assert!(is_canonical(&entry.mac), "{:?}", entry.mac);
+    }
+    for line in output.lines() {
+        let normalized = normalize_mac(line);
+        if line.chars().filter(|c| c.is_ascii_hexdigit()).count() == 12 {
+            assert!(is_canonical(&normalized), "{:?} -> {:?}", line, normalized);
+        }
+    }
+});
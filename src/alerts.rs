@@ -0,0 +1,147 @@
+use crate::churn::{self, ChurnedAp};
+use crate::config::{AlertsConfig, NotifyConfig};
+use crate::db::{ApChanges, Database};
+use crate::notify;
+use anyhow::Result;
+
+/// Consecutive disconnected runs before an AP is reported
+pub const DEFAULT_DISCONNECTED_RUNS: u32 = 3;
+
+/// Alert raised by comparing a run's inventory with the previous runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// Notification event name
+    pub event: &'static str,
+    pub message: String,
+    pub details: serde_json::Value,
+}
+
+fn hostnames<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names.collect::<Vec<_>>().join(", ")
+}
+
+/// Alerts for the enabled rules, one per kind of change
+pub fn build_alerts(changes: &ApChanges, config: &AlertsConfig) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    if config.new_ap && !changes.added.is_empty() {
+        alerts.push(Alert {
+            event: "ap_added",
+            message: format!(
+                "{} new AP(s): {}",
+                changes.added.len(),
+                hostnames(changes.added.iter().map(|c| c.hostname.as_str()))
+            ),
+            details: serde_json::json!({ "aps": changes.added }),
+        });
+    }
+
+    if config.removed_ap && !changes.removed.is_empty() {
+        alerts.push(Alert {
+            event: "ap_removed",
+            message: format!(
+                "{} AP(s) no longer in the inventory: {}",
+                changes.removed.len(),
+                hostnames(changes.removed.iter().map(|c| c.hostname.as_str()))
+            ),
+            details: serde_json::json!({ "aps": changes.removed }),
+        });
+    }
+
+    if config.disconnected_runs > 0 && !changes.disconnected.is_empty() {
+        alerts.push(Alert {
+            event: "ap_disconnected",
+            message: format!(
+                "{} AP(s) disconnected for {} runs: {}",
+                changes.disconnected.len(),
+                config.disconnected_runs,
+                hostnames(changes.disconnected.iter().map(|c| c.hostname.as_str()))
+            ),
+            details: serde_json::json!({ "aps": changes.disconnected }),
+        });
+    }
+
+    alerts
+}
+
+/// Compare a recorded run with the run history, then print and send any alerts
+pub async fn check_ap_changes(db: &Database, run_id: i64, config: &AlertsConfig, notify_config: &NotifyConfig) -> Result<()> {
+    let changes = db.ap_changes(run_id, config.disconnected_runs.into()).await?;
+
+    for alert in build_alerts(&changes, config) {
+        println!("ALERT: {}", alert.message);
+        notify::send_notifications(notify_config, alert.event, &alert.message, alert.details).await;
+    }
+
+    Ok(())
+}
+
+/// Alert for APs whose BSSIDs changed abnormally, if any
+pub fn churn_alert(churned: &[ChurnedAp]) -> Option<Alert> {
+    if churned.is_empty() {
+        return None;
+    }
+
+    Some(Alert {
+        event: "bssid_churn",
+        message: format!(
+            "{} AP(s) with abnormal BSSID churn (radio swap, RMA or cloned config?): {}",
+            churned.len(),
+            churned
+                .iter()
+                .map(|c| format!("{} ({:.0}%)", c.hostname, c.churn_pct))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        details: serde_json::json!({ "aps": churned }),
+    })
+}
+
+/// Compare each AP's BSSIDs in a run with the last run that collected it,
+/// then print and send an alert for abnormal churn
+pub async fn check_bssid_churn(db: &Database, run_id: i64, config: &AlertsConfig, notify_config: &NotifyConfig) -> Result<()> {
+    if config.bssid_churn_pct <= 0.0 {
+        return Ok(());
+    }
+
+    let churned = churn::churned_aps(&db.bssid_set_changes(run_id).await?, config.bssid_churn_pct);
+    for ap in &churned {
+        println!(
+            "WARNING: {} BSSIDs changed {:.0}% since run {} ({} kept, {} new, {} gone)",
+            ap.hostname, ap.churn_pct, ap.previous_run_id, ap.kept, ap.added, ap.removed
+        );
+    }
+    if let Some(alert) = churn_alert(&churned) {
+        println!("ALERT: {}", alert.message);
+        notify::send_notifications(notify_config, alert.event, &alert.message, alert.details).await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{ApChange, DisconnectedAp};
+
+    #[test]
+    fn test_build_alerts() {
+        let changes = ApChanges {
+            added: vec![ApChange { device_id: 1, hostname: "AP-1".into() }, ApChange { device_id: 2, hostname: "AP-2".into() }],
+            removed: vec![],
+            disconnected: vec![DisconnectedAp { device_id: 3, hostname: "AP-3".into(), runs: 3 }],
+        };
+
+        let alerts = build_alerts(&changes, &AlertsConfig::default());
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].event, "ap_added");
+        assert_eq!(alerts[0].message, "2 new AP(s): AP-1, AP-2");
+        assert_eq!(alerts[0].details["aps"][1]["device_id"], 2);
+        assert_eq!(alerts[1].message, "1 AP(s) disconnected for 3 runs: AP-3");
+
+        let quiet = AlertsConfig { new_ap: false, ..Default::default() };
+        assert_eq!(build_alerts(&changes, &quiet).len(), 1);
+
+        assert_eq!(churn_alert(&[]), None);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::db::Annotation;
+use crate::report::DeviceInterfaces;
+use std::collections::HashMap;
+
+/// Copy each device's notes onto its access-mode interfaces, so they're
+/// exported next to its BSSIDs
+pub fn apply_notes(results: &mut [DeviceInterfaces], notes: &HashMap<i64, String>) {
+    for device in results.iter_mut() {
+        let Some(note) = notes.get(&device.device_id) else {
+            continue;
+        };
+        for iface in device.interfaces.iter_mut().filter(|i| i.is_access()) {
+            iface.notes = note.clone();
+        }
+    }
+}
+
+pub fn print_annotations(annotations: &[Annotation]) {
+    if annotations.is_empty() {
+        println!("No notes found");
+        return;
+    }
+
+    println!("{:<6} {:<24} {:<20} Note", "ID", "Device", "Added");
+    for annotation in annotations {
+        let device = annotation
+            .hostname
+            .clone()
+            .unwrap_or_else(|| annotation.device_id.to_string());
+        println!(
+            "{:<6} {:<24} {:<20} {}",
+            annotation.id,
+            device,
+            annotation.created_at.as_deref().unwrap_or("-"),
+            annotation.note
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_apply_notes() {
+        let mut results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                InterfaceEntry { name: "wifi0.1".into(), mode: "access".into(), ..Default::default() },
+                InterfaceEntry { name: "mgt0".into(), mode: "backhaul".into(), ..Default::default() },
+            ],
+        }];
+        apply_notes(&mut results, &HashMap::from([(1, "radio replaced 2024-05".to_string())]));
+        assert_eq!(results[0].interfaces[0].notes, "radio replaced 2024-05");
+        assert_eq!(results[0].interfaces[1].notes, "");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::location;
+use crate::output::{create_output_file, LineEnding};
+use crate::report::DeviceInterfaces;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Collected APs as an Ansible YAML inventory
+pub const ANSIBLE_INVENTORY_YAML_FILE: &str = "ansible-inventory.yml";
+
+/// Collected APs as an Ansible INI inventory
+pub const ANSIBLE_INVENTORY_INI_FILE: &str = "ansible-inventory.ini";
+
+/// Collected AP with its Ansible host vars and groups
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryHost {
+    pub name: String,
+    pub ansible_host: Option<String>,
+    pub serial: Option<String>,
+    pub model: Option<String>,
+    /// Access-mode BSSIDs, sorted
+    pub bssids: Vec<String>,
+    /// `site_<site>` and `function_<device_function>`
+    pub groups: Vec<String>,
+}
+
+/// Group name Ansible accepts: lowercase letters, digits and underscores
+fn group_name(prefix: &str, name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", prefix, slug)
+}
+
+/// One host per collected AP, sorted by hostname
+pub fn inventory_hosts(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> Vec<InventoryHost> {
+    let mut hosts: Vec<InventoryHost> = results
+        .iter()
+        .map(|result| {
+            let device = devices
+                .iter()
+                .find(|d| d.get("id").and_then(|id| id.as_i64()) == Some(result.device_id));
+            let field = |name: &str| {
+                device
+                    .and_then(|d| d.get(name))
+                    .and_then(|v| v.as_str())
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+            };
+
+            let mut bssids: Vec<String> = result
+                .interfaces
+                .iter()
+                .filter(|i| i.is_access() && !i.mac.is_empty())
+                .map(|i| i.mac.clone())
+                .collect();
+            bssids.sort();
+            bssids.dedup();
+
+            let site = device.map(location::device_site).unwrap_or_else(|| location::UNASSIGNED.to_string());
+            let function = field("device_function").unwrap_or_else(|| "unknown".to_string());
+
+            InventoryHost {
+                name: result.hostname.clone(),
+                ansible_host: field("ip_address"),
+                serial: field("serial_number"),
+                model: field("device_model").or_else(|| field("product_type")),
+                bssids,
+                groups: vec![group_name("site", &site), group_name("function", &function)],
+            }
+        })
+        .collect();
+
+    hosts.sort_by(|a, b| a.name.cmp(&b.name));
+    hosts
+}
+
+/// Hostnames of each group, by group name
+fn groups(hosts: &[InventoryHost]) -> BTreeMap<&str, Vec<&str>> {
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for host in hosts {
+        for group in &host.groups {
+            groups.entry(group.as_str()).or_default().push(host.name.as_str());
+        }
+    }
+    groups
+}
+
+/// Double-quoted scalar; JSON string syntax is valid YAML
+fn quote(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+/// Host vars under `all.hosts`, and one child group per site and device function
+pub fn render_yaml(hosts: &[InventoryHost]) -> String {
+    let mut out = String::from("all:\n  hosts:\n");
+    for host in hosts {
+        out.push_str(&format!("    {}:\n", quote(&host.name)));
+        for (key, value) in [("ansible_host", &host.ansible_host), ("serial", &host.serial), ("model", &host.model)] {
+            if let Some(value) = value {
+                out.push_str(&format!("      {}: {}\n", key, quote(value)));
+            }
+        }
+        if host.bssids.is_empty() {
+            out.push_str("      bssids: []\n");
+        } else {
+            out.push_str("      bssids:\n");
+            for bssid in &host.bssids {
+                out.push_str(&format!("        - {}\n", quote(bssid)));
+            }
+        }
+    }
+
+    out.push_str("  children:\n");
+    for (group, members) in groups(hosts) {
+        out.push_str(&format!("    {}:\n      hosts:\n", group));
+        for member in members {
+            out.push_str(&format!("        {}:\n", quote(member)));
+        }
+    }
+    out
+}
+
+/// Hosts with their vars first (ungrouped), then one section per group
+pub fn render_ini(hosts: &[InventoryHost]) -> String {
+    let mut out = String::new();
+    for host in hosts {
+        out.push_str(&host.name);
+        for (key, value) in [("ansible_host", &host.ansible_host), ("serial", &host.serial), ("model", &host.model)] {
+            if let Some(value) = value {
+                out.push_str(&format!(" {}={}", key, quote(value)));
+            }
+        }
+        // A JSON list in single quotes is read by Ansible as a list
+        out.push_str(&format!(" bssids='{}'\n", serde_json::to_string(&host.bssids).unwrap_or_default()));
+    }
+
+    for (group, members) in groups(hosts) {
+        out.push_str(&format!("\n[{}]\n", group));
+        for member in members {
+            out.push_str(member);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_file(path: &Path, content: &str, line_ending: LineEnding) -> Result<()> {
+    let mut file = create_output_file(path, line_ending)?;
+    file.write_all(content.as_bytes())
+        .and_then(|_| file.flush())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write the YAML and INI inventories of the collected APs
+pub fn write_inventories(devices: &[serde_json::Value], results: &[DeviceInterfaces], line_ending: LineEnding) -> Result<()> {
+    let hosts = inventory_hosts(devices, results);
+    write_file(Path::new(ANSIBLE_INVENTORY_YAML_FILE), &render_yaml(&hosts), line_ending)?;
+    write_file(Path::new(ANSIBLE_INVENTORY_INI_FILE), &render_ini(&hosts), line_ending)?;
+
+    println!(
+        "Ansible inventory saved to {} and {} ({} hosts, {} groups)",
+        ANSIBLE_INVENTORY_YAML_FILE,
+        ANSIBLE_INVENTORY_INI_FILE,
+        hosts.len(),
+        groups(&hosts).len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_inventory() {
+        let devices = vec![serde_json::json!({
+            "id": 1, "ip_address": "10.0.0.11", "serial_number": "SN1", "device_model": "AP305C",
+            "device_function": "AP", "locations": [{ "name": "Global" }, { "name": "HQ East" }]
+        })];
+        let results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                InterfaceEntry { mode: "access".into(), mac: "00:11:22:33:44:52".into(), ..Default::default() },
+                InterfaceEntry { mode: "access".into(), mac: "00:11:22:33:44:51".into(), ..Default::default() },
+                InterfaceEntry { mode: "backhaul".into(), mac: "00:11:22:33:44:00".into(), ..Default::default() },
+            ],
+        }];
+
+        let hosts = inventory_hosts(&devices, &results);
+        assert_eq!(hosts[0].groups, vec!["site_hq_east", "function_ap"]);
+        assert_eq!(hosts[0].bssids, vec!["00:11:22:33:44:51", "00:11:22:33:44:52"]);
+
+        let yaml = render_yaml(&hosts);
+        assert!(yaml.contains("    \"AP-1\":\n      ansible_host: \"10.0.0.11\"\n      serial: \"SN1\"\n      model: \"AP305C\"\n"));
+        assert!(yaml.contains("        - \"00:11:22:33:44:51\"\n"));
+        assert!(yaml.contains("  children:\n    function_ap:\n      hosts:\n        \"AP-1\":\n"));
+
+        let ini = render_ini(&hosts);
+        assert!(ini.starts_with(
+            "AP-1 ansible_host=\"10.0.0.11\" serial=\"SN1\" model=\"AP305C\" bssids='[\"00:11:22:33:44:51\",\"00:11:22:33:44:52\"]'\n"
+        ));
+        assert!(ini.contains("\n[site_hq_east]\nAP-1\n"));
+    }
+}
@@ -0,0 +1,783 @@
+use crate::backup::ConfigBackup;
+use crate::cli_status::{CliResult, CliStatus};
+use crate::config::BackupConfig;
+use crate::db::{self, AccessInterface, Database};
+use crate::error;
+use crate::mesh::{self, MeshDevice};
+use crate::parser_registry::{self, ParserRegistry};
+use crate::progress::{self, ProgressEvent};
+use crate::radio::{self, RadioDetail};
+use crate::report::DeviceInterfaces;
+use crate::ssid::{self, SsidSecurity};
+use crate::stats::{self, InterfaceCounters};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Calls made to the CloudIQ API. `CloudIQClient` talks to XIQ; `FakeApi`
+/// answers from canned data so the collection steps built on top can be
+/// tested without network access. The API calls return [`error::Error`];
+/// the collection steps also read and write the database and return
+/// `anyhow::Error`.
+#[async_trait]
+pub trait XiqApi: Send + Sync {
+    /// Every managed device, across all pages
+    async fn get_devices(&self) -> error::Result<Vec<serde_json::Value>>;
+
+    /// Run a CLI command on devices; returns each device's output and the
+    /// status XIQ reported for it
+    async fn send_cli_command_with_status(&self, device_ids: &[i64], command: &str) -> error::Result<Vec<CliResult>>;
+
+    /// Run a CLI command on devices; returns the output of each device the
+    /// command succeeded on
+    async fn send_cli_command(&self, device_ids: &[i64], command: &str) -> error::Result<Vec<(i64, String)>> {
+        Ok(self
+            .send_cli_command_with_status(device_ids, command)
+            .await?
+            .into_iter()
+            .filter(CliResult::succeeded)
+            .map(|r| (r.device_id, r.output))
+            .collect())
+    }
+
+    async fn locate_device(&self, device_id: i64) -> error::Result<()>;
+
+    async fn reboot_devices(&self, device_ids: &[i64]) -> error::Result<()>;
+
+    /// Push the current configuration to devices via a deployment
+    async fn push_config(&self, device_ids: &[i64]) -> error::Result<()>;
+
+    async fn locate_by_hostname(&self, hostname: &str) -> error::Result<()> {
+        let devices = self.get_devices().await?;
+
+        let device = find_device_by_hostname(&devices, hostname)
+            .ok_or_else(|| error::Error::invalid(format!("No device found with hostname '{}'", hostname)))?;
+        let device_id = device.get("id")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(|| error::Error::invalid("Device record has no id"))?;
+
+        println!("\nTriggering locate (LED blink) on {} (ID: {})...", hostname, device_id);
+        self.locate_device(device_id).await?;
+        println!("Locate request accepted for {}", hostname);
+
+        Ok(())
+    }
+
+    async fn reboot_targets(&self, db: &Database, targets: &[String]) -> Result<()> {
+        let devices = self.get_devices().await?;
+        let resolved = resolve_targets(&devices, targets)?;
+
+        println!("\n=== Rebooting {} device(s) ===", resolved.len());
+        for (id, hostname) in &resolved {
+            println!("  - {} (ID: {})", hostname, id);
+        }
+
+        let device_ids: Vec<i64> = resolved.iter().map(|(id, _)| *id).collect();
+        let target_list = resolved.iter()
+            .map(|(id, hostname)| format!("{} ({})", hostname, id))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match self.reboot_devices(&device_ids).await {
+            Ok(()) => {
+                db.log_audit("device reboot", &target_list, "success").await?;
+                println!("Reboot request accepted");
+                Ok(())
+            }
+            Err(e) => {
+                db.log_audit("device reboot", &target_list, &format!("failed: {}", e)).await?;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Push config to the listed targets, or to every mismatched device when none are given
+    async fn push_config_targets(
+        &self,
+        db: &Database,
+        devices: &[serde_json::Value],
+        mismatched: &[db::MismatchedDevice],
+        targets: &[String],
+    ) -> Result<()> {
+        let resolved = if targets.is_empty() {
+            mismatched.iter().map(|d| (d.id, d.hostname.clone())).collect()
+        } else {
+            resolve_targets(devices, targets)?
+        };
+
+        if resolved.is_empty() {
+            println!("\nNo devices to push configuration to");
+            return Ok(());
+        }
+
+        println!("\n=== Pushing configuration to {} device(s) ===", resolved.len());
+        for (id, hostname) in &resolved {
+            println!("  - {} (ID: {})", hostname, id);
+        }
+
+        let device_ids: Vec<i64> = resolved.iter().map(|(id, _)| *id).collect();
+        let target_list = resolved.iter()
+            .map(|(id, hostname)| format!("{} ({})", hostname, id))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match self.push_config(&device_ids).await {
+            Ok(()) => {
+                db.log_audit("config push", &target_list, "success").await?;
+                println!("Config push request accepted");
+                Ok(())
+            }
+            Err(e) => {
+                db.log_audit("config push", &target_list, &format!("failed: {}", e)).await?;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Capture the configuration of every connected device, sending each
+    /// device family its configured backup command
+    async fn backup_configs(&self, devices: &[serde_json::Value], config: &BackupConfig) -> Result<Vec<ConfigBackup>> {
+        let mut by_command: std::collections::BTreeMap<&str, Vec<(i64, String)>> = std::collections::BTreeMap::new();
+        for device in devices {
+            let connected = device.get("connected").and_then(|v| v.as_bool()).unwrap_or(false);
+            let Some(id) = device.get("id").and_then(|v| v.as_i64()) else {
+                continue;
+            };
+            if !connected {
+                continue;
+            }
+
+            let hostname = device.get("hostname")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+            let family = parser_registry::device_family(device);
+            by_command
+                .entry(config.command_for(family.as_deref()))
+                .or_default()
+                .push((id, hostname));
+        }
+
+        let mut backups = Vec::new();
+        for (command, targets) in by_command {
+            println!("Sending '{}' to {} connected device(s)...", command, targets.len());
+
+            let device_ids: Vec<i64> = targets.iter().map(|(id, _)| *id).collect();
+            let hostname_map: std::collections::HashMap<i64, String> = targets.into_iter().collect();
+
+            for (device_id, output) in self.send_cli_command(&device_ids, command).await? {
+                if output.trim().is_empty() {
+                    continue;
+                }
+                backups.push(ConfigBackup {
+                    device_id,
+                    hostname: hostname_map.get(&device_id).cloned().unwrap_or_else(|| "unknown".to_string()),
+                    command: command.to_string(),
+                    config: output,
+                });
+            }
+        }
+
+        Ok(backups)
+    }
+
+    /// Send each radio detail command to the collected APs and parse the
+    /// transmit power and PHY mode of every radio they report
+    async fn collect_radio_details(&self, aps: &[DeviceInterfaces], commands: &[String]) -> Result<Vec<RadioDetail>> {
+        if aps.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let device_ids: Vec<i64> = aps.iter().map(|ap| ap.device_id).collect();
+        let hostname_map: std::collections::HashMap<i64, &str> =
+            aps.iter().map(|ap| (ap.device_id, ap.hostname.as_str())).collect();
+
+        let mut details = Vec::new();
+        for command in commands {
+            println!("Sending radio detail command '{}' to {} AP(s)...", command, device_ids.len());
+            let default_radio = radio::radio_from_command(command);
+
+            for (device_id, output) in self.send_cli_command(&device_ids, command).await? {
+                for detail in radio::parse_radio_details(&output, default_radio.as_deref()) {
+                    details.push(RadioDetail {
+                        device_id,
+                        hostname: hostname_map.get(&device_id).copied().unwrap_or("unknown").to_string(),
+                        ..detail
+                    });
+                }
+            }
+        }
+
+        println!("Collected TX power and PHY mode for {} radio(s)", details.len());
+
+        Ok(details)
+    }
+
+    /// Send the SSID listing command to the collected APs and parse each
+    /// AP's SSID security settings
+    async fn collect_ssid_security(&self, aps: &[DeviceInterfaces], command: &str) -> Result<Vec<(i64, Vec<SsidSecurity>)>> {
+        if aps.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let device_ids: Vec<i64> = aps.iter().map(|ap| ap.device_id).collect();
+        println!("Sending '{}' to {} AP(s)...", command, device_ids.len());
+
+        let security: Vec<(i64, Vec<SsidSecurity>)> = self
+            .send_cli_command(&device_ids, command)
+            .await?
+            .into_iter()
+            .map(|(device_id, output)| (device_id, ssid::parse_show_ssid(&output)))
+            .collect();
+
+        let ssids: usize = security.iter().map(|(_, ssids)| ssids.len()).sum();
+        println!("Collected security settings for {} SSID(s)", ssids);
+
+        Ok(security)
+    }
+
+    /// Send the hive membership and AMRP neighbor commands to the collected
+    /// APs and parse each AP's hive and mesh neighbors
+    async fn collect_mesh(&self, aps: &[DeviceInterfaces], hive_command: &str, neighbor_command: &str) -> Result<Vec<MeshDevice>> {
+        if aps.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let device_ids: Vec<i64> = aps.iter().map(|ap| ap.device_id).collect();
+        let mut mesh: Vec<MeshDevice> = aps
+            .iter()
+            .map(|ap| MeshDevice {
+                device_id: ap.device_id,
+                hostname: ap.hostname.clone(),
+                ..Default::default()
+            })
+            .collect();
+
+        println!("Sending '{}' to {} AP(s)...", hive_command, device_ids.len());
+        for (device_id, output) in self.send_cli_command(&device_ids, hive_command).await? {
+            if let Some(device) = mesh.iter_mut().find(|d| d.device_id == device_id) {
+                device.hive = mesh::parse_hive_name(&output);
+            }
+        }
+
+        println!("Sending '{}' to {} AP(s)...", neighbor_command, device_ids.len());
+        for (device_id, output) in self.send_cli_command(&device_ids, neighbor_command).await? {
+            if let Some(device) = mesh.iter_mut().find(|d| d.device_id == device_id) {
+                device.neighbors = mesh::parse_amrp_neighbors(&output);
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    /// Poll the counters of each access interface on the connected APs,
+    /// sending one command per interface name to every AP that has it
+    async fn collect_interface_stats(
+        &self,
+        devices: &[serde_json::Value],
+        targets: &[AccessInterface],
+        command_template: &str,
+    ) -> Result<Vec<InterfaceCounters>> {
+        let connected: std::collections::HashSet<i64> =
+            get_connected_aps(devices).into_iter().map(|(id, _)| id).collect();
+
+        let mut by_name: std::collections::BTreeMap<&str, Vec<&AccessInterface>> = std::collections::BTreeMap::new();
+        for target in targets.iter().filter(|t| connected.contains(&t.device_id)) {
+            by_name.entry(target.name.as_str()).or_default().push(target);
+        }
+
+        let mut counters = Vec::new();
+        for (name, interfaces) in by_name {
+            let command = command_template.replace("{interface}", name);
+            let device_ids: Vec<i64> = interfaces.iter().map(|i| i.device_id).collect();
+            println!("Sending '{}' to {} AP(s)...", command, device_ids.len());
+
+            for (device_id, output) in self.send_cli_command(&device_ids, &command).await? {
+                let Some(interface) = interfaces.iter().find(|i| i.device_id == device_id) else {
+                    continue;
+                };
+                let (rx_bytes, tx_bytes, clients) = stats::parse_counters(&output);
+                counters.push(InterfaceCounters {
+                    device_id,
+                    hostname: interface.hostname.clone(),
+                    interface: interface.name.clone(),
+                    mac: interface.mac.clone(),
+                    rx_bytes,
+                    tx_bytes,
+                    clients,
+                });
+            }
+        }
+
+        counters.sort_by(|a, b| (&a.hostname, &a.interface).cmp(&(&b.hostname, &b.interface)));
+        Ok(counters)
+    }
+
+    async fn run_command_on_connected_aps(
+        &self,
+        devices: &[serde_json::Value],
+        command: &str,
+        parsers: &ParserRegistry,
+    ) -> Result<Vec<DeviceInterfaces>> {
+        let outputs = self.dispatch_to_connected_aps(devices, command).await?;
+        parse_cli_results(devices, outputs, command, parsers)
+    }
+
+    /// Send a CLI command to every connected AP; returns the raw output per device
+    async fn dispatch_to_connected_aps(&self, devices: &[serde_json::Value], command: &str) -> Result<Vec<CliResult>> {
+        let connected_aps = get_connected_aps(devices);
+
+        if connected_aps.is_empty() {
+            println!("No connected APs found.");
+            return Ok(Vec::new());
+        }
+
+        println!("\n=== Found {} connected APs ===", connected_aps.len());
+        for (id, hostname) in &connected_aps {
+            println!("  - {} (ID: {})", hostname, id);
+        }
+        println!();
+
+        let device_ids: Vec<i64> = connected_aps.iter().map(|(id, _)| *id).collect();
+
+        println!("Sending command '{}' to all connected APs...\n", command);
+
+        let results = self.send_cli_command_with_status(&device_ids, command).await?;
+        progress::emit(ProgressEvent::ChunkCompleted {
+            chunk: 1,
+            chunks: 1,
+            devices: results.len(),
+            percent: 100,
+        });
+
+        Ok(results)
+    }
+}
+
+/// Devices parsed per rayon thread before the batch is handed to `on_device`
+const PARSE_BATCH_PER_THREAD: usize = 4;
+
+/// Parse each device's CLI output with the parser registered for the command
+/// and the device's family. Devices the command failed on are skipped.
+pub fn parse_cli_results(
+    devices: &[serde_json::Value],
+    outputs: Vec<CliResult>,
+    command: &str,
+    parsers: &ParserRegistry,
+) -> Result<Vec<DeviceInterfaces>> {
+    parse_cli_results_with(devices, outputs, command, parsers, |_| Ok(()))
+}
+
+/// Like `parse_cli_results`, calling `on_device` with each device as soon as
+/// it is parsed, before it is added to the results
+pub fn parse_cli_results_with(
+    devices: &[serde_json::Value],
+    outputs: Vec<CliResult>,
+    command: &str,
+    parsers: &ParserRegistry,
+    mut on_device: impl FnMut(&mut DeviceInterfaces) -> Result<()>,
+) -> Result<Vec<DeviceInterfaces>> {
+    // Create a map of device_id -> hostname for output, of every device since
+    // APs collected over SSH needn't be connected to XIQ
+    let hostname_map: HashMap<i64, String> = devices
+        .iter()
+        .filter_map(|device| {
+            let hostname = device.get("hostname").and_then(|v| v.as_str()).unwrap_or("unknown");
+            Some((device.get("id")?.as_i64()?, hostname.to_string()))
+        })
+        .collect();
+
+    // Device family of each device, for parser selection
+    let family_map: HashMap<i64, Option<String>> = devices
+        .iter()
+        .filter_map(|device| Some((device.get("id")?.as_i64()?, parser_registry::device_family(device))))
+        .collect();
+
+    let mut collected = Vec::new();
+
+    if !outputs.is_empty() {
+        println!("=== CLI Command Results ===\n");
+    }
+    // Parsing is pure CPU work, so devices are parsed across the rayon pool.
+    // It runs in batches a few times the pool size, handed to `on_device` in
+    // the order of the outputs before the next batch starts, so a streaming
+    // `on_device` can write and drop each device's output without waiting
+    // for the whole fleet
+    let total = outputs.iter().filter(|r| r.succeeded()).count();
+    let batch_size = rayon::current_num_threads() * PARSE_BATCH_PER_THREAD;
+    let mut outputs = outputs.into_iter().filter(CliResult::succeeded).peekable();
+    let mut index = 0;
+    while outputs.peek().is_some() {
+        let batch: Vec<CliResult> = outputs.by_ref().take(batch_size).collect();
+        let parsed: Vec<(i64, String, Result<Vec<_>>)> = batch
+            .into_par_iter()
+            .map(|CliResult { device_id, output, .. }| {
+                let hostname = hostname_map.get(&device_id).map(|s| s.as_str()).unwrap_or("unknown");
+
+                // Extract and normalize interface entries with the parser registered for this command
+                let family = family_map.get(&device_id).cloned().flatten();
+                let interfaces = match parsers.lookup(command, family.as_deref()) {
+                    Some(parser) => parser
+                        .parse(&output)
+                        .with_context(|| format!("Parser '{}' failed for {} (ID: {})", parser.name(), hostname, device_id)),
+                    None => Ok(Vec::new()),
+                };
+                (device_id, output, interfaces)
+            })
+            .collect();
+
+        for (device_id, output, interfaces) in parsed {
+            let interfaces = interfaces?;
+            index += 1;
+            let hostname = hostname_map.get(&device_id).map(|s| s.as_str()).unwrap_or("unknown");
+            if !interfaces.is_empty() {
+                println!("  {} (ID: {}): Found {} interface(s)", hostname, device_id, interfaces.len());
+            }
+            progress::emit(ProgressEvent::DeviceParsed {
+                device_id,
+                hostname: hostname.to_string(),
+                interfaces: interfaces.len(),
+                parsed: index,
+                total,
+                percent: progress::percent(index, total),
+            });
+
+            let mut result = DeviceInterfaces {
+                device_id,
+                hostname: hostname.to_string(),
+                output,
+                interfaces,
+            };
+            on_device(&mut result)?;
+            collected.push(result);
+        }
+    }
+
+    Ok(collected)
+}
+
+pub fn find_device_by_hostname<'a>(devices: &'a [serde_json::Value], hostname: &str) -> Option<&'a serde_json::Value> {
+    devices.iter().find(|device| {
+        device.get("hostname")
+            .and_then(|v| v.as_str())
+            .map(|s| s.eq_ignore_ascii_case(hostname))
+            .unwrap_or(false)
+    })
+}
+
+/// Resolve each target (device ID or hostname) to exactly one known device.
+/// Any target that doesn't match is an error, so a typo never reboots the wrong AP.
+pub fn resolve_targets(devices: &[serde_json::Value], targets: &[String]) -> Result<Vec<(i64, String)>> {
+    let mut resolved = Vec::new();
+
+    for target in targets {
+        let device = match target.parse::<i64>() {
+            Ok(id) => devices.iter().find(|d| d.get("id").and_then(|v| v.as_i64()) == Some(id)),
+            Err(_) => find_device_by_hostname(devices, target),
+        }
+        .with_context(|| format!("No device found matching '{}'", target))?;
+
+        let id = device.get("id")
+            .and_then(|v| v.as_i64())
+            .context("Device record has no id")?;
+        let hostname = device.get("hostname")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        if !resolved.iter().any(|(existing, _)| *existing == id) {
+            resolved.push((id, hostname));
+        }
+    }
+
+    Ok(resolved)
+}
+
+pub fn get_connected_aps(devices: &[serde_json::Value]) -> Vec<(i64, String)> {
+    devices
+        .iter()
+        .filter(|device| {
+            let connected = device.get("connected")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let is_ap = device.get("device_function")
+                .and_then(|v| v.as_str())
+                .map(|s| s == "AP")
+                .unwrap_or(false);
+            connected && is_ap
+        })
+        .filter_map(|device| {
+            let id = device.get("id")?.as_i64()?;
+            let hostname = device.get("hostname")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+            Some((id, hostname))
+        })
+        .collect()
+}
+
+/// API call recorded by `FakeApi`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FakeCall {
+    GetDevices,
+    Cli { command: String, device_ids: Vec<i64> },
+    Locate(i64),
+    Reboot(Vec<i64>),
+    PushConfig(Vec<i64>),
+}
+
+/// In-memory `XiqApi` that serves a fixed device list and canned CLI output,
+/// and records every call it receives
+#[derive(Debug, Default)]
+pub struct FakeApi {
+    devices: Vec<serde_json::Value>,
+    /// CLI status and output by command and device ID
+    outputs: BTreeMap<(String, i64), (CliStatus, String)>,
+    calls: Mutex<Vec<FakeCall>>,
+}
+
+impl FakeApi {
+    pub fn new(devices: Vec<serde_json::Value>) -> Self {
+        Self {
+            devices,
+            ..Default::default()
+        }
+    }
+
+    /// Answer `command` on `device_id` with `output`. Devices without an
+    /// output for a command are left out of the response, like devices XIQ
+    /// couldn't reach.
+    pub fn with_output(self, command: &str, device_id: i64, output: &str) -> Self {
+        self.with_status(command, device_id, CliStatus::Success, output)
+    }
+
+    /// Answer `command` on `device_id` with `status`, and `output` as the
+    /// output or error message
+    pub fn with_status(mut self, command: &str, device_id: i64, status: CliStatus, output: &str) -> Self {
+        self.outputs.insert((command.to_string(), device_id), (status, output.to_string()));
+        self
+    }
+
+    /// Calls received so far, in order
+    pub fn calls(&self) -> Vec<FakeCall> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn record(&self, call: FakeCall) {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).push(call);
+    }
+}
+
+#[async_trait]
+impl XiqApi for FakeApi {
+    async fn get_devices(&self) -> error::Result<Vec<serde_json::Value>> {
+        self.record(FakeCall::GetDevices);
+        Ok(self.devices.clone())
+    }
+
+    async fn send_cli_command_with_status(&self, device_ids: &[i64], command: &str) -> error::Result<Vec<CliResult>> {
+        self.record(FakeCall::Cli {
+            command: command.to_string(),
+            device_ids: device_ids.to_vec(),
+        });
+        let outputs: HashMap<i64, &(CliStatus, String)> = self
+            .outputs
+            .iter()
+            .filter(|((c, _), _)| c == command)
+            .map(|((_, id), output)| (*id, output))
+            .collect();
+        Ok(device_ids
+            .iter()
+            .filter_map(|id| {
+                let (status, output) = outputs.get(id)?;
+                Some(CliResult { device_id: *id, status: *status, output: output.clone() })
+            })
+            .collect())
+    }
+
+    async fn locate_device(&self, device_id: i64) -> error::Result<()> {
+        self.record(FakeCall::Locate(device_id));
+        Ok(())
+    }
+
+    async fn reboot_devices(&self, device_ids: &[i64]) -> error::Result<()> {
+        self.record(FakeCall::Reboot(device_ids.to_vec()));
+        Ok(())
+    }
+
+    async fn push_config(&self, device_ids: &[i64]) -> error::Result<()> {
+        self.record(FakeCall::PushConfig(device_ids.to_vec()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices() -> Vec<serde_json::Value> {
+        vec![
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "device_function": "AP", "connected": true }),
+            serde_json::json!({ "id": 2, "hostname": "AP-2", "device_function": "AP", "connected": false }),
+            serde_json::json!({ "id": 3, "hostname": "SW-1", "device_function": "SWITCH", "connected": true }),
+            serde_json::json!({ "id": 4, "hostname": "AP-4", "device_function": "AP", "connected": true }),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_run_command_on_connected_aps() {
+        let output = "\
+Name     MAC addr           Mode   State  Chan(Width) VLAN  Radio Hive SSID
+wifi0.1  00:11:22:33:44:56  access up     11(20)      1     wifi0 hive1 Corp
+";
+        let api = FakeApi::new(devices())
+            .with_output("show interface", 1, output)
+            .with_status("show interface", 4, CliStatus::Unsupported, output);
+
+        let results = api
+            .run_command_on_connected_aps(&devices(), "show interface", &ParserRegistry::with_defaults())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            api.calls(),
+            vec![FakeCall::Cli { command: "show interface".into(), device_ids: vec![1, 4] }]
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].device_id, results[0].hostname.as_str()), (1, "AP-1"));
+        assert_eq!(results[0].interfaces[0].ssid, "Corp");
+    }
+
+    #[test]
+    fn test_parse_cli_results_in_batches() {
+        use crate::parser::InterfaceEntry;
+        use crate::parser_registry::CliOutputParser;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct CountingParser(Arc<AtomicUsize>);
+        impl CliOutputParser for CountingParser {
+            fn name(&self) -> &str {
+                "counting"
+            }
+            fn parse(&self, _output: &str) -> crate::error::Result<Vec<InterfaceEntry>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(Vec::new())
+            }
+        }
+
+        let parsed = Arc::new(AtomicUsize::new(0));
+        let mut parsers = ParserRegistry::with_defaults();
+        parsers.add_parser(Arc::new(CountingParser(parsed.clone())));
+        parsers.register("", None, "counting").unwrap();
+
+        let batch_size = rayon::current_num_threads() * PARSE_BATCH_PER_THREAD;
+        let total = batch_size * 2 + 1;
+        let devices: Vec<_> = (1..=total as i64)
+            .map(|id| serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": true }))
+            .collect();
+        let outputs: Vec<_> = (1..=total as i64)
+            .map(|device_id| CliResult { device_id, status: CliStatus::Success, output: String::new() })
+            .collect();
+
+        // The first device is handed over after its batch, not after the whole fleet
+        let mut seen = Vec::new();
+        let mut parsed_at_first = None;
+        let results = parse_cli_results_with(&devices, outputs, "show interface", &parsers, |ap| {
+            parsed_at_first.get_or_insert(parsed.load(Ordering::SeqCst));
+            seen.push(ap.device_id);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(parsed_at_first, Some(batch_size));
+        assert_eq!(seen, (1..=total as i64).collect::<Vec<_>>());
+        assert_eq!(results.len(), total);
+    }
+
+    #[tokio::test]
+    async fn test_collect_interface_stats() {
+        let api = FakeApi::new(devices())
+            .with_output("show interface wifi0.1", 1, "Rx bytes=10; Tx bytes=20;")
+            .with_output("show interface wifi1.1", 4, "Rx bytes=30; Tx bytes=40;");
+        let target = |device_id: i64, name: &str| AccessInterface {
+            device_id,
+            hostname: format!("AP-{}", device_id),
+            name: name.to_string(),
+            mac: String::new(),
+        };
+        // AP-2 is disconnected and isn't polled
+        let targets = [target(1, "wifi0.1"), target(2, "wifi0.1"), target(4, "wifi1.1")];
+
+        let counters = api
+            .collect_interface_stats(&devices(), &targets, stats::DEFAULT_STATS_COMMAND)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            api.calls(),
+            vec![
+                FakeCall::Cli { command: "show interface wifi0.1".into(), device_ids: vec![1] },
+                FakeCall::Cli { command: "show interface wifi1.1".into(), device_ids: vec![4] },
+            ]
+        );
+        let totals: Vec<_> = counters.iter().map(|c| (c.device_id, c.rx_bytes, c.tx_bytes)).collect();
+        assert_eq!(totals, vec![(1, Some(10), Some(20)), (4, Some(30), Some(40))]);
+    }
+
+    #[test]
+    fn test_parse_cli_results_keeps_order() {
+        let devices: Vec<_> = (1..=200)
+            .map(|id| serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": true }))
+            .collect();
+        let outputs: Vec<_> = (1..=200)
+            .rev()
+            .map(|id| CliResult {
+                device_id: id,
+                status: if id % 50 == 0 { CliStatus::Error } else { CliStatus::Success },
+                output: format!("wifi0.1  00:11:22:33:{:02x}:{:02x}  access up 11(20) 1 wifi0 hive1 Corp", id / 256, id % 256),
+            })
+            .collect();
+
+        let mut seen = Vec::new();
+        let results = parse_cli_results_with(&devices, outputs, "show interface", &ParserRegistry::with_defaults(), |ap| {
+            seen.push(ap.device_id);
+            Ok(())
+        })
+        .unwrap();
+
+        let expected: Vec<i64> = (1..=200).rev().filter(|id| id % 50 != 0).collect();
+        assert_eq!(seen, expected);
+        assert_eq!(results.iter().map(|r| r.device_id).collect::<Vec<_>>(), expected);
+        assert_eq!(results[0].hostname, "AP-199");
+        assert!(results.iter().all(|r| r.interfaces.len() == 1));
+    }
+
+    #[test]
+    fn test_parse_cli_results_names_disconnected_aps() {
+        // Output collected over SSH from an AP XIQ lists as disconnected
+        let devices = [serde_json::json!({ "id": 7, "hostname": "AP-Lobby", "device_function": "AP", "connected": false })];
+        let outputs = vec![CliResult {
+            device_id: 7,
+            status: CliStatus::Success,
+            output: "wifi0.1  00:11:22:33:44:55  access up 11(20) 1 wifi0 hive1 Corp".to_string(),
+        }];
+
+        let results = parse_cli_results(&devices, outputs, "show interface", &ParserRegistry::with_defaults()).unwrap();
+        assert_eq!(results[0].hostname, "AP-Lobby");
+    }
+
+    #[test]
+    fn test_resolve_targets() {
+        let targets = ["ap-4".to_string(), "1".to_string(), "AP-1".to_string()];
+        assert_eq!(
+            resolve_targets(&devices(), &targets).unwrap(),
+            vec![(4, "AP-4".to_string()), (1, "AP-1".to_string())]
+        );
+        assert!(resolve_targets(&devices(), &["AP-9".to_string()]).is_err());
+        assert_eq!(get_connected_aps(&devices()).len(), 2);
+    }
+}
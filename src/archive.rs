@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Default archive directory, relative to the working directory
+pub const DEFAULT_ARCHIVE_DIR: &str = "archive";
+
+/// Default number of archived runs to keep
+pub const DEFAULT_ARCHIVE_KEEP: usize = 10;
+
+/// Name of a run directory, before the `-N` suffix of runs in the same second
+const RUN_STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Files a collection run may write to the working directory
+pub const OUTPUT_FILES: &[&str] = &[
+    "devices.json",
+    "full_cli.json",
+    "full_cli.jsonl",
+    "bssids.txt",
+    "wifi-bssids.txt",
+    "wifi-bssids.csv",
+    "wifi-bssids.json",
+    "wifi-bssids.jsonl",
+    "wifi-bssids.xlsx",
+    "ssid-summary.csv",
+    "site-summary.csv",
+    "policy-summary.csv",
+    "mist-aps.csv",
+    "ansible-inventory.yml",
+    "ansible-inventory.ini",
+    "terraform-bssids.json",
+    "channel-histogram.csv",
+    "co-channel-conflicts.csv",
+    "radio-bssids.csv",
+    "invalid-bssids.csv",
+    "unreachable-aps.csv",
+    "ssid-compliance.csv",
+    "validation-report.csv",
+    "mesh-topology.dot",
+    "mesh-links.csv",
+    "manifest.json",
+];
+
+/// Copy the output files written since `since` into `archive_dir/<timestamp>/`,
+/// then remove the oldest archived runs beyond `keep`.
+/// Returns the new archive directory, or `None` when nothing was written.
+pub fn archive_outputs(archive_dir: &Path, files: &[PathBuf], since: SystemTime, keep: usize) -> Result<Option<PathBuf>> {
+    let written: Vec<&PathBuf> = files
+        .iter()
+        .filter(|f| {
+            fs::metadata(f)
+                .and_then(|m| m.modified())
+                .map(|modified| modified >= since)
+                .unwrap_or(false)
+        })
+        .collect();
+
+    if written.is_empty() {
+        return Ok(None);
+    }
+
+    let target = new_run_dir(archive_dir)?;
+    for file in written {
+        let name = file.file_name().context("Output file has no name")?;
+        fs::copy(file, target.join(name))
+            .context(format!("Failed to archive {}", file.display()))?;
+    }
+
+    rotate(archive_dir, keep)?;
+
+    Ok(Some(target))
+}
+
+/// Create a new timestamped run directory
+fn new_run_dir(archive_dir: &Path) -> Result<PathBuf> {
+    let stamp = chrono::Utc::now().format(RUN_STAMP_FORMAT).to_string();
+
+    // Two runs in the same second get a numeric suffix
+    let mut target = archive_dir.join(&stamp);
+    let mut n = 1;
+    while target.exists() {
+        target = archive_dir.join(format!("{}-{}", stamp, n));
+        n += 1;
+    }
+
+    fs::create_dir_all(&target)
+        .context(format!("Failed to create archive directory: {}", target.display()))?;
+
+    Ok(target)
+}
+
+/// Time and same-second suffix of a run directory name such as
+/// `20240101T000000Z` or `20240101T000000Z-10`; `None` for other names
+fn run_order(name: &str) -> Option<(NaiveDateTime, u32)> {
+    let (stamp, n) = match name.split_once('-') {
+        Some((stamp, n)) => (stamp, n.parse().ok()?),
+        None => (name, 0),
+    };
+    Some((NaiveDateTime::parse_from_str(stamp, RUN_STAMP_FORMAT).ok()?, n))
+}
+
+/// Remove the oldest run directories so at most `keep` remain. Only
+/// directories named like a run are counted; anything else is left alone.
+/// Returns how many were removed.
+pub fn rotate(archive_dir: &Path, keep: usize) -> Result<usize> {
+    let mut runs: Vec<((NaiveDateTime, u32), PathBuf)> = fs::read_dir(archive_dir)
+        .context(format!("Failed to read archive directory: {}", archive_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .filter_map(|path| Some((run_order(path.file_name()?.to_str()?)?, path)))
+        .collect();
+
+    if runs.len() <= keep {
+        return Ok(0);
+    }
+
+    runs.sort();
+    let excess = runs.len() - keep;
+    for (_, run) in &runs[..excess] {
+        fs::remove_dir_all(run)
+            .context(format!("Failed to remove old archive: {}", run.display()))?;
+    }
+
+    Ok(excess)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_archive_and_rotate() {
+        let dir = std::env::temp_dir().join(format!("xiq-archive-test-{}", std::process::id()));
+        let archive_dir = dir.join("archive");
+        for old in ["20240101T000000Z", "20240102T000000Z", "20240103T000000Z"] {
+            fs::create_dir_all(archive_dir.join(old)).unwrap();
+        }
+
+        let before = SystemTime::now() - Duration::from_secs(2);
+        let fresh = dir.join("full_cli.json");
+        fs::write(&fresh, "{}").unwrap();
+        let missing = dir.join("bssids.txt");
+
+        let target = archive_outputs(&archive_dir, &[fresh, missing], before, 2).unwrap().unwrap();
+        assert!(target.join("full_cli.json").exists());
+        assert!(!target.join("bssids.txt").exists());
+
+        let mut remaining: Vec<_> = fs::read_dir(&archive_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining[0], "20240103T000000Z");
+
+        // Nothing written since: no new archive
+        let later = SystemTime::now() + Duration::from_secs(60);
+        assert_eq!(archive_outputs(&archive_dir, &[dir.join("full_cli.json")], later, 2).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_only_run_directories() {
+        let archive_dir = std::env::temp_dir().join(format!("xiq-archive-rotate-test-{}", std::process::id()));
+        for name in ["20240101T000000Z-10", "20240101T000000Z-2", "20240101T000000Z", "reports", "2024-old"] {
+            fs::create_dir_all(archive_dir.join(name)).unwrap();
+        }
+
+        assert_eq!(rotate(&archive_dir, 1).unwrap(), 2);
+        let mut remaining: Vec<_> = fs::read_dir(&archive_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["2024-old", "20240101T000000Z-10", "reports"]);
+
+        fs::remove_dir_all(&archive_dir).unwrap();
+    }
+}
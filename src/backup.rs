@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use similar::TextDiff;
+use std::io::{Read, Write};
+
+/// Default command used to back up a device's configuration
+pub const DEFAULT_BACKUP_COMMAND: &str = "show running-config";
+
+/// One device's configuration captured during a backup run
+#[derive(Debug, Clone)]
+pub struct ConfigBackup {
+    pub device_id: i64,
+    pub hostname: String,
+    pub command: String,
+    pub config: String,
+}
+
+/// Hex SHA-256 of a configuration, used to spot unchanged backups
+pub fn config_hash(config: &str) -> String {
+    crate::manifest::sha256_hex(config.as_bytes())
+}
+
+/// Gzip a configuration or raw CLI output for storage
+pub fn compress(text: &str) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(text.as_bytes())
+        .context("Failed to compress text for storage")?;
+    encoder.finish().context("Failed to compress text for storage")
+}
+
+pub fn decompress(data: &[u8]) -> Result<String> {
+    let mut text = String::new();
+    GzDecoder::new(data)
+        .read_to_string(&mut text)
+        .context("Failed to decompress stored text")?;
+    Ok(text)
+}
+
+/// Unified diff between two stored configurations
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let config = "hostname AP-1\ninterface wifi0 radio channel 36\n".repeat(50);
+        let packed = compress(&config).unwrap();
+        assert!(packed.len() < config.len());
+        assert_eq!(decompress(&packed).unwrap(), config);
+        assert_eq!(config_hash("a"), "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb");
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("a\nb\nc\n", "a\nB\nc\n", "run 1", "run 2");
+        assert!(diff.contains("--- run 1"));
+        assert!(diff.contains("-b\n"));
+        assert!(diff.contains("+B\n"));
+        assert!(unified_diff("a\n", "a\n", "x", "y").is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::client::DEVICE_PAGE_LIMIT;
+use crate::db::InventoryCounts;
+use std::time::Duration;
+
+/// Expected seconds per short API call (login, device page) when not configured
+pub const DEFAULT_CALL_SECS: u64 = 2;
+
+/// Expected seconds per `:cli` call when not configured
+pub const DEFAULT_CLI_SECS: u64 = 30;
+
+/// API usage limits of the org, from `[budget]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiBudget {
+    /// Runs estimated to make more calls than this are refused
+    pub max_calls: Option<u64>,
+    /// Rate limit the calls are paced by
+    pub calls_per_minute: Option<u64>,
+    pub call_time: Duration,
+    pub cli_time: Duration,
+}
+
+impl Default for ApiBudget {
+    fn default() -> Self {
+        Self {
+            max_calls: None,
+            calls_per_minute: None,
+            call_time: Duration::from_secs(DEFAULT_CALL_SECS),
+            cli_time: Duration::from_secs(DEFAULT_CLI_SECS),
+        }
+    }
+}
+
+/// What a collection run will do, as far as it's known before it starts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunPlan {
+    /// Stored inventory from earlier runs
+    pub inventory: InventoryCounts,
+    /// Radio detail, SSID and mesh commands sent after the main command
+    pub detail_commands: usize,
+    /// `--spread` window and chunk size
+    pub spread: Option<(Duration, usize)>,
+}
+
+/// API calls a collection run is expected to make
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CallEstimate {
+    /// Login and logout
+    pub session_calls: u64,
+    pub device_pages: u64,
+    /// `:cli` calls of the main command
+    pub cli_chunks: u64,
+    /// `:cli` calls of the detail commands
+    pub detail_calls: u64,
+    pub duration: Duration,
+}
+
+impl CallEstimate {
+    pub fn total(&self) -> u64 {
+        self.session_calls + self.device_pages + self.cli_chunks + self.detail_calls
+    }
+}
+
+/// Estimate the calls and duration of a run over the stored inventory. The
+/// calls are made one after another; a rate limit or a spread window can
+/// only make the run longer.
+pub fn estimate(plan: &RunPlan, budget: &ApiBudget) -> CallEstimate {
+    let devices = plan.inventory.devices.max(0) as u64;
+    let cloud_aps = (plan.inventory.connected_aps - plan.inventory.local_aps).max(0) as u64;
+
+    let device_pages = devices.div_ceil(DEVICE_PAGE_LIMIT as u64).max(1);
+    let (cli_chunks, detail_calls) = match (cloud_aps, plan.spread) {
+        (0, _) => (0, 0),
+        (aps, Some((_, chunk_size))) => (aps.div_ceil(chunk_size.max(1) as u64), plan.detail_commands as u64),
+        (_, None) => (1, plan.detail_commands as u64),
+    };
+
+    let mut estimate = CallEstimate {
+        session_calls: 2,
+        device_pages,
+        cli_chunks,
+        detail_calls,
+        duration: Duration::ZERO,
+    };
+
+    let mut duration = budget.call_time * (estimate.session_calls + device_pages) as u32
+        + budget.cli_time * (cli_chunks + detail_calls) as u32;
+    if let Some(per_minute) = budget.calls_per_minute.filter(|n| *n > 0) {
+        let paced = Duration::from_secs_f64(estimate.total().saturating_sub(1) as f64 * 60.0 / per_minute as f64);
+        duration = duration.max(paced);
+    }
+    if let Some((window, _)) = plan.spread {
+        duration = duration.max(window);
+    }
+    estimate.duration = duration;
+    estimate
+}
+
+/// The reason the run is refused, when it's over budget
+pub fn over_budget(estimate: &CallEstimate, budget: &ApiBudget) -> Option<String> {
+    let max_calls = budget.max_calls?;
+    (estimate.total() > max_calls).then(|| {
+        format!(
+            "Run refused: an estimated {} API calls exceeds the budget of {} ([budget] max_calls)",
+            estimate.total(),
+            max_calls
+        )
+    })
+}
+
+pub fn print_estimate(plan: &RunPlan, estimate: &CallEstimate, budget: &ApiBudget) {
+    println!("\n=== API Call Estimate ===");
+    if plan.inventory.devices == 0 {
+        println!("No devices stored yet; the estimate only covers login and the first device page");
+    } else {
+        println!(
+            "Based on {} stored devices, {} connected APs ({} collected over SSH)",
+            plan.inventory.devices, plan.inventory.connected_aps, plan.inventory.local_aps
+        );
+    }
+    println!("  Login/logout:     {:>6}", estimate.session_calls);
+    println!("  Device pages:     {:>6}", estimate.device_pages);
+    println!("  CLI chunks:       {:>6}", estimate.cli_chunks);
+    println!("  Detail commands:  {:>6}", estimate.detail_calls);
+    println!("  Total:            {:>6}", estimate.total());
+
+    let secs = estimate.duration.as_secs();
+    println!("Expected duration: {}h {:02}m {:02}s", secs / 3600, secs / 60 % 60, secs % 60);
+    if let Some(max_calls) = budget.max_calls {
+        println!("API budget: {} calls per run", max_calls);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let budget = ApiBudget { max_calls: Some(20), ..Default::default() };
+        let mut plan = RunPlan {
+            inventory: InventoryCounts { devices: 250, connected_aps: 180, local_aps: 30 },
+            detail_commands: 2,
+            spread: None,
+        };
+
+        let estimate = estimate(&plan, &budget);
+        assert_eq!((estimate.device_pages, estimate.cli_chunks, estimate.detail_calls), (3, 1, 2));
+        assert_eq!(estimate.total(), 8);
+        assert_eq!(estimate.duration, Duration::from_secs(5 * 2 + 3 * 30));
+        assert_eq!(over_budget(&estimate, &budget), None);
+
+        // 150 cloud APs in chunks of 10, over a 6h window
+        plan.spread = Some((Duration::from_secs(6 * 3600), 10));
+        let spread = super::estimate(&plan, &budget);
+        assert_eq!(spread.cli_chunks, 15);
+        assert_eq!(spread.duration, Duration::from_secs(6 * 3600));
+        assert!(over_budget(&spread, &budget).unwrap().contains("22 API calls exceeds the budget of 20"));
+
+        // A rate limit of 2 calls per minute paces the 8 calls over 3.5 minutes
+        plan.spread = None;
+        let paced = super::estimate(&plan, &ApiBudget { calls_per_minute: Some(2), ..Default::default() });
+        assert_eq!(paced.duration, Duration::from_secs(210));
+
+        let empty = super::estimate(&RunPlan::default(), &budget);
+        assert_eq!((empty.total(), empty.device_pages), (3, 1));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::db::CalendarRun;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::path::Path;
+use std::time::Duration;
+
+/// File written by `calendar export` when `--output` isn't given
+pub const DEFAULT_CALENDAR_FILE: &str = "xiq-calendar.ics";
+
+/// Completed runs in the calendar when not configured
+pub const DEFAULT_CALENDAR_RUNS: i64 = 100;
+
+/// Length of runs without stage timings (stats, config backups)
+const DEFAULT_RUN_LENGTH: Duration = Duration::from_secs(60);
+
+/// Longest content line before it's folded (RFC 5545, in octets)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Contents of the calendar feed, from `[calendar]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarFeed {
+    pub windows: Vec<ScheduledWindow>,
+    /// Completed runs listed, newest first
+    pub runs: i64,
+}
+
+/// Collection window from `[[calendar.windows]]`, times in UTC
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledWindow {
+    pub name: String,
+    pub start: NaiveDateTime,
+    pub duration: Duration,
+    /// iCalendar recurrence rule (`FREQ=DAILY`, `FREQ=WEEKLY;BYDAY=SU`, ...)
+    pub rrule: Option<String>,
+}
+
+/// `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DDTHH:MM[:SS]`, UTC
+pub fn parse_start(value: &str) -> Result<NaiveDateTime> {
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok())
+        .with_context(|| format!("Invalid start '{}' (use YYYY-MM-DD HH:MM)", value))
+}
+
+/// UTC time in iCalendar form, `20240501T020000Z`
+fn ics_time(time: &NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line into CRLF-terminated lines of at most 75 octets,
+/// without splitting a UTF-8 character
+fn push_line(ics: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        // Continuation lines start with a space, which counts
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            ics.push_str("\r\n ");
+            octets = 1;
+        }
+        ics.push(c);
+        octets += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// What a run collected, for its event description
+fn run_description(run: &CalendarRun) -> String {
+    let mut lines = Vec::new();
+    match run.bssids {
+        Some(bssids) => lines.push(format!("{} BSSIDs from {} devices", bssids, run.devices)),
+        None => lines.push(format!("{} devices", run.devices)),
+    }
+    if run.cli_failures > 0 {
+        lines.push(format!("{} CLI failures", run.cli_failures));
+    }
+    if run.events > 0 {
+        lines.push(format!("{} inventory changes", run.events));
+    }
+    if run.millis > 0 {
+        let secs = run.millis / 1000;
+        lines.push(format!("Took {}m {}s", secs / 60, secs % 60));
+    }
+    lines.join("\n")
+}
+
+/// iCalendar feed of the scheduled collection windows and the completed runs
+pub fn render(windows: &[ScheduledWindow], runs: &[CalendarRun], now: DateTime<Utc>) -> String {
+    let stamp = ics_time(&now.naive_utc());
+    let mut ics = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//xiq_cli_tool//BSSID sweeps//EN",
+        "CALSCALE:GREGORIAN",
+        "X-WR-CALNAME:XIQ BSSID sweeps",
+    ] {
+        push_line(&mut ics, line);
+    }
+
+    for (index, window) in windows.iter().enumerate() {
+        let end = window.start + window.duration;
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:window-{}-{}@xiq-cli-tool", index, ics_time(&window.start)));
+        push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut ics, &format!("DTSTART:{}", ics_time(&window.start)));
+        push_line(&mut ics, &format!("DTEND:{}", ics_time(&end)));
+        if let Some(rrule) = &window.rrule {
+            push_line(&mut ics, &format!("RRULE:{}", rrule));
+        }
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&window.name)));
+        push_line(&mut ics, "DESCRIPTION:Scheduled BSSID collection window");
+        push_line(&mut ics, "END:VEVENT");
+    }
+
+    for run in runs {
+        let Ok(start) = NaiveDateTime::parse_from_str(&run.started_at, "%Y-%m-%d %H:%M:%S") else {
+            continue;
+        };
+        let length = match run.millis {
+            0 => DEFAULT_RUN_LENGTH,
+            millis => Duration::from_millis(millis as u64),
+        };
+        let command = run.command.as_deref().unwrap_or("run");
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:run-{}@xiq-cli-tool", run.id));
+        push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut ics, &format!("DTSTART:{}", ics_time(&start)));
+        push_line(&mut ics, &format!("DTEND:{}", ics_time(&(start + length))));
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&format!("XIQ run {}: {}", run.id, command))));
+        push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&run_description(run))));
+        push_line(&mut ics, "END:VEVENT");
+    }
+
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+pub fn write_calendar(windows: &[ScheduledWindow], runs: &[CalendarRun], path: &Path) -> Result<()> {
+    std::fs::write(path, render(windows, runs, Utc::now()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!(
+        "Calendar with {} scheduled windows and {} runs saved to {}",
+        windows.len(),
+        runs.len(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_calendar() {
+        let windows = vec![ScheduledWindow {
+            name: "Nightly sweep, HQ".to_string(),
+            start: parse_start("2024-05-01 02:00").unwrap(),
+            duration: Duration::from_secs(3600),
+            rrule: Some("FREQ=DAILY".to_string()),
+        }];
+        let runs = vec![
+            CalendarRun {
+                id: 42,
+                command: Some("show interface".to_string()),
+                bssids: Some(1234),
+                started_at: "2024-05-02 02:00:05".to_string(),
+                millis: 192_000,
+                devices: 150,
+                cli_failures: 3,
+                events: 0,
+            },
+            CalendarRun { id: 43, started_at: "not a time".to_string(), ..Default::default() },
+        ];
+        let now = DateTime::from_timestamp(1_714_615_200, 0).unwrap();
+
+        let ics = render(&windows, &runs, now);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20240501T020000Z\r\nDTEND:20240501T030000Z\r\nRRULE:FREQ=DAILY\r\n"));
+        assert!(ics.contains("SUMMARY:Nightly sweep\\, HQ\r\n"));
+        assert!(ics.contains("UID:run-42@xiq-cli-tool\r\nDTSTAMP:20240502T020000Z\r\n"));
+        assert!(ics.contains("DTSTART:20240502T020005Z\r\nDTEND:20240502T020317Z\r\n"));
+        assert!(ics.contains("DESCRIPTION:1234 BSSIDs from 150 devices\\n3 CLI failures\\nTook 3m 12s\r\n"));
+        assert!(!ics.contains("run-43"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(parse_start("02:00").is_err());
+    }
+
+    #[test]
+    fn test_fold_long_lines() {
+        let mut ics = String::new();
+        push_line(&mut ics, &format!("SUMMARY:{}", "é".repeat(60)));
+        let lines: Vec<&str> = ics.trim_end().split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines.concat().replacen(' ', "", 1), format!("SUMMARY:{}", "é".repeat(60)));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::db::BssidSetChange;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Churn at or above which an AP's BSSID change is reported
+pub const DEFAULT_BSSID_CHURN_PCT: f64 = 75.0;
+
+/// AP whose BSSIDs changed abnormally between two runs. A complete change
+/// usually means a radio swap, an RMA, or a cloned configuration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChurnedAp {
+    pub device_id: i64,
+    pub hostname: String,
+    pub previous_run_id: i64,
+    pub churn_pct: f64,
+    /// BSSIDs in both runs
+    pub kept: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Percentage of the BSSIDs across both runs that are in only one of them
+/// (0 when nothing changed, 100 when no BSSID was kept)
+pub fn churn_pct(previous: &[String], current: &[String]) -> f64 {
+    let previous: HashSet<_> = previous.iter().collect();
+    let current: HashSet<_> = current.iter().collect();
+    let union = previous.union(&current).count();
+    if union == 0 {
+        return 0.0;
+    }
+    let kept = previous.intersection(&current).count();
+    (union - kept) as f64 / union as f64 * 100.0
+}
+
+/// APs whose churn is at least `min_churn_pct`, worst first
+pub fn churned_aps(changes: &[BssidSetChange], min_churn_pct: f64) -> Vec<ChurnedAp> {
+    let mut churned: Vec<_> = changes
+        .iter()
+        .filter_map(|change| {
+            let churn = churn_pct(&change.previous, &change.current);
+            if churn < min_churn_pct {
+                return None;
+            }
+            let previous: HashSet<_> = change.previous.iter().collect();
+            let current: HashSet<_> = change.current.iter().collect();
+            Some(ChurnedAp {
+                device_id: change.device_id,
+                hostname: change.hostname.clone(),
+                previous_run_id: change.previous_run_id,
+                churn_pct: churn,
+                kept: previous.intersection(&current).count(),
+                added: current.difference(&previous).count(),
+                removed: previous.difference(&current).count(),
+            })
+        })
+        .collect();
+
+    churned.sort_by(|a, b| b.churn_pct.total_cmp(&a.churn_pct).then_with(|| a.hostname.cmp(&b.hostname)));
+    churned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn macs(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_churn() {
+        assert_eq!(churn_pct(&macs(&["a", "b"]), &macs(&["a", "b"])), 0.0);
+        assert_eq!(churn_pct(&macs(&["a", "b"]), &macs(&["c", "d"])), 100.0);
+        assert_eq!(churn_pct(&macs(&["a", "b", "c"]), &macs(&["a", "b", "c", "d"])), 25.0);
+
+        let changes = vec![
+            BssidSetChange {
+                device_id: 1,
+                hostname: "AP-1".into(),
+                previous_run_id: 4,
+                previous: macs(&["a", "b"]),
+                current: macs(&["c", "d"]),
+            },
+            BssidSetChange {
+                device_id: 2,
+                hostname: "AP-2".into(),
+                previous_run_id: 4,
+                previous: macs(&["e", "f", "g"]),
+                current: macs(&["e", "f", "g", "h"]),
+            },
+        ];
+        let churned = churned_aps(&changes, DEFAULT_BSSID_CHURN_PCT);
+        assert_eq!(churned.len(), 1);
+        assert_eq!(churned[0].hostname, "AP-1");
+        assert_eq!((churned[0].kept, churned[0].added, churned[0].removed), (0, 2, 2));
+    }
+}
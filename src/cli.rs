@@ -0,0 +1,1298 @@
+use crate::db::DeviceQuery;
+use crate::events;
+use crate::grafana::Datasource;
+use crate::interrupt::StopReason;
+use crate::lookup;
+use crate::output::LineEnding;
+use crate::query;
+use crate::run_diff::DiffFormat;
+use crate::sink::{SplitBy, TxtLayout, SINK_NAMES};
+use crate::spread;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default CLI command sent to APs when none is given
+pub const DEFAULT_CLI_COMMAND: &str = "show interface";
+
+/// Parsed command line: global options plus the selected action
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    pub global: GlobalOptions,
+    pub command: Command,
+}
+
+/// Options accepted by every subcommand
+#[derive(Debug, Default, PartialEq)]
+pub struct GlobalOptions {
+    /// Database file or directory (overrides XIQ_DB_PATH), or `:memory:`
+    pub db_path: Option<PathBuf>,
+    /// Copy the database to this file when the run finishes
+    pub db_dump: Option<PathBuf>,
+    /// Config file (overrides XIQ_CONFIG and ./xiq.toml)
+    pub config: Option<PathBuf>,
+    /// Named profile from the config file (overrides XIQ_PROFILE)
+    pub profile: Option<String>,
+    /// Print progress events as JSON lines on stderr (`--progress json`)
+    pub progress_json: bool,
+}
+
+/// Top-level action selected from the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Fetch devices and run a CLI command on all connected APs; `collect`
+    /// is a run that only stores its results for `export`
+    Run(RunOptions),
+    /// Write the output files of a collected run from the database
+    Export(ExportOptions),
+    /// Trigger the locate (LED blink) action on a single device
+    Locate { hostname: String },
+    /// Reboot explicitly listed devices (IDs or hostnames)
+    DeviceReboot { targets: Vec<String> },
+    /// Report devices in config mismatch, optionally pushing config to them.
+    /// `push` is `Some` when `--push` was given; an empty list means every mismatched device.
+    ConfigMismatch { push: Option<Vec<String>> },
+    /// Back up the running configuration of every connected device
+    ConfigBackup,
+    /// Diff a device's stored configurations from two backup runs
+    ConfigDiff { device: String, run_a: i64, run_b: i64 },
+    /// Print the raw CLI output a device (ID or hostname) returned, from one
+    /// run or the latest run that kept it
+    RawShow { device: String, run: Option<i64> },
+    /// Print the JSON Schema of one output file, or all of them
+    Schema { name: Option<String> },
+    /// Import other database files into the devices and interfaces tables
+    DbMerge { sources: Vec<MergeSource> },
+    /// Serve the web UI and JSON API over the database
+    Serve { listen: String, allow_anonymous_read: bool },
+    /// Poll traffic and client counters of every access interface
+    Stats,
+    /// Attach a local note to a stored device (ID or hostname)
+    NoteAdd { device: String, text: String },
+    /// List the notes of one device, or of every device
+    NoteList { device: Option<String> },
+    /// Poll the device list and refresh the BSSIDs of APs that connect or are added
+    Subscribe { interval: Duration },
+    /// Report recently rebooted and stale devices from the database
+    Health { rebooted_within: Duration, stale_after: Duration },
+    /// Compare the devices and BSSIDs of two recorded runs. `output` is the
+    /// HTML file, when not the default name.
+    ReportDiff { from: i64, to: i64, format: DiffFormat, output: Option<PathBuf> },
+    /// Load a devices JSON or CSV export into the database, without the API
+    ImportDevices { path: PathBuf },
+    /// Find the AP, SSID and site of a BSSID in the database. `mac` is normalized.
+    Lookup { mac: String },
+    /// Look up every MAC found in a file and write a matched/unmatched
+    /// report. `output` is the report file, when not the default name.
+    LookupFile { path: PathBuf, output: Option<PathBuf> },
+    /// Write a Grafana dashboard charting BSSIDs per SSID and site over time.
+    /// `output` is the dashboard file, when not the default name.
+    GrafanaExport { datasource: Datasource, output: Option<PathBuf> },
+    /// List stored devices matching the filters, including JSON path
+    /// conditions on their unmapped API fields
+    QueryDevices { query: DeviceQuery },
+    /// Write an iCalendar file of the scheduled collection windows and the
+    /// completed runs. `output` is the file, when not the default name;
+    /// `runs` overrides the configured number of runs.
+    CalendarExport { output: Option<PathBuf>, runs: Option<i64> },
+    /// List the most recent inventory change events
+    EventsTail { limit: i64 },
+    /// List the inventory change events since a date. `since` is normalized
+    /// to `YYYY-MM-DD HH:MM:SS`.
+    EventsSince { since: String },
+}
+
+/// Database file to merge and the XIQ account (`owner_id`) its rows are saved
+/// for; `None` keeps the owner the source saved them for
+#[derive(Debug, PartialEq)]
+pub struct MergeSource {
+    pub owner_id: Option<i64>,
+    pub path: PathBuf,
+}
+
+impl MergeSource {
+    /// `owner_id=path`, or just `path`
+    fn parse(arg: &str) -> Result<Self> {
+        match arg.split_once('=') {
+            Some((owner_id, path)) => Ok(Self {
+                owner_id: Some(owner_id.parse().with_context(|| format!("Invalid owner_id in '{}'", arg))?),
+                path: PathBuf::from(path),
+            }),
+            None => Ok(Self { owner_id: None, path: PathBuf::from(arg) }),
+        }
+    }
+}
+
+/// Options for the default collection run
+#[derive(Debug, PartialEq)]
+pub struct RunOptions {
+    pub cli_command: String,
+    /// Custom report template rendered after collection
+    pub template: Option<PathBuf>,
+    /// Where the rendered template is written
+    pub template_output: Option<PathBuf>,
+    /// Line ending for text output files
+    pub line_ending: LineEnding,
+    /// Save devices and interfaces to the database
+    pub db: bool,
+    /// Write the output files (devices.json, full_cli.json, bssids/wifi-bssids)
+    pub files: bool,
+    /// Write devices.json
+    pub device_export: bool,
+    /// Fail the run when fewer BSSIDs than this are collected
+    pub min_bssids: Option<usize>,
+    /// Fail the run when the BSSID count drops by more than this percentage vs the previous run
+    pub max_bssid_drop: Option<f64>,
+    /// CSV header profile from the config file
+    pub csv_profile: Option<String>,
+    /// Also write the BSSID exports as one file per site, hostname prefix or AP
+    pub split_by: Option<SplitBy>,
+    /// Stop collecting after this long and save what was collected
+    pub max_duration: Option<Duration>,
+    /// Write the csv/txt/json exports device by device while parsing
+    pub stream: bool,
+    /// Only collect from devices that weren't in the database before this run
+    pub only_new: bool,
+    /// Layout of wifi-bssids.txt
+    pub txt_layout: TxtLayout,
+    /// Sinks run in addition to `output.sinks` (`--format`)
+    pub formats: Vec<String>,
+    /// Send the CLI command in chunks spread evenly over this window,
+    /// resuming an unfinished spread collection of the same command
+    pub spread: Option<Duration>,
+    /// APs per chunk with `spread`
+    pub chunk_size: usize,
+    /// Print the API call estimate and stop before logging in
+    pub dry_run: bool,
+    /// Only collect from these devices (IDs or hostnames), from `--targets`
+    pub targets: Vec<String>,
+    /// Store the devices and parsed results in the database for `export` (`collect`)
+    pub snapshot: bool,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            cli_command: DEFAULT_CLI_COMMAND.to_string(),
+            template: None,
+            template_output: None,
+            line_ending: LineEnding::Lf,
+            db: true,
+            files: true,
+            device_export: true,
+            min_bssids: None,
+            max_bssid_drop: None,
+            csv_profile: None,
+            split_by: None,
+            max_duration: None,
+            stream: false,
+            only_new: false,
+            txt_layout: TxtLayout::Grouped,
+            formats: Vec::new(),
+            spread: None,
+            chunk_size: spread::DEFAULT_CHUNK_SIZE,
+            dry_run: false,
+            targets: Vec::new(),
+            snapshot: false,
+        }
+    }
+}
+
+impl RunOptions {
+    /// Only some of the devices are collected (`--only-new` or `--targets`)
+    pub fn is_partial(&self) -> bool {
+        self.only_new || !self.targets.is_empty()
+    }
+
+    /// The results leave out devices a full run would have: a partial run,
+    /// or one stopped early by Ctrl-C or `--max-duration`
+    pub fn is_incomplete(&self, stop_reason: Option<StopReason>) -> bool {
+        self.is_partial() || stop_reason.is_some()
+    }
+}
+
+/// Options of `export`
+#[derive(Debug, Default, PartialEq)]
+pub struct ExportOptions {
+    /// Run to export; the latest collected run when unset
+    pub run: Option<i64>,
+    /// Sinks run in addition to `output.sinks` (`--format`)
+    pub formats: Vec<String>,
+    pub split_by: Option<SplitBy>,
+    pub txt_layout: TxtLayout,
+    pub line_ending: LineEnding,
+    pub csv_profile: Option<String>,
+    /// Parse the stored raw output again with the current parsers
+    pub reparse: bool,
+}
+
+/// Split `--flag=value` into the flag and its inline value
+fn split_flag(arg: &str) -> (&str, Option<&str>) {
+    match arg.split_once('=') {
+        Some((flag, value)) => (flag, Some(value)),
+        None => (arg, None),
+    }
+}
+
+/// Non-empty lines that aren't `#` comments
+fn list_entries(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Entries of a list file, one per line, or of stdin when the path is `-`
+fn read_list(flag: &str, path: &str) -> Result<Vec<String>> {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin()).with_context(|| format!("Failed to read {} from stdin", flag))?
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {} file {}", flag, path))?
+    };
+    let entries = list_entries(&text);
+    if entries.is_empty() {
+        anyhow::bail!("{} {} is empty", flag, if path == "-" { "stdin" } else { path });
+    }
+    Ok(entries)
+}
+
+/// Duration such as `90s`, `30m`, `2h` or `1h30m`; a bare number is seconds
+fn parse_duration(value: &str) -> Result<Duration> {
+    let mut total = 0;
+    let mut digits = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => anyhow::bail!("Unknown duration unit '{}' (use s, m, h or d)", c),
+        };
+        let amount: u64 = digits.parse().context("Expected a number before each unit")?;
+        total += amount * unit;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        total += digits.parse::<u64>()?;
+    }
+    if total == 0 {
+        anyhow::bail!("Duration must be greater than zero");
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Value for a flag, either inline (`--flag=value`) or the next argument
+fn flag_value(flag: &str, inline: Option<&str>, iter: &mut std::slice::Iter<String>) -> Result<String> {
+    match inline {
+        Some(v) => Ok(v.to_string()),
+        None => iter
+            .next()
+            .cloned()
+            .with_context(|| format!("{} requires a value", flag)),
+    }
+}
+
+/// Parse command line arguments (excluding the program name)
+///
+/// Global options may appear anywhere. Anything that isn't a known subcommand
+/// is treated as the CLI command to send to the APs, so
+/// `xiq_cli_tool show interface wifi0` keeps working.
+pub fn parse_args(args: &[String]) -> Result<Cli> {
+    let mut global = GlobalOptions::default();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--db-path" | "--db" => global.db_path = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            "--config" => global.config = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            "--db-dump" => global.db_dump = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            "--profile" => global.profile = Some(flag_value(flag, inline, &mut iter)?),
+            "--progress" => match flag_value(flag, inline, &mut iter)?.as_str() {
+                "json" => global.progress_json = true,
+                other => anyhow::bail!("Unknown --progress format '{}' (available: json)", other),
+            },
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    let command = parse_command(&rest)?;
+
+    Ok(Cli { global, command })
+}
+
+fn parse_command(args: &[String]) -> Result<Command> {
+    match args.first().map(|s| s.as_str()) {
+        Some("locate") => {
+            let hostname = args[1..].join(" ");
+            if hostname.trim().is_empty() {
+                anyhow::bail!("Usage: locate <hostname>");
+            }
+            Ok(Command::Locate { hostname })
+        }
+        Some("collect") => parse_collect_args(&args[1..]).map(Command::Run),
+        Some("export") => parse_export_args(&args[1..]),
+        Some("device") => parse_device_args(&args[1..]),
+        Some("config") => parse_config_args(&args[1..]),
+        Some("raw") => parse_raw_args(&args[1..]),
+        Some("db") => match args.get(1).map(|s| s.as_str()) {
+            Some("merge") if args.len() > 2 => Ok(Command::DbMerge {
+                sources: args[2..].iter().map(|a| MergeSource::parse(a)).collect::<Result<_>>()?,
+            }),
+            _ => anyhow::bail!("Usage: db merge [<owner_id>=]<file>..."),
+        },
+        Some("serve") => parse_serve_args(&args[1..]),
+        Some("note") => parse_note_args(&args[1..]),
+        Some("subscribe") => parse_subscribe_args(&args[1..]),
+        Some("health") => parse_health_args(&args[1..]),
+        Some("report") => parse_report_args(&args[1..]),
+        Some("lookup") => parse_lookup_args(&args[1..]),
+        Some("grafana") => parse_grafana_args(&args[1..]),
+        Some("query") => parse_query_args(&args[1..]),
+        Some("events") => parse_events_args(&args[1..]),
+        Some("calendar") => parse_calendar_args(&args[1..]),
+        Some("import") => match &args[1..] {
+            [kind, path] if kind == "devices" => Ok(Command::ImportDevices { path: PathBuf::from(path) }),
+            _ => anyhow::bail!("Usage: import devices <file.json|file.csv>"),
+        },
+        Some("stats") => match &args[1..] {
+            [] => Ok(Command::Stats),
+            _ => anyhow::bail!("Usage: stats"),
+        },
+        Some("schema") => match &args[1..] {
+            [] => Ok(Command::Schema { name: None }),
+            [name] => Ok(Command::Schema { name: Some(name.clone()) }),
+            _ => anyhow::bail!("Usage: schema [devices|full_cli|wifi-bssids]"),
+        },
+        _ => parse_run_args(args).map(Command::Run),
+    }
+}
+
+/// Split `--flag value` / `--flag=value` pairs from the words of the CLI command
+fn parse_run_args(args: &[String]) -> Result<RunOptions> {
+    let mut options = RunOptions::default();
+    let mut words = Vec::new();
+    let mut commands = Vec::new();
+    let mut stdin_flag = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with("--") {
+            words.push(arg.clone());
+            continue;
+        }
+
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--crlf" => options.line_ending = LineEnding::Crlf,
+            "--no-db" => options.db = false,
+            "--no-files" => options.files = false,
+            "--no-device-export" => options.device_export = false,
+            "--stream" => options.stream = true,
+            "--only-new" => options.only_new = true,
+            "--dry-run" => options.dry_run = true,
+            "--flat-txt" => options.txt_layout = TxtLayout::Flat,
+            "--min-bssids" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                options.min_bssids = Some(value.parse().with_context(|| format!("Invalid --min-bssids: {}", value))?);
+            }
+            "--max-bssid-drop" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                let pct = value.trim_end_matches('%');
+                options.max_bssid_drop = Some(pct.parse().with_context(|| format!("Invalid --max-bssid-drop: {}", value))?);
+            }
+            "--csv-profile" => options.csv_profile = Some(flag_value(flag, inline, &mut iter)?),
+            "--format" => {
+                let format = flag_value(flag, inline, &mut iter)?;
+                if !SINK_NAMES.contains(&format.as_str()) {
+                    anyhow::bail!("Unknown --format '{}' (available: {})", format, SINK_NAMES.join(", "));
+                }
+                options.formats.push(format);
+            }
+            "--split-by" => options.split_by = Some(SplitBy::parse(&flag_value(flag, inline, &mut iter)?)?),
+            "--max-duration" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                options.max_duration = Some(parse_duration(&value).with_context(|| format!("Invalid --max-duration: {}", value))?);
+            }
+            "--spread" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                options.spread = Some(parse_duration(&value).with_context(|| format!("Invalid --spread: {}", value))?);
+            }
+            "--chunk-size" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                options.chunk_size = value
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .with_context(|| format!("Invalid --chunk-size: {}", value))?;
+            }
+            "--targets" | "--command-file" => {
+                let path = flag_value(flag, inline, &mut iter)?;
+                if path == "-" {
+                    // stdin can only be read once
+                    if let Some(first) = stdin_flag.replace(flag) {
+                        anyhow::bail!("{} and {} can't both read stdin", first, flag);
+                    }
+                }
+                let entries = read_list(flag, &path)?;
+                match flag {
+                    "--targets" => options.targets.extend(entries),
+                    _ => commands.extend(entries),
+                }
+            }
+            "--template" => options.template = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            "--template-output" => {
+                options.template_output = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?))
+            }
+            _ => anyhow::bail!("Unknown option: {}", flag),
+        }
+    }
+
+    // A command set is sent to each AP in one `:cli` call, one command per line
+    match (words.is_empty(), commands.is_empty()) {
+        (false, false) => anyhow::bail!("--command-file can't be combined with a command on the command line"),
+        (false, true) => options.cli_command = words.join(" "),
+        (true, false) => options.cli_command = commands.join("\n"),
+        (true, true) => {}
+    }
+
+    // New devices are found by comparing with the database, and a partial
+    // collection can't be held to fleet-wide BSSID counts
+    if options.only_new && !options.db {
+        anyhow::bail!("--only-new can't be combined with --no-db");
+    }
+    if options.is_partial() && (options.min_bssids.is_some() || options.max_bssid_drop.is_some()) {
+        let flag = if options.only_new { "--only-new" } else { "--targets" };
+        anyhow::bail!("{} can't be combined with --min-bssids or --max-bssid-drop", flag);
+    }
+
+    // Spread progress is kept in the database so the collection can resume
+    if options.spread.is_some() && !options.db {
+        anyhow::bail!("--spread can't be combined with --no-db");
+    }
+    if options.chunk_size != spread::DEFAULT_CHUNK_SIZE && options.spread.is_none() {
+        anyhow::bail!("--chunk-size only applies with --spread");
+    }
+
+    Ok(options)
+}
+
+fn parse_serve_args(args: &[String]) -> Result<Command> {
+    let mut listen = crate::server::DEFAULT_LISTEN.to_string();
+    let mut allow_anonymous_read = false;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--listen" => listen = flag_value(flag, inline, &mut iter)?,
+            "--allow-anonymous-read" => allow_anonymous_read = true,
+            _ => anyhow::bail!("Usage: serve [--listen <addr:port>] [--allow-anonymous-read]"),
+        }
+    }
+
+    Ok(Command::Serve { listen, allow_anonymous_read })
+}
+
+fn parse_device_args(args: &[String]) -> Result<Command> {
+    match args.first().map(|s| s.as_str()) {
+        Some("reboot") => {
+            let mut confirmed = false;
+            let mut targets = Vec::new();
+            let mut iter = args[1..].iter();
+            while let Some(arg) = iter.next() {
+                let (flag, inline) = split_flag(arg);
+                match flag {
+                    "--yes" => confirmed = true,
+                    "--targets" => targets.extend(read_list(flag, &flag_value(flag, inline, &mut iter)?)?),
+                    _ => targets.push(arg.clone()),
+                }
+            }
+
+            if targets.is_empty() {
+                anyhow::bail!("Usage: device reboot <id|hostname>... [--targets <file|->] --yes");
+            }
+            if !confirmed {
+                anyhow::bail!(
+                    "Refusing to reboot {} device(s) without --yes",
+                    targets.len()
+                );
+            }
+
+            Ok(Command::DeviceReboot { targets })
+        }
+        _ => anyhow::bail!("Usage: device reboot <id|hostname>... [--targets <file|->] --yes"),
+    }
+}
+
+fn parse_subscribe_args(args: &[String]) -> Result<Command> {
+    let mut interval = crate::subscribe::DEFAULT_POLL_INTERVAL;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--interval" => interval = parse_duration(&flag_value(flag, inline, &mut iter)?)?,
+            _ => anyhow::bail!("Usage: subscribe [--interval <duration>]"),
+        }
+    }
+
+    Ok(Command::Subscribe { interval })
+}
+
+fn parse_health_args(args: &[String]) -> Result<Command> {
+    let mut rebooted_within = crate::health::DEFAULT_REBOOTED_WITHIN;
+    let mut stale_after = crate::health::DEFAULT_STALE_AFTER;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--rebooted-within" => rebooted_within = parse_duration(&flag_value(flag, inline, &mut iter)?)?,
+            "--stale-after" => stale_after = parse_duration(&flag_value(flag, inline, &mut iter)?)?,
+            _ => anyhow::bail!("Usage: health [--rebooted-within <duration>] [--stale-after <duration>]"),
+        }
+    }
+
+    Ok(Command::Health { rebooted_within, stale_after })
+}
+
+/// `collect` takes the run flags but writes no files; its results are kept
+/// in the database until `export` writes them
+fn parse_collect_args(args: &[String]) -> Result<RunOptions> {
+    let mut options = parse_run_args(args)?;
+    if !options.db {
+        anyhow::bail!("collect stores its results in the database, so it can't be combined with --no-db");
+    }
+    if options.stream {
+        anyhow::bail!("collect writes no files, so it can't be combined with --stream");
+    }
+    if !options.formats.is_empty() || options.split_by.is_some() {
+        anyhow::bail!("collect writes no files; pass --format and --split-by to export instead");
+    }
+    options.files = false;
+    options.snapshot = true;
+    Ok(options)
+}
+
+fn parse_export_args(args: &[String]) -> Result<Command> {
+    let mut options = ExportOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--run" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                options.run = Some(value.parse().with_context(|| format!("Invalid run ID: {}", value))?);
+            }
+            "--format" => {
+                let format = flag_value(flag, inline, &mut iter)?;
+                if !SINK_NAMES.contains(&format.as_str()) || format == "db" {
+                    anyhow::bail!("Unknown --format '{}' (available: {})", format, export_sinks().join(", "));
+                }
+                options.formats.push(format);
+            }
+            "--split-by" => options.split_by = Some(SplitBy::parse(&flag_value(flag, inline, &mut iter)?)?),
+            "--flat-txt" => options.txt_layout = TxtLayout::Flat,
+            "--crlf" => options.line_ending = LineEnding::Crlf,
+            "--csv-profile" => options.csv_profile = Some(flag_value(flag, inline, &mut iter)?),
+            "--reparse" => options.reparse = true,
+            _ => anyhow::bail!(
+                "Usage: export [--run <id>] [--format <sink>]... [--split-by <group>] [--flat-txt] [--crlf] [--csv-profile <name>] [--reparse]"
+            ),
+        }
+    }
+    Ok(Command::Export(options))
+}
+
+/// Sinks `export` can write; the database is where it reads from
+fn export_sinks() -> Vec<&'static str> {
+    SINK_NAMES.iter().copied().filter(|name| *name != "db").collect()
+}
+
+fn parse_raw_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: raw show <id|hostname> [--run <id>]";
+
+    if args.first().map(|s| s.as_str()) != Some("show") {
+        anyhow::bail!(USAGE);
+    }
+
+    let (mut device, mut run) = (None, None);
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--run" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                run = Some(value.parse().with_context(|| format!("Invalid run ID: {}", value))?);
+            }
+            _ if !arg.starts_with("--") && device.is_none() => device = Some(arg.clone()),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    match device {
+        Some(device) => Ok(Command::RawShow { device, run }),
+        None => anyhow::bail!(USAGE),
+    }
+}
+
+fn parse_report_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: report diff --from <run> --to <run> [--format text|html] [--output <file>]";
+
+    if args.first().map(|s| s.as_str()) != Some("diff") {
+        anyhow::bail!(USAGE);
+    }
+
+    let (mut from, mut to, mut format, mut output) = (None, None, DiffFormat::Text, None);
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--from" | "--to" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                let run: i64 = value.parse().with_context(|| format!("Invalid run ID: {}", value))?;
+                if flag == "--from" {
+                    from = Some(run);
+                } else {
+                    to = Some(run);
+                }
+            }
+            "--format" => format = DiffFormat::parse(&flag_value(flag, inline, &mut iter)?)?,
+            "--output" => output = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    match (from, to) {
+        (Some(from), Some(to)) => Ok(Command::ReportDiff { from, to, format, output }),
+        _ => anyhow::bail!(USAGE),
+    }
+}
+
+fn parse_lookup_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: lookup <mac> | lookup --file <macs.txt> [--output <report.csv>]";
+
+    let (mut mac, mut path, mut output) = (None, None, None);
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--file" => path = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            "--output" => output = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            _ if !arg.starts_with("--") && mac.is_none() => mac = Some(lookup::parse_mac(arg)?),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    match (mac, path) {
+        (Some(mac), None) if output.is_none() => Ok(Command::Lookup { mac }),
+        (None, Some(path)) => Ok(Command::LookupFile { path, output }),
+        _ => anyhow::bail!(USAGE),
+    }
+}
+
+fn parse_grafana_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: grafana export-dashboards [--datasource sqlite|postgres] [--output <file>]";
+
+    if args.first().map(|s| s.as_str()) != Some("export-dashboards") {
+        anyhow::bail!(USAGE);
+    }
+
+    let (mut datasource, mut output) = (Datasource::Sqlite, None);
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--datasource" => datasource = Datasource::parse(&flag_value(flag, inline, &mut iter)?)?,
+            "--output" => output = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    Ok(Command::GrafanaExport { datasource, output })
+}
+
+fn parse_query_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str =
+        "Usage: query devices [--hostname <text>] [--function <ap|switch>] [--owner <id>] [--where <path>[=<value>]]... [--limit <n>]";
+
+    if args.first().map(|s| s.as_str()) != Some("devices") {
+        anyhow::bail!(USAGE);
+    }
+
+    let mut query = DeviceQuery::default();
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--hostname" => query.hostname = Some(flag_value(flag, inline, &mut iter)?),
+            "--function" => query.device_function = Some(flag_value(flag, inline, &mut iter)?),
+            "--owner" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                query.owner_id = Some(value.parse().with_context(|| format!("Invalid --owner: {}", value))?);
+            }
+            "--where" => query.json_filters.push(query::parse_json_filter(&flag_value(flag, inline, &mut iter)?)?),
+            "--limit" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                query.limit = Some(value.parse().with_context(|| format!("Invalid --limit: {}", value))?);
+            }
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    Ok(Command::QueryDevices { query })
+}
+
+fn parse_calendar_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: calendar export [--output <file.ics>] [--runs <n>]";
+
+    if args.first().map(|s| s.as_str()) != Some("export") {
+        anyhow::bail!(USAGE);
+    }
+
+    let (mut output, mut runs) = (None, None);
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--output" => output = Some(PathBuf::from(flag_value(flag, inline, &mut iter)?)),
+            "--runs" => {
+                let value = flag_value(flag, inline, &mut iter)?;
+                runs = Some(value.parse().with_context(|| format!("Invalid --runs: {}", value))?);
+            }
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    Ok(Command::CalendarExport { output, runs })
+}
+
+fn parse_events_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: events tail [--limit <n>] | events since <date>";
+
+    match args.first().map(|s| s.as_str()) {
+        Some("since") => match &args[1..] {
+            [] => anyhow::bail!(USAGE),
+            date => Ok(Command::EventsSince { since: events::parse_since(&date.join(" "))? }),
+        },
+        Some("tail") => {
+            let mut limit = events::DEFAULT_TAIL_LIMIT;
+            let mut iter = args[1..].iter();
+            while let Some(arg) = iter.next() {
+                let (flag, inline) = split_flag(arg);
+                match flag {
+                    "--limit" => {
+                        let value = flag_value(flag, inline, &mut iter)?;
+                        limit = value.parse().with_context(|| format!("Invalid --limit: {}", value))?;
+                    }
+                    _ => anyhow::bail!(USAGE),
+                }
+            }
+            Ok(Command::EventsTail { limit })
+        }
+        _ => anyhow::bail!(USAGE),
+    }
+}
+
+fn parse_note_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: note add <id|hostname> \"text\" | note list [<id|hostname>]";
+
+    match args {
+        [action, device, text @ ..] if action == "add" && !text.is_empty() => {
+            let text = text.join(" ");
+            if text.trim().is_empty() {
+                anyhow::bail!(USAGE);
+            }
+            Ok(Command::NoteAdd { device: device.clone(), text })
+        }
+        [action] if action == "list" => Ok(Command::NoteList { device: None }),
+        [action, device] if action == "list" => Ok(Command::NoteList { device: Some(device.clone()) }),
+        _ => anyhow::bail!(USAGE),
+    }
+}
+
+fn parse_config_args(args: &[String]) -> Result<Command> {
+    const USAGE: &str = "Usage: config mismatch [--push [<id|hostname>...] --yes] | config backup | config diff <device> <runA> <runB>";
+
+    match args.first().map(|s| s.as_str()) {
+        Some("backup") if args.len() == 1 => Ok(Command::ConfigBackup),
+        Some("diff") => match &args[1..] {
+            [device, run_a, run_b] => Ok(Command::ConfigDiff {
+                device: device.clone(),
+                run_a: run_a.parse().with_context(|| format!("Invalid run ID: {}", run_a))?,
+                run_b: run_b.parse().with_context(|| format!("Invalid run ID: {}", run_b))?,
+            }),
+            _ => anyhow::bail!("Usage: config diff <device> <runA> <runB>"),
+        },
+        Some("mismatch") => {
+            let rest = &args[1..];
+            let push = rest.iter().any(|a| a == "--push");
+            let confirmed = rest.iter().any(|a| a == "--yes");
+            let targets: Vec<String> = rest
+                .iter()
+                .filter(|a| a.as_str() != "--push" && a.as_str() != "--yes")
+                .cloned()
+                .collect();
+
+            if let Some(unknown) = targets.iter().find(|a| a.starts_with("--")) {
+                anyhow::bail!("Unknown option: {}", unknown);
+            }
+            if !push {
+                if !targets.is_empty() {
+                    anyhow::bail!(USAGE);
+                }
+                return Ok(Command::ConfigMismatch { push: None });
+            }
+            if !confirmed {
+                anyhow::bail!("Refusing to push configuration without --yes");
+            }
+
+            Ok(Command::ConfigMismatch { push: Some(targets) })
+        }
+        _ => anyhow::bail!(USAGE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn command(list: &[&str]) -> Result<Command> {
+        parse_args(&args(list)).map(|cli| cli.command)
+    }
+
+    #[test]
+    fn test_default_command() {
+        assert_eq!(command(&[]).unwrap(), Command::Run(RunOptions::default()));
+        assert_eq!(
+            command(&["show", "interface", "wifi0"]).unwrap(),
+            Command::Run(RunOptions {
+                cli_command: "show interface wifi0".to_string(),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_flags() {
+        assert_eq!(
+            command(&["show", "interface", "--template", "report.html.j2", "--template-output=out.html"]).unwrap(),
+            Command::Run(RunOptions {
+                cli_command: "show interface".to_string(),
+                template: Some(PathBuf::from("report.html.j2")),
+                template_output: Some(PathBuf::from("out.html")),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            command(&["--crlf"]).unwrap(),
+            Command::Run(RunOptions { line_ending: LineEnding::Crlf, ..Default::default() })
+        );
+        assert_eq!(
+            command(&["--no-db", "--no-files", "--no-device-export"]).unwrap(),
+            Command::Run(RunOptions { db: false, files: false, device_export: false, ..Default::default() })
+        );
+        assert_eq!(
+            command(&["--min-bssids", "500", "--max-bssid-drop=25%"]).unwrap(),
+            Command::Run(RunOptions { min_bssids: Some(500), max_bssid_drop: Some(25.0), ..Default::default() })
+        );
+        assert!(command(&["--min-bssids", "many"]).is_err());
+        assert_eq!(
+            command(&["--csv-profile", "dnac"]).unwrap(),
+            Command::Run(RunOptions { csv_profile: Some("dnac".to_string()), ..Default::default() })
+        );
+        assert_eq!(
+            command(&["--split-by=site"]).unwrap(),
+            Command::Run(RunOptions { split_by: Some(SplitBy::Site), ..Default::default() })
+        );
+        assert_eq!(
+            command(&["--split-by", "policy"]).unwrap(),
+            Command::Run(RunOptions { split_by: Some(SplitBy::Policy), ..Default::default() })
+        );
+        assert!(command(&["--split-by", "floor"]).is_err());
+        assert_eq!(
+            command(&["--max-duration", "30m"]).unwrap(),
+            Command::Run(RunOptions { max_duration: Some(Duration::from_secs(1800)), ..Default::default() })
+        );
+        assert!(command(&["--max-duration", "soon"]).is_err());
+        assert_eq!(
+            command(&["--spread", "6h", "--chunk-size=250"]).unwrap(),
+            Command::Run(RunOptions { spread: Some(Duration::from_secs(6 * 3600)), chunk_size: 250, ..Default::default() })
+        );
+        assert!(command(&["--spread", "6h", "--no-db"]).is_err());
+        assert!(command(&["--chunk-size", "250"]).is_err());
+        assert!(command(&["--spread", "6h", "--chunk-size", "0"]).is_err());
+        assert_eq!(
+            command(&["--stream"]).unwrap(),
+            Command::Run(RunOptions { stream: true, ..Default::default() })
+        );
+        assert_eq!(
+            command(&["--only-new"]).unwrap(),
+            Command::Run(RunOptions { only_new: true, ..Default::default() })
+        );
+        assert!(command(&["--only-new", "--no-db"]).is_err());
+        assert_eq!(
+            command(&["--dry-run", "--spread", "6h"]).unwrap(),
+            Command::Run(RunOptions { dry_run: true, spread: Some(Duration::from_secs(6 * 3600)), ..Default::default() })
+        );
+        assert_eq!(
+            command(&["--format", "ansible-inventory"]).unwrap(),
+            Command::Run(RunOptions { formats: vec!["ansible-inventory".to_string()], ..Default::default() })
+        );
+        assert!(command(&["--format", "yaml"]).is_err());
+        assert_eq!(
+            command(&["--flat-txt"]).unwrap(),
+            Command::Run(RunOptions { txt_layout: TxtLayout::Flat, ..Default::default() })
+        );
+        assert!(command(&["--only-new", "--min-bssids", "100"]).is_err());
+        assert!(command(&["--template"]).is_err());
+        assert!(command(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_targets_and_command_file() {
+        let dir = std::env::temp_dir().join(format!("xiq-cli-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = dir.join("targets.txt");
+        let commands = dir.join("commands.txt");
+        std::fs::write(&targets, "# lobby\nAP-Lobby\n\n  12345  \n").unwrap();
+        std::fs::write(&commands, "show interface\nshow station\n").unwrap();
+        let targets = targets.to_str().unwrap();
+        let commands = commands.to_str().unwrap();
+
+        assert_eq!(
+            command(&["--targets", targets, "--command-file", commands]).unwrap(),
+            Command::Run(RunOptions {
+                targets: vec!["AP-Lobby".to_string(), "12345".to_string()],
+                cli_command: "show interface\nshow station".to_string(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            command(&["device", "reboot", "AP-2", &format!("--targets={}", targets), "--yes"]).unwrap(),
+            Command::DeviceReboot {
+                targets: vec!["AP-2".to_string(), "AP-Lobby".to_string(), "12345".to_string()]
+            }
+        );
+        assert!(command(&["show", "station", "--command-file", commands]).is_err());
+        assert!(command(&["--targets", targets, "--max-bssid-drop", "10"]).is_err());
+        assert!(command(&["--targets", dir.join("missing.txt").to_str().unwrap()]).is_err());
+        assert!(list_entries("# only a comment\n\n").is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_collect_and_export() {
+        assert_eq!(
+            command(&["collect", "--only-new"]).unwrap(),
+            Command::Run(RunOptions { files: false, snapshot: true, only_new: true, ..Default::default() })
+        );
+        assert!(command(&["collect", "--no-db"]).is_err());
+        assert!(command(&["collect", "--format", "xlsx"]).is_err());
+        let stream = command(&["collect", "--stream"]).unwrap_err().to_string();
+        assert!(!stream.contains("export"), "{}", stream);
+
+        assert_eq!(command(&["export"]).unwrap(), Command::Export(ExportOptions::default()));
+        assert_eq!(
+            command(&["export", "--run=12", "--format", "xlsx", "--split-by", "site", "--reparse"]).unwrap(),
+            Command::Export(ExportOptions {
+                run: Some(12),
+                formats: vec!["xlsx".to_string()],
+                split_by: Some(SplitBy::Site),
+                reparse: true,
+                ..Default::default()
+            })
+        );
+        assert!(command(&["export", "--format", "db"]).is_err());
+        assert!(command(&["export", "--run", "latest"]).is_err());
+    }
+
+    #[test]
+    fn test_raw_show() {
+        assert_eq!(
+            command(&["raw", "show", "AP-1"]).unwrap(),
+            Command::RawShow { device: "AP-1".to_string(), run: None }
+        );
+        assert_eq!(
+            command(&["raw", "show", "--run=7", "42"]).unwrap(),
+            Command::RawShow { device: "42".to_string(), run: Some(7) }
+        );
+        assert!(command(&["raw", "show"]).is_err());
+        assert!(command(&["raw", "show", "AP-1", "AP-2"]).is_err());
+        assert!(command(&["raw", "list"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5 weeks").is_err());
+    }
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(
+            command(&["locate", "AP-Lobby"]).unwrap(),
+            Command::Locate { hostname: "AP-Lobby".to_string() }
+        );
+        assert!(command(&["locate"]).is_err());
+    }
+
+    #[test]
+    fn test_device_reboot_requires_yes() {
+        assert!(command(&["device", "reboot", "AP-1"]).is_err());
+        assert!(command(&["device", "reboot", "--yes"]).is_err());
+        assert_eq!(
+            command(&["device", "reboot", "AP-1", "12345", "--yes"]).unwrap(),
+            Command::DeviceReboot {
+                targets: vec!["AP-1".to_string(), "12345".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_mismatch() {
+        assert_eq!(command(&["config", "mismatch"]).unwrap(), Command::ConfigMismatch { push: None });
+        assert_eq!(
+            command(&["config", "mismatch", "--push", "--yes"]).unwrap(),
+            Command::ConfigMismatch { push: Some(vec![]) }
+        );
+        assert_eq!(
+            command(&["config", "mismatch", "--push", "AP-1", "--yes"]).unwrap(),
+            Command::ConfigMismatch { push: Some(vec!["AP-1".to_string()]) }
+        );
+        assert!(command(&["config", "mismatch", "--push"]).is_err());
+        assert!(command(&["config", "mismatch", "AP-1"]).is_err());
+        assert!(command(&["config"]).is_err());
+    }
+
+    #[test]
+    fn test_config_backup_and_diff() {
+        assert_eq!(command(&["config", "backup"]).unwrap(), Command::ConfigBackup);
+        assert_eq!(
+            command(&["config", "diff", "AP-1", "3", "5"]).unwrap(),
+            Command::ConfigDiff { device: "AP-1".to_string(), run_a: 3, run_b: 5 }
+        );
+        assert!(command(&["config", "diff", "AP-1", "3"]).is_err());
+        assert!(command(&["config", "diff", "AP-1", "x", "5"]).is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        assert_eq!(command(&["schema"]).unwrap(), Command::Schema { name: None });
+        assert_eq!(
+            command(&["schema", "devices"]).unwrap(),
+            Command::Schema { name: Some("devices".to_string()) }
+        );
+        assert!(command(&["schema", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_db_merge() {
+        assert_eq!(
+            command(&["db", "merge", "tenants/acme.db", "102030=/data/dump.db"]).unwrap(),
+            Command::DbMerge {
+                sources: vec![
+                    MergeSource { owner_id: None, path: PathBuf::from("tenants/acme.db") },
+                    MergeSource { owner_id: Some(102030), path: PathBuf::from("/data/dump.db") },
+                ]
+            }
+        );
+        assert!(command(&["db", "merge"]).is_err());
+        assert!(command(&["db", "merge", "=x.db"]).is_err());
+        assert!(command(&["db", "merge", "globex=x.db"]).is_err());
+    }
+
+    #[test]
+    fn test_serve() {
+        assert_eq!(
+            command(&["serve"]).unwrap(),
+            Command::Serve { listen: "127.0.0.1:8080".to_string(), allow_anonymous_read: false }
+        );
+        assert_eq!(
+            command(&["serve", "--listen", "0.0.0.0:9000"]).unwrap(),
+            Command::Serve { listen: "0.0.0.0:9000".to_string(), allow_anonymous_read: false }
+        );
+        assert_eq!(
+            command(&["serve", "--allow-anonymous-read"]).unwrap(),
+            Command::Serve { listen: "127.0.0.1:8080".to_string(), allow_anonymous_read: true }
+        );
+        assert!(command(&["serve", "--port", "9000"]).is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(command(&["stats"]).unwrap(), Command::Stats);
+    }
+
+    #[test]
+    fn test_note_commands() {
+        assert_eq!(
+            command(&["note", "add", "AP-1", "radio replaced 2024-05"]).unwrap(),
+            Command::NoteAdd { device: "AP-1".to_string(), text: "radio replaced 2024-05".to_string() }
+        );
+        assert_eq!(command(&["note", "list"]).unwrap(), Command::NoteList { device: None });
+        assert_eq!(command(&["note", "list", "42"]).unwrap(), Command::NoteList { device: Some("42".to_string()) });
+        assert!(command(&["note", "add", "AP-1"]).is_err());
+        assert!(command(&["stats", "wifi0"]).is_err());
+    }
+
+    #[test]
+    fn test_subscribe() {
+        assert_eq!(command(&["subscribe"]).unwrap(), Command::Subscribe { interval: Duration::from_secs(60) });
+        assert_eq!(
+            command(&["subscribe", "--interval", "5m"]).unwrap(),
+            Command::Subscribe { interval: Duration::from_secs(300) }
+        );
+        assert!(command(&["subscribe", "--interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_health() {
+        assert_eq!(
+            command(&["health"]).unwrap(),
+            Command::Health { rebooted_within: Duration::from_secs(86400), stale_after: Duration::from_secs(7 * 86400) }
+        );
+        assert_eq!(
+            command(&["health", "--rebooted-within", "6h", "--stale-after=30d"]).unwrap(),
+            Command::Health { rebooted_within: Duration::from_secs(6 * 3600), stale_after: Duration::from_secs(30 * 86400) }
+        );
+        assert!(command(&["health", "--stale"]).is_err());
+    }
+
+    #[test]
+    fn test_report_diff() {
+        assert_eq!(
+            command(&["report", "diff", "--from", "3", "--to=7"]).unwrap(),
+            Command::ReportDiff { from: 3, to: 7, format: DiffFormat::Text, output: None }
+        );
+        assert_eq!(
+            command(&["report", "diff", "--from", "3", "--to", "7", "--format", "html", "--output", "change.html"]).unwrap(),
+            Command::ReportDiff { from: 3, to: 7, format: DiffFormat::Html, output: Some(PathBuf::from("change.html")) }
+        );
+        assert!(command(&["report", "diff", "--from", "3"]).is_err());
+        assert!(command(&["report", "diff", "--from", "3", "--to", "7", "--format", "pdf"]).is_err());
+    }
+
+    #[test]
+    fn test_import_devices() {
+        assert_eq!(
+            command(&["import", "devices", "export.csv"]).unwrap(),
+            Command::ImportDevices { path: PathBuf::from("export.csv") }
+        );
+        assert!(command(&["import", "devices"]).is_err());
+        assert!(command(&["import", "clients", "export.csv"]).is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        for mac in ["00:19:77:aa:bb:01", "00-19-77-AA-BB-01", "0019.77aa.bb01", "001977aabb01"] {
+            assert_eq!(command(&["lookup", mac]).unwrap(), Command::Lookup { mac: "00:19:77:AA:BB:01".to_string() });
+        }
+        assert!(command(&["lookup", "00:19:77:aa:bb"]).is_err());
+        assert!(command(&["lookup", "AP-1"]).is_err());
+        assert!(command(&["lookup"]).is_err());
+        assert_eq!(
+            command(&["lookup", "--file", "wips.csv", "--output=unknown.csv"]).unwrap(),
+            Command::LookupFile { path: PathBuf::from("wips.csv"), output: Some(PathBuf::from("unknown.csv")) }
+        );
+        assert!(command(&["lookup", "001977aabb01", "--file", "wips.csv"]).is_err());
+    }
+
+    #[test]
+    fn test_grafana_export() {
+        assert_eq!(
+            command(&["grafana", "export-dashboards"]).unwrap(),
+            Command::GrafanaExport { datasource: Datasource::Sqlite, output: None }
+        );
+        assert_eq!(
+            command(&["grafana", "export-dashboards", "--datasource=postgres", "--output", "xiq.json"]).unwrap(),
+            Command::GrafanaExport { datasource: Datasource::Postgres, output: Some(PathBuf::from("xiq.json")) }
+        );
+        assert!(command(&["grafana"]).is_err());
+        assert!(command(&["grafana", "export-dashboards", "--datasource", "influx"]).is_err());
+    }
+
+    #[test]
+    fn test_query_devices() {
+        let Command::QueryDevices { query } =
+            command(&["query", "devices", "--function", "ap", "--where", "radios[0].channel=36", "--where=wifi6"]).unwrap()
+        else {
+            panic!("expected query devices");
+        };
+        assert_eq!(query.device_function.as_deref(), Some("ap"));
+        assert_eq!(query.owner_id, None);
+        assert!(matches!(
+            command(&["query", "devices", "--owner", "102030"]).unwrap(),
+            Command::QueryDevices { query: DeviceQuery { owner_id: Some(102030), .. } }
+        ));
+        assert_eq!(query.json_filters.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), vec!["$.radios[0].channel", "$.wifi6"]);
+        assert!(command(&["query", "interfaces"]).is_err());
+        assert!(command(&["query", "devices", "--limit", "all"]).is_err());
+    }
+
+    #[test]
+    fn test_calendar_export() {
+        assert_eq!(command(&["calendar", "export"]).unwrap(), Command::CalendarExport { output: None, runs: None });
+        assert_eq!(
+            command(&["calendar", "export", "--output=noc.ics", "--runs", "30"]).unwrap(),
+            Command::CalendarExport { output: Some(PathBuf::from("noc.ics")), runs: Some(30) }
+        );
+        assert!(command(&["calendar"]).is_err());
+        assert!(command(&["calendar", "export", "--runs", "all"]).is_err());
+    }
+
+    #[test]
+    fn test_events() {
+        assert_eq!(command(&["events", "tail"]).unwrap(), Command::EventsTail { limit: events::DEFAULT_TAIL_LIMIT });
+        assert_eq!(command(&["events", "tail", "--limit=5"]).unwrap(), Command::EventsTail { limit: 5 });
+        assert_eq!(
+            command(&["events", "since", "2024-05-01", "08:30"]).unwrap(),
+            Command::EventsSince { since: "2024-05-01 08:30:00".to_string() }
+        );
+        assert!(command(&["events", "since"]).is_err());
+        assert!(command(&["events", "since", "last", "week"]).is_err());
+        assert!(command(&["events", "tail", "--limit", "all"]).is_err());
+        assert!(command(&["events"]).is_err());
+    }
+
+    #[test]
+    fn test_global_options_anywhere() {
+        let cli = parse_args(&args(&["device", "--db-path", "/data/org1", "reboot", "AP-1", "--yes"])).unwrap();
+        assert_eq!(cli.global.db_path, Some(PathBuf::from("/data/org1")));
+        assert_eq!(cli.command, Command::DeviceReboot { targets: vec!["AP-1".to_string()] });
+
+        let cli = parse_args(&args(&["--db", ":memory:", "--db-dump", "out.db"])).unwrap();
+        assert_eq!(cli.global.db_path, Some(PathBuf::from(":memory:")));
+        assert_eq!(cli.global.db_dump, Some(PathBuf::from("out.db")));
+
+        let cli = parse_args(&args(&["--db-path=x.db"])).unwrap();
+        assert_eq!(cli.global.db_path, Some(PathBuf::from("x.db")));
+        assert!(parse_args(&args(&["--db-path"])).is_err());
+
+        let cli = parse_args(&args(&["config", "backup", "--profile", "prod"])).unwrap();
+        assert_eq!(cli.global.profile.as_deref(), Some("prod"));
+        assert_eq!(cli.command, Command::ConfigBackup);
+
+        let cli = parse_args(&args(&["--progress", "json", "--only-new"])).unwrap();
+        assert!(cli.global.progress_json);
+        assert_eq!(cli.command, Command::Run(RunOptions { only_new: true, ..Default::default() }));
+        assert!(parse_args(&args(&["--progress=text"])).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::api::get_connected_aps;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Outcome XIQ reported for one device's CLI command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CliStatus {
+    #[default]
+    Success,
+    /// XIQ couldn't reach the device, or the device left it out of the response
+    Unreachable,
+    /// The device rejected the command
+    Unsupported,
+    Timeout,
+    /// Any other failure code
+    Error,
+}
+
+impl CliStatus {
+    /// Classify a `response_code` from the `:cli` response
+    pub fn from_response_code(code: &str) -> Self {
+        let code = code.trim().to_ascii_uppercase();
+        if code.is_empty() || code.starts_with("SUCCE") || code == "OK" {
+            Self::Success
+        } else if code.contains("TIMEOUT") || code.contains("TIMED_OUT") {
+            Self::Timeout
+        } else if code.contains("UNSUPPORT") || code.contains("NOT_SUPPORT") || code.contains("INVALID_COMMAND") {
+            Self::Unsupported
+        } else if code.contains("DISCONNECT") || code.contains("UNREACHABLE") || code.contains("OFFLINE") {
+            Self::Unreachable
+        } else {
+            Self::Error
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Unreachable => "unreachable",
+            Self::Unsupported => "unsupported command",
+            Self::Timeout => "timeout",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// One device's answer to a CLI command. For failures, `output` holds the
+/// error message XIQ returned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliResult {
+    pub device_id: i64,
+    pub status: CliStatus,
+    pub output: String,
+}
+
+impl CliResult {
+    pub fn succeeded(&self) -> bool {
+        self.status == CliStatus::Success
+    }
+}
+
+/// How the collection command went on one connected AP
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceStatus {
+    pub device_id: i64,
+    pub hostname: String,
+    pub status: CliStatus,
+    /// First line of the error message, empty on success
+    pub message: String,
+}
+
+/// Status of every connected AP in `devices`. APs missing from `results`
+/// are unreachable.
+pub fn device_statuses(devices: &[serde_json::Value], results: &[CliResult]) -> Vec<DeviceStatus> {
+    let by_id: HashMap<i64, &CliResult> = results.iter().map(|r| (r.device_id, r)).collect();
+
+    get_connected_aps(devices)
+        .into_iter()
+        .map(|(device_id, hostname)| {
+            let (status, message) = match by_id.get(&device_id) {
+                Some(result) if result.succeeded() => (CliStatus::Success, String::new()),
+                Some(result) => (
+                    result.status,
+                    result.output.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default().to_string(),
+                ),
+                None => (CliStatus::Unreachable, "no output returned".to_string()),
+            };
+            DeviceStatus { device_id, hostname, status, message }
+        })
+        .collect()
+}
+
+pub fn print_failure_summary(statuses: &[DeviceStatus]) {
+    let failed: Vec<&DeviceStatus> = statuses.iter().filter(|s| s.status != CliStatus::Success).collect();
+    if failed.is_empty() {
+        return;
+    }
+
+    println!("\n=== CLI Failures ({} of {} APs) ===", failed.len(), statuses.len());
+    for status in [CliStatus::Unreachable, CliStatus::Unsupported, CliStatus::Timeout, CliStatus::Error] {
+        let count = failed.iter().filter(|s| s.status == status).count();
+        if count > 0 {
+            println!("  {:<20} {}", status.label(), count);
+        }
+    }
+    for device in &failed {
+        println!("  - {} (ID: {}): {} {}", device.hostname, device.device_id, device.status.label(), device.message);
+    }
+    println!("=====================================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_statuses() {
+        assert_eq!(CliStatus::from_response_code("SUCCEED"), CliStatus::Success);
+        assert_eq!(CliStatus::from_response_code("TIMEOUT"), CliStatus::Timeout);
+        assert_eq!(CliStatus::from_response_code("device_disconnected"), CliStatus::Unreachable);
+        assert_eq!(CliStatus::from_response_code("UNSUPPORTED_CLI"), CliStatus::Unsupported);
+        assert_eq!(CliStatus::from_response_code("FAILED"), CliStatus::Error);
+
+        let devices: Vec<serde_json::Value> = (1..=3)
+            .map(|id| serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": true }))
+            .collect();
+        let results = vec![
+            CliResult { device_id: 1, status: CliStatus::Success, output: "Name MAC addr".to_string() },
+            CliResult { device_id: 2, status: CliStatus::Timeout, output: "\nCommand timed out\nretry".to_string() },
+        ];
+
+        let statuses: Vec<_> = device_statuses(&devices, &results)
+            .into_iter()
+            .map(|s| (s.device_id, s.status, s.message))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, CliStatus::Success, String::new()),
+                (2, CliStatus::Timeout, "Command timed out".to_string()),
+                (3, CliStatus::Unreachable, "no output returned".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,898 @@
+use crate::api::XiqApi;
+use crate::cli_status::{CliResult, CliStatus};
+use crate::config::{HttpConfig, HttpPoolConfig};
+use crate::error::{Error, Result};
+use crate::latency::{LatencyRecorder, DEFAULT_SLOW_CALL_MS};
+use crate::progress::{self, ProgressEvent};
+use async_trait::async_trait;
+use bytes::{Buf, Bytes};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginResponse {
+    access_token: String,
+}
+
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct DevicesResponse {
+    data: Vec<serde_json::Value>,
+    total_pages: Option<i32>,
+    total_count: Option<i32>,
+    page: Option<i32>,
+}
+
+/// Response body chunks buffered between the download and the parser
+const CLI_CHUNK_BUFFER: usize = 16;
+
+/// Devices requested per page of the devices API
+pub const DEVICE_PAGE_LIMIT: usize = 100;
+
+/// API used when no base URL is configured
+pub const DEFAULT_BASE_URL: &str = "https://api.extremecloudiq.com";
+
+/// Hostnames of the XIQ web UI, which don't serve the API
+const UI_HOSTNAMES: &[&str] = &["extremecloudiq.com", "www.extremecloudiq.com"];
+
+/// How long the reachability probe waits for the API to answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Defaults for an `[http]` client; see `HttpPoolConfig`
+struct PoolDefaults {
+    pool_max_idle: usize,
+    pool_idle_timeout_secs: u64,
+    tcp_keepalive_secs: u64,
+    http2: bool,
+    gzip: bool,
+    timeout_secs: u64,
+}
+
+/// Pages of the devices call: quick requests, large compressible JSON
+const BULK_DEFAULTS: PoolDefaults = PoolDefaults {
+    pool_max_idle: 16,
+    pool_idle_timeout_secs: 90,
+    tcp_keepalive_secs: 30,
+    http2: true,
+    gzip: true,
+    timeout_secs: 120,
+};
+
+/// Login, device actions and `:cli` calls, which can run for minutes while
+/// XIQ waits on the APs, so they get no overall timeout
+const CONTROL_DEFAULTS: PoolDefaults = PoolDefaults {
+    pool_max_idle: 4,
+    pool_idle_timeout_secs: 90,
+    tcp_keepalive_secs: 30,
+    http2: true,
+    gzip: false,
+    timeout_secs: 0,
+};
+
+fn build_http_client(config: &HttpPoolConfig, defaults: &PoolDefaults) -> Result<reqwest::Client> {
+    let secs = |value: Option<u64>, default: u64| Some(value.unwrap_or(default)).filter(|s| *s > 0).map(Duration::from_secs);
+
+    let mut builder = reqwest::Client::builder()
+        .pool_max_idle_per_host(config.pool_max_idle.unwrap_or(defaults.pool_max_idle))
+        .pool_idle_timeout(secs(config.pool_idle_timeout_secs, defaults.pool_idle_timeout_secs))
+        .tcp_keepalive(secs(config.tcp_keepalive_secs, defaults.tcp_keepalive_secs))
+        .gzip(config.gzip.unwrap_or(defaults.gzip));
+    if !config.http2.unwrap_or(defaults.http2) {
+        builder = builder.http1_only();
+    }
+    if let Some(timeout) = secs(config.timeout_secs, defaults.timeout_secs) {
+        builder = builder.timeout(timeout);
+    }
+
+    builder.build().map_err(|e| Error::network("Failed to create HTTP client", e))
+}
+
+/// Clean up a pasted base URL: add a missing `https://` and drop trailing
+/// slashes. URLs that can't be the API fail with a message saying what to use.
+pub fn normalize_base_url(raw: &str) -> Result<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err(Error::invalid(format!("XIQ base URL is empty (default: {})", DEFAULT_BASE_URL)));
+    }
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+
+    let url = reqwest::Url::parse(&with_scheme).map_err(|e| {
+        Error::parse(format!("XIQ base URL '{}' is not a valid URL (e.g. {})", raw, DEFAULT_BASE_URL), e)
+    })?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Error::invalid(format!("XIQ base URL '{}' must use https:// (e.g. {})", raw, DEFAULT_BASE_URL)));
+    }
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    if UI_HOSTNAMES.contains(&host.as_str()) {
+        return Err(Error::invalid(format!(
+            "XIQ base URL '{}' is the web UI, not the API; use {} instead",
+            raw,
+            DEFAULT_BASE_URL
+        )));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(Error::invalid(format!(
+            "XIQ base URL '{}' has a query or #fragment, likely copied from the browser; use {} instead",
+            raw,
+            DEFAULT_BASE_URL
+        )));
+    }
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Decode unpadded base64url, as used in JWT segments
+fn decode_base64url(input: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in input.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// XIQ account (`owner_id` claim) of an access token. The token isn't
+/// verified; it only comes from our own login.
+pub fn token_owner_id(token: &str) -> Option<i64> {
+    let payload = decode_base64url(token.split('.').nth(1)?)?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    match claims.get("owner_id")? {
+        serde_json::Value::Number(id) => id.as_i64(),
+        serde_json::Value::String(id) => id.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Device views of the devices API, from the least to the most data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceView {
+    Basic,
+    Status,
+    Location,
+    Client,
+    #[default]
+    Full,
+    Detail,
+}
+
+impl DeviceView {
+    fn api_name(self) -> &'static str {
+        match self {
+            Self::Basic => "BASIC",
+            Self::Status => "STATUS",
+            Self::Location => "LOCATION",
+            Self::Client => "CLIENT",
+            Self::Full => "FULL",
+            Self::Detail => "DETAIL",
+        }
+    }
+}
+
+/// Device fields stored in the `devices` table, as named in the API response
+pub const STORED_DEVICE_FIELDS: &[&str] = &[
+    "id",
+    "config_mismatch",
+    "connected",
+    "description",
+    "device_admin_state",
+    "device_function",
+    "hostname",
+    "ip_address",
+    "mac_address",
+    "managed_by",
+    "org_id",
+    "product_type",
+    "serial_number",
+    "simulated",
+    "software_version",
+    "system_up_time",
+    "location_id",
+    "locations",
+    "network_policy_name",
+    "hive_name",
+    "device_model",
+    "last_connect_time",
+];
+
+/// What the devices call asks for
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceProjection {
+    pub view: DeviceView,
+    /// Request only `STORED_DEVICE_FIELDS` and `extra_fields` instead of the whole view
+    pub project: bool,
+    /// More fields to keep, moved under `extra` in each device and stored
+    /// as JSON in `devices.extra`
+    pub extra_fields: Vec<String>,
+}
+
+impl DeviceProjection {
+    /// `views` and `fields` query parameters; the API takes field names in upper case
+    pub fn query(&self) -> String {
+        let mut query = format!("views={}", self.view.api_name());
+        if self.project {
+            let fields = STORED_DEVICE_FIELDS.iter().copied().chain(self.extra_fields.iter().map(String::as_str));
+            for field in fields {
+                query.push_str("&fields=");
+                query.push_str(&field.to_ascii_uppercase());
+            }
+        }
+        query
+    }
+
+    /// Move the extra fields the device has into its `extra` object
+    pub fn nest_extra_fields(&self, device: &mut serde_json::Value) {
+        let Some(object) = device.as_object_mut() else {
+            return;
+        };
+        let extra: serde_json::Map<String, serde_json::Value> = self
+            .extra_fields
+            .iter()
+            .filter_map(|field| Some((field.clone(), object.remove(field)?)))
+            .collect();
+        if !extra.is_empty() {
+            object.insert("extra".to_string(), extra.into());
+        }
+    }
+}
+
+/// `:cli` response, keeping only each device's joined output text and status
+#[derive(Debug, Deserialize)]
+struct CliResponse {
+    #[serde(default)]
+    device_cli_outputs: BTreeMap<String, CliOutput>,
+}
+
+/// One device's CLI output: an array of objects with an "output" field
+/// (joined with newlines), a plain string, or any other JSON value as text.
+/// The status comes from the first failing `response_code`.
+#[derive(Debug, PartialEq)]
+struct CliOutput {
+    status: CliStatus,
+    output: String,
+}
+
+impl CliOutput {
+    fn text(output: String) -> Self {
+        Self { status: CliStatus::Success, output }
+    }
+}
+
+/// Status of a `:cli` item or object from its `response_code`, if it has one
+fn response_status(item: &serde_json::Value) -> CliStatus {
+    item.get("response_code")
+        .and_then(|v| v.as_str())
+        .map(CliStatus::from_response_code)
+        .unwrap_or_default()
+}
+
+impl<'de> Deserialize<'de> for CliOutput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct OutputVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for OutputVisitor {
+            type Value = CliOutput;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("CLI output")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text(value.to_string()))
+            }
+
+            fn visit_string<E: serde::de::Error>(self, value: String) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text(value))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<CliOutput, A::Error> {
+                let mut parts = Vec::new();
+                let mut status = CliStatus::Success;
+                while let Some(item) = seq.next_element::<serde_json::Value>()? {
+                    if status == CliStatus::Success {
+                        status = response_status(&item);
+                    }
+                    if let Some(serde_json::Value::String(output)) = item.get("output") {
+                        parts.push(output.clone());
+                    }
+                }
+                Ok(CliOutput { status, output: parts.join("\n") })
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> std::result::Result<CliOutput, A::Error> {
+                let value = serde_json::Value::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                Ok(CliOutput { status: response_status(&value), output: value.to_string() })
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, value: bool) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text(value.to_string()))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text(value.to_string()))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text(value.to_string()))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, value: f64) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text(serde_json::Value::from(value).to_string()))
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> std::result::Result<CliOutput, E> {
+                Ok(CliOutput::text("null".to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(OutputVisitor)
+    }
+}
+
+/// Blocking reader over response body chunks sent from the async download
+struct ChunkReader {
+    chunks: tokio::sync::mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while !self.current.has_remaining() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.current.remaining());
+        self.current.copy_to_slice(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Per-device results of a `:cli` response, parsed as the body is read
+fn parse_cli_response(reader: impl Read) -> Result<Vec<CliResult>> {
+    let response: CliResponse = serde_json::from_reader(std::io::BufReader::new(reader))
+        .map_err(|e| Error::parse("Failed to parse CLI response as JSON", e))?;
+
+    Ok(response
+        .device_cli_outputs
+        .into_iter()
+        .filter_map(|(id, output)| {
+            Some(CliResult {
+                device_id: id.parse::<i64>().ok()?,
+                status: output.status,
+                output: output.output,
+            })
+        })
+        .collect())
+}
+
+/// A way to authenticate: a username and password sent to `/login`, or an
+/// API token generated in XIQ
+#[derive(Clone)]
+pub enum Credential {
+    Password { username: String, password: String },
+    Token(String),
+}
+
+impl Credential {
+    fn describe(&self) -> String {
+        match self {
+            Self::Password { username, .. } => format!("password for {}", username),
+            Self::Token(_) => "API token".to_string(),
+        }
+    }
+}
+
+/// Client for the Extreme CloudIQ REST API
+pub struct CloudIQClient {
+    /// Paginated devices calls
+    bulk: reqwest::Client,
+    /// Everything else
+    control: reqwest::Client,
+    base_url: String,
+    access_token: Option<String>,
+    /// The token came from `login`, so `logout` revokes it; API tokens given
+    /// to `use_token` outlive the run
+    revoke_on_logout: bool,
+    devices: DeviceProjection,
+    latency: LatencyRecorder,
+}
+
+impl CloudIQClient {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            bulk: reqwest::Client::new(),
+            control: reqwest::Client::new(),
+            base_url,
+            access_token: None,
+            revoke_on_logout: false,
+            devices: DeviceProjection::default(),
+            latency: LatencyRecorder::default(),
+        }
+    }
+
+    /// Account the client is logged in to, from the access token
+    pub fn owner_id(&self) -> Option<i64> {
+        self.access_token.as_deref().and_then(token_owner_id)
+    }
+
+    /// Replace the default HTTP clients with ones tuned by `[http]`
+    pub fn with_http_config(mut self, http: &HttpConfig) -> Result<Self> {
+        self.bulk = build_http_client(&http.bulk, &BULK_DEFAULTS)?;
+        self.control = build_http_client(&http.control, &CONTROL_DEFAULTS)?;
+        self.latency = LatencyRecorder::new(Duration::from_millis(http.slow_call_ms.unwrap_or(DEFAULT_SLOW_CALL_MS)));
+        Ok(self)
+    }
+
+    /// How long each API call took to answer
+    pub fn latency(&self) -> &LatencyRecorder {
+        &self.latency
+    }
+
+    /// Send a request, recording the time until the response headers arrive
+    async fn send(&self, operation: &'static str, request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+        let started = Instant::now();
+        let result = request.send().await;
+        self.latency.record(operation, started.elapsed());
+        result
+    }
+
+    /// Select the view and fields requested by `get_devices`
+    pub fn with_device_projection(mut self, devices: DeviceProjection) -> Self {
+        self.devices = devices;
+        self
+    }
+
+    /// Check that something answers at the base URL before logging in, so a
+    /// wrong hostname fails with a clear message. Any HTTP response counts.
+    pub async fn check_reachable(&self) -> Result<()> {
+        match self.control.get(&self.base_url).timeout(PROBE_TIMEOUT).send().await {
+            Ok(_) => Ok(()),
+            Err(e) if e.is_timeout() => Err(Error::network(
+                format!(
+                    "XIQ API at {} didn't answer within {}s; check XIQ_BASE_URL and any proxy settings",
+                    self.base_url,
+                    PROBE_TIMEOUT.as_secs()
+                ),
+                e,
+            )),
+            Err(e) => Err(Error::network(
+                format!(
+                    "Can't reach the XIQ API at {}; check the hostname in XIQ_BASE_URL (default: {})",
+                    self.base_url, DEFAULT_BASE_URL
+                ),
+                e,
+            )),
+        }
+    }
+
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<()> {
+        let login_url = format!("{}/login", self.base_url);
+
+        let login_payload = LoginRequest {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+
+        let request = self.control.post(&login_url).json(&login_payload);
+        let response = self
+            .send("Login", request)
+            .await
+            .map_err(|e| Error::network("Failed to send login request", e))?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_response("Login", response).await);
+        }
+
+        let login_response: LoginResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::parse("Failed to parse login response", e))?;
+
+        self.access_token = Some(login_response.access_token);
+        self.revoke_on_logout = true;
+        println!("Successfully authenticated with CloudIQ API");
+
+        Ok(())
+    }
+
+    /// Authenticate with an existing API token instead of logging in
+    pub fn use_token(&mut self, token: String) {
+        self.access_token = Some(token);
+        self.revoke_on_logout = false;
+    }
+
+    /// Authenticate with an existing API token, checked with a one-device
+    /// page so a revoked token is rejected here rather than mid-run
+    pub async fn login_with_token(&mut self, token: &str) -> Result<()> {
+        self.use_token(token.to_string());
+        let request = self
+            .control
+            .get(format!("{}/devices?page=1&limit=1", self.base_url))
+            .headers(self.auth_headers()?);
+        let response = self
+            .send("Token check", request)
+            .await
+            .map_err(|e| Error::network("Failed to send token check request", e))?;
+
+        if !response.status().is_success() {
+            self.access_token = None;
+            return Err(Error::from_response("Token check", response).await);
+        }
+
+        Ok(())
+    }
+
+    /// Authenticate with the first of `credentials` the API accepts. The next
+    /// one is only tried when the previous one is rejected (401); other
+    /// failures are returned as they are. Returns the index of the credential
+    /// used.
+    pub async fn authenticate(&mut self, credentials: &[Credential]) -> Result<usize> {
+        let mut rejected = None;
+        for (index, credential) in credentials.iter().enumerate() {
+            if let Some(error) = &rejected {
+                eprintln!("Warning: {}; trying fallback {}", error, credential.describe());
+            }
+            let result = match credential {
+                Credential::Password { username, password } => self.login(username, password).await,
+                Credential::Token(token) => self.login_with_token(token).await,
+            };
+            match result {
+                Ok(()) => {
+                    let kind = if index == 0 { "primary" } else { "fallback" };
+                    println!("Authenticated with {} {}", kind, credential.describe());
+                    return Ok(index);
+                }
+                Err(error @ Error::Auth { .. }) => rejected = Some(error),
+                Err(error) => return Err(error),
+            }
+        }
+
+        Err(rejected.unwrap_or_else(|| Error::Auth { status: None, message: "No credentials configured".to_string() }))
+    }
+
+    /// Revoke the access token from `login`, so finished runs don't leave
+    /// valid tokens behind. Does nothing when not logged in; a token from
+    /// `use_token` is only forgotten.
+    pub async fn logout(&mut self) -> Result<()> {
+        let Ok(headers) = self.auth_headers() else {
+            return Ok(());
+        };
+        self.access_token = None;
+        if !std::mem::take(&mut self.revoke_on_logout) {
+            return Ok(());
+        }
+
+        let request = self.control.post(format!("{}/logout", self.base_url)).headers(headers);
+        let response = self
+            .send("Logout", request)
+            .await
+            .map_err(|e| Error::network("Failed to send logout request", e))?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_response("Logout", response).await);
+        }
+
+        println!("Logged out of CloudIQ API");
+
+        Ok(())
+    }
+
+    pub fn auth_headers(&self) -> Result<HeaderMap> {
+        let token = self
+            .access_token
+            .as_ref()
+            .ok_or_else(|| Error::Auth { status: None, message: "Not authenticated. Please login first.".to_string() })?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| Error::parse("Failed to create authorization header", e))?,
+        );
+
+        Ok(headers)
+    }
+}
+
+#[async_trait]
+impl XiqApi for CloudIQClient {
+    async fn get_devices(&self) -> Result<Vec<serde_json::Value>> {
+        let mut all_devices = Vec::new();
+        let mut page = 1;
+        let limit = DEVICE_PAGE_LIMIT;
+
+        loop {
+            println!("Fetching page {} with limit {}...", page, limit);
+
+            let devices_url = format!(
+                "{}/devices?page={}&limit={}&deviceTypes=REAL&{}&async=false",
+                self.base_url,
+                page,
+                limit,
+                self.devices.query()
+            );
+
+            let request = self.bulk.get(&devices_url).headers(self.auth_headers()?);
+            let response = self
+                .send("Device fetch", request)
+                .await
+                .map_err(|e| Error::network("Failed to send devices request", e))?;
+
+            if !response.status().is_success() {
+                return Err(Error::from_response("Device fetch", response).await);
+            }
+
+            let devices_response: DevicesResponse = response
+                .json()
+                .await
+                .map_err(|e| Error::parse("Failed to parse devices response", e))?;
+
+            let devices_in_page = devices_response.data.len();
+            println!("Retrieved {} devices from page {}", devices_in_page, page);
+            progress::emit(ProgressEvent::PageFetched {
+                page,
+                devices: devices_in_page,
+                total_pages: devices_response.total_pages,
+                percent: devices_response
+                    .total_pages
+                    .map(|total| progress::percent(page.max(0) as usize, total.max(0) as usize)),
+            });
+
+            all_devices.extend(devices_response.data.into_iter().map(|mut device| {
+                self.devices.nest_extra_fields(&mut device);
+                device
+            }));
+
+            // Check if we have more pages to fetch
+            if let Some(total_pages) = devices_response.total_pages {
+                if page >= total_pages {
+                    println!("Reached last page ({}/{})", page, total_pages);
+                    break;
+                }
+            } else if devices_in_page < limit {
+                // If no total_pages info, stop when we get fewer devices than the limit
+                println!("Reached last page (received {} devices, less than limit of {})", devices_in_page, limit);
+                break;
+            }
+
+            page += 1;
+        }
+
+        println!("Successfully retrieved {} total devices across all pages", all_devices.len());
+
+        Ok(all_devices)
+    }
+
+    async fn send_cli_command_with_status(&self, device_ids: &[i64], command: &str) -> Result<Vec<CliResult>> {
+        let cli_url = format!("{}/devices/:cli", self.base_url);
+
+        let mut headers = self.auth_headers()?;
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+
+        // A command set (one command per line) goes out as one call
+        let payload = serde_json::json!({
+            "devices": {
+                "ids": device_ids
+            },
+            "clis": command.lines().collect::<Vec<_>>()
+        });
+
+        let request = self.control.post(&cli_url).headers(headers).json(&payload);
+        let response = self
+            .send("CLI command", request)
+            .await
+            .map_err(|e| Error::network("Failed to send CLI command request", e))?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_response("CLI command", response).await);
+        }
+
+        // Responses for many APs can be tens of MB, so parse the body while it
+        // downloads instead of buffering it and building a JSON tree
+        let (sender, chunks) = tokio::sync::mpsc::channel(CLI_CHUNK_BUFFER);
+        let parser = tokio::task::spawn_blocking(move || {
+            parse_cli_response(ChunkReader {
+                chunks,
+                current: Bytes::new(),
+            })
+        });
+
+        let mut response = response;
+        while let Some(chunk) = response.chunk().await.map_err(|e| Error::network("Failed to read CLI response", e))? {
+            // The parser has stopped on an error; it's reported below
+            if sender.send(chunk).await.is_err() {
+                break;
+            }
+        }
+        drop(sender);
+
+        parser.await.map_err(|e| Error::parse("CLI response parser panicked", e))?
+    }
+
+    async fn locate_device(&self, device_id: i64) -> Result<()> {
+        let locate_url = format!("{}/devices/{}/:locate", self.base_url, device_id);
+
+        let request = self.control.post(&locate_url).headers(self.auth_headers()?);
+        let response = self
+            .send("Locate", request)
+            .await
+            .map_err(|e| Error::network("Failed to send locate request", e))?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_response("Locate", response).await);
+        }
+
+        Ok(())
+    }
+
+    async fn reboot_devices(&self, device_ids: &[i64]) -> Result<()> {
+        let reboot_url = format!("{}/devices/:reboot", self.base_url);
+
+        let payload = serde_json::json!({
+            "ids": device_ids
+        });
+
+        let request = self.control.post(&reboot_url).headers(self.auth_headers()?).json(&payload);
+        let response = self
+            .send("Reboot", request)
+            .await
+            .map_err(|e| Error::network("Failed to send reboot request", e))?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_response("Reboot", response).await);
+        }
+
+        Ok(())
+    }
+
+    /// Push the current configuration to devices via a deployment
+    async fn push_config(&self, device_ids: &[i64]) -> Result<()> {
+        let deploy_url = format!("{}/deployments", self.base_url);
+
+        let payload = serde_json::json!({
+            "devices": {
+                "ids": device_ids
+            },
+            "policy": {
+                "enable_complete_configuration_update": false
+            }
+        });
+
+        let request = self.control.post(&deploy_url).headers(self.auth_headers()?).json(&payload);
+        let response = self
+            .send("Config push", request)
+            .await
+            .map_err(|e| Error::network("Failed to send config push request", e))?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_response("Config push", response).await);
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cli_response() {
+        let body = r#"{
+            "device_cli_outputs": {
+                "2": "Name MAC addr",
+                "1": [{ "cli": "show interface", "output": "line 1" }, { "output": "line 2" }, { "response_code": "ERROR" }],
+                "3": { "output": "nested" },
+                "4": [{ "response_code": "SUCCEED", "output": "ok" }, { "response_code": "TIMEOUT", "output": "timed out" }],
+                "abc": "ignored"
+            },
+            "extra": [1, 2, 3]
+        }"#;
+
+        let outputs: Vec<_> = parse_cli_response(body.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.device_id, r.status, r.output))
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                (1, CliStatus::Error, "line 1\nline 2".to_string()),
+                (2, CliStatus::Success, "Name MAC addr".to_string()),
+                (3, CliStatus::Success, r#"{"output":"nested"}"#.to_string()),
+                (4, CliStatus::Timeout, "ok\ntimed out".to_string()),
+            ]
+        );
+        assert!(parse_cli_response(&b"{}"[..]).unwrap().is_empty());
+        assert!(parse_cli_response(&b"<html>"[..]).is_err());
+    }
+
+    #[test]
+    fn test_normalize_base_url() {
+        assert_eq!(normalize_base_url("https://api.extremecloudiq.com/").unwrap(), DEFAULT_BASE_URL);
+        assert_eq!(normalize_base_url(" api.extremecloudiq.com ").unwrap(), DEFAULT_BASE_URL);
+        assert_eq!(normalize_base_url("http://localhost:8080/xiq//").unwrap(), "http://localhost:8080/xiq");
+
+        for bad in ["", "ftp://api.extremecloudiq.com", "https://extremecloudiq.com", "https://api.extremecloudiq.com/#/dashboard"] {
+            let error = normalize_base_url(bad).unwrap_err().to_string();
+            assert!(error.contains("XIQ base URL"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_token_owner_id() {
+        // {"alg":"HS256"} . {"user_id":7,"owner_id":102030,"role":"Administrator"}
+        let token = "eyJhbGciOiJIUzI1NiJ9.eyJ1c2VyX2lkIjo3LCJvd25lcl9pZCI6MTAyMDMwLCJyb2xlIjoiQWRtaW5pc3RyYXRvciJ9.sig";
+        assert_eq!(token_owner_id(token), Some(102030));
+        assert_eq!(decode_base64url("aGk_"), Some(vec![b'h', b'i', 0x3f]));
+        assert_eq!(token_owner_id("opaque-token"), None);
+    }
+
+    #[test]
+    fn test_device_projection() {
+        assert_eq!(DeviceProjection::default().query(), "views=FULL");
+
+        let projection = DeviceProjection {
+            view: DeviceView::Basic,
+            project: true,
+            extra_fields: vec!["active_clients".to_string()],
+        };
+        let query = projection.query();
+        assert!(query.starts_with("views=BASIC&fields=ID&fields=CONFIG_MISMATCH&"));
+        assert!(query.ends_with("&fields=LAST_CONNECT_TIME&fields=ACTIVE_CLIENTS"));
+
+        let mut device = serde_json::json!({ "id": 1, "active_clients": 12 });
+        projection.nest_extra_fields(&mut device);
+        assert_eq!(device, serde_json::json!({ "id": 1, "extra": { "active_clients": 12 } }));
+    }
+
+    #[test]
+    fn test_chunk_reader() {
+        let (sender, chunks) = tokio::sync::mpsc::channel(4);
+        for part in [r#"{"device_cli_outputs":{"7":"#, r#""ok"}}"#] {
+            sender.try_send(Bytes::from(part)).unwrap();
+        }
+        drop(sender);
+
+        let reader = ChunkReader { chunks, current: Bytes::new() };
+        assert_eq!(
+            parse_cli_response(reader).unwrap(),
+            vec![CliResult { device_id: 7, status: CliStatus::Success, output: "ok".to_string() }]
+        );
+    }
+}
@@ -0,0 +1,985 @@
+use crate::alerts::DEFAULT_DISCONNECTED_RUNS;
+use crate::archive::{DEFAULT_ARCHIVE_DIR, DEFAULT_ARCHIVE_KEEP};
+use crate::backup::DEFAULT_BACKUP_COMMAND;
+use crate::budget::{ApiBudget, DEFAULT_CALL_SECS, DEFAULT_CLI_SECS};
+use crate::calendar::{self, CalendarFeed, ScheduledWindow, DEFAULT_CALENDAR_RUNS};
+use crate::churn::DEFAULT_BSSID_CHURN_PCT;
+use crate::client::DeviceProjection;
+use crate::csv_output::CsvProfile;
+use crate::db::{DEFAULT_RAW_OUTPUT_RUNS, DEFAULT_SNAPSHOT_RUNS};
+use crate::dns::{DEFAULT_DNS_CONCURRENCY, DEFAULT_DNS_TIMEOUT_SECS};
+use crate::locale::{Locale, NumberStyle};
+use crate::mesh::{DEFAULT_HIVE_COMMAND, DEFAULT_NEIGHBOR_COMMAND};
+use crate::oui::DEFAULT_ALLOWED_VENDORS;
+use crate::parser_registry::{ExternalParserConfig, ParserRule};
+use crate::reachability::{
+    default_ping_command, ReachabilityChecks, DEFAULT_REACHABILITY_CONCURRENCY, DEFAULT_REACHABILITY_TIMEOUT_SECS,
+};
+use crate::rf::DEFAULT_MAX_BSSIDS_PER_RADIO;
+use crate::snmp::{default_walk_command, SnmpFallback, DEFAULT_SNMP_CONCURRENCY, DEFAULT_SNMP_TIMEOUT_SECS};
+use crate::ssh::{SshCollector, DEFAULT_SSH_CONCURRENCY, DEFAULT_SSH_MANAGED_BY, DEFAULT_SSH_PORT, DEFAULT_SSH_TIMEOUT_SECS};
+use crate::stats::DEFAULT_STATS_COMMAND;
+use crate::validation::Severity;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Config file read from the working directory when no path is given
+pub const DEFAULT_CONFIG_FILE: &str = "xiq.toml";
+
+/// Optional settings loaded from the TOML config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub output: OutputConfig,
+    /// View and fields requested from the devices API
+    pub devices: DeviceProjection,
+    pub http: HttpConfig,
+    pub rf: RfConfig,
+    pub radio: RadioConfig,
+    pub ssid: SsidConfig,
+    pub mesh: MeshConfig,
+    pub dns: DnsConfig,
+    pub reachability: ReachabilityConfig,
+    pub snmp_fallback: SnmpFallbackConfig,
+    pub ssh: SshConfig,
+    pub stats: StatsConfig,
+    pub backup: BackupConfig,
+    pub archive: ArchiveConfig,
+    pub raw_outputs: RawOutputsConfig,
+    pub collect: CollectConfig,
+    pub notify: NotifyConfig,
+    pub alerts: AlertsConfig,
+    pub vendors: VendorsConfig,
+    pub validation: ValidationConfig,
+    pub server: ServerConfig,
+    pub calendar: CalendarConfig,
+    pub budget: BudgetConfig,
+    pub postprocess: PostprocessConfig,
+    pub locale: LocaleConfig,
+    /// Named environments (tenants, credentials, paths), selected with `--profile`
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// Named CSV header profiles, selected with `output.csv_profile` or `--csv-profile`
+    pub csv_profiles: HashMap<String, CsvProfile>,
+    /// Parser selection rules, applied on top of the built-in defaults
+    pub parsers: Vec<ParserRule>,
+    /// External parser programs, usable by name in `parsers` rules
+    pub external_parsers: Vec<ExternalParserConfig>,
+}
+
+/// Which output sinks receive the collected interfaces
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Sink names, see `sink::SINK_NAMES`
+    pub sinks: Vec<String>,
+    pub webhook: Option<WebhookConfig>,
+    /// Default CSV header profile
+    pub csv_profile: Option<String>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            sinks: ["json", "txt", "csv", "ssid-summary", "rf-summary", "db"].iter().map(|s| s.to_string()).collect(),
+            webhook: None,
+            csv_profile: None,
+        }
+    }
+}
+
+/// RF design checks
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RfConfig {
+    /// Warn when a radio broadcasts more enabled BSSIDs than this
+    pub max_bssids_per_radio: usize,
+}
+
+impl Default for RfConfig {
+    fn default() -> Self {
+        Self {
+            max_bssids_per_radio: DEFAULT_MAX_BSSIDS_PER_RADIO,
+        }
+    }
+}
+
+/// Per-radio detail commands run after the main command
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RadioConfig {
+    /// Commands whose output reports TX power and PHY mode, such as
+    /// `show interface wifi0`; empty skips the extra commands
+    pub commands: Vec<String>,
+}
+
+/// SSID security lookup run after the main command, and the SSID compliance check
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SsidConfig {
+    /// Command listing each SSID's security settings, such as `show ssid`;
+    /// unset skips the lookup
+    pub command: Option<String>,
+    /// Authoritative SSID list; BSSIDs broadcasting anything else are reported.
+    /// Empty turns the check off.
+    pub allowed: Vec<String>,
+    /// SSIDs every collected AP must broadcast
+    pub required: Vec<String>,
+    /// SSIDs required on the APs of each network policy, by policy name
+    pub required_by_policy: HashMap<String, Vec<String>>,
+}
+
+/// Site-specific cleanups of the parsed entries, applied before they're
+/// exported or stored
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostprocessConfig {
+    /// SSIDs to rename, old name to new name
+    pub ssid_renames: HashMap<String, String>,
+    /// Hive names to rewrite, old name to new name
+    pub hive_renames: HashMap<String, String>,
+    /// Interfaces on these VLANs are dropped
+    pub drop_vlans: Vec<String>,
+}
+
+impl PostprocessConfig {
+    /// Whether any cleanup is configured
+    pub fn is_empty(&self) -> bool {
+        self.ssid_renames.is_empty() && self.hive_renames.is_empty() && self.drop_vlans.is_empty()
+    }
+}
+
+/// Time zone and number style of the human-facing reports
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocaleConfig {
+    /// IANA time zone name, such as `Europe/Berlin`
+    pub timezone: String,
+    pub numbers: NumberStyle,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self {
+            timezone: "UTC".to_string(),
+            numbers: NumberStyle::Plain,
+        }
+    }
+}
+
+impl LocaleConfig {
+    pub fn locale(&self) -> Result<Locale> {
+        Locale::new(&self.timezone, self.numbers).context("Invalid [locale] timezone")
+    }
+}
+
+impl SsidConfig {
+    /// Whether any SSID compliance rule is configured
+    pub fn has_compliance_rules(&self) -> bool {
+        !self.allowed.is_empty() || !self.required.is_empty() || !self.required_by_policy.is_empty()
+    }
+}
+
+/// Hive membership and AMRP neighbor collection for the mesh topology
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshConfig {
+    pub enabled: bool,
+    pub hive_command: String,
+    pub neighbor_command: String,
+}
+
+impl Default for MeshConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hive_command: DEFAULT_HIVE_COMMAND.to_string(),
+            neighbor_command: DEFAULT_NEIGHBOR_COMMAND.to_string(),
+        }
+    }
+}
+
+/// Connection settings of the two HTTP clients used for the API: `bulk`
+/// for the paginated devices call, `control` for login, `:cli` and device
+/// actions. Unset values keep each client's defaults (see `client`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub bulk: HttpPoolConfig,
+    pub control: HttpPoolConfig,
+    /// Calls taking longer than this many milliseconds to answer are
+    /// reported; see `latency::DEFAULT_SLOW_CALL_MS`
+    pub slow_call_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpPoolConfig {
+    /// Idle connections kept open to the API
+    pub pool_max_idle: Option<usize>,
+    /// Seconds an idle connection is kept before it's closed
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// Seconds between TCP keep-alive probes; 0 turns them off
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Use HTTP/2 when the API offers it; false forces HTTP/1.1
+    pub http2: Option<bool>,
+    /// Ask for gzip-compressed responses
+    pub gzip: Option<bool>,
+    /// Seconds a whole request may take; 0 means no limit
+    pub timeout_secs: Option<u64>,
+}
+
+/// Reverse DNS lookup of device IPs, stored and exported as `fqdn`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DnsConfig {
+    pub enabled: bool,
+    /// Lookups in flight at once
+    pub concurrency: usize,
+    /// Seconds a single lookup may take before the device is skipped
+    pub timeout_secs: u64,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            concurrency: DEFAULT_DNS_CONCURRENCY,
+            timeout_secs: DEFAULT_DNS_TIMEOUT_SECS,
+        }
+    }
+}
+
+/// Ping and SNMP checks that connected APs are reachable from the collector
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReachabilityConfig {
+    pub enabled: bool,
+    /// Program and arguments, `{ip}` is replaced with the AP's IP; empty skips ping
+    pub ping_command: Vec<String>,
+    /// Program and arguments printing the AP's sysName; empty skips SNMP
+    pub snmp_command: Vec<String>,
+    /// APs checked at once
+    pub concurrency: usize,
+    /// Seconds before a check program is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for ReachabilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ping_command: default_ping_command(),
+            snmp_command: Vec::new(),
+            concurrency: DEFAULT_REACHABILITY_CONCURRENCY,
+            timeout_secs: DEFAULT_REACHABILITY_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl ReachabilityConfig {
+    pub fn checks(&self) -> ReachabilityChecks {
+        ReachabilityChecks {
+            ping_command: self.ping_command.clone(),
+            snmp_command: self.snmp_command.clone(),
+            concurrency: self.concurrency,
+            timeout: std::time::Duration::from_secs(self.timeout_secs),
+        }
+    }
+}
+
+/// BSSIDs walked over SNMP from APs the CLI command failed on
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnmpFallbackConfig {
+    pub enabled: bool,
+    /// Program and arguments, `{ip}` is replaced with the AP's IP and `{oid}`
+    /// with the walked column
+    pub walk_command: Vec<String>,
+    /// Vendor MIB column holding each interface's SSID, indexed by ifIndex
+    pub ssid_oid: Option<String>,
+    /// APs walked at once
+    pub concurrency: usize,
+    /// Seconds before a walk is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for SnmpFallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            walk_command: default_walk_command(),
+            ssid_oid: None,
+            concurrency: DEFAULT_SNMP_CONCURRENCY,
+            timeout_secs: DEFAULT_SNMP_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl SnmpFallbackConfig {
+    pub fn fallback(&self) -> SnmpFallback {
+        SnmpFallback {
+            walk_command: self.walk_command.clone(),
+            ssid_oid: self.ssid_oid.clone(),
+            concurrency: self.concurrency,
+            timeout: std::time::Duration::from_secs(self.timeout_secs),
+        }
+    }
+}
+
+/// Collection command run over SSH on locally managed APs, which can't take
+/// cloud CLI
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SshConfig {
+    pub enabled: bool,
+    pub username: String,
+    /// Environment variable holding the password
+    pub password_env: Option<String>,
+    /// Private key file (OpenSSH or PKCS#8 format), tried before the password
+    pub key_file: Option<PathBuf>,
+    pub port: u16,
+    /// known_hosts file the APs' host keys are checked against; the user's
+    /// `~/.ssh/known_hosts` when unset
+    pub known_hosts: Option<PathBuf>,
+    /// Record the host keys of APs missing from known_hosts instead of
+    /// refusing to log in
+    pub accept_new_hosts: bool,
+    /// `managed_by` values of the APs collected over SSH
+    pub managed_by: Vec<String>,
+    /// APs logged into at once
+    pub concurrency: usize,
+    /// Seconds before a session is dropped
+    pub timeout_secs: u64,
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            username: "admin".to_string(),
+            password_env: None,
+            key_file: None,
+            port: DEFAULT_SSH_PORT,
+            known_hosts: None,
+            accept_new_hosts: false,
+            managed_by: DEFAULT_SSH_MANAGED_BY.iter().map(|s| s.to_string()).collect(),
+            concurrency: DEFAULT_SSH_CONCURRENCY,
+            timeout_secs: DEFAULT_SSH_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl SshConfig {
+    pub fn collector(&self) -> Result<SshCollector> {
+        let password = match &self.password_env {
+            Some(var) => Some(std::env::var(var).context(format!("SSH password variable {} is not set", var))?),
+            None => None,
+        };
+        if password.is_none() && self.key_file.is_none() {
+            anyhow::bail!("[ssh] needs key_file or password_env to log into the APs");
+        }
+        Ok(SshCollector {
+            username: self.username.clone(),
+            password,
+            key_file: self.key_file.clone(),
+            port: self.port,
+            known_hosts: self.known_hosts.clone(),
+            accept_new_hosts: self.accept_new_hosts,
+            concurrency: self.concurrency,
+            timeout: std::time::Duration::from_secs(self.timeout_secs),
+        })
+    }
+}
+
+/// Per-interface counter polling for `stats`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    /// Detail command sent for each access interface; `{interface}` is
+    /// replaced with its name
+    pub command: String,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            command: DEFAULT_STATS_COMMAND.to_string(),
+        }
+    }
+}
+
+/// Commands used by `config backup`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Command for devices without a family-specific override
+    pub command: String,
+    /// Overrides keyed by device family (lowercase `device_function`)
+    pub commands: HashMap<String, String>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            command: DEFAULT_BACKUP_COMMAND.to_string(),
+            commands: HashMap::new(),
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn command_for(&self, family: Option<&str>) -> &str {
+        family
+            .and_then(|f| self.commands.get(&f.to_lowercase()))
+            .unwrap_or(&self.command)
+    }
+}
+
+/// Rotating archive of each run's output files
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    pub dir: PathBuf,
+    /// Number of archived runs to keep; 0 disables archiving
+    pub keep: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from(DEFAULT_ARCHIVE_DIR),
+            keep: DEFAULT_ARCHIVE_KEEP,
+        }
+    }
+}
+
+/// Raw CLI output kept in the database for each run
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RawOutputsConfig {
+    /// Number of runs whose raw output is kept; 0 turns storing it off
+    pub keep_runs: usize,
+}
+
+impl Default for RawOutputsConfig {
+    fn default() -> Self {
+        Self {
+            keep_runs: DEFAULT_RAW_OUTPUT_RUNS,
+        }
+    }
+}
+
+/// Results of `collect` runs stored for `export`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectConfig {
+    /// Number of collected runs kept, at least 1; older ones can't be exported
+    pub keep_runs: usize,
+}
+
+impl Default for CollectConfig {
+    fn default() -> Self {
+        Self {
+            keep_runs: DEFAULT_SNAPSHOT_RUNS,
+        }
+    }
+}
+
+/// Where run alerts (e.g. a suspiciously low BSSID count) are sent
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+/// AP inventory change alerts, sent to the notification webhooks
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// Alert when an AP appears that wasn't in the previous run
+    pub new_ap: bool,
+    /// Alert when an AP from the previous run is gone
+    pub removed_ap: bool,
+    /// Alert when an AP has been disconnected for this many consecutive runs; 0 disables
+    pub disconnected_runs: u32,
+    /// Alert when at least this percentage of an AP's BSSIDs changed since
+    /// the last run that collected it; 0 disables
+    pub bssid_churn_pct: f64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            new_ap: true,
+            removed_ap: true,
+            disconnected_runs: DEFAULT_DISCONNECTED_RUNS,
+            bssid_churn_pct: DEFAULT_BSSID_CHURN_PCT,
+        }
+    }
+}
+
+/// Which vendors' BSSIDs are expected, checked against the MAC's OUI
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VendorsConfig {
+    /// Vendor name substrings (case-insensitive) or OUIs such as "00:19:77";
+    /// empty disables the check
+    pub allowed: Vec<String>,
+    /// IEEE OUI registry CSV extending the built-in Aerohive/Extreme table
+    pub oui_file: Option<PathBuf>,
+}
+
+impl Default for VendorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed: DEFAULT_ALLOWED_VENDORS.iter().map(|s| s.to_string()).collect(),
+            oui_file: None,
+        }
+    }
+}
+
+/// Data quality rules over the parsed access-mode interfaces; findings are
+/// written to validation-report.csv
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationConfig {
+    pub enabled: bool,
+    /// SSID empty or `-`
+    pub missing_ssid: Severity,
+    /// VLAN 0
+    pub vlan_zero: Severity,
+    /// Hive empty or `-`
+    pub empty_hive: Severity,
+    /// Channel that isn't a 2.4, 5 or 6 GHz channel
+    pub malformed_channel: Severity,
+    /// Fail the run when there are more error findings than this
+    pub max_errors: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            missing_ssid: Severity::Error,
+            vlan_zero: Severity::Warning,
+            empty_hive: Severity::Warning,
+            malformed_channel: Severity::Error,
+            max_errors: 0,
+        }
+    }
+}
+
+/// Access control for `serve`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// API tokens; without any, the server is read-only and open to anyone who can reach it
+    pub tokens: Vec<ApiToken>,
+}
+
+/// Bearer token accepted by the server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiToken {
+    /// Who the token belongs to, recorded in the audit log
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+}
+
+/// What a token holder may do; each role includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Browse devices, BSSIDs and statistics
+    Viewer,
+    /// Also trigger collection runs
+    Operator,
+}
+
+/// Scheduled collection windows and completed runs in the iCalendar feed
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// Completed runs in the feed, newest first
+    pub runs: i64,
+    pub windows: Vec<CalendarWindowConfig>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            runs: DEFAULT_CALENDAR_RUNS,
+            windows: Vec::new(),
+        }
+    }
+}
+
+/// When the BSSID sweeps are scheduled to run
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CalendarWindowConfig {
+    pub name: String,
+    /// First occurrence, `YYYY-MM-DD HH:MM` in UTC
+    pub start: String,
+    pub duration_mins: u64,
+    /// iCalendar recurrence rule, e.g. `FREQ=DAILY`
+    pub rrule: Option<String>,
+}
+
+impl CalendarConfig {
+    pub fn feed(&self) -> Result<CalendarFeed> {
+        let windows = self
+            .windows
+            .iter()
+            .map(|window| {
+                Ok(ScheduledWindow {
+                    name: window.name.clone(),
+                    start: calendar::parse_start(&window.start)
+                        .with_context(|| format!("Invalid calendar window '{}'", window.name))?,
+                    duration: std::time::Duration::from_secs(window.duration_mins * 60),
+                    rrule: window.rrule.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(CalendarFeed { windows, runs: self.runs })
+    }
+}
+
+/// API usage limits of the org, checked against an estimate before each
+/// collection run
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    /// Refuse runs estimated to make more API calls than this
+    pub max_calls: Option<u64>,
+    /// The org's API rate limit, used for the expected duration
+    pub calls_per_minute: Option<u64>,
+    /// Expected seconds per login or device page call
+    pub call_secs: u64,
+    /// Expected seconds per `:cli` call
+    pub cli_secs: u64,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_calls: None,
+            calls_per_minute: None,
+            call_secs: DEFAULT_CALL_SECS,
+            cli_secs: DEFAULT_CLI_SECS,
+        }
+    }
+}
+
+impl BudgetConfig {
+    pub fn budget(&self) -> ApiBudget {
+        ApiBudget {
+            max_calls: self.max_calls,
+            calls_per_minute: self.calls_per_minute,
+            call_time: std::time::Duration::from_secs(self.call_secs),
+            cli_time: std::time::Duration::from_secs(self.cli_secs),
+        }
+    }
+}
+
+/// Environment selected with `--profile` or XIQ_PROFILE. Set values take
+/// precedence over the XIQ_* environment variables; command-line flags
+/// still override them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub base_url: Option<String>,
+    pub username: Option<String>,
+    /// Password in the config file; prefer `password_env`
+    pub password: Option<String>,
+    /// Environment variable holding the password
+    pub password_env: Option<String>,
+    /// Account for the fallback password; defaults to `username`
+    pub fallback_username: Option<String>,
+    /// Environment variable holding a password tried when the primary one is
+    /// rejected
+    pub fallback_password_env: Option<String>,
+    /// Environment variable holding an API token tried when the passwords
+    /// are rejected
+    pub fallback_token_env: Option<String>,
+    /// Database file or directory
+    pub db_path: Option<PathBuf>,
+    /// Directory the output files are written to
+    pub output_dir: Option<PathBuf>,
+}
+
+impl ProfileConfig {
+    /// Password from `password_env`, or `password`
+    pub fn password(&self) -> Result<Option<String>> {
+        match &self.password_env {
+            Some(var) => std::env::var(var)
+                .map(Some)
+                .context(format!("Profile password variable {} is not set", var)),
+            None => Ok(self.password.clone()),
+        }
+    }
+
+    /// Password from `fallback_password_env`
+    pub fn fallback_password(&self) -> Result<Option<String>> {
+        self.fallback_password_env.as_deref().map(|var| env_secret(var, "fallback password")).transpose()
+    }
+
+    /// API token from `fallback_token_env`
+    pub fn fallback_token(&self) -> Result<Option<String>> {
+        self.fallback_token_env.as_deref().map(|var| env_secret(var, "fallback token")).transpose()
+    }
+}
+
+fn env_secret(var: &str, what: &str) -> Result<String> {
+    std::env::var(var).context(format!("Profile {} variable {} is not set", what, var))
+}
+
+/// Target for the `webhook` sink or a notification
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Optional bearer token sent in the Authorization header
+    pub token: Option<String>,
+}
+
+impl Config {
+    /// Profile named `name`; unknown names are an error so a typo never falls
+    /// back to the default environment
+    pub fn profile(&self, name: &str) -> Result<&ProfileConfig> {
+        self.profiles.get(name).with_context(|| {
+            let mut names: Vec<_> = self.profiles.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            if names.is_empty() {
+                format!("Unknown profile '{}': the config file defines no [profiles]", name)
+            } else {
+                format!("Unknown profile '{}' (available: {})", name, names.join(", "))
+            }
+        })
+    }
+
+    /// Make the relative paths of files the tool reads absolute against `base`,
+    /// so they still resolve after a profile's `output_dir` is entered
+    pub fn resolve_input_paths(&mut self, base: &Path) {
+        for path in [&mut self.vendors.oui_file, &mut self.ssh.key_file, &mut self.ssh.known_hosts].into_iter().flatten() {
+            *path = base.join(&*path);
+        }
+    }
+
+    /// Load the config from `--config`, XIQ_CONFIG, or `xiq.toml` if it exists.
+    /// An explicitly named file must exist; the default one is optional.
+    pub fn load(cli_path: Option<&Path>) -> Result<Self> {
+        let explicit = cli_path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os("XIQ_CONFIG").map(PathBuf::from));
+
+        let path = match explicit {
+            Some(path) => path,
+            None => {
+                let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if !default.exists() {
+                    return Ok(Self::default());
+                }
+                default
+            }
+        };
+
+        let contents = fs::read_to_string(&path)
+            .context(format!("Failed to read config file: {}", path.display()))?;
+
+        let mut config = Self::parse(&contents)
+            .context(format!("Invalid config file: {}", path.display()))?;
+        config.resolve_input_paths(&std::env::current_dir().context("Failed to read the working directory")?);
+
+        println!("Loaded configuration from {}", path.display());
+
+        Ok(config)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents).context("Failed to parse TOML")?;
+        // Caught here rather than when the first AP is walked, after the collection
+        if config.snmp_fallback.enabled && config.snmp_fallback.walk_command.is_empty() {
+            anyhow::bail!("[snmp_fallback] is enabled but walk_command is empty");
+        }
+        Ok(config)
+    }
+
+    /// CSV profile chosen on the command line, falling back to `output.csv_profile`
+    pub fn csv_profile(&self, cli_profile: Option<&str>) -> Result<Option<&CsvProfile>> {
+        let Some(name) = cli_profile.or(self.output.csv_profile.as_deref()) else {
+            return Ok(None);
+        };
+
+        self.csv_profiles.get(name).map(Some).ok_or_else(|| {
+            let mut names: Vec<_> = self.csv_profiles.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            anyhow::anyhow!("Unknown CSV profile '{}' (available: {})", name, names.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            [output]
+            sinks = ["csv", "webhook"]
+
+            [output.webhook]
+            url = "https://example.com/hook"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.output.sinks, vec!["csv", "webhook"]);
+        assert_eq!(config.output.webhook.unwrap().url, "https://example.com/hook");
+
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.output.sinks, vec!["json", "txt", "csv", "ssid-summary", "rf-summary", "db"]);
+
+        assert_eq!(config.rf.max_bssids_per_radio, 7);
+        assert_eq!(config.archive.keep, 10);
+        assert_eq!(config.alerts.disconnected_runs, 3);
+        assert!(config.alerts.new_ap);
+        assert_eq!(config.vendors.allowed, vec!["Extreme", "Aerohive"]);
+        assert_eq!(config.stats.command, "show interface {interface}");
+        assert!(!config.validation.enabled);
+
+        let config = Config::parse("[validation]\nenabled = true\nvlan_zero = \"error\"\nmax_errors = 5").unwrap();
+        assert_eq!((config.validation.vlan_zero, config.validation.max_errors), (Severity::Error, 5));
+        assert!(Config::parse("[validation]\nempty_hive = \"fatal\"").is_err());
+
+        assert!(Config::parse("[output]\nsinkz = []").is_err());
+        assert_eq!(Config::parse("[rf]\nmax_bssids_per_radio = 4").unwrap().rf.max_bssids_per_radio, 4);
+
+        let config = Config::parse(
+            r#"
+            [[parsers]]
+            command = "show interface"
+            family = "ap"
+            parser = "bssid-lines"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.parsers[0].family.as_deref(), Some("ap"));
+
+        let config = Config::parse(
+            r#"
+            [output]
+            csv_profile = "cmdb"
+
+            [csv_profiles.cmdb]
+            "wifi-bssids.csv" = [{ column = "MAC", header = "bssid" }, { column = "SSID" }]
+            "#,
+        )
+        .unwrap();
+        let profile = config.csv_profile(None).unwrap().unwrap();
+        assert_eq!(profile["wifi-bssids.csv"][0].header.as_deref(), Some("bssid"));
+        assert!(config.csv_profile(Some("dnac")).is_err());
+
+        let config = Config::parse(
+            r#"
+            [[server.tokens]]
+            name = "noc"
+            token = "s3cret"
+            role = "operator"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.tokens[0].role, Role::Operator);
+        assert!(Role::Operator > Role::Viewer);
+        assert!(Config::parse("[[server.tokens]]\nname = \"x\"\ntoken = \"y\"\nrole = \"admin\"").is_err());
+
+        let config = Config::parse("[devices]\nview = \"basic\"\nproject = true\nextra_fields = [\"active_clients\"]").unwrap();
+        assert_eq!(config.devices.view, crate::client::DeviceView::Basic);
+        assert!(config.devices.project);
+        assert!(Config::parse("[devices]\nview = \"everything\"").is_err());
+
+        let config = Config::parse("[backup.commands]\nswitch = \"show running-config all\"").unwrap();
+        assert_eq!(config.backup.command_for(Some("switch")), "show running-config all");
+        assert_eq!(config.backup.command_for(Some("ap")), "show running-config");
+        assert_eq!(config.backup.command_for(None), "show running-config");
+
+        let config = Config::parse(
+            r#"
+            [[calendar.windows]]
+            name = "Nightly sweep"
+            start = "2024-05-01 02:00"
+            duration_mins = 45
+            rrule = "FREQ=DAILY"
+            "#,
+        )
+        .unwrap();
+        let feed = config.calendar.feed().unwrap();
+        assert_eq!(feed.windows[0].duration, std::time::Duration::from_secs(2700));
+        assert_eq!(feed.runs, DEFAULT_CALENDAR_RUNS);
+        let config = Config::parse("[[calendar.windows]]\nname = \"x\"\nstart = \"02:00\"\nduration_mins = 5").unwrap();
+        assert!(config.calendar.feed().is_err());
+
+        let budget = Config::parse("[budget]\nmax_calls = 500\ncli_secs = 45").unwrap().budget.budget();
+        assert_eq!(budget.max_calls, Some(500));
+        assert_eq!(budget.cli_time, std::time::Duration::from_secs(45));
+        assert_eq!(budget.call_time, std::time::Duration::from_secs(DEFAULT_CALL_SECS));
+        let config = Config::parse("[postprocess]\nssid_renames = { \"Corp-Old\" = \"Corp\" }\ndrop_vlans = [\"999\"]").unwrap();
+        assert_eq!(config.postprocess.ssid_renames["Corp-Old"], "Corp");
+        assert!(!config.postprocess.is_empty() && Config::parse("").unwrap().postprocess.is_empty());
+        assert!(Config::parse("[postprocess]\ndrop_vlan = [\"1\"]").is_err());
+        let locale = Config::parse("[locale]\ntimezone = \"Europe/Berlin\"\nnumbers = \"de\"").unwrap().locale.locale().unwrap();
+        assert_eq!((locale.zone(), locale.numbers), ("Europe/Berlin", NumberStyle::De));
+        assert_eq!(Config::parse("").unwrap().locale.locale().unwrap(), Locale::default());
+        assert!(Config::parse("[locale]\ntimezone = \"CEST\"").unwrap().locale.locale().is_err());
+        assert!(Config::parse("[locale]\nnumbers = \"us\"").is_err());
+        assert_eq!(Config::parse("").unwrap().raw_outputs.keep_runs, DEFAULT_RAW_OUTPUT_RUNS);
+        assert_eq!(Config::parse("[raw_outputs]\nkeep_runs = 0").unwrap().raw_outputs.keep_runs, 0);
+        assert_eq!(Config::parse("[collect]\nkeep_runs = 5").unwrap().collect.keep_runs, 5);
+        assert!(Config::parse("[snmp_fallback]\nenabled = true\nwalk_command = []").is_err());
+        assert!(Config::parse("[snmp_fallback]\nwalk_command = []").is_ok());
+        let mut config = Config::parse("[vendors]\noui_file = \"oui.csv\"\n[ssh]\nkey_file = \"/etc/xiq/ap_key\"").unwrap();
+        config.resolve_input_paths(Path::new("/srv/xiq"));
+        assert_eq!(config.vendors.oui_file.as_deref(), Some(Path::new("/srv/xiq/oui.csv")));
+        assert_eq!(config.ssh.key_file.as_deref(), Some(Path::new("/etc/xiq/ap_key")));
+        assert_eq!(config.ssh.known_hosts, None);
+    }
+
+    #[test]
+    fn test_profiles() {
+        let config = Config::parse(
+            r#"
+            [profiles.prod]
+            base_url = "https://api.extremecloudiq.com"
+            username = "ops@example.com"
+            password_env = "XIQ_TEST_PROFILE_PASSWORD_UNSET"
+            db_path = "/data/prod/"
+            output_dir = "out/prod"
+
+            [profiles.sandbox]
+            base_url = "https://sandbox.example.com"
+            password = "s3cret"
+            fallback_token_env = "XIQ_TEST_PROFILE_TOKEN_UNSET"
+            "#,
+        )
+        .unwrap();
+
+        let prod = config.profile("prod").unwrap();
+        assert_eq!(prod.db_path, Some(PathBuf::from("/data/prod/")));
+        assert_eq!(prod.output_dir, Some(PathBuf::from("out/prod")));
+        assert!(prod.password().is_err());
+        assert_eq!(config.profile("sandbox").unwrap().password().unwrap().as_deref(), Some("s3cret"));
+        assert_eq!(prod.fallback_password().unwrap(), None);
+        assert!(config.profile("sandbox").unwrap().fallback_token().is_err());
+
+        let error = config.profile("staging").unwrap_err().to_string();
+        assert!(error.contains("available: prod, sandbox"), "{}", error);
+        assert!(Config::default().profile("prod").is_err());
+        assert!(Config::parse("[profiles.prod]\nurl = \"x\"").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::output::{create_output_file, LineEnding, OutputFile};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One column of a CSV header profile
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnMapping {
+    /// Column name as the tool writes it (e.g. `MAC`)
+    pub column: String,
+    /// Header to write instead; the original name when omitted
+    pub header: Option<String>,
+}
+
+/// Column layout per CSV file name (e.g. `wifi-bssids.csv`).
+/// Files without an entry keep the default layout.
+pub type CsvProfile = HashMap<String, Vec<ColumnMapping>>;
+
+/// Escape a string for CSV output (RFC 4180 compliant)
+pub fn csv_escape(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Header names and column indices after applying a profile's mapping
+fn select_columns(headers: &[&str], mapping: Option<&[ColumnMapping]>) -> Result<Vec<(String, usize)>> {
+    let Some(mapping) = mapping else {
+        return Ok(headers.iter().enumerate().map(|(i, h)| (h.to_string(), i)).collect());
+    };
+
+    mapping
+        .iter()
+        .map(|m| {
+            let index = headers
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(&m.column))
+                .with_context(|| format!("Unknown CSV column '{}' (available: {})", m.column, headers.join(", ")))?;
+            Ok((m.header.clone().unwrap_or_else(|| headers[index].to_string()), index))
+        })
+        .collect()
+}
+
+/// Write a CSV file, renaming, reordering and dropping columns per the
+/// profile's entry for this file name
+pub fn write_csv(
+    path: &Path,
+    headers: &[&str],
+    rows: &[Vec<String>],
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    write_csv_as(path, &file_name, headers, rows, line_ending, profile)
+}
+
+/// Like `write_csv`, but looks up the profile entry for `file_name` instead
+/// of the path's own name (e.g. split files sharing the `wifi-bssids.csv` layout)
+pub fn write_csv_as(
+    path: &Path,
+    file_name: &str,
+    headers: &[&str],
+    rows: &[Vec<String>],
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let mut writer = CsvWriter::create_as(path, file_name, headers, line_ending, profile)?;
+    for row in rows {
+        writer.write_row(row)?;
+    }
+    writer.flush()
+}
+
+/// CSV file written row by row, with the profile's column mapping applied
+pub struct CsvWriter {
+    file: OutputFile,
+    path: PathBuf,
+    columns: Vec<(String, usize)>,
+}
+
+impl CsvWriter {
+    /// Create the file and write its header; the profile entry is looked up by `file_name`
+    pub fn create_as(
+        path: &Path,
+        file_name: &str,
+        headers: &[&str],
+        line_ending: LineEnding,
+        profile: Option<&CsvProfile>,
+    ) -> Result<Self> {
+        let mapping = profile.and_then(|p| p.get(file_name)).map(Vec::as_slice);
+        let columns = select_columns(headers, mapping)
+            .with_context(|| format!("Invalid CSV profile for {}", file_name))?;
+
+        let mut file = create_output_file(path, line_ending)?;
+
+        let header_line: Vec<String> = columns.iter().map(|(header, _)| csv_escape(header)).collect();
+        writeln!(file, "{}", header_line.join(","))
+            .context(format!("Failed to write CSV header to {}", path.display()))?;
+
+        Ok(Self { file, path: path.to_path_buf(), columns })
+    }
+
+    /// Write one row, given in the order of the headers passed to `create_as`
+    pub fn write_row(&mut self, row: &[String]) -> Result<()> {
+        let line: Vec<String> = self.columns.iter().map(|(_, i)| csv_escape(&row[*i])).collect();
+        writeln!(self.file, "{}", line.join(","))
+            .context(format!("Failed to write row to {}", self.path.display()))
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush().context(format!("Failed to flush {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("Corp"), "Corp");
+        assert_eq!(csv_escape("Guest, WiFi"), "\"Guest, WiFi\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let headers = ["Device", "MAC", "SSID"];
+        assert_eq!(
+            select_columns(&headers, None).unwrap(),
+            vec![("Device".to_string(), 0), ("MAC".to_string(), 1), ("SSID".to_string(), 2)]
+        );
+
+        let mapping = vec![
+            ColumnMapping { column: "mac".into(), header: Some("bssid".into()) },
+            ColumnMapping { column: "Device".into(), header: None },
+        ];
+        assert_eq!(
+            select_columns(&headers, Some(&mapping)).unwrap(),
+            vec![("bssid".to_string(), 1), ("Device".to_string(), 0)]
+        );
+
+        let bad = vec![ColumnMapping { column: "Vendor".into(), header: None }];
+        assert!(select_columns(&headers, Some(&bad)).is_err());
+    }
+}
@@ -1,31 +1,652 @@
+use crate::backup::{self, ConfigBackup};
+use crate::cli_status::{CliResult, DeviceStatus};
+use crate::client::STORED_DEVICE_FIELDS;
+use crate::events::{self, EventKind};
+use crate::location;
+use crate::manifest::StageTiming;
+use crate::mesh::MeshLink;
+use crate::radio::RadioDetail;
+use crate::report::DeviceInterfaces;
+use crate::stats::InterfaceCounters;
+use crate::summary::SiteSummary;
+use crate::subscribe::ConnectionStates;
 use anyhow::{Context, Result};
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
-use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{
+    Sqlite, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+    SqliteSynchronous,
+};
+use sqlx::QueryBuilder;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+/// Database file name used when no path (or only a directory) is given
+pub const DEFAULT_DB_FILE: &str = "xiq-db.db";
+
+/// Special path that keeps the whole database in memory
+pub const MEMORY_DB: &str = ":memory:";
+
+/// Default number of pooled connections
+pub const DEFAULT_POOL_SIZE: u32 = 5;
+
+/// Default number of runs whose raw CLI output is kept
+pub const DEFAULT_RAW_OUTPUT_RUNS: usize = 30;
+
+/// Default number of `collect` runs kept for `export`
+pub const DEFAULT_SNAPSHOT_RUNS: usize = 30;
+
+/// How long a connection waits on a locked database before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// SQLite's default limit on bound parameters per statement
+const SQLITE_MAX_VARIABLES: usize = 32766;
+
+
+/// Interfaces belong to a device and are deleted with it
+const CREATE_INTERFACES_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS interfaces (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        device_id INTEGER NOT NULL REFERENCES devices (id) ON DELETE CASCADE,
+        hostname TEXT,
+        name TEXT,
+        mac TEXT,
+        mode TEXT,
+        state TEXT,
+        channel TEXT,
+        vlan TEXT,
+        radio TEXT,
+        hive TEXT,
+        ssid TEXT,
+        owner_id INTEGER,
+        fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    )
+"#;
+const INTERFACE_COLUMNS: usize = 12;
+const HISTORY_COLUMNS: usize = 7;
+const BSSID_HISTORY_COLUMNS: usize = 6;
+const RUN_BSSID_COLUMNS: usize = 7;
+const RADIO_DETAIL_COLUMNS: usize = 6;
+const INTERFACE_STATS_COLUMNS: usize = 8;
+const MESH_LINK_COLUMNS: usize = 11;
+const CLI_RESULT_COLUMNS: usize = 5;
+const SPREAD_OUTPUT_COLUMNS: usize = 3;
+const RAW_OUTPUT_COLUMNS: usize = 6;
+const SITE_SUMMARY_COLUMNS: usize = 9;
+const EVENT_COLUMNS: usize = 8;
+
+/// Result of saving parsed interfaces: which APs' rows were rewritten
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterfaceChanges {
+    /// Hostnames of the APs whose interfaces changed since they were last saved
+    pub changed: Vec<String>,
+    /// APs whose interfaces were identical and left untouched
+    pub unchanged: usize,
+}
+
+/// Hash of the interface columns stored for one AP, used to skip rewriting
+/// devices whose output hasn't changed
+fn interfaces_hash(ap: &DeviceInterfaces) -> String {
+    let rows: Vec<[&str; 9]> = ap
+        .interfaces
+        .iter()
+        .map(|i| [&i.name, &i.mac, &i.mode, &i.state, &i.channel, &i.vlan, &i.radio, &i.hive, &i.ssid].map(String::as_str))
+        .collect();
+    let data = serde_json::to_vec(&(&ap.hostname, rows)).unwrap_or_default();
+    crate::manifest::sha256_hex(&data)
+}
+
+/// BSSID seen for the first time in a run
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct NewBssid {
+    pub device_id: i64,
+    pub hostname: String,
+    pub mac: String,
+    pub ssid: String,
+}
+
+/// Access-mode interface from the last collection run, polled by `stats`
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct AccessInterface {
+    pub device_id: i64,
+    pub hostname: String,
+    pub name: String,
+    pub mac: String,
+}
+
+/// AP that appeared in or disappeared from the inventory between two runs
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ApChange {
+    pub device_id: i64,
+    pub hostname: String,
+}
+
+/// AP that has been disconnected for a number of consecutive runs
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct DisconnectedAp {
+    pub device_id: i64,
+    pub hostname: String,
+    pub runs: i64,
+}
+
+/// Inventory changes seen in a run, compared with the previous run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApChanges {
+    pub added: Vec<ApChange>,
+    pub removed: Vec<ApChange>,
+    /// APs whose disconnected streak just reached the threshold
+    pub disconnected: Vec<DisconnectedAp>,
+}
+
+/// A device's BSSIDs in a run and in the previous run that collected it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BssidSetChange {
+    pub device_id: i64,
+    pub hostname: String,
+    pub previous_run_id: i64,
+    pub previous: Vec<String>,
+    pub current: Vec<String>,
+}
+
+/// Rows imported by one `merge_from` call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeStats {
+    pub devices: u64,
+    pub interfaces: u64,
+}
+
+/// Device row for the web UI's device list
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct DeviceSummary {
+    pub id: i64,
+    pub hostname: Option<String>,
+    pub device_function: Option<String>,
+    pub product_type: Option<String>,
+    pub ip_address: Option<String>,
+    pub mac_address: Option<String>,
+    pub connected: Option<bool>,
+    /// Access-mode interfaces from the latest run
+    pub bssids: i64,
+}
+
+/// Access-mode interface matching a BSSID search
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct BssidMatch {
+    pub device_id: i64,
+    pub hostname: Option<String>,
+    pub name: Option<String>,
+    pub mac: Option<String>,
+    pub ssid: Option<String>,
+    pub channel: Option<String>,
+    pub radio: Option<String>,
+    pub state: Option<String>,
+}
+
+/// Stored device, as saved from the devices API
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "Device", complex))]
+pub struct DeviceRow {
+    pub id: i64,
+    pub hostname: Option<String>,
+    pub device_function: Option<String>,
+    pub product_type: Option<String>,
+    pub serial_number: Option<String>,
+    pub ip_address: Option<String>,
+    /// Reverse DNS name of `ip_address`, when `[dns]` lookups are enabled
+    pub fqdn: Option<String>,
+    pub mac_address: Option<String>,
+    pub software_version: Option<String>,
+    pub connected: Option<bool>,
+    pub config_mismatch: Option<bool>,
+    pub org_id: Option<i64>,
+    pub fetched_at: Option<String>,
+    /// Unmapped API fields as a JSON object
+    pub extra: Option<String>,
+    /// XIQ account the device was saved for
+    pub owner_id: Option<i64>,
+}
+
+/// A run's BSSID with the radio and channel it was broadcast on
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow)]
+pub struct RunBssidDetail {
+    pub run_id: i64,
+    pub device_id: i64,
+    pub hostname: String,
+    pub mac: String,
+    pub ssid: String,
+    pub radio: String,
+    pub channel: String,
+}
+
+/// Inventory change recorded in the `events` table
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Event {
+    pub id: i64,
+    pub run_id: i64,
+    pub recorded_at: String,
+    pub device_id: i64,
+    pub hostname: String,
+    pub kind: String,
+    /// BSSID of BSSID and SSID events, radio of channel changes
+    pub subject: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// Local note attached to a device with `note add`
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Annotation {
+    pub id: i64,
+    pub device_id: i64,
+    pub hostname: Option<String>,
+    pub note: String,
+    pub created_at: Option<String>,
+}
+
+/// Filters for `Database::query_devices`; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceQuery {
+    pub id: Option<i64>,
+    /// Case-insensitive substring of the hostname
+    pub hostname: Option<String>,
+    /// Case-insensitive device function, e.g. `ap`
+    pub device_function: Option<String>,
+    pub connected: Option<bool>,
+    pub config_mismatch: Option<bool>,
+    /// XIQ account the device was saved for
+    pub owner_id: Option<i64>,
+    /// Conditions on the unmapped fields in `devices.extra`
+    pub json_filters: Vec<JsonFilter>,
+    pub limit: Option<i64>,
+}
+
+/// Condition on a JSON path (e.g. `$.radios[0].channel`) of `devices.extra`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonFilter {
+    pub path: String,
+    /// Value the path must equal, compared as text (`true`/`false` for
+    /// booleans); `None` only requires the path to exist
+    pub value: Option<String>,
+}
+
+/// Stored interface from the latest run
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "Interface", complex))]
+pub struct InterfaceRow {
+    pub device_id: i64,
+    pub hostname: Option<String>,
+    pub name: Option<String>,
+    pub mac: Option<String>,
+    pub mode: Option<String>,
+    pub state: Option<String>,
+    pub channel: Option<String>,
+    pub vlan: Option<String>,
+    pub radio: Option<String>,
+    pub hive: Option<String>,
+    pub ssid: Option<String>,
+}
+
+/// Recorded run
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "Run"))]
+pub struct RunRow {
+    pub id: i64,
+    pub command: Option<String>,
+    pub bssids: Option<i64>,
+    pub started_at: Option<String>,
+}
+
+/// Raw CLI output of one AP in one run, as it was returned
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawOutput {
+    pub run_id: i64,
+    pub device_id: i64,
+    pub hostname: Option<String>,
+    pub command: Option<String>,
+    /// SHA-256 of `output`, recorded when it was stored
+    pub sha256: String,
+    pub output: String,
+    pub fetched_at: Option<String>,
+}
+
+/// Device inventory and parsed results a `collect` run stored for `export`
+#[derive(Debug, Clone)]
+pub struct RunSnapshot {
+    pub run: RunRow,
+    pub devices: Vec<serde_json::Value>,
+    pub results: Vec<DeviceInterfaces>,
+}
+
+/// Size of the stored inventory, which the API call estimate is based on
+#[derive(Debug, Clone, Copy, Default, PartialEq, sqlx::FromRow)]
+pub struct InventoryCounts {
+    pub devices: i64,
+    pub connected_aps: i64,
+    /// Connected APs managed by one of the `managed_by` values given
+    pub local_aps: i64,
+}
+
+/// A recorded run with what it collected, for the calendar feed
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow)]
+pub struct CalendarRun {
+    pub id: i64,
+    pub command: Option<String>,
+    pub bssids: Option<i64>,
+    pub started_at: String,
+    /// Total of the stage timings; 0 when none were recorded
+    pub millis: i64,
+    pub devices: i64,
+    pub cli_failures: i64,
+    /// Inventory change events the run recorded
+    pub events: i64,
+}
+
+/// A device's state in one recorded run
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "DeviceHistory", complex))]
+pub struct DeviceHistoryRow {
+    pub run_id: i64,
+    pub hostname: Option<String>,
+    pub config_mismatch: Option<bool>,
+    pub connected: Option<bool>,
+}
+
+/// A device's recorded state in one run, for comparing runs
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RunDevice {
+    pub device_id: i64,
+    pub hostname: String,
+    pub connected: Option<bool>,
+    pub config_mismatch: Option<bool>,
+}
+
+/// Access-mode BSSID collected in one run
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RunBssid {
+    pub device_id: i64,
+    pub hostname: String,
+    pub mac: String,
+    pub ssid: String,
+}
+
+/// First and last sighting of one of a device's BSSIDs
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "BssidHistory"))]
+pub struct BssidHistoryRow {
+    pub mac: String,
+    pub ssid: String,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    pub first_run_id: Option<i64>,
+    pub last_run_id: Option<i64>,
+}
+
+/// Number of BSSIDs per SSID or channel
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct LabelCount {
+    pub label: String,
+    pub count: i64,
+}
+
+/// Device whose configuration differs from what XIQ last pushed
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct MismatchedDevice {
+    pub id: i64,
+    pub hostname: String,
+    /// When the current mismatch was first seen (UTC), from run history
+    pub since: Option<String>,
+    /// Seconds since the mismatch was first seen
+    pub age_secs: Option<i64>,
+    /// Consecutive recorded runs that saw the mismatch
+    pub runs: i64,
+}
+
+/// Device that booted within the `health` report window
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RebootedDevice {
+    pub id: i64,
+    pub hostname: String,
+    /// Boot time (UTC), from `system_up_time`
+    pub booted_at: String,
+    /// Seconds since the boot
+    pub uptime_secs: i64,
+}
+
+/// Device that hasn't been fetched, connected or collected from recently
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct StaleDevice {
+    pub id: i64,
+    pub hostname: String,
+    pub connected: bool,
+    /// When the device was last saved from the API (UTC)
+    pub fetched_at: Option<String>,
+    pub last_connect_time: Option<String>,
+    /// When any of the device's BSSIDs was last collected (UTC)
+    pub last_seen: Option<String>,
+}
+
+/// Interface or recorded BSSID matching a looked-up MAC
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct MacLookup {
+    pub mac: String,
+    pub device_id: i64,
+    pub hostname: String,
+    pub ssid: String,
+    /// Empty when the BSSID is only in the history
+    pub interface: String,
+    pub channel: String,
+    /// Location path of the device
+    pub site: Option<String>,
+    /// When the BSSID was last collected (UTC)
+    pub last_seen: Option<String>,
+    /// Whether the MAC is among the interfaces saved by the latest run
+    pub current: bool,
+}
+
+/// `--spread` collection of a CLI command, across runs until every AP answered
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SpreadCollection {
+    pub id: i64,
+    /// When the collection window ends (epoch seconds)
+    pub deadline: i64,
+}
+
+/// Resolve a configured database location to a file path.
+/// Existing directories, and paths ending in a separator, get the default file name.
+pub fn resolve_db_path(path: &Path) -> PathBuf {
+    if path.as_os_str() == MEMORY_DB {
+        return path.to_path_buf();
+    }
+
+    let ends_with_separator = path
+        .as_os_str()
+        .to_string_lossy()
+        .chars()
+        .last()
+        .map(std::path::is_separator)
+        .unwrap_or(false);
+
+    if path.is_dir() || ends_with_separator {
+        path.join(DEFAULT_DB_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Location entry of a device from the devices API
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ApiLocation {
+    pub id: Option<i64>,
+    pub name: Option<String>,
+}
+
+/// Device from the devices API, with the fields stored in the `devices` table.
+/// Values of the wrong type are rejected instead of being stored as NULL.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ApiDevice {
+    pub id: i64,
+    pub config_mismatch: Option<bool>,
+    pub connected: Option<bool>,
+    pub description: Option<String>,
+    pub device_admin_state: Option<String>,
+    pub device_function: Option<String>,
+    pub hostname: Option<String>,
+    pub ip_address: Option<String>,
+    pub mac_address: Option<String>,
+    pub managed_by: Option<String>,
+    pub org_id: Option<i64>,
+    pub product_type: Option<String>,
+    pub serial_number: Option<String>,
+    pub simulated: Option<bool>,
+    pub software_version: Option<String>,
+    pub system_up_time: Option<i64>,
+    pub location_id: Option<i64>,
+    #[serde(default)]
+    pub locations: Vec<ApiLocation>,
+    pub network_policy_name: Option<String>,
+    #[serde(alias = "hive")]
+    pub hive_name: Option<String>,
+    pub device_model: Option<String>,
+    /// Epoch milliseconds, or a timestamp string
+    pub last_connect_time: Option<serde_json::Value>,
+    /// Reverse DNS name of `ip_address`, added by `dns::apply_fqdns`
+    pub fqdn: Option<String>,
+    /// Fields without a column of their own, see `unmapped_fields`
+    #[serde(skip)]
+    pub extra: Option<serde_json::Value>,
+    /// Joined location names, outermost first
+    #[serde(skip)]
+    pub location_path: Option<String>,
+    /// Site level of the location, see `location::device_location`
+    #[serde(skip)]
+    pub site: Option<String>,
+}
+
+impl ApiDevice {
+    pub fn from_value(value: &serde_json::Value) -> Result<Self> {
+        let mut device: Self = serde_json::from_value(value.clone()).with_context(|| {
+            let name = value.get("hostname").or_else(|| value.get("id")).cloned().unwrap_or_default();
+            format!("Unexpected device data for {}", name)
+        })?;
+        if let Some(location) = location::device_location(value) {
+            device.location_path = Some(location.path);
+            device.site = Some(location.site);
+        }
+        device.extra = unmapped_fields(value);
+        Ok(device)
+    }
+
+    /// Innermost location ID: the last entry of `locations`, or `location_id`
+    fn innermost_location_id(&self) -> Option<i64> {
+        self.locations.last().and_then(|l| l.id).or(self.location_id)
+    }
+
+    /// Last connect time as RFC 3339; the API sends epoch milliseconds
+    fn last_connect_time(&self) -> Option<String> {
+        match self.last_connect_time.as_ref()? {
+            serde_json::Value::Number(ms) => chrono::DateTime::from_timestamp_millis(ms.as_i64()?)
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            serde_json::Value::String(time) => Some(time.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Device fields that have no column of their own, so fields XIQ adds
+/// aren't lost. The configured extra fields, nested under `extra` by the
+/// client, are flattened in. `None` when every field is mapped.
+fn unmapped_fields(value: &serde_json::Value) -> Option<serde_json::Value> {
+    let object = value.as_object()?;
+    let mut unmapped = object.get("extra").and_then(|e| e.as_object()).cloned().unwrap_or_default();
+    for (key, field) in object {
+        if !STORED_DEVICE_FIELDS.contains(&key.as_str()) && !matches!(key.as_str(), "hive" | "fqdn" | "extra") {
+            unmapped.insert(key.clone(), field.clone());
+        }
+    }
+    (!unmapped.is_empty()).then(|| unmapped.into())
+}
+
+#[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
+    /// XIQ account that devices, interfaces and runs are saved for
+    owner_id: Option<i64>,
 }
 
 impl Database {
-    pub async fn new(database_name: &str) -> Result<Self> {
-        let database_url = format!("{}.db", database_name);
+    pub async fn new(path: &Path, pool_size: u32) -> Result<Self> {
+        if path.as_os_str() == MEMORY_DB {
+            return Self::new_in_memory().await;
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create database directory: {}", parent.display()))?;
+        }
 
-        let options = SqliteConnectOptions::from_str(&database_url)?
-            .create_if_missing(true);
+        // Use filename() rather than a sqlite:// URL so Windows paths with
+        // backslashes and drive letters work unchanged.
+        // WAL lets readers run alongside a writer, and the busy timeout makes
+        // concurrent writers wait instead of failing with "database is locked".
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT)
+            .foreign_keys(true);
 
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
+            .max_connections(pool_size.max(1))
             .connect_with(options)
             .await
             .context("Failed to connect to database")?;
 
-        let db = Self { pool };
-        db.create_table().await?;
+        let db = Self { pool, owner_id: None };
+        db.create_tables().await?;
+
+        Ok(db)
+    }
+
+    /// In-memory database for CI and experiments; nothing is written to disk
+    /// unless it is explicitly dumped with `dump_to`
+    async fn new_in_memory() -> Result<Self> {
+        // Open ":memory:" as a plain filename rather than via sqlx's in-memory
+        // mode, which sets SQLITE_OPEN_MEMORY and would make VACUUM INTO write
+        // its target to memory as well.
+        // The database lives only as long as its connection, so keep exactly
+        // one connection open for the lifetime of the pool.
+        let options = SqliteConnectOptions::new().filename(MEMORY_DB).foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .context("Failed to open in-memory database")?;
+
+        let db = Self { pool, owner_id: None };
+        db.create_tables().await?;
 
         Ok(db)
     }
 
-    async fn create_table(&self) -> Result<()> {
+    /// Save devices, interfaces and runs for the XIQ account `owner_id`, and
+    /// only prune that account's rows, so one database can hold several
+    pub fn with_owner(mut self, owner_id: Option<i64>) -> Self {
+        self.owner_id = owner_id;
+        self
+    }
+
+    async fn create_tables(&self) -> Result<()> {
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS devices (
@@ -45,6 +666,15 @@ impl Database {
                 simulated BOOLEAN,
                 software_version TEXT,
                 system_up_time INTEGER,
+                location_id INTEGER,
+                location_path TEXT,
+                network_policy_name TEXT,
+                hive_name TEXT,
+                device_model TEXT,
+                last_connect_time TEXT,
+                fqdn TEXT,
+                extra TEXT,
+                owner_id INTEGER,
                 fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP
             )
             "#,
@@ -52,73 +682,3393 @@ impl Database {
         .execute(&self.pool)
         .await
         .context("Failed to create devices table")?;
+        for (column, definition) in [
+            ("location_id", "INTEGER"),
+            ("location_path", "TEXT"),
+            ("network_policy_name", "TEXT"),
+            ("hive_name", "TEXT"),
+            ("device_model", "TEXT"),
+            ("last_connect_time", "TEXT"),
+            ("fqdn", "TEXT"),
+            ("extra", "TEXT"),
+            ("owner_id", "INTEGER"),
+        ] {
+            self.add_column_if_missing("devices", column, definition).await?;
+        }
 
-        Ok(())
-    }
-
-    pub async fn clear_devices(&self) -> Result<()> {
-        sqlx::query("DELETE FROM devices")
+        self.migrate_interfaces_foreign_key().await?;
+        sqlx::query(CREATE_INTERFACES_TABLE)
             .execute(&self.pool)
             .await
-            .context("Failed to clear devices table")?;
+            .context("Failed to create interfaces table")?;
+        self.add_column_if_missing("interfaces", "owner_id", "INTEGER").await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS interfaces_device_id ON interfaces (device_id)")
+            .execute(&self.pool)
+            .await
+            .context("Failed to create interfaces index")?;
 
-        Ok(())
-    }
+        // Hash of the interfaces last saved per device
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS interface_hashes (
+                device_id INTEGER PRIMARY KEY REFERENCES devices (id) ON DELETE CASCADE,
+                sha256 TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create interface_hashes table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                action TEXT NOT NULL,
+                targets TEXT,
+                result TEXT,
+                logged_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create audit_log table")?;
 
-    pub async fn insert_device(&self, device: &serde_json::Value) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO devices (
-                id, config_mismatch, connected, description, device_admin_state,
-                device_function, hostname, ip_address, mac_address, managed_by,
-                org_id, product_type, serial_number, simulated, software_version,
-                system_up_time
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                command TEXT,
+                bssids INTEGER,
+                tool_version TEXT,
+                owner_id INTEGER,
+                started_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
             "#,
         )
-        .bind(device.get("id").and_then(|v| v.as_i64()))
-        .bind(device.get("config_mismatch").and_then(|v| v.as_bool()))
-        .bind(device.get("connected").and_then(|v| v.as_bool()))
-        .bind(device.get("description").and_then(|v| v.as_str()))
-        .bind(device.get("device_admin_state").and_then(|v| v.as_str()))
-        .bind(device.get("device_function").and_then(|v| v.as_str()))
-        .bind(device.get("hostname").and_then(|v| v.as_str()))
-        .bind(device.get("ip_address").and_then(|v| v.as_str()))
-        .bind(device.get("mac_address").and_then(|v| v.as_str()))
-        .bind(device.get("managed_by").and_then(|v| v.as_str()))
-        .bind(device.get("org_id").and_then(|v| v.as_i64()))
-        .bind(device.get("product_type").and_then(|v| v.as_str()))
-        .bind(device.get("serial_number").and_then(|v| v.as_str()))
-        .bind(device.get("simulated").and_then(|v| v.as_bool()))
-        .bind(device.get("software_version").and_then(|v| v.as_str()))
-        .bind(device.get("system_up_time").and_then(|v| v.as_i64()))
         .execute(&self.pool)
         .await
-        .context("Failed to insert device")?;
+        .context("Failed to create runs table")?;
+        self.add_column_if_missing("runs", "tool_version", "TEXT").await?;
+        self.add_column_if_missing("runs", "owner_id", "INTEGER").await?;
 
-        Ok(())
-    }
+        // How long each pipeline stage of a run took
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS run_stages (
+                run_id INTEGER NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+                stage TEXT NOT NULL,
+                millis INTEGER NOT NULL,
+                PRIMARY KEY (run_id, stage)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create run_stages table")?;
 
-    pub async fn insert_devices(&self, devices: &[serde_json::Value]) -> Result<()> {
-        // Clear existing devices first
-        self.clear_devices().await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_history (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                device_function TEXT,
+                config_mismatch BOOLEAN,
+                connected BOOLEAN,
+                site TEXT,
+                PRIMARY KEY (run_id, device_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create device_history table")?;
+        self.add_column_if_missing("device_history", "device_function", "TEXT").await?;
+        self.add_column_if_missing("device_history", "site", "TEXT").await?;
 
-        // Insert new devices
-        for device in devices {
-            self.insert_device(device).await?;
-        }
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS configs (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                command TEXT,
+                sha256 TEXT NOT NULL,
+                config BLOB NOT NULL,
+                fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (run_id, device_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create configs table")?;
 
-        println!("Successfully saved {} devices to database", devices.len());
+        // What each AP returned for the run's CLI command, gzipped, with the
+        // hash of the uncompressed text
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS raw_outputs (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                command TEXT,
+                sha256 TEXT NOT NULL,
+                output BLOB NOT NULL,
+                fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (run_id, device_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create raw_outputs table")?;
 
-        Ok(())
-    }
+        // Not tied to the devices table, so notes outlive a device dropping
+        // out of the inventory for a run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS annotations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                note TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create annotations table")?;
 
-    pub async fn count_devices(&self) -> Result<i64> {
-        let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM devices")
-            .fetch_one(&self.pool)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS bssid_history (
+                device_id INTEGER NOT NULL,
+                mac TEXT NOT NULL,
+                ssid TEXT NOT NULL,
+                hostname TEXT,
+                first_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+                first_run_id INTEGER,
+                last_run_id INTEGER,
+                PRIMARY KEY (device_id, mac, ssid)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create bssid_history table")?;
+
+        // Which BSSIDs each device broadcast in each run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS run_bssids (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                mac TEXT NOT NULL,
+                ssid TEXT NOT NULL,
+                radio TEXT,
+                channel TEXT,
+                PRIMARY KEY (run_id, device_id, mac, ssid)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create run_bssids table")?;
+        self.add_column_if_missing("run_bssids", "radio", "TEXT").await?;
+        self.add_column_if_missing("run_bssids", "channel", "TEXT").await?;
+
+        // Inventory changes detected between runs, for `events`
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id INTEGER NOT NULL,
+                owner_id INTEGER,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                kind TEXT NOT NULL,
+                subject TEXT NOT NULL DEFAULT '',
+                old_value TEXT,
+                new_value TEXT,
+                recorded_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create events table")?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS events_recorded_at ON events (recorded_at)")
+            .execute(&self.pool)
             .await
-            .context("Failed to count devices")?;
+            .context("Failed to create events index")?;
+
+        // Transmit power and PHY mode of each radio in each run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS radio_details (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                radio TEXT NOT NULL,
+                tx_power_dbm REAL,
+                phy_mode TEXT,
+                PRIMARY KEY (run_id, device_id, radio)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create radio_details table")?;
+
+        // Time-stamped traffic and client counters per BSSID, from `stats`
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS interface_stats (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                interface TEXT NOT NULL,
+                mac TEXT,
+                rx_bytes INTEGER,
+                tx_bytes INTEGER,
+                clients INTEGER,
+                collected_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create interface_stats table")?;
+
+        // AMRP mesh neighbors of each AP in each run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mesh_links (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                hive TEXT,
+                interface TEXT NOT NULL,
+                neighbor_mac TEXT NOT NULL,
+                neighbor_device_id INTEGER,
+                neighbor_hostname TEXT,
+                state TEXT,
+                metric INTEGER,
+                rssi INTEGER,
+                PRIMARY KEY (run_id, device_id, interface, neighbor_mac)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create mesh_links table")?;
+
+        // Per-site AP, BSSID and SSID counts of each run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS site_summary (
+                run_id INTEGER NOT NULL,
+                site TEXT NOT NULL,
+                aps INTEGER NOT NULL,
+                connected_aps INTEGER NOT NULL,
+                bssids INTEGER NOT NULL,
+                bssids_24ghz INTEGER NOT NULL,
+                bssids_5ghz INTEGER NOT NULL,
+                bssids_6ghz INTEGER NOT NULL,
+                ssids INTEGER NOT NULL,
+                PRIMARY KEY (run_id, site)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create site_summary table")?;
+
+        // How the collection command went on each connected AP in each run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cli_results (
+                run_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                hostname TEXT,
+                status TEXT NOT NULL,
+                message TEXT,
+                PRIMARY KEY (run_id, device_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create cli_results table")?;
+
+        // Progress of `--spread` collections, so an interrupted one can resume
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS spread_collections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                command TEXT NOT NULL,
+                deadline INTEGER NOT NULL,
+                finished BOOLEAN NOT NULL DEFAULT 0,
+                owner_id INTEGER
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create spread_collections table")?;
+        self.add_column_if_missing("spread_collections", "owner_id", "INTEGER").await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS spread_outputs (
+                collection_id INTEGER NOT NULL,
+                device_id INTEGER NOT NULL,
+                output TEXT NOT NULL,
+                PRIMARY KEY (collection_id, device_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create spread_outputs table")?;
+
+        // Devices and parsed results of `collect` runs, which `export` turns into files
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS run_snapshots (
+                run_id INTEGER PRIMARY KEY REFERENCES runs (id) ON DELETE CASCADE,
+                devices TEXT NOT NULL,
+                results TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create run_snapshots table")?;
+
+        // `db merge` used to copy into these; it now imports into devices and interfaces
+        for table in ["merged_devices", "merged_interfaces"] {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", table))
+                .execute(&self.pool)
+                .await
+                .context(format!("Failed to drop {} table", table))?;
+        }
+
+        // BSSID counts over time for Grafana: one row per run and series, with
+        // the run's start as epoch seconds. Partial runs (`--only-new`) have no
+        // BSSID total and are left out.
+        sqlx::query(
+            r#"
+            CREATE VIEW IF NOT EXISTS grafana_bssids_by_ssid AS
+            SELECT r.id AS run_id, CAST(strftime('%s', r.started_at) AS INTEGER) AS time,
+                   b.ssid AS ssid, COUNT(*) AS bssids
+            FROM run_bssids b
+            JOIN runs r ON r.id = b.run_id
+            WHERE r.bssids IS NOT NULL
+            GROUP BY r.id, b.ssid
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create grafana_bssids_by_ssid view")?;
+
+        sqlx::query(
+            r#"
+            CREATE VIEW IF NOT EXISTS grafana_bssids_by_site AS
+            SELECT r.id AS run_id, CAST(strftime('%s', r.started_at) AS INTEGER) AS time,
+                   COALESCE(h.site, 'Unassigned') AS site, COUNT(DISTINCT b.device_id || '/' || b.mac) AS bssids
+            FROM run_bssids b
+            JOIN runs r ON r.id = b.run_id
+            LEFT JOIN device_history h ON h.run_id = b.run_id AND h.device_id = b.device_id
+            WHERE r.bssids IS NOT NULL
+            GROUP BY r.id, COALESCE(h.site, 'Unassigned')
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create grafana_bssids_by_site view")?;
+
+        Ok(())
+    }
+
+    /// Rebuild an interfaces table created by an older version, which has no
+    /// foreign key to devices. SQLite can't add one in place, so the rows are
+    /// copied into a new table; interfaces of devices that no longer exist are dropped.
+    async fn migrate_interfaces_foreign_key(&self) -> Result<()> {
+        let (legacy,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'interfaces')
+               AND NOT EXISTS (SELECT 1 FROM pragma_foreign_key_list('interfaces'))
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to inspect interfaces table")?;
+
+        if !legacy {
+            return Ok(());
+        }
+
+        println!("Adding foreign key from interfaces to devices...");
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        sqlx::query("ALTER TABLE interfaces RENAME TO interfaces_legacy")
+            .execute(&mut *tx)
+            .await
+            .context("Failed to rename interfaces table")?;
+        sqlx::query(CREATE_INTERFACES_TABLE)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to create interfaces table")?;
+        sqlx::query(
+            r#"
+            INSERT INTO interfaces (id, device_id, hostname, name, mac, mode, state, channel, vlan, radio, hive, ssid, fetched_at)
+            SELECT id, device_id, hostname, name, mac, mode, state, channel, vlan, radio, hive, ssid, fetched_at
+            FROM interfaces_legacy
+            WHERE device_id IN (SELECT id FROM devices)
+            "#,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to copy interfaces")?;
+        sqlx::query("DROP TABLE interfaces_legacy")
+            .execute(&mut *tx)
+            .await
+            .context("Failed to drop old interfaces table")?;
+        tx.commit().await.context("Failed to commit interfaces migration")?;
+
+        Ok(())
+    }
+
+    /// Add a column that databases created by older versions don't have yet
+    async fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let (exists,): (bool,) = sqlx::query_as("SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("Failed to inspect {} table", table))?;
+
+        if !exists {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await
+                .context(format!("Failed to add {}.{}", table, column))?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace all devices in a single transaction.
+    /// Existing devices are updated in place so their interfaces are kept;
+    /// devices missing from `devices` are deleted along with their interfaces.
+    pub async fn insert_devices(&self, devices: &[serde_json::Value]) -> Result<()> {
+        let devices = devices.iter().map(ApiDevice::from_value).collect::<Result<Vec<_>>>()?;
+
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        let ids = serde_json::to_string(&devices.iter().map(|d| d.id).collect::<Vec<_>>())?;
+        // XIQ device IDs are unique across accounts, so an ID already stored
+        // for another account means the database mixes up two regions or
+        // instances; refuse rather than move the device between accounts
+        let taken = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM devices
+            WHERE id IN (SELECT value FROM json_each(?1)) AND ?2 IS NOT NULL AND owner_id IS NOT NULL AND owner_id != ?2
+            "#,
+            ids,
+            self.owner_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to check device owners")?;
+        if !taken.is_empty() {
+            anyhow::bail!(
+                "{} devices (IDs {}) are already stored for another XIQ account; use a separate database for each region or instance",
+                taken.len(),
+                taken.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        // Other accounts' devices are kept; unowned rows predate accounts
+        sqlx::query!(
+            "DELETE FROM devices WHERE id NOT IN (SELECT value FROM json_each(?1)) AND (owner_id IS NULL OR owner_id IS ?2)",
+            ids,
+            self.owner_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to remove old devices")?;
+
+        for device in &devices {
+            let location_id = device.innermost_location_id();
+            let last_connect_time = device.last_connect_time();
+            let extra = device.extra.as_ref().map(|extra| extra.to_string());
+            sqlx::query!(
+                r#"
+                INSERT INTO devices (
+                    id, config_mismatch, connected, description, device_admin_state,
+                    device_function, hostname, ip_address, mac_address, managed_by,
+                    org_id, product_type, serial_number, simulated, software_version,
+                    system_up_time, location_id, location_path, network_policy_name,
+                    hive_name, device_model, last_connect_time, fqdn, extra, owner_id
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (id) DO UPDATE SET
+                    config_mismatch = excluded.config_mismatch,
+                    connected = excluded.connected,
+                    description = excluded.description,
+                    device_admin_state = excluded.device_admin_state,
+                    device_function = excluded.device_function,
+                    hostname = excluded.hostname,
+                    ip_address = excluded.ip_address,
+                    mac_address = excluded.mac_address,
+                    managed_by = excluded.managed_by,
+                    org_id = excluded.org_id,
+                    product_type = excluded.product_type,
+                    serial_number = excluded.serial_number,
+                    simulated = excluded.simulated,
+                    software_version = excluded.software_version,
+                    system_up_time = excluded.system_up_time,
+                    location_id = excluded.location_id,
+                    location_path = excluded.location_path,
+                    network_policy_name = excluded.network_policy_name,
+                    hive_name = excluded.hive_name,
+                    device_model = excluded.device_model,
+                    last_connect_time = excluded.last_connect_time,
+                    fqdn = COALESCE(excluded.fqdn, devices.fqdn),
+                    extra = excluded.extra,
+                    owner_id = COALESCE(excluded.owner_id, devices.owner_id),
+                    fetched_at = CURRENT_TIMESTAMP
+                "#,
+                device.id,
+                device.config_mismatch,
+                device.connected,
+                device.description,
+                device.device_admin_state,
+                device.device_function,
+                device.hostname,
+                device.ip_address,
+                device.mac_address,
+                device.managed_by,
+                device.org_id,
+                device.product_type,
+                device.serial_number,
+                device.simulated,
+                device.software_version,
+                device.system_up_time,
+                location_id,
+                device.location_path,
+                device.network_policy_name,
+                device.hive_name,
+                device.device_model,
+                last_connect_time,
+                device.fqdn,
+                extra,
+                self.owner_id,
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to insert devices")?;
+        }
+
+        tx.commit().await.context("Failed to commit devices")?;
+
+        println!("Successfully saved {} devices to database", devices.len());
+
+        Ok(())
+    }
+
+    /// Replace all parsed interfaces in a single transaction using multi-row inserts.
+    /// APs whose interfaces hash the same as last time keep their rows; interfaces
+    /// of devices missing from `aps` are removed.
+    pub async fn insert_interfaces(&self, aps: &[DeviceInterfaces]) -> Result<InterfaceChanges> {
+        self.save_interfaces(aps, true).await
+    }
+
+    /// Replace the interfaces of just the APs in `aps`, keeping every other
+    /// device's rows, for refreshes that only reach some of the fleet
+    pub async fn update_interfaces(&self, aps: &[DeviceInterfaces]) -> Result<InterfaceChanges> {
+        self.save_interfaces(aps, false).await
+    }
+
+    async fn save_interfaces(&self, aps: &[DeviceInterfaces], prune: bool) -> Result<InterfaceChanges> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        let previous: HashMap<i64, String> = sqlx::query!(
+            r#"
+            SELECT device_id, sha256 FROM interface_hashes
+            WHERE device_id NOT IN (SELECT id FROM devices WHERE ?1 IS NOT NULL AND owner_id IS NOT NULL AND owner_id != ?1)
+            "#,
+            self.owner_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to query interface hashes")?
+        .into_iter()
+        .map(|row| (row.device_id, row.sha256))
+        .collect();
+
+        let hashes: Vec<String> = aps.iter().map(interfaces_hash).collect();
+        let changed: Vec<(&DeviceInterfaces, &String)> = aps
+            .iter()
+            .zip(&hashes)
+            .filter(|(ap, hash)| previous.get(&ap.device_id) != Some(hash))
+            .collect();
+
+        let ids = serde_json::to_string(&aps.iter().map(|ap| ap.device_id).collect::<Vec<_>>())?;
+        let changed_ids = serde_json::to_string(&changed.iter().map(|(ap, _)| ap.device_id).collect::<Vec<_>>())?;
+        if prune {
+            sqlx::query!(
+                r#"
+                DELETE FROM interfaces
+                WHERE (device_id NOT IN (SELECT value FROM json_each(?1)) AND (owner_id IS NULL OR owner_id IS ?3))
+                   OR device_id IN (SELECT value FROM json_each(?2))
+                "#,
+                ids,
+                changed_ids,
+                self.owner_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear interfaces table")?;
+            sqlx::query!(
+                r#"
+                DELETE FROM interface_hashes
+                WHERE device_id NOT IN (SELECT value FROM json_each(?1))
+                  AND device_id NOT IN (SELECT id FROM devices WHERE owner_id IS NOT NULL AND owner_id IS NOT ?2)
+                "#,
+                ids,
+                self.owner_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear interface hashes")?;
+        } else {
+            sqlx::query!(
+                "DELETE FROM interfaces WHERE device_id IN (SELECT value FROM json_each(?1)) AND (?2 IS NULL OR owner_id IS NULL OR owner_id = ?2)",
+                changed_ids,
+                self.owner_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear refreshed interfaces")?;
+        }
+
+        let rows: Vec<_> = changed
+            .iter()
+            .flat_map(|(ap, _)| ap.interfaces.iter().map(move |iface| (*ap, iface)))
+            .collect();
+
+        for chunk in rows.chunks(SQLITE_MAX_VARIABLES / INTERFACE_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT INTO interfaces (device_id, hostname, name, mac, mode, state, channel, vlan, radio, hive, ssid, owner_id) ",
+            );
+
+            builder.push_values(chunk, |mut row, (ap, iface)| {
+                row.push_bind(ap.device_id)
+                    .push_bind(&ap.hostname)
+                    .push_bind(&iface.name)
+                    .push_bind(&iface.mac)
+                    .push_bind(&iface.mode)
+                    .push_bind(&iface.state)
+                    .push_bind(&iface.channel)
+                    .push_bind(&iface.vlan)
+                    .push_bind(&iface.radio)
+                    .push_bind(&iface.hive)
+                    .push_bind(&iface.ssid)
+                    .push_bind(self.owner_id);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to insert interfaces")?;
+        }
+
+        for (ap, hash) in &changed {
+            sqlx::query!(
+                r#"
+                INSERT INTO interface_hashes (device_id, sha256) VALUES (?, ?)
+                ON CONFLICT (device_id) DO UPDATE SET sha256 = excluded.sha256, updated_at = CURRENT_TIMESTAMP
+                "#,
+                ap.device_id,
+                hash
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to save interface hash")?;
+        }
+
+        tx.commit().await.context("Failed to commit interfaces")?;
+
+        println!(
+            "Successfully saved {} interfaces to database ({} devices changed, {} unchanged)",
+            rows.len(),
+            changed.len(),
+            aps.len() - changed.len()
+        );
+
+        Ok(InterfaceChanges {
+            changed: changed.iter().map(|(ap, _)| ap.hostname.clone()).collect(),
+            unchanged: aps.len() - changed.len(),
+        })
+    }
+
+    /// Hostname and `connected` flag of every stored device, as of the last save
+    pub async fn connection_states(&self) -> Result<ConnectionStates> {
+        let rows = sqlx::query!(
+            "SELECT id, hostname, connected FROM devices WHERE ?1 IS NULL OR owner_id IS NULL OR owner_id = ?1",
+            self.owner_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query device connection states")?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.id, (row.hostname.unwrap_or_else(|| "unknown".to_string()), row.connected.unwrap_or(false))))
+            .collect())
+    }
+
+    /// IDs of every stored device of this account
+    pub async fn device_ids(&self) -> Result<HashSet<i64>> {
+        let ids = sqlx::query_scalar!("SELECT id FROM devices WHERE ?1 IS NULL OR owner_id IS NULL OR owner_id = ?1", self.owner_id)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query device IDs")?;
+        Ok(ids.into_iter().collect())
+    }
+
+    /// Record a run and a snapshot of each device's state in the run history.
+    /// Returns the new run ID.
+    pub async fn record_run(&self, command: &str, devices: &[serde_json::Value]) -> Result<i64> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        let devices = devices.iter().map(ApiDevice::from_value).collect::<Result<Vec<_>>>()?;
+
+        let version = env!("CARGO_PKG_VERSION");
+        let run_id = sqlx::query!(
+            "INSERT INTO runs (command, tool_version, owner_id) VALUES (?, ?, ?)",
+            command,
+            version,
+            self.owner_id
+        )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to record run")?
+            .last_insert_rowid();
+
+        for chunk in devices.chunks(SQLITE_MAX_VARIABLES / HISTORY_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR REPLACE INTO device_history (run_id, device_id, hostname, device_function, config_mismatch, connected, site) ",
+            );
+
+            builder.push_values(chunk, |mut row, device| {
+                row.push_bind(run_id)
+                    .push_bind(device.id)
+                    .push_bind(&device.hostname)
+                    .push_bind(&device.device_function)
+                    .push_bind(device.config_mismatch)
+                    .push_bind(device.connected)
+                    .push_bind(&device.site);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record device history")?;
+        }
+
+        // Devices added or removed since the account's previous run
+        let previous = sqlx::query_scalar!(
+            r#"
+            SELECT MAX(run_id) AS "run_id?: i64" FROM device_history
+            WHERE run_id < ?1 AND run_id IN (SELECT id FROM runs WHERE owner_id IS ?2)
+            "#,
+            run_id,
+            self.owner_id
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to find the previous run")?;
+
+        if let Some(previous) = previous {
+            let (added, removed) = (EventKind::DeviceAdded.as_str(), EventKind::DeviceRemoved.as_str());
+            sqlx::query!(
+                r#"
+                INSERT INTO events (run_id, owner_id, device_id, hostname, kind)
+                SELECT ?1, ?2, a.device_id, a.hostname, ?4 FROM device_history a
+                WHERE a.run_id = ?1
+                  AND NOT EXISTS (SELECT 1 FROM device_history b WHERE b.run_id = ?3 AND b.device_id = a.device_id)
+                UNION ALL
+                SELECT ?1, ?2, b.device_id, b.hostname, ?5 FROM device_history b
+                WHERE b.run_id = ?3
+                  AND NOT EXISTS (SELECT 1 FROM device_history a WHERE a.run_id = ?1 AND a.device_id = b.device_id)
+                "#,
+                run_id,
+                self.owner_id,
+                previous,
+                added,
+                removed
+            )
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record device events")?;
+        }
+
+        tx.commit().await.context("Failed to commit run history")?;
+
+        Ok(run_id)
+    }
+
+    /// Store the stage timings of a run, replacing any recorded earlier
+    pub async fn record_run_stages(&self, run_id: i64, stages: &[StageTiming]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for stage in stages {
+            let millis = stage.millis as i64;
+            sqlx::query!(
+                "INSERT OR REPLACE INTO run_stages (run_id, stage, millis) VALUES (?, ?, ?)",
+                run_id,
+                stage.stage,
+                millis
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to record stage timing")?;
+        }
+
+        tx.commit().await.context("Failed to commit stage timings")?;
+
+        Ok(())
+    }
+
+    /// APs added and removed since the previous recorded run, and APs whose
+    /// disconnected streak reached exactly `disconnected_runs` in this run (so
+    /// each streak is reported once). A `disconnected_runs` of 0 skips that check.
+    pub async fn ap_changes(&self, run_id: i64, disconnected_runs: i64) -> Result<ApChanges> {
+        // The previous run of the same account
+        let previous = sqlx::query_scalar!(
+            r#"
+            SELECT MAX(run_id) AS "run_id?: i64" FROM device_history
+            WHERE run_id < ?1
+              AND run_id IN (SELECT id FROM runs WHERE owner_id IS (SELECT owner_id FROM runs WHERE id = ?1))
+            "#,
+            run_id
+        )
+                .fetch_one(&self.pool)
+                .await
+                .context("Failed to find the previous run")?;
+
+        let mut changes = ApChanges::default();
+
+        // Nothing to compare with on the first recorded run
+        if let Some(previous) = previous {
+            changes.added = self.aps_missing_from(run_id, previous).await.context("Failed to find added APs")?;
+            changes.removed = self.aps_missing_from(previous, run_id).await.context("Failed to find removed APs")?;
+        }
+
+        if disconnected_runs > 0 {
+            changes.disconnected = sqlx::query_as!(
+                DisconnectedAp,
+                r#"
+                SELECT cur.device_id, COALESCE(cur.hostname, 'unknown') AS "hostname!: String", COUNT(*) AS "runs!: i64"
+                FROM device_history cur
+                JOIN device_history h
+                    ON h.device_id = cur.device_id
+                    AND h.run_id <= cur.run_id
+                    AND h.connected = 0
+                    AND h.run_id > COALESCE(
+                        (SELECT MAX(s.run_id) FROM device_history s
+                         WHERE s.device_id = cur.device_id AND s.connected = 1 AND s.run_id <= cur.run_id), 0)
+                WHERE cur.run_id = ?1 AND cur.connected = 0 AND UPPER(cur.device_function) = 'AP'
+                GROUP BY cur.device_id, cur.hostname
+                HAVING COUNT(*) = ?2
+                ORDER BY cur.hostname
+                "#,
+                run_id,
+                disconnected_runs,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to find disconnected APs")?;
+        }
+
+        Ok(changes)
+    }
+
+    /// APs recorded in `run_id` but not in `other_run_id`
+    async fn aps_missing_from(&self, run_id: i64, other_run_id: i64) -> sqlx::Result<Vec<ApChange>> {
+        sqlx::query_as!(
+            ApChange,
+            r#"
+            SELECT a.device_id, COALESCE(a.hostname, 'unknown') AS "hostname!: String"
+            FROM device_history a
+            WHERE a.run_id = ?1 AND UPPER(a.device_function) = 'AP'
+              AND NOT EXISTS (
+                  SELECT 1 FROM device_history b WHERE b.run_id = ?2 AND b.device_id = a.device_id)
+            ORDER BY a.hostname
+            "#,
+            run_id,
+            other_run_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Update first/last seen for every access-mode BSSID in a run.
+    /// Returns the BSSIDs seen for the first time; empty on the first
+    /// recorded run, when everything would be new.
+    pub async fn record_bssids(&self, run_id: i64, results: &[DeviceInterfaces]) -> Result<Vec<NewBssid>> {
+        let rows: Vec<_> = results
+            .iter()
+            .flat_map(|r| r.interfaces.iter().filter(|i| i.is_access()).map(move |i| (r, i)))
+            .collect();
+
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        // History of the run's account; its first run has no new BSSIDs
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count: i64" FROM bssid_history h
+            JOIN runs r ON r.id = h.first_run_id
+            WHERE r.owner_id IS (SELECT owner_id FROM runs WHERE id = ?)
+            "#,
+            run_id
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to count BSSID history")?;
+
+        for chunk in rows.chunks(SQLITE_MAX_VARIABLES / BSSID_HISTORY_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT INTO bssid_history (device_id, mac, ssid, hostname, first_run_id, last_run_id) ",
+            );
+
+            builder.push_values(chunk, |mut row, (ap, iface)| {
+                row.push_bind(ap.device_id)
+                    .push_bind(&iface.mac)
+                    .push_bind(&iface.ssid)
+                    .push_bind(&ap.hostname)
+                    .push_bind(run_id)
+                    .push_bind(run_id);
+            });
+            builder.push(
+                " ON CONFLICT (device_id, mac, ssid) DO UPDATE SET \
+                 hostname = excluded.hostname, last_seen = CURRENT_TIMESTAMP, last_run_id = excluded.last_run_id",
+            );
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to update BSSID history")?;
+        }
+
+        for chunk in rows.chunks(SQLITE_MAX_VARIABLES / RUN_BSSID_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> =
+                QueryBuilder::new("INSERT OR IGNORE INTO run_bssids (run_id, device_id, hostname, mac, ssid, radio, channel) ");
+
+            builder.push_values(chunk, |mut row, (ap, iface)| {
+                row.push_bind(run_id)
+                    .push_bind(ap.device_id)
+                    .push_bind(&ap.hostname)
+                    .push_bind(&iface.mac)
+                    .push_bind(&iface.ssid)
+                    .push_bind(&iface.radio)
+                    .push_bind(&iface.channel);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record run BSSIDs")?;
+        }
+
+        let new = if existing == 0 {
+            Vec::new()
+        } else {
+            sqlx::query_as!(
+                NewBssid,
+                r#"
+                SELECT device_id, COALESCE(hostname, '') AS "hostname!: String", mac, ssid FROM bssid_history
+                WHERE first_run_id = ? ORDER BY hostname, mac
+                "#,
+                run_id,
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to query new BSSIDs")?
+        };
+
+        tx.commit().await.context("Failed to commit BSSID history")?;
+
+        Ok(new)
+    }
+
+    /// Record the BSSIDs added and removed, SSIDs renamed and channels
+    /// changed on each AP collected in a run, against the last run that
+    /// collected it. Returns how many events were recorded.
+    pub async fn record_bssid_events(&self, run_id: i64) -> Result<usize> {
+        let rows = sqlx::query_as!(
+            RunBssidDetail,
+            r#"
+            SELECT r.run_id, r.device_id, COALESCE(r.hostname, '') AS "hostname!: String", r.mac, r.ssid,
+                   COALESCE(r.radio, '') AS "radio!: String", COALESCE(r.channel, '') AS "channel!: String"
+            FROM run_bssids r
+            WHERE r.device_id IN (SELECT device_id FROM run_bssids WHERE run_id = ?1)
+              AND (r.run_id = ?1
+                   OR r.run_id = (SELECT MAX(p.run_id) FROM run_bssids p
+                                  WHERE p.device_id = r.device_id AND p.run_id < ?1))
+            ORDER BY r.device_id, r.mac, r.ssid
+            "#,
+            run_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query run BSSIDs")?;
+
+        let changes = events::bssid_events(run_id, &rows);
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in changes.chunks(SQLITE_MAX_VARIABLES / EVENT_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT INTO events (run_id, owner_id, device_id, hostname, kind, subject, old_value, new_value) ",
+            );
+
+            builder.push_values(chunk, |mut row, change| {
+                row.push_bind(run_id)
+                    .push_bind(self.owner_id)
+                    .push_bind(change.device_id)
+                    .push_bind(&change.hostname)
+                    .push_bind(change.kind.as_str())
+                    .push_bind(&change.subject)
+                    .push_bind(&change.old_value)
+                    .push_bind(&change.new_value);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record BSSID events")?;
+        }
+
+        tx.commit().await.context("Failed to commit BSSID events")?;
+
+        Ok(changes.len())
+    }
+
+    /// The most recent `limit` events, oldest first
+    pub async fn events_tail(&self, limit: i64) -> Result<Vec<Event>> {
+        let mut events = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT id AS "id!: i64", run_id, recorded_at AS "recorded_at!: String", device_id,
+                   COALESCE(hostname, '') AS "hostname!: String", kind, subject, old_value, new_value
+            FROM events
+            WHERE ?1 IS NULL OR owner_id IS ?1
+            ORDER BY id DESC
+            LIMIT ?2
+            "#,
+            self.owner_id,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query events")?;
+
+        events.reverse();
+        Ok(events)
+    }
+
+    /// Events recorded at or after `since` (`YYYY-MM-DD HH:MM:SS`, UTC)
+    pub async fn events_since(&self, since: &str) -> Result<Vec<Event>> {
+        sqlx::query_as!(
+            Event,
+            r#"
+            SELECT id AS "id!: i64", run_id, recorded_at AS "recorded_at!: String", device_id,
+                   COALESCE(hostname, '') AS "hostname!: String", kind, subject, old_value, new_value
+            FROM events
+            WHERE recorded_at >= ?1 AND (?2 IS NULL OR owner_id IS ?2)
+            ORDER BY id
+            "#,
+            since,
+            self.owner_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query events")
+    }
+
+    /// Store the radio power and PHY mode collected in a run
+    pub async fn record_radio_details(&self, run_id: i64, details: &[RadioDetail]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in details.chunks(SQLITE_MAX_VARIABLES / RADIO_DETAIL_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR REPLACE INTO radio_details (run_id, device_id, hostname, radio, tx_power_dbm, phy_mode) ",
+            );
+
+            builder.push_values(chunk, |mut row, detail| {
+                row.push_bind(run_id)
+                    .push_bind(detail.device_id)
+                    .push_bind(&detail.hostname)
+                    .push_bind(&detail.radio)
+                    .push_bind(detail.tx_power_dbm)
+                    .push_bind(&detail.phy_mode);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record radio details")?;
+        }
+
+        tx.commit().await.context("Failed to commit radio details")?;
+
+        Ok(())
+    }
+
+    /// Store the CLI status of each connected AP in a run
+    pub async fn record_cli_statuses(&self, run_id: i64, statuses: &[DeviceStatus]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in statuses.chunks(SQLITE_MAX_VARIABLES / CLI_RESULT_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> =
+                QueryBuilder::new("INSERT OR REPLACE INTO cli_results (run_id, device_id, hostname, status, message) ");
+
+            builder.push_values(chunk, |mut row, status| {
+                row.push_bind(run_id)
+                    .push_bind(status.device_id)
+                    .push_bind(&status.hostname)
+                    .push_bind(status.status.label())
+                    .push_bind(&status.message);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record CLI statuses")?;
+        }
+
+        tx.commit().await.context("Failed to commit CLI statuses")?;
+
+        Ok(())
+    }
+
+    /// Latest unfinished `--spread` collection of `command` by this account
+    pub async fn unfinished_spread(&self, command: &str) -> Result<Option<SpreadCollection>> {
+        sqlx::query_as!(
+            SpreadCollection,
+            "SELECT id, deadline FROM spread_collections WHERE command = ?1 AND owner_id IS ?2 AND NOT finished ORDER BY id DESC LIMIT 1",
+            command,
+            self.owner_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to query spread collections")
+    }
+
+    /// Start a `--spread` collection of `command` ending at `deadline` (epoch seconds)
+    pub async fn create_spread(&self, command: &str, deadline: i64) -> Result<SpreadCollection> {
+        let id = sqlx::query!(
+            "INSERT INTO spread_collections (command, deadline, owner_id) VALUES (?, ?, ?)",
+            command,
+            deadline,
+            self.owner_id
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to record spread collection")?
+        .last_insert_rowid();
+
+        Ok(SpreadCollection { id, deadline })
+    }
+
+    /// CLI output collected so far in a `--spread` collection, by device
+    pub async fn spread_outputs(&self, collection_id: i64) -> Result<Vec<(i64, String)>> {
+        let rows = sqlx::query!("SELECT device_id, output FROM spread_outputs WHERE collection_id = ?", collection_id)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query spread outputs")?;
+
+        Ok(rows.into_iter().map(|r| (r.device_id, r.output)).collect())
+    }
+
+    /// Keep the successful outputs of a chunk; failed APs are retried on resume
+    pub async fn record_spread_outputs(&self, collection_id: i64, results: &[CliResult]) -> Result<()> {
+        let succeeded: Vec<&CliResult> = results.iter().filter(|r| r.succeeded()).collect();
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in succeeded.chunks(SQLITE_MAX_VARIABLES / SPREAD_OUTPUT_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> =
+                QueryBuilder::new("INSERT OR REPLACE INTO spread_outputs (collection_id, device_id, output) ");
+
+            builder.push_values(chunk, |mut row, result| {
+                row.push_bind(collection_id)
+                    .push_bind(result.device_id)
+                    .push_bind(&result.output);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record spread outputs")?;
+        }
+
+        tx.commit().await.context("Failed to commit spread outputs")?;
+
+        Ok(())
+    }
+
+    pub async fn finish_spread(&self, collection_id: i64) -> Result<()> {
+        sqlx::query!("UPDATE spread_collections SET finished = 1 WHERE id = ?", collection_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to finish spread collection")?;
+
+        Ok(())
+    }
+
+    /// Store the mesh links collected in a run
+    pub async fn record_mesh_links(&self, run_id: i64, links: &[MeshLink]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in links.chunks(SQLITE_MAX_VARIABLES / MESH_LINK_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR REPLACE INTO mesh_links (run_id, device_id, hostname, hive, interface, neighbor_mac, \
+                 neighbor_device_id, neighbor_hostname, state, metric, rssi) ",
+            );
+
+            builder.push_values(chunk, |mut row, link| {
+                row.push_bind(run_id)
+                    .push_bind(link.device_id)
+                    .push_bind(&link.hostname)
+                    .push_bind(&link.hive)
+                    .push_bind(&link.interface)
+                    .push_bind(&link.neighbor_mac)
+                    .push_bind(link.neighbor_device_id)
+                    .push_bind(&link.neighbor_hostname)
+                    .push_bind(&link.state)
+                    .push_bind(link.metric)
+                    .push_bind(link.rssi);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record mesh links")?;
+        }
+
+        tx.commit().await.context("Failed to commit mesh links")?;
+
+        Ok(())
+    }
+
+    pub async fn record_site_summary(&self, run_id: i64, summary: &[SiteSummary]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in summary.chunks(SQLITE_MAX_VARIABLES / SITE_SUMMARY_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT OR REPLACE INTO site_summary (run_id, site, aps, connected_aps, bssids, \
+                 bssids_24ghz, bssids_5ghz, bssids_6ghz, ssids) ",
+            );
+
+            builder.push_values(chunk, |mut row, site| {
+                row.push_bind(run_id)
+                    .push_bind(&site.site)
+                    .push_bind(site.aps as i64)
+                    .push_bind(site.connected_aps as i64)
+                    .push_bind(site.bssids as i64)
+                    .push_bind(site.bssids_24ghz as i64)
+                    .push_bind(site.bssids_5ghz as i64)
+                    .push_bind(site.bssids_6ghz as i64)
+                    .push_bind(site.ssids as i64);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record site summary")?;
+        }
+
+        tx.commit().await.context("Failed to commit site summary")?;
+
+        Ok(())
+    }
+
+    /// Named access-mode interfaces from the last collection run
+    pub async fn access_interfaces(&self) -> Result<Vec<AccessInterface>> {
+        sqlx::query_as!(
+            AccessInterface,
+            r#"
+            SELECT device_id, COALESCE(hostname, '') AS "hostname!: String", name AS "name!", COALESCE(mac, '') AS "mac!: String"
+            FROM interfaces
+            WHERE LOWER(mode) = 'access' AND COALESCE(name, '') <> '' ORDER BY hostname, name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query access interfaces")
+    }
+
+    /// Store the counters polled in a `stats` run
+    pub async fn insert_interface_stats(&self, run_id: i64, counters: &[InterfaceCounters]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in counters.chunks(SQLITE_MAX_VARIABLES / INTERFACE_STATS_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT INTO interface_stats (run_id, device_id, hostname, interface, mac, rx_bytes, tx_bytes, clients) ",
+            );
+
+            builder.push_values(chunk, |mut row, c| {
+                row.push_bind(run_id)
+                    .push_bind(c.device_id)
+                    .push_bind(&c.hostname)
+                    .push_bind(&c.interface)
+                    .push_bind(&c.mac)
+                    .push_bind(c.rx_bytes)
+                    .push_bind(c.tx_bytes)
+                    .push_bind(c.clients);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to insert interface stats")?;
+        }
+
+        tx.commit().await.context("Failed to commit interface stats")?;
+
+        println!("Successfully saved {} interface counter(s) to database", counters.len());
+
+        Ok(())
+    }
+
+    /// Each device's BSSID MACs in a run next to those from the last earlier
+    /// run that collected the device. Devices seen for the first time are left out.
+    pub async fn bssid_set_changes(&self, run_id: i64) -> Result<Vec<BssidSetChange>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT r.device_id, r.run_id, r.hostname, r.mac
+            FROM run_bssids r
+            WHERE r.run_id = ?1
+               OR r.run_id = (SELECT MAX(p.run_id) FROM run_bssids p
+                              WHERE p.device_id = r.device_id AND p.run_id < ?1)
+            ORDER BY r.device_id, r.mac
+            "#,
+            run_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query run BSSIDs")?;
+
+        let mut changes: BTreeMap<i64, BssidSetChange> = BTreeMap::new();
+        for row in rows {
+            let change = changes.entry(row.device_id).or_insert_with(|| BssidSetChange {
+                device_id: row.device_id,
+                ..Default::default()
+            });
+            if row.run_id == run_id {
+                change.hostname = row.hostname.unwrap_or_default();
+                change.current.push(row.mac);
+            } else {
+                change.previous_run_id = row.run_id;
+                change.previous.push(row.mac);
+            }
+        }
+
+        Ok(changes
+            .into_values()
+            .filter(|c| !c.current.is_empty() && !c.previous.is_empty())
+            .collect())
+    }
+
+    /// Store the number of BSSIDs a run collected
+    pub async fn set_run_bssids(&self, run_id: i64, bssids: usize) -> Result<()> {
+        let bssids = bssids as i64;
+        sqlx::query!("UPDATE runs SET bssids = ? WHERE id = ?", bssids, run_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update run BSSID count")?;
+
+        Ok(())
+    }
+
+    /// BSSID count of the most recent run before `run_id` that recorded one
+    pub async fn previous_run_bssids(&self, run_id: i64) -> Result<Option<i64>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT bssids AS "bssids!" FROM runs
+            WHERE id < ?1 AND bssids IS NOT NULL AND owner_id IS (SELECT owner_id FROM runs WHERE id = ?1)
+            ORDER BY id DESC LIMIT 1
+            "#,
+            run_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to query previous run")
+    }
+
+    /// Devices currently in config mismatch, with how long each has been
+    /// mismatched according to the run history (oldest first)
+    pub async fn config_mismatches(&self) -> Result<Vec<MismatchedDevice>> {
+        // The current streak starts after the last run that saw the device in sync
+        sqlx::query_as!(
+            MismatchedDevice,
+            r#"
+            SELECT d.id, COALESCE(d.hostname, 'unknown') AS "hostname!: String",
+                   MIN(r.started_at) AS "since?: String",
+                   CAST((julianday('now') - julianday(MIN(r.started_at))) * 86400 AS INTEGER) AS "age_secs?: i64",
+                   COUNT(h.run_id) AS "runs!: i64"
+            FROM devices d
+            LEFT JOIN device_history h
+                ON h.device_id = d.id
+                AND h.config_mismatch = 1
+                AND h.run_id > COALESCE(
+                    (SELECT MAX(s.run_id) FROM device_history s
+                     WHERE s.device_id = d.id AND s.config_mismatch = 0), 0)
+            LEFT JOIN runs r ON r.id = h.run_id
+            WHERE d.config_mismatch = 1 AND (?1 IS NULL OR d.owner_id IS NULL OR d.owner_id = ?1)
+            GROUP BY d.id, d.hostname
+            ORDER BY MIN(r.started_at) IS NULL, MIN(r.started_at), d.hostname
+            "#,
+            self.owner_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query config mismatches")
+    }
+
+    /// Devices whose `system_up_time` (the boot time in epoch milliseconds)
+    /// is less than `within` ago, most recent boot first
+    pub async fn rebooted_devices(&self, within: Duration) -> Result<Vec<RebootedDevice>> {
+        let within = within.as_secs() as i64;
+        sqlx::query_as!(
+            RebootedDevice,
+            r#"
+            SELECT id, COALESCE(hostname, 'unknown') AS "hostname!: String",
+                   datetime(system_up_time / 1000, 'unixepoch') AS "booted_at!: String",
+                   CAST(strftime('%s', 'now') AS INTEGER) - system_up_time / 1000 AS "uptime_secs!: i64"
+            FROM devices
+            WHERE system_up_time / 1000 >= CAST(strftime('%s', 'now') AS INTEGER) - ?
+            ORDER BY system_up_time DESC, hostname
+            "#,
+            within,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query rebooted devices")
+    }
+
+    /// Devices not fetched from the API, disconnected devices not connected,
+    /// or APs whose BSSIDs weren't collected, for longer than `after`
+    pub async fn stale_devices(&self, after: Duration) -> Result<Vec<StaleDevice>> {
+        let days = after.as_secs_f64() / 86400.0;
+        sqlx::query_as!(
+            StaleDevice,
+            r#"
+            SELECT d.id, COALESCE(d.hostname, 'unknown') AS "hostname!: String",
+                   COALESCE(d.connected, 0) AS "connected!: bool",
+                   d.fetched_at AS "fetched_at?: String",
+                   d.last_connect_time AS "last_connect_time?: String",
+                   MAX(b.last_seen) AS "last_seen?: String"
+            FROM devices d
+            LEFT JOIN bssid_history b ON b.device_id = d.id
+            WHERE ?2 IS NULL OR d.owner_id IS NULL OR d.owner_id = ?2
+            GROUP BY d.id
+            HAVING julianday(d.fetched_at) < julianday('now') - ?1
+                OR (COALESCE(d.connected, 0) = 0 AND julianday(d.last_connect_time) < julianday('now') - ?1)
+                OR julianday(MAX(b.last_seen)) < julianday('now') - ?1
+            ORDER BY d.hostname, d.id
+            "#,
+            days,
+            self.owner_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query stale devices")
+    }
+
+    /// Current interfaces and recorded BSSIDs with the normalized `mac`,
+    /// current ones first
+    pub async fn lookup_mac(&self, mac: &str) -> Result<Vec<MacLookup>> {
+        self.lookup_macs(&[mac.to_string()]).await
+    }
+
+    /// Like `lookup_mac` for many normalized MACs at once, ordered by MAC
+    pub async fn lookup_macs(&self, macs: &[String]) -> Result<Vec<MacLookup>> {
+        let macs = serde_json::to_string(macs)?;
+        sqlx::query_as!(
+            MacLookup,
+            r#"
+            SELECT i.mac AS "mac!: String", i.device_id AS "device_id!: i64",
+                   COALESCE(d.hostname, i.hostname, '') AS "hostname!: String",
+                   COALESCE(i.ssid, '') AS "ssid!: String", COALESCE(i.name, '') AS "interface!: String",
+                   COALESCE(i.channel, '') AS "channel!: String", d.location_path AS "site?: String",
+                   COALESCE(h.last_seen, i.fetched_at) AS "last_seen?: String", 1 AS "current!: bool"
+            FROM interfaces i
+            LEFT JOIN devices d ON d.id = i.device_id
+            LEFT JOIN bssid_history h ON h.device_id = i.device_id AND h.mac = i.mac AND h.ssid = i.ssid
+            WHERE i.mac IN (SELECT value FROM json_each(?1))
+            UNION ALL
+            SELECT h.mac, h.device_id, COALESCE(d.hostname, h.hostname, ''), h.ssid, '', '', d.location_path, h.last_seen, 0
+            FROM bssid_history h
+            LEFT JOIN devices d ON d.id = h.device_id
+            WHERE h.mac IN (SELECT value FROM json_each(?1))
+              AND NOT EXISTS (SELECT 1 FROM interfaces i WHERE i.device_id = h.device_id AND i.mac = h.mac AND i.ssid = h.ssid)
+            ORDER BY 1, 9 DESC, 8 DESC
+            "#,
+            macs,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to look up MAC addresses")
+    }
+
+    /// Store gzip-compressed configurations for a backup run
+    pub async fn insert_configs(&self, run_id: i64, configs: &[ConfigBackup]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for config in configs {
+            let sha256 = backup::config_hash(&config.config);
+            let compressed = backup::compress(&config.config)?;
+            sqlx::query!(
+                "INSERT OR REPLACE INTO configs (run_id, device_id, hostname, command, sha256, config) VALUES (?, ?, ?, ?, ?, ?)",
+                run_id,
+                config.device_id,
+                config.hostname,
+                config.command,
+                sha256,
+                compressed,
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to insert configuration")?;
+        }
+
+        tx.commit().await.context("Failed to commit configurations")?;
+
+        Ok(())
+    }
+
+    /// Hash of each device's most recent stored configuration
+    pub async fn latest_config_hashes(&self) -> Result<HashMap<i64, String>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT c.device_id, c.sha256 FROM configs c
+            WHERE c.run_id = (SELECT MAX(l.run_id) FROM configs l WHERE l.device_id = c.device_id)
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query configuration hashes")?;
+
+        Ok(rows.into_iter().map(|row| (row.device_id, row.sha256)).collect())
+    }
+
+    /// Keep the raw CLI output of every AP that returned any, compressed
+    pub async fn record_raw_outputs(
+        &self,
+        run_id: i64,
+        command: &str,
+        devices: &[serde_json::Value],
+        outputs: &[CliResult],
+    ) -> Result<()> {
+        let hostnames: HashMap<i64, &str> = devices
+            .iter()
+            .filter_map(|d| Some((d.get("id")?.as_i64()?, d.get("hostname")?.as_str()?)))
+            .collect();
+        let rows = outputs
+            .iter()
+            .filter(|result| !result.output.is_empty())
+            .map(|result| {
+                let sha256 = crate::manifest::sha256_hex(result.output.as_bytes());
+                Ok((result.device_id, sha256, backup::compress(&result.output)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        for chunk in rows.chunks(SQLITE_MAX_VARIABLES / RAW_OUTPUT_COLUMNS) {
+            let mut builder: QueryBuilder<Sqlite> =
+                QueryBuilder::new("INSERT OR REPLACE INTO raw_outputs (run_id, device_id, hostname, command, sha256, output) ");
+
+            builder.push_values(chunk, |mut row, (device_id, sha256, output)| {
+                row.push_bind(run_id)
+                    .push_bind(device_id)
+                    .push_bind(hostnames.get(device_id).copied())
+                    .push_bind(command)
+                    .push_bind(sha256)
+                    .push_bind(output);
+            });
+
+            builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .context("Failed to record raw outputs")?;
+        }
+
+        tx.commit().await.context("Failed to commit raw outputs")?;
+
+        Ok(())
+    }
+
+    /// Drop the raw output of all but this account's latest `keep` runs that
+    /// have any; other accounts' runs are left alone
+    pub async fn prune_raw_outputs(&self, keep: usize) -> Result<u64> {
+        let keep = keep as i64;
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM raw_outputs
+            WHERE run_id IN (SELECT id FROM runs WHERE owner_id IS ?1)
+              AND run_id NOT IN (
+                  SELECT DISTINCT o.run_id FROM raw_outputs o JOIN runs r ON r.id = o.run_id
+                  WHERE r.owner_id IS ?1
+                  ORDER BY o.run_id DESC
+                  LIMIT ?2
+              )
+            "#,
+            self.owner_id,
+            keep,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to prune raw outputs")?
+        .rows_affected();
+
+        Ok(removed)
+    }
+
+    /// Stored raw output of a device (ID or hostname) from one run, or from
+    /// the latest run that kept any for it
+    pub async fn raw_output(&self, device: &str, run_id: Option<i64>) -> Result<Option<RawOutput>> {
+        let Some(row) = sqlx::query!(
+            r#"
+            SELECT run_id, device_id, hostname, command, sha256, output, fetched_at AS "fetched_at?: String"
+            FROM raw_outputs
+            WHERE (CAST(device_id AS TEXT) = ?1 OR hostname = ?1 COLLATE NOCASE) AND (?2 IS NULL OR run_id = ?2)
+            ORDER BY run_id DESC
+            LIMIT 1
+            "#,
+            device,
+            run_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to load raw output")?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(RawOutput {
+            run_id: row.run_id,
+            device_id: row.device_id,
+            hostname: row.hostname,
+            command: row.command,
+            sha256: row.sha256,
+            output: backup::decompress(&row.output)?,
+            fetched_at: row.fetched_at,
+        }))
+    }
+
+    /// Every stored raw output of a run, by device ID
+    pub async fn run_raw_outputs(&self, run_id: i64) -> Result<Vec<RawOutput>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT run_id, device_id, hostname, command, sha256, output, fetched_at AS "fetched_at?: String"
+            FROM raw_outputs
+            WHERE run_id = ?
+            ORDER BY device_id
+            "#,
+            run_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to load raw outputs")?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(RawOutput {
+                    run_id: row.run_id,
+                    device_id: row.device_id,
+                    hostname: row.hostname,
+                    command: row.command,
+                    sha256: row.sha256,
+                    output: backup::decompress(&row.output)?,
+                    fetched_at: row.fetched_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Stored configuration for a device (ID or hostname) from one run
+    pub async fn load_config(&self, device: &str, run_id: i64) -> Result<Option<String>> {
+        let data = sqlx::query_scalar!(
+            r#"
+            SELECT config FROM configs
+            WHERE run_id = ?1 AND (CAST(device_id AS TEXT) = ?2 OR hostname = ?2 COLLATE NOCASE)
+            "#,
+            run_id,
+            device,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to load configuration")?;
+
+        data.map(|data| backup::decompress(&data)).transpose()
+    }
+
+    /// Attach a note to a stored device, given by ID or hostname
+    pub async fn add_annotation(&self, device: &str, note: &str) -> Result<Annotation> {
+        let found = sqlx::query!(
+            "SELECT id, hostname FROM devices WHERE CAST(id AS TEXT) = ?1 OR hostname = ?1 COLLATE NOCASE",
+            device,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to look up device")?
+        .with_context(|| format!("No device '{}' in the database; run a collection first", device))?;
+
+        let id = sqlx::query!(
+            "INSERT INTO annotations (device_id, hostname, note) VALUES (?, ?, ?)",
+            found.id,
+            found.hostname,
+            note,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to save note")?
+        .last_insert_rowid();
+
+        sqlx::query_as!(
+            Annotation,
+            r#"SELECT id, device_id, hostname, note, created_at AS "created_at?: String" FROM annotations WHERE id = ?"#,
+            id,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to load note")
+    }
+
+    /// Notes of one device (ID or hostname), or of every device, oldest first
+    pub async fn annotations(&self, device: Option<&str>) -> Result<Vec<Annotation>> {
+        sqlx::query_as!(
+            Annotation,
+            r#"
+            SELECT id, device_id, hostname, note, created_at AS "created_at?: String" FROM annotations
+            WHERE ?1 IS NULL OR CAST(device_id AS TEXT) = ?1 OR hostname = ?1 COLLATE NOCASE
+            ORDER BY created_at, id
+            "#,
+            device,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to list notes")
+    }
+
+    /// Each annotated device's notes joined with `; `, oldest first
+    pub async fn device_notes(&self) -> Result<HashMap<i64, String>> {
+        let mut notes: HashMap<i64, String> = HashMap::new();
+        for annotation in self.annotations(None).await? {
+            let joined = notes.entry(annotation.device_id).or_default();
+            if !joined.is_empty() {
+                joined.push_str("; ");
+            }
+            joined.push_str(&annotation.note);
+        }
+        Ok(notes)
+    }
+
+    /// Import the devices and interfaces of another database file (or dump)
+    /// into this one, owned by `owner_id` or, when it's `None`, by the owner
+    /// the source saved them for. Devices are updated in place by ID, and a
+    /// device's interfaces are replaced by the source's.
+    pub async fn merge_from(&self, source: &Path, owner_id: Option<i64>) -> Result<MergeStats> {
+        if !source.is_file() {
+            anyhow::bail!("Database file not found: {}", source.display());
+        }
+
+        // ATTACH is per connection, so keep every statement on one connection
+        let mut conn = self.pool.acquire().await.context("Failed to acquire database connection")?;
+
+        sqlx::query("ATTACH DATABASE ? AS source")
+            .bind(source.to_string_lossy().as_ref())
+            .execute(&mut *conn)
+            .await
+            .context(format!("Failed to attach {}", source.display()))?;
+
+        let result = Self::merge_attached(&mut conn, owner_id).await;
+
+        sqlx::query("DETACH DATABASE source")
+            .execute(&mut *conn)
+            .await
+            .context(format!("Failed to detach {}", source.display()))?;
+
+        result.with_context(|| format!("Failed to merge {}", source.display()))
+    }
+
+    /// Columns of `table` in both this database and the attached source,
+    /// so sources saved by older versions merge the columns they have
+    async fn shared_columns(conn: &mut sqlx::SqliteConnection, table: &str) -> Result<Vec<String>> {
+        let columns: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT name FROM pragma_table_info(?1, 'main')
+            WHERE name NOT IN ('id', 'owner_id') OR (?1 = 'devices' AND name = 'id')
+            INTERSECT
+            SELECT name FROM pragma_table_info(?1, 'source')
+            "#,
+        )
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!("Failed to read {} columns", table))?;
+        Ok(columns.into_iter().map(|(name,)| name).collect())
+    }
+
+    async fn merge_attached(conn: &mut sqlx::SqliteConnection, owner_id: Option<i64>) -> Result<MergeStats> {
+        let tables: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM source.sqlite_master WHERE type = 'table' AND name IN ('devices', 'interfaces')",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to read source schema")?;
+        let has_table = |name: &str| tables.iter().any(|(t,)| t == name);
+
+        if !has_table("devices") {
+            anyhow::bail!("Source has no devices table");
+        }
+
+        let (source_owned,): (bool,) =
+            sqlx::query_as("SELECT COUNT(*) > 0 FROM pragma_table_info('devices', 'source') WHERE name = 'owner_id'")
+                .fetch_one(&mut *conn)
+                .await
+                .context("Failed to read source schema")?;
+        if owner_id.is_none() {
+            let unowned: i64 = if source_owned {
+                sqlx::query_scalar("SELECT COUNT(*) FROM source.devices WHERE owner_id IS NULL")
+            } else {
+                sqlx::query_scalar("SELECT COUNT(*) FROM source.devices")
+            }
+            .fetch_one(&mut *conn)
+            .await
+            .context("Failed to read source devices")?;
+            if unowned > 0 {
+                anyhow::bail!("{} source devices have no owner_id; give one as <owner_id>=<file>", unowned);
+            }
+        }
+
+        let columns = Self::shared_columns(&mut *conn, "devices").await?;
+        let updates: Vec<String> = columns
+            .iter()
+            .filter(|column| *column != "id")
+            .map(|column| format!("{} = excluded.{}", column, column))
+            .collect();
+        let owner = if source_owned { "COALESCE(?, owner_id)" } else { "?" };
+
+        let mut tx = sqlx::Connection::begin(&mut *conn).await.context("Failed to start transaction")?;
+
+        // Device IDs are global in XIQ, so a device stored for another account
+        // is refused rather than moved to the merged one
+        let taken: Vec<i64> = sqlx::query_scalar(&format!(
+            r#"
+            SELECT m.id FROM main.devices m
+            JOIN (SELECT id, {owner} AS owner_id FROM source.devices) s ON s.id = m.id
+            WHERE m.owner_id IS NOT NULL AND m.owner_id IS NOT s.owner_id
+            ORDER BY m.id
+            "#,
+            owner = owner,
+        ))
+        .bind(owner_id)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to check device owners")?;
+        if !taken.is_empty() {
+            anyhow::bail!(
+                "{} devices (IDs {}) are already stored for another XIQ account",
+                taken.len(),
+                taken.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        // `WHERE true` keeps SQLite from reading ON CONFLICT as a join constraint
+        let devices = sqlx::query(&format!(
+            r#"
+            INSERT INTO main.devices ({columns}, owner_id)
+            SELECT {columns}, {owner} FROM source.devices WHERE true
+            ON CONFLICT (id) DO UPDATE SET {updates}, owner_id = excluded.owner_id
+            "#,
+            columns = columns.join(", "),
+            owner = owner,
+            updates = updates.join(", "),
+        ))
+        .bind(owner_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to merge devices")?
+        .rows_affected();
+
+        let mut interfaces = 0;
+        if has_table("interfaces") {
+            // The saved hashes no longer describe the merged interfaces. Only
+            // the merged devices, now all of the merged account, are replaced.
+            for table in ["interfaces", "interface_hashes"] {
+                sqlx::query(&format!(
+                    r#"
+                    DELETE FROM main.{}
+                    WHERE device_id IN (SELECT DISTINCT device_id FROM source.interfaces)
+                      AND device_id IN (SELECT id FROM source.devices)
+                    "#,
+                    table
+                ))
+                .execute(&mut *tx)
+                .await
+                .context("Failed to replace merged interfaces")?;
+            }
+
+            let columns = Self::shared_columns(&mut tx, "interfaces").await?.join(", ");
+            interfaces = sqlx::query(&format!(
+                r#"
+                INSERT INTO main.interfaces ({columns}, owner_id)
+                SELECT {columns}, (SELECT owner_id FROM main.devices WHERE id = device_id)
+                FROM source.interfaces
+                WHERE device_id IN (SELECT id FROM source.devices)
+                "#,
+                columns = columns,
+            ))
+            .execute(&mut *tx)
+            .await
+            .context("Failed to merge interfaces")?
+            .rows_affected();
+        }
+
+        tx.commit().await.context("Failed to commit merge")?;
+
+        Ok(MergeStats { devices, interfaces })
+    }
+
+    pub async fn count_devices(&self) -> Result<i64> {
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count: i64" FROM devices"#)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count devices")
+    }
+
+    /// Stored devices and connected APs, counting separately the APs whose
+    /// `managed_by` is one of `managed_by` (case-insensitive)
+    pub async fn inventory_counts(&self, managed_by: &[String]) -> Result<InventoryCounts> {
+        let managed_by = serde_json::to_string(managed_by)?;
+        sqlx::query_as!(
+            InventoryCounts,
+            r#"
+            SELECT COUNT(*) AS "devices!: i64",
+                   COALESCE(SUM(device_function = 'AP' AND connected), 0) AS "connected_aps!: i64",
+                   COALESCE(SUM(device_function = 'AP' AND connected
+                                AND UPPER(managed_by) IN (SELECT UPPER(value) FROM json_each(?))), 0) AS "local_aps!: i64"
+            FROM devices
+            "#,
+            managed_by,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to count stored devices")
+    }
+
+    /// Every device with its BSSID count, ordered by hostname
+    pub async fn device_summaries(&self) -> Result<Vec<DeviceSummary>> {
+        sqlx::query_as!(
+            DeviceSummary,
+            r#"
+            SELECT d.id, d.hostname, d.device_function, d.product_type, d.ip_address,
+                   d.mac_address, d.connected,
+                   (SELECT COUNT(*) FROM interfaces i
+                    WHERE i.device_id = d.id AND LOWER(i.mode) = 'access') AS "bssids!: i64"
+            FROM devices d
+            WHERE ?1 IS NULL OR d.owner_id IS NULL OR d.owner_id = ?1
+            ORDER BY d.hostname COLLATE NOCASE, d.id
+            "#,
+            self.owner_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to list devices")
+    }
+
+    /// Access-mode interfaces whose MAC, SSID or hostname contains `query`.
+    /// MACs match with or without separators, in any case.
+    pub async fn search_bssids(&self, query: &str, limit: i64) -> Result<Vec<BssidMatch>> {
+        let query = query.trim();
+        let pattern = format!("%{}%", query);
+        // MAC fragments like "0011.2233" are also matched against the MAC without separators
+        let is_mac_fragment = query.chars().any(|c| c.is_ascii_hexdigit())
+            && query.chars().all(|c| c.is_ascii_hexdigit() || ":.-".contains(c));
+        let compact = if is_mac_fragment {
+            let digits: String = query.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+            format!("%{}%", digits)
+        } else {
+            String::new()
+        };
+
+        sqlx::query_as!(
+            BssidMatch,
+            r#"
+            SELECT device_id, hostname, name, mac, ssid, channel, radio, state
+            FROM interfaces
+            WHERE LOWER(mode) = 'access'
+              AND (mac LIKE ?1 OR REPLACE(mac, ':', '') LIKE ?2 OR ssid LIKE ?1 OR hostname LIKE ?1)
+              AND (?4 IS NULL OR owner_id IS NULL OR owner_id = ?4)
+            ORDER BY hostname COLLATE NOCASE, name
+            LIMIT ?3
+            "#,
+            pattern,
+            compact,
+            limit,
+            self.owner_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to search BSSIDs")
+    }
+
+    /// Access-mode BSSIDs per SSID, most common first
+    pub async fn ssid_counts(&self) -> Result<Vec<LabelCount>> {
+        self.access_counts_by("ssid").await
+    }
+
+    /// Access-mode BSSIDs per channel, most common first
+    pub async fn channel_counts(&self) -> Result<Vec<LabelCount>> {
+        self.access_counts_by("channel").await
+    }
+
+    async fn access_counts_by(&self, column: &str) -> Result<Vec<LabelCount>> {
+        sqlx::query_as(&format!(
+            r#"
+            SELECT COALESCE({column}, '') AS label, COUNT(*) AS count
+            FROM interfaces
+            WHERE LOWER(mode) = 'access'
+            GROUP BY label
+            ORDER BY count DESC, label
+            "#
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .context(format!("Failed to count BSSIDs by {}", column))
+    }
+
+    /// Devices matching every set filter, ordered by hostname
+    pub async fn query_devices(&self, query: &DeviceQuery) -> Result<Vec<DeviceRow>> {
+        let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+            r#"
+            SELECT id, hostname, device_function, product_type, serial_number, ip_address, fqdn,
+                   mac_address, software_version, connected, config_mismatch, org_id, fetched_at, extra,
+                   owner_id
+            FROM devices WHERE 1 = 1"#,
+        );
+        if let Some(id) = query.id {
+            builder.push(" AND id = ").push_bind(id);
+        }
+        if let Some(hostname) = &query.hostname {
+            builder.push(" AND hostname LIKE ").push_bind(format!("%{}%", hostname));
+        }
+        if let Some(function) = &query.device_function {
+            builder.push(" AND LOWER(device_function) = LOWER(").push_bind(function).push(")");
+        }
+        if let Some(connected) = query.connected {
+            builder.push(" AND connected = ").push_bind(connected);
+        }
+        if let Some(mismatch) = query.config_mismatch {
+            builder.push(" AND config_mismatch = ").push_bind(mismatch);
+        }
+        if let Some(owner_id) = query.owner_id {
+            builder.push(" AND owner_id = ").push_bind(owner_id);
+        }
+        for filter in &query.json_filters {
+            match &filter.value {
+                Some(value) => {
+                    // json_extract gives 1/0 for booleans, so those are compared by type
+                    builder
+                        .push(" AND CASE json_type(extra, ")
+                        .push_bind(&filter.path)
+                        .push(") WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' ELSE CAST(json_extract(extra, ")
+                        .push_bind(&filter.path)
+                        .push(") AS TEXT) END = ")
+                        .push_bind(value);
+                }
+                None => {
+                    builder.push(" AND json_type(extra, ").push_bind(&filter.path).push(") IS NOT NULL");
+                }
+            }
+        }
+        builder.push(" ORDER BY hostname COLLATE NOCASE, id");
+        if let Some(limit) = query.limit {
+            builder.push(" LIMIT ").push_bind(limit);
+        }
+
+        builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query devices")
+    }
+
+    /// A device's interfaces from the latest run, optionally only one mode (e.g. `access`)
+    pub async fn device_interfaces(&self, device_id: i64, mode: Option<&str>) -> Result<Vec<InterfaceRow>> {
+        sqlx::query_as!(
+            InterfaceRow,
+            r#"
+            SELECT device_id, hostname, name, mac, mode, state, channel, vlan, radio, hive, ssid
+            FROM interfaces
+            WHERE device_id = ?1 AND (?2 IS NULL OR LOWER(mode) = LOWER(?2))
+            ORDER BY id
+            "#,
+            device_id,
+            mode,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query interfaces")
+    }
+
+    /// A device's state in recorded runs, newest first
+    pub async fn device_history(&self, device_id: i64, limit: i64) -> Result<Vec<DeviceHistoryRow>> {
+        sqlx::query_as!(
+            DeviceHistoryRow,
+            r#"
+            SELECT run_id, hostname, config_mismatch, connected
+            FROM device_history
+            WHERE device_id = ?
+            ORDER BY run_id DESC
+            LIMIT ?
+            "#,
+            device_id,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query device history")
+    }
+
+    /// Every device's state recorded for a run
+    pub async fn run_devices(&self, run_id: i64) -> Result<Vec<RunDevice>> {
+        sqlx::query_as!(
+            RunDevice,
+            r#"
+            SELECT device_id, COALESCE(hostname, 'unknown') AS "hostname!: String", connected, config_mismatch
+            FROM device_history
+            WHERE run_id = ?
+            ORDER BY device_id
+            "#,
+            run_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query run devices")
+    }
+
+    /// The access-mode BSSIDs a run collected
+    pub async fn run_bssids(&self, run_id: i64) -> Result<Vec<RunBssid>> {
+        sqlx::query_as!(
+            RunBssid,
+            r#"
+            SELECT device_id, COALESCE(hostname, 'unknown') AS "hostname!: String", mac, ssid
+            FROM run_bssids
+            WHERE run_id = ?
+            ORDER BY device_id, mac, ssid
+            "#,
+            run_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query run BSSIDs")
+    }
+
+    /// Every BSSID a device has broadcast, most recently seen first
+    pub async fn device_bssid_history(&self, device_id: i64) -> Result<Vec<BssidHistoryRow>> {
+        sqlx::query_as!(
+            BssidHistoryRow,
+            r#"
+            SELECT mac, ssid, first_seen AS "first_seen?: String", last_seen AS "last_seen?: String",
+                   first_run_id, last_run_id
+            FROM bssid_history
+            WHERE device_id = ?
+            ORDER BY last_run_id DESC, mac
+            "#,
+            device_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query BSSID history")
+    }
+
+    /// Recorded runs, newest first
+    pub async fn runs(&self, limit: i64) -> Result<Vec<RunRow>> {
+        sqlx::query_as!(
+            RunRow,
+            r#"SELECT id, command, bssids, started_at AS "started_at?: String" FROM runs WHERE ?1 IS NULL OR owner_id IS ?1 ORDER BY id DESC LIMIT ?2"#,
+            self.owner_id,
+            limit,
+        )
+        .fetch_all(&self.pool)
+            .await
+            .context("Failed to query runs")
+    }
+
+    pub async fn run(&self, id: i64) -> Result<Option<RunRow>> {
+        sqlx::query_as!(
+            RunRow,
+            r#"SELECT id, command, bssids, started_at AS "started_at?: String" FROM runs WHERE id = ?"#,
+            id,
+        )
+        .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query run")
+    }
+
+    /// Keep a run's device inventory and parsed results so its exports can be
+    /// written later. The raw output is left out; it's kept in `raw_outputs`.
+    pub async fn record_run_snapshot(&self, run_id: i64, devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> Result<()> {
+        let results: Vec<DeviceInterfaces> = results
+            .iter()
+            .map(|result| DeviceInterfaces { output: String::new(), ..result.clone() })
+            .collect();
+        let devices = serde_json::to_string(devices).context("Failed to serialize devices")?;
+        let results = serde_json::to_string(&results).context("Failed to serialize results")?;
+        sqlx::query!(
+            "INSERT OR REPLACE INTO run_snapshots (run_id, devices, results) VALUES (?, ?, ?)",
+            run_id,
+            devices,
+            results
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to record run snapshot")?;
+
+        Ok(())
+    }
+
+    /// Drop the snapshots of all but this account's latest `keep` collected
+    /// runs; other accounts' runs are left alone
+    pub async fn prune_run_snapshots(&self, keep: usize) -> Result<u64> {
+        let keep = keep as i64;
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM run_snapshots
+            WHERE run_id IN (SELECT id FROM runs WHERE owner_id IS ?1)
+              AND run_id NOT IN (
+                  SELECT s.run_id FROM run_snapshots s JOIN runs r ON r.id = s.run_id
+                  WHERE r.owner_id IS ?1
+                  ORDER BY s.run_id DESC
+                  LIMIT ?2
+              )
+            "#,
+            self.owner_id,
+            keep,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to prune run snapshots")?
+        .rows_affected();
+
+        Ok(removed)
+    }
+
+    /// Stored snapshot of a run, or of the latest run with one, of this account
+    pub async fn run_snapshot(&self, run_id: Option<i64>) -> Result<Option<RunSnapshot>> {
+        let Some(row) = sqlx::query!(
+            r#"
+            SELECT s.run_id, s.devices, s.results
+            FROM run_snapshots s JOIN runs r ON r.id = s.run_id
+            WHERE (?2 IS NULL OR r.owner_id IS ?2)
+              AND s.run_id = COALESCE(?1, (
+                  SELECT MAX(o.run_id) FROM run_snapshots o JOIN runs q ON q.id = o.run_id WHERE ?2 IS NULL OR q.owner_id IS ?2
+              ))
+            "#,
+            run_id,
+            self.owner_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to query run snapshot")?
+        else {
+            return Ok(None);
+        };
+
+        let run = self.run(row.run_id).await?.with_context(|| format!("Run {} no longer exists", row.run_id))?;
+        Ok(Some(RunSnapshot {
+            run,
+            devices: serde_json::from_str(&row.devices).context("Invalid stored devices")?,
+            results: serde_json::from_str(&row.results).context("Invalid stored results")?,
+        }))
+    }
+
+    /// Recorded runs with their length and counts, newest first
+    pub async fn calendar_runs(&self, limit: i64) -> Result<Vec<CalendarRun>> {
+        sqlx::query_as!(
+            CalendarRun,
+            r#"
+            SELECT r.id, r.command, r.bssids, r.started_at AS "started_at!: String",
+                   (SELECT COALESCE(SUM(s.millis), 0) FROM run_stages s WHERE s.run_id = r.id) AS "millis!: i64",
+                   (SELECT COUNT(*) FROM device_history h WHERE h.run_id = r.id) AS "devices!: i64",
+                   (SELECT COUNT(*) FROM cli_results c WHERE c.run_id = r.id AND c.status != 'success') AS "cli_failures!: i64",
+                   (SELECT COUNT(*) FROM events e WHERE e.run_id = r.id) AS "events!: i64"
+            FROM runs r
+            ORDER BY r.id DESC
+            LIMIT ?
+            "#,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query runs")
+    }
+
+    /// Write a consistent copy of the database to `path`, replacing any existing file
+    pub async fn dump_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+        if path.exists() {
+            fs::remove_file(path)
+                .context(format!("Failed to replace existing file: {}", path.display()))?;
+        }
+
+        // VACUUM INTO doesn't take bound parameters, so quote the path as a SQL literal
+        let target = path.to_string_lossy().replace('\'', "''");
+        sqlx::query(&format!("VACUUM INTO '{}'", target))
+            .execute(&self.pool)
+            .await
+            .context(format!("Failed to dump database to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Record a device action (reboot, config push, ...) in the audit trail
+    pub async fn log_audit(&self, action: &str, targets: &str, result: &str) -> Result<()> {
+        sqlx::query!("INSERT INTO audit_log (action, targets, result) VALUES (?, ?, ?)", action, targets, result)
+            .execute(&self.pool)
+            .await
+            .context("Failed to write audit log entry")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_status::CliStatus;
+
+    #[tokio::test]
+    async fn test_batched_inserts_span_chunks() {
+        let dir = std::env::temp_dir().join(format!("xiq-db-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(&dir.join("batch.db"), DEFAULT_POOL_SIZE).await.unwrap();
+
+        // More interface rows than fit in a single statement
+        let count = SQLITE_MAX_VARIABLES / INTERFACE_COLUMNS * 2 + 7;
+        let devices: Vec<_> = (0..count)
+            .map(|i| serde_json::json!({ "id": i, "hostname": format!("AP-{}", i), "connected": true }))
+            .collect();
+        let aps: Vec<_> = (0..count)
+            .map(|i| DeviceInterfaces {
+                device_id: i as i64,
+                hostname: format!("AP-{}", i),
+                output: String::new(),
+                interfaces: vec![crate::parser::InterfaceEntry { name: "wifi0.1".into(), ..Default::default() }],
+            })
+            .collect();
+
+        db.insert_devices(&devices).await.unwrap();
+        db.insert_devices(&devices).await.unwrap();
+        assert_eq!(db.count_devices().await.unwrap(), count as i64);
+        db.insert_interfaces(&aps).await.unwrap();
+        db.insert_interfaces(&aps).await.unwrap();
+        let (interfaces,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM interfaces").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(interfaces, count as i64);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_db_dump() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 1, "hostname": "AP-1" })]).await.unwrap();
+        assert_eq!(db.count_devices().await.unwrap(), 1);
+
+        let dir = std::env::temp_dir().join(format!("xiq-db-dump-test-{}", std::process::id()));
+        let dump = dir.join("dump.db");
+        db.dump_to(&dump).await.unwrap();
+
+        let copy = Database::new(&dump, DEFAULT_POOL_SIZE).await.unwrap();
+        assert_eq!(copy.count_devices().await.unwrap(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_insert_interfaces_skips_unchanged() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let ap = |id: i64, ssid: &str| DeviceInterfaces {
+            device_id: id,
+            hostname: format!("AP-{}", id),
+            output: String::new(),
+            interfaces: vec![crate::parser::InterfaceEntry { name: "wifi0.1".into(), ssid: ssid.into(), ..Default::default() }],
+        };
+        let row_ids = |db: &Database| {
+            let pool = db.pool.clone();
+            async move {
+                let ids: Vec<(i64, i64)> = sqlx::query_as("SELECT device_id, id FROM interfaces ORDER BY device_id")
+                    .fetch_all(&pool)
+                    .await
+                    .unwrap();
+                ids
+            }
+        };
+        db.insert_devices(&[serde_json::json!({ "id": 1 }), serde_json::json!({ "id": 2 }), serde_json::json!({ "id": 3 })])
+            .await
+            .unwrap();
+
+        let changes = db.insert_interfaces(&[ap(1, "Corp"), ap(2, "Corp"), ap(3, "Corp")]).await.unwrap();
+        assert_eq!(changes.changed, vec!["AP-1", "AP-2", "AP-3"]);
+        let before = row_ids(&db).await;
+
+        // Only AP-2 changed; AP-3 wasn't collected and loses its rows
+        let changes = db.insert_interfaces(&[ap(1, "Corp"), ap(2, "Guest")]).await.unwrap();
+        assert_eq!(changes, InterfaceChanges { changed: vec!["AP-2".to_string()], unchanged: 1 });
+        let after = row_ids(&db).await;
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[0], before[0]);
+        assert_ne!(after[1], before[1]);
+
+        // AP-3 is rewritten when it comes back, even with the same interfaces
+        let changes = db.insert_interfaces(&[ap(1, "Corp"), ap(2, "Guest"), ap(3, "Corp")]).await.unwrap();
+        assert_eq!(changes, InterfaceChanges { changed: vec!["AP-3".to_string()], unchanged: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_full_view_device_columns() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let device = serde_json::json!({
+            "id": 1,
+            "hostname": "AP-1",
+            "locations": [{ "id": 10, "name": "Global" }, { "id": 20, "name": "HQ" }, { "id": 30, "name": "Floor 1" }],
+            "network_policy_name": "Corp-Policy",
+            "hive_name": "corp-hive",
+            "device_model": "AP_410C",
+            "last_connect_time": 1700000000000_i64,
+            "extra": { "active_clients": 12 }
+        });
+        db.insert_devices(&[device]).await.unwrap();
+
+        let (location_id, location_path): (Option<i64>, Option<String>) =
+            sqlx::query_as("SELECT location_id, location_path FROM devices").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(location_id, Some(30));
+        assert_eq!(location_path.as_deref(), Some("Global/HQ/Floor 1"));
+
+        let row: (String, String, String, String) = sqlx::query_as(
+            "SELECT network_policy_name, hive_name, device_model, last_connect_time FROM devices",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(row.0, "Corp-Policy");
+        assert_eq!(row.1, "corp-hive");
+        assert_eq!(row.2, "AP_410C");
+        assert_eq!(row.3, "2023-11-14T22:13:20Z");
+
+        let extra: String = sqlx::query_scalar("SELECT extra FROM devices").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(extra, r#"{"active_clients":12}"#);
+    }
+
+    #[tokio::test]
+    async fn test_interfaces_cascade_with_devices() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let ap = |id: i64| DeviceInterfaces {
+            device_id: id,
+            hostname: format!("AP-{}", id),
+            output: String::new(),
+            interfaces: vec![crate::parser::InterfaceEntry { name: "wifi0.1".into(), ..Default::default() }],
+        };
+        let count = |db: &Database| {
+            let pool = db.pool.clone();
+            async move {
+                let (n,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM interfaces").fetch_one(&pool).await.unwrap();
+                n
+            }
+        };
+
+        db.insert_devices(&[serde_json::json!({ "id": 1 }), serde_json::json!({ "id": 2 })]).await.unwrap();
+        db.insert_interfaces(&[ap(1), ap(2)]).await.unwrap();
+        assert_eq!(count(&db).await, 2);
+
+        // Re-importing keeps interfaces of devices that are still there
+        db.insert_devices(&[serde_json::json!({ "id": 1, "hostname": "AP-1" })]).await.unwrap();
+        assert_eq!(count(&db).await, 1);
+        assert!(db.insert_interfaces(&[ap(3)]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interfaces_foreign_key_migration() {
+        let dir = std::env::temp_dir().join(format!("xiq-db-fk-test-{}", std::process::id()));
+        let path = dir.join("legacy.db");
+        let db = Database::new(&path, DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 1 })]).await.unwrap();
+        for statement in [
+            "DROP TABLE interfaces",
+            "CREATE TABLE interfaces (id INTEGER PRIMARY KEY AUTOINCREMENT, device_id INTEGER NOT NULL, hostname TEXT, name TEXT, mac TEXT, mode TEXT, state TEXT, channel TEXT, vlan TEXT, radio TEXT, hive TEXT, ssid TEXT, fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP)",
+            "INSERT INTO interfaces (device_id, name) VALUES (1, 'wifi0.1'), (9, 'wifi0.1')",
+        ] {
+            sqlx::query(statement).execute(&db.pool).await.unwrap();
+        }
+        db.pool.close().await;
+
+        let db = Database::new(&path, DEFAULT_POOL_SIZE).await.unwrap();
+        let (keys,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM pragma_foreign_key_list('interfaces')")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM interfaces").fetch_one(&db.pool).await.unwrap();
+        assert_eq!((keys, rows), (1, 1));
+        db.pool.close().await;
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_config_mismatch_history() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let snapshot = |ap1: bool, ap2: bool| {
+            vec![
+                serde_json::json!({ "id": 1, "hostname": "AP-1", "config_mismatch": ap1 }),
+                serde_json::json!({ "id": 2, "hostname": "AP-2", "config_mismatch": ap2 }),
+            ]
+        };
+
+        for (ap1, ap2) in [(true, false), (false, true), (true, true), (true, true)] {
+            let devices = snapshot(ap1, ap2);
+            db.insert_devices(&devices).await.unwrap();
+            db.record_run("show interface", &devices).await.unwrap();
+        }
+
+        let mismatches = db.config_mismatches().await.unwrap();
+        let runs: Vec<_> = mismatches.iter().map(|m| (m.hostname.as_str(), m.runs)).collect();
+        assert_eq!(runs.len(), 2);
+        assert!(runs.contains(&("AP-1", 2)));
+        assert!(runs.contains(&("AP-2", 3)));
+        assert!(mismatches.iter().all(|m| m.since.is_some()));
+
+        // Devices saved without a recorded run still show up, just without a duration
+        db.insert_devices(&[serde_json::json!({ "id": 3, "hostname": "AP-3", "config_mismatch": true })]).await.unwrap();
+        let mismatches = db.config_mismatches().await.unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!((mismatches[0].runs, mismatches[0].since.as_deref()), (0, None));
+    }
+
+    #[tokio::test]
+    async fn test_rebooted_and_stale_devices() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let hour_ms = 3600 * 1000;
+        db.insert_devices(&[
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "connected": true, "system_up_time": now_ms - 2 * hour_ms }),
+            serde_json::json!({ "id": 2, "hostname": "AP-2", "connected": true, "system_up_time": now_ms - 48 * hour_ms }),
+            serde_json::json!({ "id": 3, "hostname": "AP-3", "connected": false, "last_connect_time": now_ms - 240 * hour_ms }),
+            serde_json::json!({ "id": 4, "hostname": "AP-4", "connected": true }),
+        ])
+        .await
+        .unwrap();
+
+        let rebooted = db.rebooted_devices(Duration::from_secs(86400)).await.unwrap();
+        assert_eq!(rebooted.iter().map(|d| d.id).collect::<Vec<_>>(), vec![1]);
+        assert!((7190..=7210).contains(&rebooted[0].uptime_secs));
+
+        // AP-4's BSSIDs were last collected 10 days ago
+        sqlx::query("INSERT INTO bssid_history (device_id, mac, ssid, last_seen) VALUES (4, 'aa', 'Corp', datetime('now', '-10 days'))")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let stale = db.stale_devices(Duration::from_secs(7 * 86400)).await.unwrap();
+        assert_eq!(stale.iter().map(|d| d.id).collect::<Vec<_>>(), vec![3, 4]);
+        assert!(stale[1].last_seen.is_some());
+
+        sqlx::query("UPDATE devices SET fetched_at = datetime('now', '-8 days') WHERE id = 1")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.stale_devices(Duration::from_secs(7 * 86400)).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ap_changes() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let ap = |id: i64, connected: bool| {
+            serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": connected })
+        };
+        let switch = serde_json::json!({ "id": 9, "hostname": "SW-9", "device_function": "SWITCH", "connected": true });
+
+        let first = db.record_run("show interface", &[ap(1, true), ap(2, false), switch.clone()]).await.unwrap();
+        assert_eq!(db.ap_changes(first, 2).await.unwrap(), ApChanges::default());
+
+        let second = db.record_run("show interface", &[ap(2, false), ap(3, true)]).await.unwrap();
+        let changes = db.ap_changes(second, 2).await.unwrap();
+        assert_eq!(changes.added, vec![ApChange { device_id: 3, hostname: "AP-3".into() }]);
+        // The switch disappearing isn't an AP change
+        assert_eq!(changes.removed, vec![ApChange { device_id: 1, hostname: "AP-1".into() }]);
+        assert_eq!(changes.disconnected, vec![DisconnectedAp { device_id: 2, hostname: "AP-2".into(), runs: 2 }]);
+
+        // Reported once per streak
+        let third = db.record_run("show interface", &[ap(2, false), ap(3, true)]).await.unwrap();
+        assert_eq!(db.ap_changes(third, 2).await.unwrap(), ApChanges::default());
+    }
+
+    #[tokio::test]
+    async fn test_previous_run_bssids() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let first = db.record_run("show interface", &[]).await.unwrap();
+        assert_eq!(db.previous_run_bssids(first).await.unwrap(), None);
+
+        db.set_run_bssids(first, 120).await.unwrap();
+        let backup = db.record_run("config backup", &[]).await.unwrap();
+        let second = db.record_run("show interface", &[]).await.unwrap();
+        assert!(backup < second);
+        assert_eq!(db.previous_run_bssids(second).await.unwrap(), Some(120));
+    }
+
+    #[tokio::test]
+    async fn test_grafana_views() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let devices = vec![
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "locations": [{ "name": "Global" }, { "name": "HQ" }] }),
+            serde_json::json!({ "id": 2, "hostname": "AP-2" }),
+        ];
+        let ap = |device_id: i64, ifaces: &[(&str, &str)]| DeviceInterfaces {
+            device_id,
+            hostname: format!("AP-{}", device_id),
+            output: String::new(),
+            interfaces: ifaces
+                .iter()
+                .map(|(mac, ssid)| crate::parser::InterfaceEntry {
+                    mac: mac.to_string(),
+                    mode: "access".to_string(),
+                    ssid: ssid.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+
+        let run_id = db.record_run("show interface", &devices).await.unwrap();
+        db.record_bssids(
+            run_id,
+            &[
+                ap(1, &[("00:11:22:33:44:01", "Corp"), ("00:11:22:33:44:02", "Guest")]),
+                ap(2, &[("00:11:22:33:44:03", "Corp")]),
+            ],
+        )
+        .await
+        .unwrap();
+
+        // Not in the views until the run has its BSSID total
+        let by_ssid = "SELECT ssid, bssids FROM grafana_bssids_by_ssid ORDER BY ssid";
+        assert!(sqlx::query(by_ssid).fetch_all(&db.pool).await.unwrap().is_empty());
+
+        db.set_run_bssids(run_id, 3).await.unwrap();
+        let rows: Vec<(String, i64)> = sqlx::query_as(by_ssid).fetch_all(&db.pool).await.unwrap();
+        assert_eq!(rows, vec![("Corp".to_string(), 2), ("Guest".to_string(), 1)]);
+
+        let rows: Vec<(String, i64)> = sqlx::query_as("SELECT site, bssids FROM grafana_bssids_by_site ORDER BY site")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, vec![("HQ".to_string(), 2), ("Unassigned".to_string(), 1)]);
+
+        let time: i64 = sqlx::query_scalar("SELECT time FROM grafana_bssids_by_site LIMIT 1")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert!(time > 1_600_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_bssid_first_last_seen() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let ap = |macs: &[&str]| DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: macs
+                .iter()
+                .map(|mac| crate::parser::InterfaceEntry {
+                    mac: mac.to_string(),
+                    mode: "access".to_string(),
+                    ssid: "Corp".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+
+        let first = db.record_run("show interface", &[]).await.unwrap();
+        assert!(db.record_bssids(first, &[ap(&["00:11:22:33:44:55"])]).await.unwrap().is_empty());
+
+        let second = db.record_run("show interface", &[]).await.unwrap();
+        let new = db
+            .record_bssids(second, &[ap(&["00:11:22:33:44:55", "00:11:22:33:44:56"])])
+            .await
+            .unwrap();
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].mac, "00:11:22:33:44:56");
+
+        let seen: Vec<(String, i64, i64)> =
+            sqlx::query_as("SELECT mac, first_run_id, last_run_id FROM bssid_history ORDER BY mac")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            seen,
+            vec![
+                ("00:11:22:33:44:55".to_string(), first, second),
+                ("00:11:22:33:44:56".to_string(), second, second),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_mac() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 1, "hostname": "AP-1", "locations": [{ "id": 5, "name": "HQ" }] })])
+            .await
+            .unwrap();
+        let ap = |mac: &str| DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![crate::parser::InterfaceEntry {
+                name: "wifi0.1".to_string(),
+                mac: mac.to_string(),
+                mode: "access".to_string(),
+                channel: "36".to_string(),
+                ssid: "Corp".to_string(),
+                ..Default::default()
+            }],
+        };
+
+        let run_id = db.record_run("show interface", &[]).await.unwrap();
+        db.record_bssids(run_id, &[ap("00:11:22:33:44:55"), ap("00:11:22:33:44:56")]).await.unwrap();
+        db.insert_interfaces(&[ap("00:11:22:33:44:55")]).await.unwrap();
+
+        let current = db.lookup_mac("00:11:22:33:44:55").await.unwrap();
+        assert_eq!(current.len(), 1);
+        assert_eq!(
+            (current[0].hostname.as_str(), current[0].interface.as_str(), current[0].channel.as_str(), current[0].site.as_deref()),
+            ("AP-1", "wifi0.1", "36", Some("HQ"))
+        );
+        assert!(current[0].current && current[0].last_seen.is_some());
+
+        let history = db.lookup_mac("00:11:22:33:44:56").await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(!history[0].current && history[0].interface.is_empty());
+        assert!(db.lookup_mac("00:11:22:33:44:57").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_run_stages() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let run_id = db.record_run("show interface", &[]).await.unwrap();
+        let stage = |name: &str, millis| StageTiming { stage: name.to_string(), millis };
+        db.record_run_stages(run_id, &[stage("login", 800), stage("parse", 1200)]).await.unwrap();
+        db.record_run_stages(run_id, &[stage("parse", 900)]).await.unwrap();
+
+        let stages: Vec<(String, i64)> = sqlx::query_as("SELECT stage, millis FROM run_stages ORDER BY stage")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(stages, vec![("login".to_string(), 800), ("parse".to_string(), 900)]);
+
+        let (version,): (String,) = sqlx::query_as("SELECT tool_version FROM runs").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn test_bssid_set_changes() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let ap = |id: i64, macs: &[&str]| DeviceInterfaces {
+            device_id: id,
+            hostname: format!("AP-{}", id),
+            output: String::new(),
+            interfaces: macs
+                .iter()
+                .map(|mac| crate::parser::InterfaceEntry {
+                    mac: mac.to_string(),
+                    mode: "access".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+
+        let first = db.record_run("show interface", &[]).await.unwrap();
+        db.record_bssids(first, &[ap(1, &["00:00:00:00:00:01"])]).await.unwrap();
+        // AP-1 isn't collected in the second run
+        let second = db.record_run("show interface", &[]).await.unwrap();
+        db.record_bssids(second, &[ap(2, &["00:00:00:00:00:02"])]).await.unwrap();
+        let third = db.record_run("show interface", &[]).await.unwrap();
+        db.record_bssids(third, &[ap(1, &["00:00:00:00:00:03"]), ap(2, &["00:00:00:00:00:02"]), ap(3, &["00:00:00:00:00:04"])])
+            .await
+            .unwrap();
+
+        let changes = db.bssid_set_changes(third).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].hostname, "AP-1");
+        assert_eq!(changes[0].previous_run_id, first);
+        assert_eq!(changes[0].previous, vec!["00:00:00:00:00:01"]);
+        assert_eq!(changes[0].current, vec!["00:00:00:00:00:03"]);
+        assert_eq!(changes[1].previous_run_id, second);
+    }
+
+    #[tokio::test]
+    async fn test_inventory_counts() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "device_function": "AP", "connected": true, "managed_by": "XIQ" }),
+            serde_json::json!({ "id": 2, "hostname": "AP-2", "device_function": "AP", "connected": true, "managed_by": "LOCAL" }),
+            serde_json::json!({ "id": 3, "hostname": "AP-3", "device_function": "AP", "connected": false }),
+            serde_json::json!({ "id": 4, "hostname": "SW-4", "device_function": "SWITCH", "connected": true }),
+        ])
+        .await
+        .unwrap();
+
+        let counts = db.inventory_counts(&["local".to_string()]).await.unwrap();
+        assert_eq!(counts, InventoryCounts { devices: 4, connected_aps: 2, local_aps: 1 });
+        assert_eq!(db.inventory_counts(&[]).await.unwrap().local_aps, 0);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let device = |id: i64| serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP" });
+        let ap = |ssid: &str, channel: &str| DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![crate::parser::InterfaceEntry {
+                mac: "00:00:00:00:00:01".to_string(),
+                mode: "access".to_string(),
+                radio: "wifi0".to_string(),
+                channel: channel.to_string(),
+                ssid: ssid.to_string(),
+                ..Default::default()
+            }],
+        };
+
+        let first = db.record_run("show interface", &[device(1), device(2)]).await.unwrap();
+        db.record_bssids(first, &[ap("Corp", "1(20)")]).await.unwrap();
+        assert_eq!(db.record_bssid_events(first).await.unwrap(), 0);
+
+        let second = db.record_run("show interface", &[device(1), device(3)]).await.unwrap();
+        db.record_bssids(second, &[ap("Staff", "6(20)")]).await.unwrap();
+        assert_eq!(db.record_bssid_events(second).await.unwrap(), 2);
+
+        let summary = |events: Vec<Event>| {
+            events.into_iter().map(|e| (e.run_id, e.hostname, e.kind, e.new_value)).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(db.events_tail(10).await.unwrap()),
+            vec![
+                (second, "AP-3".to_string(), "device_added".to_string(), None),
+                (second, "AP-2".to_string(), "device_removed".to_string(), None),
+                (second, "AP-1".to_string(), "ssid_renamed".to_string(), Some("Staff".to_string())),
+                (second, "AP-1".to_string(), "channel_changed".to_string(), Some("6(20)".to_string())),
+            ]
+        );
+        assert_eq!(db.events_tail(1).await.unwrap()[0].kind, "channel_changed");
+        assert_eq!(db.events_since("2000-01-01 00:00:00").await.unwrap().len(), 4);
+        assert!(db.events_since("2999-01-01 00:00:00").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_radio_details_and_mesh_links() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let detail = |power: Option<f64>| RadioDetail {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            radio: "wifi1".to_string(),
+            tx_power_dbm: power,
+            phy_mode: "ax".to_string(),
+            band: None,
+        };
+
+        let run_id = db.record_run("show interface", &[]).await.unwrap();
+        db.record_radio_details(run_id, &[detail(Some(17.0))]).await.unwrap();
+        db.record_radio_details(run_id, &[detail(Some(20.0))]).await.unwrap();
+        db.record_radio_details(run_id + 1, &[detail(None)]).await.unwrap();
+
+        let link = crate::mesh::MeshLink {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            interface: "wifi1.1".to_string(),
+            neighbor_mac: "00:19:77:00:00:02".to_string(),
+            neighbor_device_id: Some(2),
+            ..Default::default()
+        };
+        db.record_mesh_links(run_id, &[link.clone(), link]).await.unwrap();
+        let (links,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM mesh_links").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(links, 1);
+
+        let site = SiteSummary { site: "HQ".to_string(), aps: 2, bssids_5ghz: 4, ..Default::default() };
+        db.record_site_summary(run_id, &[site]).await.unwrap();
+        let (aps, bssids_5ghz): (i64, i64) = sqlx::query_as("SELECT aps, bssids_5ghz FROM site_summary WHERE site = 'HQ'")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!((aps, bssids_5ghz), (2, 4));
+
+        let rows: Vec<(String, Option<f64>, String)> =
+            sqlx::query_as("SELECT radio, tx_power_dbm, phy_mode FROM radio_details WHERE run_id = ?")
+                .bind(run_id)
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(rows, vec![("wifi1".to_string(), Some(20.0), "ax".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_run_snapshots() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        assert!(db.run_snapshot(None).await.unwrap().is_none());
+
+        let devices = vec![serde_json::json!({ "id": 1, "hostname": "AP-1", "network_policy_name": "HQ" })];
+        let ap = |ssid: &str| DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: format!("wifi0.1 ... {}", ssid),
+            interfaces: vec![crate::parser::InterfaceEntry { mode: "access".to_string(), ssid: ssid.to_string(), ..Default::default() }],
+        };
+        let first = db.record_run("show interface", &devices).await.unwrap();
+        db.record_run_snapshot(first, &devices, &[ap("Corp")]).await.unwrap();
+        let second = db.record_run("show interface", &devices).await.unwrap();
+        db.record_run_snapshot(second, &devices, &[ap("Guest")]).await.unwrap();
+        db.record_run("config backup", &devices).await.unwrap();
+
+        let latest = db.run_snapshot(None).await.unwrap().unwrap();
+        assert_eq!(latest.run.id, second);
+        assert_eq!(latest.devices, devices);
+        assert_eq!(latest.results[0].interfaces[0].ssid, "Guest");
+        // The raw output is only kept in raw_outputs
+        assert_eq!(latest.results[0].output, "");
+
+        let earlier = db.run_snapshot(Some(first)).await.unwrap().unwrap();
+        assert_eq!(earlier.run.command.as_deref(), Some("show interface"));
+        assert_eq!(earlier.results[0].interfaces[0].ssid, "Corp");
+        assert!(db.run_snapshot(Some(second + 1)).await.unwrap().is_none());
+
+        assert_eq!(db.prune_run_snapshots(1).await.unwrap(), 1);
+        assert!(db.run_snapshot(Some(first)).await.unwrap().is_none());
+        assert_eq!(db.run_snapshot(None).await.unwrap().unwrap().run.id, second);
+    }
+
+    #[tokio::test]
+    async fn test_raw_outputs() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let devices = vec![serde_json::json!({ "id": 1, "hostname": "AP-1" }), serde_json::json!({ "id": 2, "hostname": "AP-2" })];
+        let output = |device_id: i64, status: CliStatus, output: &str| CliResult { device_id, status, output: output.to_string() };
+
+        let first = db.record_run("show interface", &devices).await.unwrap();
+        let table = "wifi0.1  0019.7700.0001  access up 6(20) 1 wifi0 hive0 Corp\n".repeat(20);
+        db.record_raw_outputs(first, "show interface", &devices, &[output(1, CliStatus::Success, &table), output(2, CliStatus::Timeout, "")])
+            .await
+            .unwrap();
+        let second = db.record_run("show interface", &devices).await.unwrap();
+        db.record_raw_outputs(second, "show interface", &devices, &[output(1, CliStatus::Success, "changed\n")])
+            .await
+            .unwrap();
+
+        let raw = db.raw_output("ap-1", Some(first)).await.unwrap().unwrap();
+        assert_eq!((raw.run_id, raw.device_id, raw.hostname.as_deref()), (first, 1, Some("AP-1")));
+        assert_eq!(raw.output, table);
+        assert_eq!(raw.sha256, crate::manifest::sha256_hex(table.as_bytes()));
+        let (stored,): (i64,) = sqlx::query_as("SELECT LENGTH(output) FROM raw_outputs WHERE run_id = ?")
+            .bind(first)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert!((stored as usize) < table.len());
+
+        // Empty output isn't stored; the latest run is the default
+        assert!(db.raw_output("2", None).await.unwrap().is_none());
+        assert_eq!(db.raw_output("1", None).await.unwrap().unwrap().output, "changed\n");
+        let run_outputs = db.run_raw_outputs(first).await.unwrap();
+        assert_eq!(run_outputs.iter().map(|raw| (raw.device_id, raw.output.as_str())).collect::<Vec<_>>(), vec![(1, table.as_str())]);
+
+        assert_eq!(db.prune_raw_outputs(1).await.unwrap(), 1);
+        assert!(db.raw_output("AP-1", Some(first)).await.unwrap().is_none());
+        assert!(db.raw_output("AP-1", Some(second)).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_record_cli_statuses() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let status = |device_id: i64, status: CliStatus, message: &str| DeviceStatus {
+            device_id,
+            hostname: format!("AP-{}", device_id),
+            status,
+            message: message.to_string(),
+        };
+
+        let run_id = db.record_run("show interface", &[]).await.unwrap();
+        db.record_cli_statuses(run_id, &[status(1, CliStatus::Success, ""), status(2, CliStatus::Timeout, "timed out")])
+            .await
+            .unwrap();
+
+        let rows: Vec<(i64, String, String)> =
+            sqlx::query_as("SELECT device_id, status, message FROM cli_results WHERE run_id = ? ORDER BY device_id")
+                .bind(run_id)
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            vec![(1, "success".to_string(), String::new()), (2, "timeout".to_string(), "timed out".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interface_stats() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 1, "hostname": "AP-1" })]).await.unwrap();
+        db.insert_interfaces(&[DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                crate::parser::InterfaceEntry { name: "wifi0".into(), mode: "AP".into(), ..Default::default() },
+                crate::parser::InterfaceEntry {
+                    name: "wifi0.1".into(),
+                    mac: "00:11:22:33:44:55".into(),
+                    mode: "access".into(),
+                    ..Default::default()
+                },
+            ],
+        }])
+        .await
+        .unwrap();
+
+        let targets = db.access_interfaces().await.unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].name, "wifi0.1");
+
+        let run_id = db.record_run("stats", &[]).await.unwrap();
+        let counters = InterfaceCounters {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            interface: "wifi0.1".to_string(),
+            mac: "00:11:22:33:44:55".to_string(),
+            rx_bytes: Some(100),
+            tx_bytes: Some(200),
+            clients: None,
+        };
+        db.insert_interface_stats(run_id, &[counters]).await.unwrap();
+
+        let (rx, clients): (i64, Option<i64>) =
+            sqlx::query_as("SELECT rx_bytes, clients FROM interface_stats WHERE run_id = ? AND collected_at IS NOT NULL")
+                .bind(run_id)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!((rx, clients), (100, None));
+    }
+
+    #[tokio::test]
+    async fn test_config_backups() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let backup = |config: &str| ConfigBackup {
+            device_id: 7,
+            hostname: "AP-7".to_string(),
+            command: backup::DEFAULT_BACKUP_COMMAND.to_string(),
+            config: config.to_string(),
+        };
+
+        db.insert_configs(1, &[backup("ssid Corp\n")]).await.unwrap();
+        db.insert_configs(2, &[backup("ssid Corp\nssid Guest\n")]).await.unwrap();
+
+        assert_eq!(db.load_config("ap-7", 1).await.unwrap().as_deref(), Some("ssid Corp\n"));
+        assert_eq!(db.load_config("7", 2).await.unwrap().as_deref(), Some("ssid Corp\nssid Guest\n"));
+        assert_eq!(db.load_config("AP-7", 3).await.unwrap(), None);
+        assert_eq!(
+            db.latest_config_hashes().await.unwrap().get(&7),
+            Some(&backup::config_hash("ssid Corp\nssid Guest\n"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_databases() {
+        let dir = std::env::temp_dir().join(format!("xiq-db-merge-test-{}", std::process::id()));
+        let tenant = dir.join("tenant-a.db");
+        {
+            let source = Database::new(&tenant, 1).await.unwrap();
+            source
+                .insert_devices(&[
+                    serde_json::json!({ "id": 1, "hostname": "AP-1", "device_model": "AP4000", "fqdn": "ap-1.example.com" }),
+                    serde_json::json!({ "id": 2, "hostname": "AP-2" }),
+                ])
+                .await
+                .unwrap();
+            source
+                .insert_interfaces(&[DeviceInterfaces {
+                    device_id: 1,
+                    hostname: "AP-1".to_string(),
+                    output: String::new(),
+                    interfaces: vec![Default::default(), Default::default()],
+                }])
+                .await
+                .unwrap();
+            source.pool.close().await;
+        }
+
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 3, "hostname": "AP-3" })]).await.unwrap();
+
+        // The source predates accounts, so it needs an owner
+        assert!(db.merge_from(&tenant, None).await.is_err());
+        let stats = db.merge_from(&tenant, Some(7)).await.unwrap();
+        assert_eq!(stats, MergeStats { devices: 2, interfaces: 2 });
+
+        // Merging again replaces rather than duplicates; another account
+        // can't take the devices over
+        db.merge_from(&tenant, Some(7)).await.unwrap();
+        let error = format!("{:#}", db.merge_from(&tenant, Some(8)).await.unwrap_err());
+        assert!(error.contains("IDs 1, 2"), "{}", error);
+        let devices: Vec<(i64, Option<i64>, Option<String>)> =
+            sqlx::query_as("SELECT id, owner_id, device_model || ' ' || fqdn FROM devices ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            devices,
+            vec![(1, Some(7), Some("AP4000 ap-1.example.com".to_string())), (2, Some(7), None), (3, None, None)]
+        );
+        let interfaces: Vec<(i64, Option<i64>)> = sqlx::query_as("SELECT device_id, owner_id FROM interfaces")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(interfaces, vec![(1, Some(7)), (1, Some(7))]);
+        assert_eq!(
+            db.query_devices(&DeviceQuery { owner_id: Some(7), ..Default::default() }).await.unwrap().len(),
+            2
+        );
+
+        assert!(db.merge_from(&dir.join("missing.db"), Some(9)).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_browse_queries() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 1, "hostname": "AP-1" })]).await.unwrap();
+        let iface = |mac: &str, ssid: &str, mode: &str| crate::parser::InterfaceEntry {
+            mac: mac.to_string(),
+            ssid: ssid.to_string(),
+            mode: mode.to_string(),
+            channel: "36(80)".to_string(),
+            ..Default::default()
+        };
+        db.insert_interfaces(&[DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                iface("00:11:22:33:44:55", "Corp", "access"),
+                iface("00:11:22:33:44:56", "Guest", "access"),
+                iface("00:11:22:33:44:57", "", "backhaul"),
+            ],
+        }])
+        .await
+        .unwrap();
+
+        assert_eq!(db.device_summaries().await.unwrap()[0].bssids, 2);
+        assert_eq!(db.search_bssids("0011.2233.4455", 10).await.unwrap()[0].ssid.as_deref(), Some("Corp"));
+        assert_eq!(db.search_bssids("guest", 10).await.unwrap().len(), 1);
+        assert_eq!(db.search_bssids("ap-1", 10).await.unwrap().len(), 2);
+        assert!(db.search_bssids("lobby", 10).await.unwrap().is_empty());
+        assert_eq!(db.channel_counts().await.unwrap(), vec![LabelCount { label: "36(80)".to_string(), count: 2 }]);
+        assert_eq!(db.ssid_counts().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_owner_partitioning() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let first = db.clone().with_owner(Some(100));
+        let second = db.clone().with_owner(Some(200));
+        let ap = |id: i64| DeviceInterfaces {
+            device_id: id,
+            hostname: format!("AP-{}", id),
+            output: String::new(),
+            interfaces: vec![crate::parser::InterfaceEntry { name: "wifi0.1".into(), ssid: "Corp".into(), ..Default::default() }],
+        };
+
+        // A row saved before accounts were tracked is claimed by the next owner
+        db.insert_devices(&[serde_json::json!({ "id": 1 })]).await.unwrap();
+        first.insert_devices(&[serde_json::json!({ "id": 1 })]).await.unwrap();
+        first.insert_interfaces(&[ap(1)]).await.unwrap();
+        let first_run = first.record_run("show interface", &[]).await.unwrap();
+        first.set_run_bssids(first_run, 10).await.unwrap();
+
+        // The second account's fetch doesn't remove the first account's rows
+        second.insert_devices(&[serde_json::json!({ "id": 2 })]).await.unwrap();
+        second.insert_interfaces(&[ap(2)]).await.unwrap();
+        let second_run = second.record_run("show interface", &[]).await.unwrap();
+        assert_eq!(second.previous_run_bssids(second_run).await.unwrap(), None);
+
+        let owned = |owner_id: i64| DeviceQuery { owner_id: Some(owner_id), ..Default::default() };
+        let ids = |devices: Vec<DeviceRow>| devices.iter().map(|d| d.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.query_devices(&DeviceQuery::default()).await.unwrap()), vec![1, 2]);
+        assert_eq!(ids(db.query_devices(&owned(100)).await.unwrap()), vec![1]);
+        assert_eq!(ids(db.query_devices(&owned(200)).await.unwrap()), vec![2]);
+
+        let interfaces: Vec<(i64, i64)> = sqlx::query_as("SELECT device_id, owner_id FROM interfaces ORDER BY device_id")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(interfaces, vec![(1, 100), (2, 200)]);
+
+        let next_run = first.record_run("show interface", &[]).await.unwrap();
+        assert_eq!(first.previous_run_bssids(next_run).await.unwrap(), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_owner_scoped_reads_and_prunes() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let first = db.clone().with_owner(Some(100));
+        let second = db.clone().with_owner(Some(200));
+        let ap = |id: i64| DeviceInterfaces {
+            device_id: id,
+            hostname: format!("AP-{}", id),
+            output: String::new(),
+            interfaces: vec![crate::parser::InterfaceEntry {
+                name: "wifi0.1".into(),
+                mode: "access".into(),
+                ssid: "Corp".into(),
+                ..Default::default()
+            }],
+        };
+        let output = |device_id: i64| CliResult { device_id, status: CliStatus::Success, output: "wifi0.1\n".to_string() };
+
+        let devices = [serde_json::json!({ "id": 1, "hostname": "AP-1" })];
+        first.insert_devices(&devices).await.unwrap();
+        first.insert_interfaces(&[ap(1)]).await.unwrap();
+        // Device 3 is gone by the second run, which records two events
+        let gone = [serde_json::json!({ "id": 3, "hostname": "AP-3" })];
+        let mut first_runs = Vec::new();
+        for recorded in [&gone, &devices] {
+            let run_id = first.record_run("show interface", recorded).await.unwrap();
+            first.record_run_snapshot(run_id, &devices, &[ap(1)]).await.unwrap();
+            first.record_raw_outputs(run_id, "show interface", &devices, &[output(1)]).await.unwrap();
+            first_runs.push(run_id);
+        }
+
+        let other = [serde_json::json!({ "id": 2, "hostname": "AP-2" })];
+        second.insert_devices(&other).await.unwrap();
+        second.insert_interfaces(&[ap(2)]).await.unwrap();
+        let second_run = second.record_run("show interface", &other).await.unwrap();
+        second.record_run_snapshot(second_run, &other, &[ap(2)]).await.unwrap();
+        second.record_raw_outputs(second_run, "show interface", &other, &[output(2)]).await.unwrap();
+
+        // Device IDs are global, so one account can't take over another's device
+        let error = second.insert_devices(&devices).await.unwrap_err().to_string();
+        assert!(error.contains("IDs 1"), "{}", error);
+        assert_eq!(first.device_ids().await.unwrap(), HashSet::from([1]));
+
+        assert_eq!(second.device_ids().await.unwrap(), HashSet::from([2]));
+        assert_eq!(db.device_ids().await.unwrap(), HashSet::from([1, 2]));
+        assert_eq!(second.connection_states().await.unwrap().keys().copied().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(first.device_summaries().await.unwrap().iter().map(|d| d.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(second.search_bssids("corp", 10).await.unwrap().iter().map(|b| b.device_id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(db.search_bssids("corp", 10).await.unwrap().len(), 2);
+        assert_eq!(second.runs(10).await.unwrap().iter().map(|r| r.id).collect::<Vec<_>>(), vec![second_run]);
+        assert_eq!(first.events_tail(10).await.unwrap().len(), 2);
+        assert!(second.events_since("2000-01-01 00:00:00").await.unwrap().is_empty());
+
+        // The latest snapshot is the account's own, and another account's run isn't served
+        assert_eq!(first.run_snapshot(None).await.unwrap().unwrap().run.id, first_runs[1]);
+        assert!(first.run_snapshot(Some(second_run)).await.unwrap().is_none());
+        assert_eq!(db.run_snapshot(None).await.unwrap().unwrap().run.id, second_run);
+
+        // Pruning keeps the latest runs of each account
+        assert_eq!(first.prune_run_snapshots(1).await.unwrap(), 1);
+        assert_eq!(first.prune_raw_outputs(1).await.unwrap(), 1);
+        assert_eq!(second.run_snapshot(None).await.unwrap().unwrap().run.id, second_run);
+        assert!(db.raw_output("AP-2", Some(second_run)).await.unwrap().is_some());
+        assert!(db.raw_output("AP-1", Some(first_runs[1])).await.unwrap().is_some());
+        assert!(db.raw_output("AP-1", Some(first_runs[0])).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_devices() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let devices = [
+            serde_json::json!({
+                "id": 1, "hostname": "AP-Lobby", "device_function": "AP", "connected": true,
+                "radios": [{ "channel": 36 }], "wifi6": true, "extra": { "active_clients": 12 }
+            }),
+            serde_json::json!({ "id": 2, "hostname": "AP-Office", "device_function": "AP", "connected": false, "wifi6": false }),
+            serde_json::json!({ "id": 3, "hostname": "SW-Core", "device_function": "SWITCH", "connected": true }),
+        ];
+        db.insert_devices(&devices).await.unwrap();
+        let run_id = db.record_run("show interface", &devices).await.unwrap();
+
+        assert_eq!(db.query_devices(&DeviceQuery::default()).await.unwrap().len(), 3);
+        let aps = DeviceQuery { device_function: Some("ap".into()), ..Default::default() };
+        assert_eq!(db.query_devices(&aps).await.unwrap().len(), 2);
+        let connected_aps = DeviceQuery {
+            hostname: Some("ap-".into()),
+            connected: Some(true),
+            ..Default::default()
+        };
+        let connected_aps = db.query_devices(&connected_aps).await.unwrap();
+        assert_eq!(connected_aps.len(), 1);
+        assert_eq!(connected_aps[0].id, 1);
+        assert_eq!(
+            connected_aps[0].extra.as_deref(),
+            Some(r#"{"active_clients":12,"radios":[{"channel":36}],"wifi6":true}"#)
+        );
+
+        let filter = |path: &str, value: Option<&str>| DeviceQuery {
+            json_filters: vec![JsonFilter { path: path.to_string(), value: value.map(str::to_string) }],
+            ..Default::default()
+        };
+        let ids = |devices: Vec<DeviceRow>| devices.iter().map(|d| d.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.query_devices(&filter("$.radios[0].channel", Some("36"))).await.unwrap()), vec![1]);
+        assert_eq!(ids(db.query_devices(&filter("$.wifi6", Some("false"))).await.unwrap()), vec![2]);
+        assert_eq!(ids(db.query_devices(&filter("$.wifi6", None)).await.unwrap()), vec![1, 2]);
+        assert!(db.query_devices(&filter("$.active_clients", Some("13"))).await.unwrap().is_empty());
+
+        assert_eq!(db.device_history(2, 10).await.unwrap()[0].connected, Some(false));
+        assert_eq!(db.runs(10).await.unwrap()[0].id, run_id);
+        assert!(db.run(run_id + 1).await.unwrap().is_none());
+    }
 
-        Ok(row.0)
+    #[test]
+    fn test_resolve_db_path() {
+        assert_eq!(resolve_db_path(Path::new(MEMORY_DB)), PathBuf::from(MEMORY_DB));
+        let dir = std::env::temp_dir();
+        assert_eq!(resolve_db_path(&dir), dir.join(DEFAULT_DB_FILE));
+        assert_eq!(resolve_db_path(Path::new("data/")), Path::new("data/").join(DEFAULT_DB_FILE));
+        assert_eq!(resolve_db_path(Path::new("data/org1.db")), PathBuf::from("data/org1.db"));
     }
 }
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Default number of reverse lookups in flight at once
+pub const DEFAULT_DNS_CONCURRENCY: usize = 16;
+
+/// Default time a single reverse lookup may take
+pub const DEFAULT_DNS_TIMEOUT_SECS: u64 = 2;
+
+/// Reverse DNS name of each device's `ip_address`, keyed by device ID.
+/// Devices without an address, or whose lookup fails or times out, are left out.
+pub async fn reverse_lookup(devices: &[serde_json::Value], concurrency: usize, timeout: Duration) -> HashMap<i64, String> {
+    let targets: Vec<(i64, IpAddr)> = devices
+        .iter()
+        .filter_map(|d| {
+            let id = d.get("id")?.as_i64()?;
+            let ip = d.get("ip_address")?.as_str()?.parse().ok()?;
+            Some((id, ip))
+        })
+        .collect();
+
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut lookups = JoinSet::new();
+    for (id, ip) in targets.iter().copied() {
+        let permits = permits.clone();
+        lookups.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok()?;
+            let lookup = tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&ip));
+            let name = tokio::time::timeout(timeout, lookup).await.ok()?.ok()?.ok()?;
+            fqdn(&name, &ip).map(|name| (id, name))
+        });
+    }
+
+    let mut names = HashMap::new();
+    while let Some(result) = lookups.join_next().await {
+        if let Ok(Some((id, name))) = result {
+            names.insert(id, name);
+        }
+    }
+
+    println!("Resolved {} of {} device IPs via reverse DNS", names.len(), targets.len());
+    names
+}
+
+/// Name returned by the resolver without the trailing dot, or `None` when
+/// there is no PTR record and the resolver just echoed the address back
+fn fqdn(name: &str, ip: &IpAddr) -> Option<String> {
+    let name = name.trim_end_matches('.');
+    if name.is_empty() || name.parse::<IpAddr>().is_ok_and(|parsed| parsed == *ip) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// Add each resolved name to its device record as `fqdn`, so it's saved to the
+/// database and exported with the device inventory
+pub fn apply_fqdns(devices: &mut [serde_json::Value], names: &HashMap<i64, String>) {
+    for device in devices.iter_mut() {
+        let Some(id) = device.get("id").and_then(|v| v.as_i64()) else {
+            continue;
+        };
+        if let (Some(name), Some(record)) = (names.get(&id), device.as_object_mut()) {
+            record.insert("fqdn".to_string(), serde_json::Value::String(name.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fqdn() {
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+        assert_eq!(fqdn("ap-bldg1-03.corp.example.com.", &ip).as_deref(), Some("ap-bldg1-03.corp.example.com"));
+        assert_eq!(fqdn("10.1.2.3", &ip), None);
+        assert_eq!(fqdn("", &ip), None);
+    }
+
+    #[test]
+    fn test_apply_fqdns() {
+        let mut devices = vec![serde_json::json!({ "id": 1 }), serde_json::json!({ "id": 2 })];
+        apply_fqdns(&mut devices, &HashMap::from([(1, "ap1.example.com".to_string())]));
+        assert_eq!(devices[0]["fqdn"], "ap1.example.com");
+        assert!(devices[1].get("fqdn").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_lookup_skips_missing_addresses() {
+        // Devices without a usable address are skipped without a lookup
+        let devices = vec![serde_json::json!({ "id": 1 }), serde_json::json!({ "id": 2, "ip_address": "not an ip" })];
+        assert!(reverse_lookup(&devices, 4, Duration::from_secs(1)).await.is_empty());
+    }
+}
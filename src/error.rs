@@ -0,0 +1,82 @@
+use reqwest::StatusCode;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors from the CloudIQ client and the CLI output parsers. The `XiqApi`
+/// calls and `CliOutputParser::parse` return them directly; the steps built
+/// on top wrap them in `anyhow::Error`, where `downcast_ref::<Error>()` gets
+/// them back.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Not logged in (no status), or the API answered 401: bad credentials
+    /// or an expired token
+    #[error("{message}")]
+    Auth {
+        status: Option<StatusCode>,
+        message: String,
+    },
+
+    /// The API answered with an error status other than 401
+    #[error("{operation} failed with status {status}: {body}")]
+    Api {
+        operation: &'static str,
+        status: StatusCode,
+        body: String,
+    },
+
+    /// No response: DNS, connection or timeout failures
+    #[error("{context}")]
+    Network {
+        context: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// A response body, a value given to the client, or CLI output that
+    /// couldn't be read
+    #[error("{context}")]
+    Parse {
+        context: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+}
+
+impl Error {
+    /// Error for a response with a failure status, including its body
+    pub(crate) async fn from_response(operation: &'static str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if status == StatusCode::UNAUTHORIZED {
+            Self::Auth {
+                status: Some(status),
+                message: format!("{} failed with status {}: {}", operation, status, body),
+            }
+        } else {
+            Self::Api { operation, status, body }
+        }
+    }
+
+    pub(crate) fn network(context: impl Into<String>, source: reqwest::Error) -> Self {
+        Self::Network { context: context.into(), source }
+    }
+
+    pub(crate) fn parse(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Parse { context: context.into(), source: Some(source.into()) }
+    }
+
+    pub(crate) fn invalid(context: impl Into<String>) -> Self {
+        Self::Parse { context: context.into(), source: None }
+    }
+
+    /// HTTP status the API answered with, if it answered
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Auth { status, .. } => *status,
+            Self::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::db::{Event, RunBssidDetail};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::BTreeMap;
+
+/// Events listed by `events tail` without `--limit`
+pub const DEFAULT_TAIL_LIMIT: i64 = 20;
+
+/// Kind of inventory change, stored in `events.kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    DeviceAdded,
+    DeviceRemoved,
+    BssidAdded,
+    BssidRemoved,
+    SsidRenamed,
+    ChannelChanged,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DeviceAdded => "device_added",
+            Self::DeviceRemoved => "device_removed",
+            Self::BssidAdded => "bssid_added",
+            Self::BssidRemoved => "bssid_removed",
+            Self::SsidRenamed => "ssid_renamed",
+            Self::ChannelChanged => "channel_changed",
+        }
+    }
+}
+
+/// Change detected on an AP, before it's stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewEvent {
+    pub device_id: i64,
+    pub hostname: String,
+    pub kind: EventKind,
+    /// BSSID of BSSID and SSID events, radio of channel changes
+    pub subject: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// BSSID, SSID and channel changes between each AP's BSSIDs in `run_id` and
+/// in the previous run that collected it. APs collected for the first time
+/// have nothing to compare with and no events.
+pub fn bssid_events(run_id: i64, rows: &[RunBssidDetail]) -> Vec<NewEvent> {
+    let mut by_device: BTreeMap<i64, (Vec<&RunBssidDetail>, Vec<&RunBssidDetail>)> = BTreeMap::new();
+    for row in rows {
+        let (previous, current) = by_device.entry(row.device_id).or_default();
+        if row.run_id == run_id {
+            current.push(row);
+        } else {
+            previous.push(row);
+        }
+    }
+
+    let mut events = Vec::new();
+    for (device_id, (previous, current)) in by_device {
+        let Some(hostname) = current.first().map(|row| row.hostname.clone()) else {
+            continue;
+        };
+        if previous.is_empty() {
+            continue;
+        }
+        let event = |kind, subject: &str, old_value: Option<&str>, new_value: Option<&str>| NewEvent {
+            device_id,
+            hostname: hostname.clone(),
+            kind,
+            subject: subject.to_string(),
+            old_value: old_value.map(str::to_string),
+            new_value: new_value.map(str::to_string),
+        };
+
+        let old: BTreeMap<&str, &str> = previous.iter().map(|row| (row.mac.as_str(), row.ssid.as_str())).collect();
+        let new: BTreeMap<&str, &str> = current.iter().map(|row| (row.mac.as_str(), row.ssid.as_str())).collect();
+        for (mac, ssid) in &new {
+            match old.get(mac) {
+                None => events.push(event(EventKind::BssidAdded, mac, None, Some(ssid))),
+                Some(old_ssid) if old_ssid != ssid => {
+                    events.push(event(EventKind::SsidRenamed, mac, Some(old_ssid), Some(ssid)))
+                }
+                Some(_) => {}
+            }
+        }
+        for (mac, ssid) in &old {
+            if !new.contains_key(mac) {
+                events.push(event(EventKind::BssidRemoved, mac, Some(ssid), None));
+            }
+        }
+
+        // Every BSSID of a radio is on its channel; one event per radio
+        let old_channels = radio_channels(&previous);
+        for (radio, channel) in radio_channels(&current) {
+            if let Some(old_channel) = old_channels.get(radio).filter(|c| **c != channel) {
+                events.push(event(EventKind::ChannelChanged, radio, Some(old_channel), Some(channel)));
+            }
+        }
+    }
+
+    events
+}
+
+fn radio_channels<'a>(rows: &[&'a RunBssidDetail]) -> BTreeMap<&'a str, &'a str> {
+    let mut channels = BTreeMap::new();
+    for row in rows.iter().filter(|row| !row.radio.is_empty() && !row.channel.is_empty()) {
+        channels.entry(row.radio.as_str()).or_insert(row.channel.as_str());
+    }
+    channels
+}
+
+/// `YYYY-MM-DD`, `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DDTHH:MM[:SS]` (UTC) in
+/// the form event timestamps are stored in
+pub fn parse_since(value: &str) -> Result<String> {
+    let value = value.trim();
+    let parsed = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .or_else(|| {
+            ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        });
+
+    match parsed {
+        Some(since) => Ok(since.format("%Y-%m-%d %H:%M:%S").to_string()),
+        None => anyhow::bail!("Invalid date '{}' (use YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)", value),
+    }
+}
+
+pub fn print_events(events: &[Event]) {
+    if events.is_empty() {
+        println!("No events found");
+        return;
+    }
+
+    println!("{:<20} {:<6} {:<24} {:<16} {:<18} Change", "Recorded", "Run", "Device", "Event", "Subject");
+    for event in events {
+        let device = if event.hostname.is_empty() { event.device_id.to_string() } else { event.hostname.clone() };
+        let change = match (&event.old_value, &event.new_value) {
+            (Some(old), Some(new)) => format!("{} -> {}", old, new),
+            (Some(old), None) => old.clone(),
+            (None, Some(new)) => new.clone(),
+            (None, None) => String::new(),
+        };
+        println!(
+            "{:<20} {:<6} {:<24} {:<16} {:<18} {}",
+            event.recorded_at, event.run_id, device, event.kind, event.subject, change
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bssid_events() {
+        let row = |run_id: i64, device_id: i64, mac: &str, ssid: &str, radio: &str, channel: &str| RunBssidDetail {
+            run_id,
+            device_id,
+            hostname: format!("AP-{}", device_id),
+            mac: mac.to_string(),
+            ssid: ssid.to_string(),
+            radio: radio.to_string(),
+            channel: channel.to_string(),
+        };
+        let rows = vec![
+            row(1, 1, "00:00:00:00:00:01", "Corp", "wifi0", "1(20)"),
+            row(1, 1, "00:00:00:00:00:02", "Guest", "wifi0", "1(20)"),
+            row(1, 1, "00:00:00:00:00:11", "Corp", "wifi1", "36(80)"),
+            row(2, 1, "00:00:00:00:00:01", "Corp", "wifi0", "6(20)"),
+            row(2, 1, "00:00:00:00:00:03", "IoT", "wifi0", "6(20)"),
+            row(2, 1, "00:00:00:00:00:11", "Staff", "wifi1", "36(80)"),
+            // First collection of AP-2
+            row(2, 2, "00:00:00:00:00:21", "Corp", "wifi0", "11(20)"),
+        ];
+
+        let summary: Vec<_> = bssid_events(2, &rows)
+            .iter()
+            .map(|e| (e.kind, e.subject.clone(), e.old_value.clone(), e.new_value.clone()))
+            .collect();
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            summary,
+            vec![
+                (EventKind::BssidAdded, "00:00:00:00:00:03".to_string(), None, some("IoT")),
+                (EventKind::SsidRenamed, "00:00:00:00:00:11".to_string(), some("Corp"), some("Staff")),
+                (EventKind::BssidRemoved, "00:00:00:00:00:02".to_string(), some("Guest"), None),
+                (EventKind::ChannelChanged, "wifi0".to_string(), some("1(20)"), some("6(20)")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("2024-05-01").unwrap(), "2024-05-01 00:00:00");
+        assert_eq!(parse_since("2024-05-01 08:30").unwrap(), "2024-05-01 08:30:00");
+        assert_eq!(parse_since("2024-05-01T08:30:15").unwrap(), "2024-05-01 08:30:15");
+        assert!(parse_since("yesterday").is_err());
+        assert!(parse_since("2024-13-01").is_err());
+    }
+}
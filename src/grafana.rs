@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::path::Path;
+
+/// Default file for `grafana export-dashboards`
+pub const DEFAULT_DASHBOARD_FILE: &str = "grafana-dashboard.json";
+
+/// Grafana datasource the dashboard queries the database through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Datasource {
+    /// The database file itself, through the `frser-sqlite-datasource` plugin
+    #[default]
+    Sqlite,
+    /// The database tables copied into PostgreSQL (e.g. with pgloader)
+    Postgres,
+}
+
+impl Datasource {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "sqlite" => Ok(Self::Sqlite),
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            other => anyhow::bail!("Unknown --datasource '{}' (available: sqlite, postgres)", other),
+        }
+    }
+
+    fn plugin(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Sqlite => ("frser-sqlite-datasource", "SQLite"),
+            Self::Postgres => ("grafana-postgresql-datasource", "PostgreSQL"),
+        }
+    }
+
+    /// Query target for a series query returning `time`, `metric` and a count
+    fn target(self, sql: &str) -> serde_json::Value {
+        match self {
+            Self::Sqlite => json!({
+                "refId": "A",
+                "queryType": "time series",
+                "queryText": sql,
+                "rawQueryText": sql,
+                "timeColumns": ["time"],
+            }),
+            Self::Postgres => json!({
+                "refId": "A",
+                "format": "time_series",
+                "rawQuery": true,
+                "editorMode": "code",
+                "rawSql": sql,
+            }),
+        }
+    }
+}
+
+/// BSSIDs per SSID and per site over time. SQLite reads the
+/// `grafana_bssids_*` views; PostgreSQL has no copy of the views, so the same
+/// counts are taken from the tables.
+fn queries(datasource: Datasource) -> [(&'static str, &'static str); 2] {
+    match datasource {
+        Datasource::Sqlite => [
+            (
+                "BSSIDs per SSID",
+                "SELECT time, ssid AS metric, bssids FROM grafana_bssids_by_ssid \
+                 WHERE time >= $__unixEpochFrom() AND time <= $__unixEpochTo() ORDER BY time",
+            ),
+            (
+                "BSSIDs per site",
+                "SELECT time, site AS metric, bssids FROM grafana_bssids_by_site \
+                 WHERE time >= $__unixEpochFrom() AND time <= $__unixEpochTo() ORDER BY time",
+            ),
+        ],
+        Datasource::Postgres => [
+            (
+                "BSSIDs per SSID",
+                "SELECT r.started_at AS time, b.ssid AS metric, COUNT(*) AS bssids \
+                 FROM run_bssids b JOIN runs r ON r.id = b.run_id \
+                 WHERE r.bssids IS NOT NULL AND $__timeFilter(r.started_at) \
+                 GROUP BY r.started_at, b.ssid ORDER BY 1",
+            ),
+            (
+                "BSSIDs per site",
+                "SELECT r.started_at AS time, COALESCE(h.site, 'Unassigned') AS metric, \
+                 COUNT(DISTINCT b.device_id || '/' || b.mac) AS bssids \
+                 FROM run_bssids b JOIN runs r ON r.id = b.run_id \
+                 LEFT JOIN device_history h ON h.run_id = b.run_id AND h.device_id = b.device_id \
+                 WHERE r.bssids IS NOT NULL AND $__timeFilter(r.started_at) \
+                 GROUP BY r.started_at, COALESCE(h.site, 'Unassigned') ORDER BY 1",
+            ),
+        ],
+    }
+}
+
+/// Dashboard JSON for Grafana's "Import dashboard", which asks for the
+/// datasource to bind to `${DS_XIQ}`
+pub fn dashboard(datasource: Datasource) -> serde_json::Value {
+    let (plugin_id, plugin_name) = datasource.plugin();
+    let panels: Vec<serde_json::Value> = queries(datasource)
+        .iter()
+        .enumerate()
+        .map(|(index, (title, sql))| {
+            json!({
+                "id": index + 1,
+                "type": "timeseries",
+                "title": title,
+                "datasource": { "type": plugin_id, "uid": "${DS_XIQ}" },
+                "gridPos": { "h": 10, "w": 24, "x": 0, "y": index * 10 },
+                "fieldConfig": {
+                    "defaults": { "custom": { "drawStyle": "line", "showPoints": "always" }, "unit": "short" },
+                    "overrides": [],
+                },
+                "options": { "legend": { "displayMode": "table", "placement": "right", "calcs": ["lastNotNull"] } },
+                "targets": [datasource.target(sql)],
+            })
+        })
+        .collect();
+
+    json!({
+        "__inputs": [{
+            "name": "DS_XIQ",
+            "label": "XIQ database",
+            "type": "datasource",
+            "pluginId": plugin_id,
+            "pluginName": plugin_name,
+        }],
+        "uid": "xiq-bssids",
+        "title": "XIQ BSSIDs",
+        "tags": ["xiq"],
+        "editable": true,
+        "schemaVersion": 39,
+        "time": { "from": "now-30d", "to": "now" },
+        "panels": panels,
+    })
+}
+
+pub fn write_dashboard(datasource: Datasource, path: &Path) -> Result<()> {
+    let json_data = serde_json::to_string_pretty(&dashboard(datasource)).context("Failed to serialize dashboard")?;
+    std::fs::write(path, json_data).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!("Grafana dashboard saved to {}", path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dashboard() {
+        let sqlite = dashboard(Datasource::Sqlite);
+        assert_eq!(sqlite["__inputs"][0]["pluginId"], "frser-sqlite-datasource");
+        let panels = sqlite["panels"].as_array().unwrap();
+        assert_eq!(panels.len(), 2);
+        assert_eq!(panels[1]["gridPos"]["y"], 10);
+        assert_eq!(panels[0]["datasource"]["uid"], "${DS_XIQ}");
+        assert!(panels[1]["targets"][0]["queryText"].as_str().unwrap().contains("FROM grafana_bssids_by_site"));
+
+        let postgres = dashboard(Datasource::Postgres);
+        assert_eq!(postgres["panels"][0]["datasource"]["type"], "grafana-postgresql-datasource");
+        assert_eq!(postgres["panels"][0]["targets"][0]["format"], "time_series");
+        assert!(postgres["panels"][0]["targets"][0]["rawSql"].as_str().unwrap().contains("$__timeFilter"));
+
+        assert_eq!(Datasource::parse("postgresql").unwrap(), Datasource::Postgres);
+        assert!(Datasource::parse("mysql").is_err());
+    }
+}
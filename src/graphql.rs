@@ -0,0 +1,162 @@
+use crate::db::{
+    BssidHistoryRow, Database, DeviceHistoryRow, DeviceQuery, DeviceRow, InterfaceRow, RunRow,
+};
+use async_graphql::{ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Result, Schema};
+
+/// Deepest nesting a query may use, so device → interfaces → device → ... can't loop forever
+const MAX_QUERY_DEPTH: usize = 8;
+
+/// Default number of history entries returned per device
+const DEFAULT_HISTORY_LIMIT: i64 = 20;
+
+pub type XiqSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Read-only schema over the devices, interfaces and run history in `db`
+pub fn schema(db: Database) -> XiqSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .finish()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Devices matching every given filter. `hostname` matches a substring,
+    /// case-insensitively; `deviceFunction` is e.g. "AP" or "SWITCH".
+    async fn devices(
+        &self,
+        ctx: &Context<'_>,
+        hostname: Option<String>,
+        device_function: Option<String>,
+        connected: Option<bool>,
+        config_mismatch: Option<bool>,
+        limit: Option<i64>,
+    ) -> Result<Vec<DeviceRow>> {
+        let query = DeviceQuery {
+            hostname,
+            device_function,
+            connected,
+            config_mismatch,
+            limit,
+            ..Default::default()
+        };
+        Ok(ctx.data::<Database>()?.query_devices(&query).await?)
+    }
+
+    /// One device by ID or exact hostname (case-insensitive)
+    async fn device(&self, ctx: &Context<'_>, id: Option<i64>, hostname: Option<String>) -> Result<Option<DeviceRow>> {
+        if id.is_none() && hostname.is_none() {
+            return Err("device needs an id or a hostname".into());
+        }
+        let query = DeviceQuery {
+            id,
+            hostname: hostname.clone(),
+            ..Default::default()
+        };
+        let devices = ctx.data::<Database>()?.query_devices(&query).await?;
+        Ok(devices.into_iter().find(|d| match &hostname {
+            Some(hostname) => d.hostname.as_deref().is_some_and(|h| h.eq_ignore_ascii_case(hostname)),
+            None => true,
+        }))
+    }
+
+    /// Recorded runs, newest first
+    async fn runs(&self, ctx: &Context<'_>, #[graphql(default = 20)] limit: i64) -> Result<Vec<RunRow>> {
+        Ok(ctx.data::<Database>()?.runs(limit).await?)
+    }
+
+    async fn run(&self, ctx: &Context<'_>, id: i64) -> Result<Option<RunRow>> {
+        Ok(ctx.data::<Database>()?.run(id).await?)
+    }
+}
+
+#[ComplexObject]
+impl DeviceRow {
+    /// Interfaces from the latest run, optionally only one mode (e.g. "access")
+    async fn interfaces(&self, ctx: &Context<'_>, mode: Option<String>) -> Result<Vec<InterfaceRow>> {
+        Ok(ctx.data::<Database>()?.device_interfaces(self.id, mode.as_deref()).await?)
+    }
+
+    /// State in recorded runs, newest first
+    async fn history(&self, ctx: &Context<'_>, limit: Option<i64>) -> Result<Vec<DeviceHistoryRow>> {
+        let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+        Ok(ctx.data::<Database>()?.device_history(self.id, limit).await?)
+    }
+
+    /// Every BSSID the device has broadcast, most recently seen first
+    async fn bssid_history(&self, ctx: &Context<'_>) -> Result<Vec<BssidHistoryRow>> {
+        Ok(ctx.data::<Database>()?.device_bssid_history(self.id).await?)
+    }
+}
+
+#[ComplexObject]
+impl InterfaceRow {
+    async fn device(&self, ctx: &Context<'_>) -> Result<Option<DeviceRow>> {
+        let query = DeviceQuery {
+            id: Some(self.device_id),
+            ..Default::default()
+        };
+        Ok(ctx.data::<Database>()?.query_devices(&query).await?.into_iter().next())
+    }
+}
+
+#[ComplexObject]
+impl DeviceHistoryRow {
+    async fn run(&self, ctx: &Context<'_>) -> Result<Option<RunRow>> {
+        Ok(ctx.data::<Database>()?.run(self.run_id).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MEMORY_DB;
+    use crate::parser::InterfaceEntry;
+    use crate::report::DeviceInterfaces;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_nested_query() {
+        let db = Database::new(Path::new(MEMORY_DB), 1).await.unwrap();
+        let devices = [
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "device_function": "AP" }),
+            serde_json::json!({ "id": 2, "hostname": "SW-1", "device_function": "SWITCH" }),
+        ];
+        db.insert_devices(&devices).await.unwrap();
+        db.record_run("show interface", &devices).await.unwrap();
+        db.insert_interfaces(&[DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                InterfaceEntry { mac: "00:11:22:33:44:55".into(), mode: "access".into(), ..Default::default() },
+                InterfaceEntry { mac: "00:11:22:33:44:56".into(), mode: "backhaul".into(), ..Default::default() },
+            ],
+        }])
+        .await
+        .unwrap();
+
+        let response = schema(db)
+            .execute(
+                r#"{
+                    devices(deviceFunction: "ap") {
+                        hostname
+                        interfaces(mode: "access") { mac device { id } }
+                        history { connected run { command } }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let data = response.data.into_json().unwrap();
+        let devices = data["devices"].as_array().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0]["interfaces"][0]["mac"], "00:11:22:33:44:55");
+        assert_eq!(devices[0]["interfaces"][0]["device"]["id"], 1);
+        assert_eq!(devices[0]["interfaces"].as_array().unwrap().len(), 1);
+        assert_eq!(devices[0]["history"][0]["run"]["command"], "show interface");
+    }
+}
@@ -0,0 +1,71 @@
+use crate::db::{RebootedDevice, StaleDevice};
+use crate::locale::Locale;
+use crate::mismatch::format_age;
+use std::time::Duration;
+
+/// Default window for devices counted as recently rebooted in `health`
+pub const DEFAULT_REBOOTED_WITHIN: Duration = Duration::from_secs(86400);
+
+/// Default age after which a device counts as stale in `health`
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(7 * 86400);
+
+pub fn print_health_report(
+    rebooted: &[RebootedDevice],
+    stale: &[StaleDevice],
+    rebooted_within: Duration,
+    stale_after: Duration,
+    locale: &Locale,
+) {
+    println!("\n=== Recently Rebooted ===");
+    if rebooted.is_empty() {
+        println!("No devices rebooted in the last {}", format_age(rebooted_within.as_secs() as i64));
+    } else {
+        println!("{:<30} {:>12}  {:<20} {:>10}", "Device", "ID", format!("Booted ({})", locale.zone()), "Uptime");
+        for device in rebooted {
+            println!(
+                "{:<30} {:>12}  {:<20} {:>10}",
+                device.hostname,
+                device.id,
+                locale.timestamp(&device.booted_at),
+                format_age(device.uptime_secs)
+            );
+        }
+        println!(
+            "{} device(s) rebooted in the last {}",
+            locale.integer(rebooted.len() as i64),
+            format_age(rebooted_within.as_secs() as i64)
+        );
+    }
+
+    println!("\n=== Stale Devices ===");
+    if stale.is_empty() {
+        println!("No devices stale for more than {}", format_age(stale_after.as_secs() as i64));
+    } else {
+        println!(
+            "{:<30} {:>12}  {:<9} {:<20} {:<20} {:<20}",
+            "Device",
+            "ID",
+            "Connected",
+            format!("Fetched ({})", locale.zone()),
+            "Last Connect",
+            format!("BSSIDs Seen ({})", locale.zone())
+        );
+        for device in stale {
+            println!(
+                "{:<30} {:>12}  {:<9} {:<20} {:<20} {:<20}",
+                device.hostname,
+                device.id,
+                if device.connected { "yes" } else { "no" },
+                device.fetched_at.as_deref().map_or("-".to_string(), |at| locale.timestamp(at)),
+                device.last_connect_time.as_deref().map_or("-".to_string(), |at| locale.timestamp(at)),
+                device.last_seen.as_deref().map_or("-".to_string(), |at| locale.timestamp(at))
+            );
+        }
+        println!(
+            "{} device(s) stale for more than {}",
+            locale.integer(stale.len() as i64),
+            format_age(stale_after.as_secs() as i64)
+        );
+    }
+    println!("=====================");
+}
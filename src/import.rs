@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Device fields parsed as integers when imported from CSV
+const INTEGER_FIELDS: &[&str] = &["id", "org_id", "location_id", "system_up_time"];
+
+/// Device fields parsed as booleans when imported from CSV
+const BOOLEAN_FIELDS: &[&str] = &["connected", "config_mismatch", "simulated"];
+
+/// Read a device export received outside the API: a `.csv` file with one
+/// device per row, or JSON holding a device array (like `devices.json`) or
+/// an API page with the devices under `data`
+pub fn load_devices(path: &Path) -> Result<Vec<serde_json::Value>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let is_csv = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+
+    let devices = if is_csv {
+        devices_from_csv(&content)
+    } else {
+        devices_from_json(&content)
+    }
+    .with_context(|| format!("Failed to import devices from {}", path.display()))?;
+
+    for (index, device) in devices.iter().enumerate() {
+        if device.get("id").and_then(|v| v.as_i64()).is_none() {
+            anyhow::bail!("Device {} in {} has no numeric id", index + 1, path.display());
+        }
+    }
+
+    Ok(devices)
+}
+
+fn devices_from_json(content: &str) -> Result<Vec<serde_json::Value>> {
+    let value: serde_json::Value = serde_json::from_str(content).context("Invalid JSON")?;
+    match value {
+        serde_json::Value::Array(devices) => Ok(devices),
+        serde_json::Value::Object(mut page) => match page.remove("data") {
+            Some(serde_json::Value::Array(devices)) => Ok(devices),
+            _ => anyhow::bail!("Expected a device array or an object with a \"data\" array"),
+        },
+        _ => anyhow::bail!("Expected a device array or an object with a \"data\" array"),
+    }
+}
+
+/// Header cell as a device field name: `Host Name` and `host-name` become `host_name`
+fn field_name(header: &str) -> String {
+    header
+        .trim()
+        .trim_start_matches('\u{feff}')
+        .to_ascii_lowercase()
+        .replace([' ', '-'], "_")
+}
+
+fn devices_from_csv(content: &str) -> Result<Vec<serde_json::Value>> {
+    let mut records = parse_csv(content).into_iter();
+    let header: Vec<String> = records.next().context("CSV file is empty")?.iter().map(|h| field_name(h)).collect();
+    if !header.iter().any(|h| h == "id") {
+        anyhow::bail!("CSV header has no id column");
+    }
+
+    let mut devices = Vec::new();
+    for (line, record) in records.enumerate() {
+        if record.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+
+        let mut device = serde_json::Map::new();
+        for (name, cell) in header.iter().zip(&record) {
+            let cell = cell.trim();
+            if cell.is_empty() {
+                continue;
+            }
+            let value = if INTEGER_FIELDS.contains(&name.as_str()) {
+                let number: i64 = cell
+                    .parse()
+                    .with_context(|| format!("Row {}: {} is not a number: {}", line + 2, name, cell))?;
+                number.into()
+            } else if BOOLEAN_FIELDS.contains(&name.as_str()) {
+                match cell.to_ascii_lowercase().as_str() {
+                    "true" | "yes" | "1" => true.into(),
+                    "false" | "no" | "0" => false.into(),
+                    _ => anyhow::bail!("Row {}: {} is not true or false: {}", line + 2, name, cell),
+                }
+            } else if name == "last_connect_time" {
+                // Epoch milliseconds stay numbers, timestamps stay strings
+                cell.parse::<i64>().map(Into::into).unwrap_or_else(|_| cell.into())
+            } else {
+                cell.into()
+            };
+            device.insert(name.clone(), value);
+        }
+        devices.push(serde_json::Value::Object(device));
+    }
+
+    Ok(devices)
+}
+
+/// Split CSV text into records, honouring quoted cells with commas, doubled
+/// quotes and line breaks
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut cell)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut cell));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !record.is_empty() {
+        record.push(cell);
+        records.push(record);
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_from_csv() {
+        let content = "\u{feff}ID,Hostname,Device Function,Connected,Description,last_connect_time\r\n\
+                       101,AP-1,AP,true,\"Lobby, 2nd \"\"floor\"\"\",1700000000000\r\n\
+                       102,AP-2,AP,no,,\r\n\
+                       ,,,,,\r\n";
+
+        let devices = devices_from_csv(content).unwrap();
+        assert_eq!(
+            devices,
+            vec![
+                serde_json::json!({
+                    "id": 101,
+                    "hostname": "AP-1",
+                    "device_function": "AP",
+                    "connected": true,
+                    "description": "Lobby, 2nd \"floor\"",
+                    "last_connect_time": 1700000000000i64,
+                }),
+                serde_json::json!({ "id": 102, "hostname": "AP-2", "device_function": "AP", "connected": false }),
+            ]
+        );
+
+        assert!(devices_from_csv("hostname\nAP-1\n").is_err());
+        assert!(devices_from_csv("id,connected\n1,maybe\n").is_err());
+        assert_eq!(devices_from_json(r#"{"data": [{"id": 1}], "page": 1}"#).unwrap().len(), 1);
+        assert!(devices_from_json(r#"{"id": 1}"#).is_err());
+    }
+}
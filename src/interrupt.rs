@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Exit code of a run stopped with Ctrl-C (128 + SIGINT)
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Exit code of a run stopped by `--max-duration`, as used by `timeout(1)`
+pub const TIMED_OUT_EXIT_CODE: i32 = 124;
+
+/// Why a run stopped before collecting everything
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// Ctrl-C
+    Interrupted,
+    /// `--max-duration` elapsed
+    TimedOut,
+}
+
+impl StopReason {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Interrupted => INTERRUPTED_EXIT_CODE,
+            Self::TimedOut => TIMED_OUT_EXIT_CODE,
+        }
+    }
+}
+
+/// Error returned by a stopped run, after saving what it collected
+#[derive(Debug)]
+pub struct Interrupted(pub StopReason);
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            StopReason::Interrupted => f.write_str("Run interrupted by Ctrl-C"),
+            StopReason::TimedOut => f.write_str("Run stopped after reaching --max-duration"),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Set when Ctrl-C is received or the run deadline passes; clones share the same state
+#[derive(Clone)]
+pub struct Interrupt {
+    sender: Arc<watch::Sender<Option<StopReason>>>,
+}
+
+impl Default for Interrupt {
+    fn default() -> Self {
+        Self {
+            sender: Arc::new(watch::channel(None).0),
+        }
+    }
+}
+
+impl Interrupt {
+    /// Listen for Ctrl-C. The first one cancels the guarded stages so partial
+    /// results can be saved; a second one exits immediately.
+    pub fn install() -> Self {
+        let interrupt = Self::default();
+        let handle = interrupt.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            eprintln!("\nInterrupted: cancelling requests and saving partial results (Ctrl-C again to quit now)");
+            handle.trigger(StopReason::Interrupted);
+
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+        });
+        interrupt
+    }
+
+    /// Also stop the run once `max_duration` has passed
+    pub fn set_deadline(&self, max_duration: Duration) {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(max_duration).await;
+            if handle.reason().is_none() {
+                eprintln!("\nMaximum run duration reached: cancelling requests and saving partial results");
+                handle.trigger(StopReason::TimedOut);
+            }
+        });
+    }
+
+    /// Stop the run; the first reason given is kept
+    pub fn trigger(&self, reason: StopReason) {
+        self.sender.send_if_modified(|current| {
+            let unset = current.is_none();
+            if unset {
+                *current = Some(reason);
+            }
+            unset
+        });
+    }
+
+    /// Why the run was stopped, if it was
+    pub fn reason(&self) -> Option<StopReason> {
+        *self.sender.borrow()
+    }
+
+    /// Run `future` unless interrupted first, in which case it is dropped
+    /// (cancelling its in-flight requests) and `None` is returned
+    pub async fn guard<T>(&self, future: impl Future<Output = T>) -> Option<T> {
+        let mut receiver = self.sender.subscribe();
+        tokio::select! {
+            biased;
+            value = future => Some(value),
+            _ = receiver.wait_for(Option::is_some) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_guard() {
+        let interrupt = Interrupt::default();
+        assert_eq!(interrupt.guard(async { 1 }).await, Some(1));
+
+        let pending = interrupt.guard(std::future::pending::<()>());
+        let trigger = async {
+            tokio::task::yield_now().await;
+            interrupt.trigger(StopReason::Interrupted);
+        };
+        let (result, _) = tokio::join!(pending, trigger);
+        assert_eq!(result, None);
+        assert_eq!(interrupt.reason(), Some(StopReason::Interrupted));
+
+        // Once interrupted, stages that would wait return right away
+        assert_eq!(interrupt.guard(std::future::pending::<()>()).await, None);
+        interrupt.trigger(StopReason::TimedOut);
+        assert_eq!(interrupt.reason(), Some(StopReason::Interrupted));
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let interrupt = Interrupt::default();
+        interrupt.set_deadline(Duration::from_millis(10));
+        assert_eq!(interrupt.guard(std::future::pending::<()>()).await, None);
+        assert_eq!(interrupt.reason(), Some(StopReason::TimedOut));
+        assert_eq!(StopReason::TimedOut.exit_code(), TIMED_OUT_EXIT_CODE);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// API calls slower than this are reported as they happen
+pub const DEFAULT_SLOW_CALL_MS: u64 = 5000;
+
+/// Latency percentiles of one kind of API call
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencySummary {
+    pub operation: &'static str,
+    pub calls: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Nearest-rank percentile of sorted durations
+pub fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Time each API call took to answer, by operation
+pub struct LatencyRecorder {
+    slow_call: Duration,
+    calls: Mutex<BTreeMap<&'static str, Vec<Duration>>>,
+}
+
+impl Default for LatencyRecorder {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_SLOW_CALL_MS))
+    }
+}
+
+impl LatencyRecorder {
+    pub fn new(slow_call: Duration) -> Self {
+        Self { slow_call, calls: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Record a call, warning when it took longer than the slow call threshold
+    pub fn record(&self, operation: &'static str, elapsed: Duration) {
+        if elapsed > self.slow_call {
+            eprintln!(
+                "Warning: {} call took {}ms (slow call threshold {}ms)",
+                operation,
+                elapsed.as_millis(),
+                self.slow_call.as_millis()
+            );
+        }
+        self.calls.lock().unwrap().entry(operation).or_default().push(elapsed);
+    }
+
+    pub fn summary(&self) -> Vec<LatencySummary> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(operation, durations)| {
+                let mut sorted = durations.clone();
+                sorted.sort_unstable();
+                LatencySummary {
+                    operation,
+                    calls: sorted.len(),
+                    p50: percentile(&sorted, 50.0),
+                    p90: percentile(&sorted, 90.0),
+                    p99: percentile(&sorted, 99.0),
+                    max: sorted.last().copied().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn print_summary(&self) {
+        let summary = self.summary();
+        if summary.is_empty() {
+            return;
+        }
+
+        println!("\n=== API Latency (ms) ===");
+        println!("{:<20} {:>6} {:>8} {:>8} {:>8} {:>8}", "Call", "Count", "p50", "p90", "p99", "Max");
+        for row in summary {
+            println!(
+                "{:<20} {:>6} {:>8} {:>8} {:>8} {:>8}",
+                row.operation,
+                row.calls,
+                row.p50.as_millis(),
+                row.p90.as_millis(),
+                row.p99.as_millis(),
+                row.max.as_millis()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_summary() {
+        let recorder = LatencyRecorder::new(Duration::from_millis(500));
+        for ms in (1..=100).rev() {
+            recorder.record("Device fetch", Duration::from_millis(ms * 10));
+        }
+        recorder.record("Login", Duration::from_millis(80));
+
+        let summary = recorder.summary();
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].operation, "Device fetch");
+        assert_eq!(summary[0].calls, 100);
+        assert_eq!(summary[0].p50, Duration::from_millis(500));
+        assert_eq!(summary[0].p90, Duration::from_millis(900));
+        assert_eq!(summary[0].p99, Duration::from_millis(990));
+        assert_eq!(summary[0].max, Duration::from_millis(1000));
+        assert_eq!(summary[1].p99, Duration::from_millis(80));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+}
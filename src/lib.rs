@@ -0,0 +1,63 @@
+//! Library API behind `xiq_cli_tool`: the CloudIQ client, CLI output
+//! parsers, the database layer and output sinks.
+
+pub mod alerts;
+pub mod annotations;
+pub mod ansible;
+pub mod api;
+pub mod archive;
+pub mod backup;
+pub mod budget;
+pub mod calendar;
+pub mod churn;
+pub mod cli;
+pub mod cli_status;
+pub mod client;
+pub mod config;
+pub mod csv_output;
+pub mod db;
+pub mod dns;
+pub mod error;
+pub mod events;
+pub mod grafana;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod health;
+pub mod import;
+pub mod interrupt;
+pub mod latency;
+pub mod locale;
+pub mod location;
+pub mod lookup;
+pub mod mac_check;
+pub mod manifest;
+pub mod mesh;
+pub mod mismatch;
+pub mod mist;
+pub mod notify;
+pub mod oui;
+pub mod output;
+pub mod parser;
+pub mod parser_registry;
+pub mod postprocess;
+pub mod progress;
+pub mod query;
+pub mod radio;
+pub mod reachability;
+pub mod report;
+pub mod rf;
+pub mod run_diff;
+pub mod schema;
+pub mod server;
+pub mod sink;
+pub mod snmp;
+pub mod spread;
+pub mod ssh;
+pub mod ssid;
+pub mod stats;
+pub mod stream;
+pub mod subscribe;
+pub mod summary;
+pub mod terraform;
+pub mod validation;
+
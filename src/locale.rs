@@ -0,0 +1,161 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+/// Digit grouping and decimal mark of numbers in the reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberStyle {
+    /// `1234567.5`, as the machine-readable exports write them
+    #[default]
+    Plain,
+    /// `1,234,567.5`
+    En,
+    /// `1.234.567,5`
+    De,
+    /// `1 234 567,5`, grouped with a narrow no-break space
+    Fr,
+}
+
+impl NumberStyle {
+    /// Group separator and decimal mark
+    fn separators(self) -> (Option<char>, char) {
+        match self {
+            NumberStyle::Plain => (None, '.'),
+            NumberStyle::En => (Some(','), '.'),
+            NumberStyle::De => (Some('.'), ','),
+            NumberStyle::Fr => (Some('\u{202f}'), ','),
+        }
+    }
+}
+
+/// How the human-facing reports show timestamps and numbers, from `[locale]`.
+/// The database, CSV and JSON exports stay in UTC and plain numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    pub timezone: Tz,
+    pub numbers: NumberStyle,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self { timezone: Tz::UTC, numbers: NumberStyle::Plain }
+    }
+}
+
+impl Locale {
+    /// Locale with an IANA time zone name such as `America/New_York`
+    pub fn new(timezone: &str, numbers: NumberStyle) -> Result<Self> {
+        let timezone = timezone.parse::<Tz>().map_err(|_| {
+            anyhow::anyhow!("Unknown time zone '{}' (use an IANA name such as Europe/Berlin)", timezone)
+        })?;
+        Ok(Self { timezone, numbers })
+    }
+
+    /// Zone name for column headers, e.g. `Started (America/New_York)`
+    pub fn zone(&self) -> &'static str {
+        self.timezone.name()
+    }
+
+    /// `YYYY-MM-DD HH:MM:SS` in the configured zone
+    pub fn datetime(&self, time: DateTime<Utc>) -> String {
+        time.with_timezone(&self.timezone).format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    /// A UTC timestamp as stored in the database (`YYYY-MM-DD HH:MM:SS` or
+    /// RFC 3339) in the configured zone; anything else is returned as it is
+    pub fn timestamp(&self, utc: &str) -> String {
+        match parse_utc(utc) {
+            Some(time) => self.datetime(time),
+            None => utc.to_string(),
+        }
+    }
+
+    /// Whole number with the configured digit grouping
+    pub fn integer(&self, value: i64) -> String {
+        let digits = value.unsigned_abs().to_string();
+        let grouped = match self.numbers.separators().0 {
+            Some(separator) => group_digits(&digits, separator),
+            None => digits,
+        };
+        if value < 0 {
+            format!("-{}", grouped)
+        } else {
+            grouped
+        }
+    }
+
+    /// Number with `places` decimals, the configured grouping and decimal mark
+    pub fn decimal(&self, value: f64, places: usize) -> String {
+        let (separator, mark) = self.numbers.separators();
+        let formatted = format!("{:.*}", places, value.abs());
+        let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let mut out = String::new();
+        if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        out.push_str(&match separator {
+            Some(separator) => group_digits(whole, separator),
+            None => whole.to_string(),
+        });
+        if !fraction.is_empty() {
+            out.push(mark);
+            out.push_str(fraction);
+        }
+        out
+    }
+}
+
+/// A UTC timestamp as stored in the database, `YYYY-MM-DD HH:MM:SS` or RFC 3339
+pub fn parse_utc(utc: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(utc)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S").map(|time| time.and_utc()))
+        .ok()
+}
+
+/// Insert `separator` between groups of three digits
+fn group_digits(digits: &str, separator: char) -> String {
+    let mut out = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push(separator);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        let utc = Locale::default();
+        assert_eq!(utc.zone(), "UTC");
+        assert_eq!(utc.timestamp("2024-07-01 14:30:00"), "2024-07-01 14:30:00");
+
+        let ny = Locale::new("America/New_York", NumberStyle::Plain).unwrap();
+        // Daylight saving time in July, standard time in January
+        assert_eq!(ny.timestamp("2024-07-01 14:30:00"), "2024-07-01 10:30:00");
+        assert_eq!(ny.timestamp("2024-01-15T14:30:00Z"), "2024-01-15 09:30:00");
+        assert_eq!(ny.timestamp("2024-01-15T15:30:00+01:00"), "2024-01-15 09:30:00");
+        assert_eq!(ny.timestamp("-"), "-");
+        assert!(Locale::new("Mars/Olympus_Mons", NumberStyle::Plain).is_err());
+    }
+
+    #[test]
+    fn test_numbers() {
+        let style = |numbers| Locale { numbers, ..Default::default() };
+        assert_eq!(style(NumberStyle::Plain).integer(1234567), "1234567");
+        assert_eq!(style(NumberStyle::En).integer(1234567), "1,234,567");
+        assert_eq!(style(NumberStyle::De).integer(-1234), "-1.234");
+        assert_eq!(style(NumberStyle::En).integer(999), "999");
+        assert_eq!(style(NumberStyle::De).decimal(1234.567, 1), "1.234,6");
+        assert_eq!(style(NumberStyle::Fr).decimal(98.5, 2), "98,50");
+        assert_eq!(style(NumberStyle::Fr).integer(12345), "12\u{202f}345");
+        assert_eq!(style(NumberStyle::En).decimal(-0.01, 1), "0.0");
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+
+/// Site assigned to devices without location data
+pub const UNASSIGNED: &str = "Unassigned";
+
+/// Position of a device in the XIQ location hierarchy
+/// (Location → Site → Building → Floor)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceLocation {
+    pub site: String,
+    pub building: Option<String>,
+    pub floor: Option<String>,
+    /// Full path, e.g. `Global/HQ/Building 1/Floor 2`
+    pub path: String,
+}
+
+/// Location of a device from the `locations` array returned by the API
+/// (ordered from the root), or `None` when the device has no location data
+pub fn device_location(device: &serde_json::Value) -> Option<DeviceLocation> {
+    let names: Vec<&str> = device
+        .get("locations")?
+        .as_array()?
+        .iter()
+        .filter_map(|l| l.get("name").and_then(|n| n.as_str()))
+        .collect();
+
+    // Buildings and floors are always the last two levels; anything above
+    // the site (the root "Global" location, regions) is only kept in the path
+    let n = names.len();
+    let (site, building, floor) = match n {
+        0 => return None,
+        1 => (names[0], None, None),
+        2 => (names[1], None, None),
+        3 => (names[1], None, Some(names[2])),
+        _ => (names[n - 3], Some(names[n - 2]), Some(names[n - 1])),
+    };
+
+    let location = DeviceLocation {
+        site: site.to_string(),
+        building: building.map(str::to_string),
+        floor: floor.map(str::to_string),
+        path: names.join("/"),
+    };
+
+    Some(location)
+}
+
+/// Site name for a device, falling back to `Unassigned`
+pub fn device_site(device: &serde_json::Value) -> String {
+    device_location(device)
+        .map(|l| l.site)
+        .unwrap_or_else(|| UNASSIGNED.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(names: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "locations": names.iter().map(|n| serde_json::json!({ "name": n })).collect::<Vec<_>>()
+        })
+    }
+
+    #[test]
+    fn test_device_location() {
+        let loc = device_location(&device(&["Global", "HQ", "Building 1", "Floor 2"])).unwrap();
+        assert_eq!(loc.site, "HQ");
+        assert_eq!(loc.building.as_deref(), Some("Building 1"));
+        assert_eq!(loc.floor.as_deref(), Some("Floor 2"));
+        assert_eq!(loc.path, "Global/HQ/Building 1/Floor 2");
+
+        let loc = device_location(&device(&["Global", "HQ"])).unwrap();
+        assert_eq!(loc.site, "HQ");
+        assert_eq!(loc.floor, None);
+
+        assert_eq!(device_location(&serde_json::json!({ "id": 1 })), None);
+        assert_eq!(device_site(&serde_json::json!({ "id": 1 })), UNASSIGNED);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::csv_output::{write_csv, CsvProfile};
+use crate::db::MacLookup;
+use crate::locale::Locale;
+use crate::output::LineEnding;
+use crate::parser::normalize_mac;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Default report of a bulk lookup
+pub const DEFAULT_LOOKUP_REPORT: &str = "mac-lookup.csv";
+
+/// Normalize a MAC in any common notation (`aa:bb:..`, `AA-BB-..`,
+/// `aabb.cc..`, bare hex) to `AA:BB:CC:DD:EE:FF`
+pub fn parse_mac(value: &str) -> Result<String> {
+    let hex_digits = value.chars().filter(|c| c.is_ascii_hexdigit()).count();
+    let separators_only = value.chars().all(|c| c.is_ascii_hexdigit() || matches!(c, ':' | '-' | '.' | ' '));
+    if hex_digits != 12 || !separators_only {
+        anyhow::bail!("Not a MAC address: {}", value);
+    }
+    Ok(normalize_mac(value))
+}
+
+/// Every MAC in a file, in order and without duplicates. MACs can be
+/// anywhere on a line, so DHCP or WIPS exports work as they are.
+pub fn read_macs(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(extract_macs(&content))
+}
+
+/// MAC in colon, dash, Cisco dotted or bare form
+static MAC_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:[0-9a-f]{2}[:-]){5}[0-9a-f]{2}\b|\b[0-9a-f]{4}\.[0-9a-f]{4}\.[0-9a-f]{4}\b|\b[0-9a-f]{12}\b")
+        .expect("valid MAC pattern")
+});
+
+fn extract_macs(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    MAC_PATTERN
+        .find_iter(content)
+        .map(|m| normalize_mac(m.as_str()))
+        .filter(|mac| seen.insert(mac.clone()))
+        .collect()
+}
+
+/// Matched and unmatched MACs of a bulk lookup, one row per match
+pub fn write_lookup_report(
+    macs: &[String],
+    matches: &[MacLookup],
+    path: &Path,
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let mut rows = Vec::new();
+    for mac in macs {
+        let mut found = matches.iter().filter(|row| &row.mac == mac).peekable();
+        if found.peek().is_none() {
+            rows.push(vec![mac.clone(), "no".to_string()]);
+        }
+        for row in found {
+            rows.push(vec![
+                mac.clone(),
+                "yes".to_string(),
+                row.hostname.clone(),
+                row.device_id.to_string(),
+                row.interface.clone(),
+                row.ssid.clone(),
+                row.channel.clone(),
+                row.site.clone().unwrap_or_default(),
+                row.last_seen.clone().unwrap_or_default(),
+                if row.current { "yes" } else { "no" }.to_string(),
+            ]);
+        }
+    }
+    write_csv(
+        path,
+        &["MAC", "Matched", "Device", "DeviceID", "Interface", "SSID", "Channel", "Site", "LastSeen", "Current"],
+        &rows,
+        line_ending,
+        profile,
+    )
+}
+
+pub fn print_lookup_summary(macs: &[String], matches: &[MacLookup], report: &Path) {
+    let matched: HashSet<&str> = matches.iter().map(|row| row.mac.as_str()).collect();
+    let current: HashSet<&str> = matches.iter().filter(|row| row.current).map(|row| row.mac.as_str()).collect();
+
+    println!("\n=== MAC Lookup ===");
+    println!("MACs read:             {}", macs.len());
+    println!("Matched:               {}", matched.len());
+    println!("  in latest collection {}", current.len());
+    println!("Unmatched:             {}", macs.len() - matched.len());
+    println!("Report saved to {}", report.display());
+    println!("==================");
+}
+
+pub fn print_mac_lookup(mac: &str, matches: &[MacLookup], locale: &Locale) {
+    println!("\n=== {} ===", mac);
+    if matches.is_empty() {
+        println!("Not found in the database");
+        println!("=========================");
+        return;
+    }
+
+    println!(
+        "{:<30} {:>12}  {:<10} {:<24} {:<10} {:<30} {:<20}",
+        "Device",
+        "ID",
+        "Interface",
+        "SSID",
+        "Channel",
+        "Site",
+        format!("Last Seen ({})", locale.zone())
+    );
+    for row in matches {
+        println!(
+            "{:<30} {:>12}  {:<10} {:<24} {:<10} {:<30} {:<20}",
+            row.hostname,
+            row.device_id,
+            if row.interface.is_empty() { "-" } else { &row.interface },
+            row.ssid,
+            if row.channel.is_empty() { "-" } else { &row.channel },
+            row.site.as_deref().unwrap_or("-"),
+            row.last_seen.as_deref().map_or("-".to_string(), |seen| locale.timestamp(seen))
+        );
+    }
+    if !matches.iter().any(|row| row.current) {
+        println!("Not broadcast in the latest collection; last known location shown");
+    }
+    println!("=========================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_macs() {
+        let content = "\
+time,client,bssid
+2024-05-01 10:00,aa-bb-cc-dd-ee-ff,0019.77aa.bb01
+2024-05-01 10:05,AABBCCDDEEFF,00:19:77:AA:BB:02
+not a mac: 00:19:77:aa:bb
+";
+        assert_eq!(
+            extract_macs(content),
+            vec!["AA:BB:CC:DD:EE:FF", "00:19:77:AA:BB:01", "00:19:77:AA:BB:02"]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::csv_output::{write_csv, CsvProfile};
+use crate::oui::{is_locally_administered, oui};
+use crate::output::LineEnding;
+use crate::report::DeviceInterfaces;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+/// Report of the BSSIDs held back from the exports
+pub const INVALID_BSSIDS_FILE: &str = "invalid-bssids.csv";
+
+/// Why a parsed MAC can't be used as a BSSID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacIssue {
+    /// Not six hex octets
+    Malformed,
+    /// Group bit set; a BSSID is always unicast
+    Multicast,
+    /// Locally administered bit set, so not a vendor-assigned address
+    LocallyAdministered,
+}
+
+impl MacIssue {
+    pub fn label(self) -> &'static str {
+        match self {
+            MacIssue::Malformed => "malformed",
+            MacIssue::Multicast => "multicast",
+            MacIssue::LocallyAdministered => "locally administered",
+        }
+    }
+}
+
+/// What's wrong with `mac`, if anything
+pub fn mac_issue(mac: &str) -> Option<MacIssue> {
+    let Some(oui) = oui(mac) else {
+        return Some(MacIssue::Malformed);
+    };
+    let first = u8::from_str_radix(&oui[..2], 16).ok()?;
+    if first & 0x01 != 0 {
+        Some(MacIssue::Multicast)
+    } else if is_locally_administered(mac) {
+        Some(MacIssue::LocallyAdministered)
+    } else {
+        None
+    }
+}
+
+/// Access-mode interface whose MAC failed validation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvalidBssid {
+    pub device_id: i64,
+    pub hostname: String,
+    pub interface: String,
+    pub mac: String,
+    pub ssid: String,
+    pub issue: MacIssue,
+}
+
+/// Remove access-mode interfaces with invalid MACs from `results`, so they
+/// stay out of the BSSID exports, and return them for a separate report
+pub fn take_invalid_bssids(results: &mut [DeviceInterfaces]) -> Vec<InvalidBssid> {
+    let mut invalid = Vec::new();
+    for device in results.iter_mut() {
+        device.interfaces.retain(|iface| {
+            if !iface.is_access() {
+                return true;
+            }
+            let Some(issue) = mac_issue(&iface.mac) else {
+                return true;
+            };
+            invalid.push(InvalidBssid {
+                device_id: device.device_id,
+                hostname: device.hostname.clone(),
+                interface: iface.name.clone(),
+                mac: iface.mac.clone(),
+                ssid: iface.ssid.clone(),
+                issue,
+            });
+            false
+        });
+    }
+    invalid
+}
+
+pub fn write_invalid_bssids(
+    invalid: &[InvalidBssid],
+    path: &Path,
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let rows: Vec<Vec<String>> = invalid
+        .iter()
+        .map(|b| {
+            vec![
+                b.hostname.clone(),
+                b.interface.clone(),
+                b.mac.clone(),
+                b.ssid.clone(),
+                b.issue.label().to_string(),
+            ]
+        })
+        .collect();
+    write_csv(path, &["Device", "Interface", "MAC", "SSID", "Issue"], &rows, line_ending, profile)
+}
+
+pub fn print_invalid_bssids(invalid: &[InvalidBssid]) {
+    if invalid.is_empty() {
+        return;
+    }
+
+    println!("\n=== Invalid BSSIDs Excluded From Exports ({}) ===", invalid.len());
+    println!("{:<24} {:<12} {:<20} {:<22} SSID", "Device", "Interface", "MAC", "Issue");
+    for bssid in invalid {
+        println!(
+            "{:<24} {:<12} {:<20} {:<22} {}",
+            bssid.hostname,
+            bssid.interface,
+            bssid.mac,
+            bssid.issue.label(),
+            bssid.ssid
+        );
+    }
+    println!("================================================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    fn iface(name: &str, mac: &str, mode: &str) -> InterfaceEntry {
+        InterfaceEntry {
+            name: name.to_string(),
+            mac: mac.to_string(),
+            mode: mode.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mac_issue() {
+        assert_eq!(mac_issue("00:19:77:AA:BB:CC"), None);
+        assert_eq!(mac_issue("0019.77aa.bbcc"), None);
+        assert_eq!(mac_issue("01:00:5E:00:00:01"), Some(MacIssue::Multicast));
+        assert_eq!(mac_issue("FF:FF:FF:FF:FF:FF"), Some(MacIssue::Multicast));
+        assert_eq!(mac_issue("02:19:77:AA:BB:CC"), Some(MacIssue::LocallyAdministered));
+        assert_eq!(mac_issue("00:19:77:AA:BB"), Some(MacIssue::Malformed));
+        assert_eq!(mac_issue(""), Some(MacIssue::Malformed));
+        assert_eq!(mac_issue("zz:19:77:aa:bb:cc"), Some(MacIssue::Malformed));
+    }
+
+    #[test]
+    fn test_take_invalid_bssids() {
+        let mut results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                iface("wifi0.1", "00:19:77:00:00:01", "access"),
+                iface("wifi0.2", "06:19:77:00:00:02", "access"),
+                iface("wifi0.3", "N/A", "access"),
+                iface("mgt0", "N/A", "backhaul"),
+            ],
+        }];
+
+        let invalid = take_invalid_bssids(&mut results);
+        assert_eq!(invalid.len(), 2);
+        assert_eq!(invalid[0].interface, "wifi0.2");
+        assert_eq!(invalid[0].issue, MacIssue::LocallyAdministered);
+        assert_eq!(invalid[1].issue, MacIssue::Malformed);
+        let names: Vec<_> = results[0].interfaces.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["wifi0.1", "mgt0"]);
+    }
+}
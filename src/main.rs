@@ -1,483 +1,1165 @@
-mod db;
-mod parser;
-
 use anyhow::{Context, Result};
-use db::Database;
-use parser::extract_interfaces;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
-use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use xiq_cli_tool::api::{self, XiqApi};
+use xiq_cli_tool::cli::{self, Command};
+use xiq_cli_tool::cli_status::{CliResult, CliStatus};
+use xiq_cli_tool::client::{self, CloudIQClient, Credential};
+use xiq_cli_tool::config::{Config, ProfileConfig};
+use xiq_cli_tool::db::{self, Database};
+use xiq_cli_tool::interrupt::{Interrupt, Interrupted, StopReason};
+use xiq_cli_tool::output::{create_output_file, LineEnding};
+use xiq_cli_tool::parser_registry::ParserRegistry;
+use xiq_cli_tool::progress::{self, ProgressEvent};
+use xiq_cli_tool::report::{self, TemplateContext};
+use xiq_cli_tool::run_diff::{self, DiffFormat};
+use xiq_cli_tool::sink::{self, ExportContext};
+use xiq_cli_tool::stream::{self, StreamWriter};
+use xiq_cli_tool::{alerts, annotations, archive, backup, budget, calendar, cli_status, dns, events, grafana, health, import, locale, lookup, mac_check, manifest, mesh, mismatch, notify, oui, postprocess, query, radio, reachability, rf, schema, server, snmp, spread, ssh, ssid, stats, subscribe, summary, validation};
+
+fn save_devices_to_file(devices: &[serde_json::Value], path: &Path, line_ending: LineEnding) -> Result<()> {
+    let json_data = serde_json::to_string_pretty(devices)
+        .context("Failed to serialize devices to JSON")?;
+
+    let mut file = create_output_file(path, line_ending)?;
+
+    file.write_all(json_data.as_bytes())
+        .and_then(|_| file.flush())
+        .context("Failed to write data to file")?;
+
+    println!("Devices saved to {}", path.display());
 
-/// Escape a string for CSV output (RFC 4180 compliant)
-fn csv_escape(s: &str) -> String {
-    if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
-        format!("\"{}\"", s.replace('"', "\"\""))
-    } else {
-        s.to_string()
-    }
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct LoginRequest {
-    username: String,
-    password: String,
+async fn save_devices_to_db(db: &Database, devices: &[serde_json::Value]) -> Result<()> {
+    // Count devices by device_function
+    let total_devices = devices.len();
+    let ap_devices = devices.iter()
+        .filter(|device| {
+            device.get("device_function")
+                .and_then(|v| v.as_str())
+                .map(|s| s == "AP")
+                .unwrap_or(false)
+        })
+        .count();
+
+    println!("\n=== Device Import Summary ===");
+    println!("Total devices imported: {}", total_devices);
+    println!("Devices with device_function 'AP': {}", ap_devices);
+    println!("============================\n");
+
+    db.insert_devices(devices).await?;
+    Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct LoginResponse {
-    access_token: String,
+/// Database pool size from XIQ_DB_POOL_SIZE, falling back to the default
+fn db_pool_size() -> Result<u32> {
+    match env::var("XIQ_DB_POOL_SIZE") {
+        Ok(value) => value
+            .parse()
+            .context(format!("Invalid XIQ_DB_POOL_SIZE: {}", value)),
+        Err(_) => Ok(db::DEFAULT_POOL_SIZE),
+    }
 }
 
+/// Database file from --db-path, the profile or XIQ_DB_PATH, defaulting to
+/// xiq-db.db in the CWD
+fn db_path(cli_path: Option<&Path>, profile: Option<&ProfileConfig>) -> PathBuf {
+    let configured = cli_path
+        .map(Path::to_path_buf)
+        .or_else(|| profile.and_then(|p| p.db_path.clone()))
+        .or_else(|| env::var_os("XIQ_DB_PATH").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(db::DEFAULT_DB_FILE));
 
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct DevicesResponse {
-    data: Vec<serde_json::Value>,
-    total_pages: Option<i32>,
-    total_count: Option<i32>,
-    page: Option<i32>,
+    db::resolve_db_path(&configured)
 }
 
-struct CloudIQClient {
-    client: reqwest::Client,
-    base_url: String,
-    access_token: Option<String>,
+/// Switch to the profile's output directory so every output file lands there.
+/// Returns `db_path` made absolute, since it was resolved against the old CWD,
+/// and does the same for the other paths given on the command line.
+fn enter_output_dir(profile: Option<&ProfileConfig>, db_path: PathBuf, cli: &mut cli::Cli) -> Result<PathBuf> {
+    let Some(dir) = profile.and_then(|p| p.output_dir.as_deref()) else {
+        return Ok(db_path);
+    };
+
+    let cwd = env::current_dir().context("Failed to read the working directory")?;
+    let db_path = if db_path.as_os_str() == db::MEMORY_DB { db_path } else { cwd.join(db_path) };
+    let mut paths = vec![&mut cli.global.db_dump];
+    if let Command::Run(options) = &mut cli.command {
+        paths.extend([&mut options.template, &mut options.template_output]);
+    }
+    for path in paths.into_iter().flatten() {
+        *path = cwd.join(&*path);
+    }
+
+    std::fs::create_dir_all(dir).context(format!("Failed to create output directory: {}", dir.display()))?;
+    env::set_current_dir(dir).context(format!("Failed to enter output directory: {}", dir.display()))?;
+    println!("Writing output files to {}", dir.display());
+
+    Ok(db_path)
 }
 
-impl CloudIQClient {
-    fn new(base_url: String) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            base_url,
-            access_token: None,
+/// Copy the database to the --db-dump file, if one was requested
+async fn dump_database(db: &Database, dump: Option<&Path>) -> Result<()> {
+    if let Some(path) = dump {
+        db.dump_to(path).await?;
+        println!("Database dumped to {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let result = run().await;
+    if let Err(e) = &result {
+        progress::emit(ProgressEvent::Error { message: format!("{:#}", e) });
+        if let Some(Interrupted(reason)) = e.downcast_ref::<Interrupted>() {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(reason.exit_code());
         }
     }
+    result
+}
 
-    async fn login(&mut self, username: &str, password: &str) -> Result<()> {
-        let login_url = format!("{}/login", self.base_url);
+async fn run() -> Result<()> {
+    dotenv::dotenv().ok();
+
+    let args: Vec<String> = env::args().collect();
+    let mut cli = cli::parse_args(&args[1..])?;
+    if cli.global.progress_json {
+        progress::enable();
+    }
 
-        let login_payload = LoginRequest {
-            username: username.to_string(),
-            password: password.to_string(),
+    // Schemas go to stdout on their own so they can be redirected to a file
+    if let Command::Schema { name } = &cli.command {
+        let schemas = match name {
+            Some(name) => serde_json::to_value(schema::schema(name)?)?,
+            None => serde_json::Value::Object(
+                schema::SCHEMA_NAMES
+                    .iter()
+                    .map(|name| Ok((name.to_string(), serde_json::to_value(schema::schema(name)?)?)))
+                    .collect::<Result<_>>()?,
+            ),
         };
+        println!("{}", serde_json::to_string_pretty(&schemas)?);
+        return Ok(());
+    }
+
+    // Dashboards only hold queries, so neither the API nor the database is needed
+    if let Command::GrafanaExport { datasource, output } = &cli.command {
+        let path = output.clone().unwrap_or_else(|| PathBuf::from(grafana::DEFAULT_DASHBOARD_FILE));
+        grafana::write_dashboard(*datasource, &path)?;
+        return Ok(());
+    }
 
-        let response = self
-            .client
-            .post(&login_url)
-            .json(&login_payload)
-            .send()
-            .await
-            .context("Failed to send login request")?;
+    println!("Developed by Jeff Buddington www.linkedin.com/in/jeff-buddington-5178ba4");
+    println!();
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Login failed with status {}: {}", status, error_text);
+    let config = Config::load(cli.global.config.as_deref())?;
+    let profile_name = cli.global.profile.clone().or_else(|| env::var("XIQ_PROFILE").ok());
+    let profile = match &profile_name {
+        Some(name) => {
+            println!("Using profile '{}'", name);
+            Some(config.profile(name)?)
+        }
+        None => None,
+    };
+    let db_path = db_path(cli.global.db_path.as_deref(), profile);
+
+    // Merging only combines local databases, so it doesn't need API credentials
+    if let Command::DbMerge { sources } = &cli.command {
+        println!("Connecting to database {}...", db_path.display());
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+
+        for source in sources {
+            let stats = db.merge_from(&source.path, source.owner_id).await?;
+            let owner = source.owner_id.map(|id| format!(" for owner {}", id)).unwrap_or_default();
+            println!(
+                "Merged {}{}: {} devices, {} interfaces",
+                source.path.display(),
+                owner,
+                stats.devices,
+                stats.interfaces
+            );
         }
 
-        let login_response: LoginResponse = response
-            .json()
-            .await
-            .context("Failed to parse login response")?;
+        dump_database(&db, cli.global.db_dump.as_deref()).await?;
+        println!("\nDone!");
+        return Ok(());
+    }
 
-        self.access_token = Some(login_response.access_token);
-        println!("Successfully authenticated with CloudIQ API");
+    // Diffs only read stored backups, so they don't need API credentials
+    if let Command::ConfigDiff { device, run_a, run_b } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let old = db.load_config(device, *run_a).await?
+            .with_context(|| format!("No configuration stored for '{}' in run {}", device, run_a))?;
+        let new = db.load_config(device, *run_b).await?
+            .with_context(|| format!("No configuration stored for '{}' in run {}", device, run_b))?;
+
+        let diff = backup::unified_diff(&old, &new, &format!("run {}", run_a), &format!("run {}", run_b));
+        if diff.is_empty() {
+            println!("No configuration changes for {} between runs {} and {}", device, run_a, run_b);
+        } else {
+            print!("{}", diff);
+        }
+        return Ok(());
+    }
 
-        Ok(())
+    // Raw outputs were stored by earlier runs, so they don't need API credentials
+    if let Command::RawShow { device, run } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let raw = db.raw_output(device, *run).await?.with_context(|| match run {
+            Some(run) => format!("No raw output stored for '{}' in run {}", device, run),
+            None => format!("No raw output stored for '{}'", device),
+        })?;
+
+        let locale = config.locale.locale()?;
+        println!(
+            "# {} (ID: {}), run {}, fetched {} {}",
+            raw.hostname.as_deref().unwrap_or("unknown"),
+            raw.device_id,
+            raw.run_id,
+            raw.fetched_at.as_deref().map_or("-".to_string(), |at| locale.timestamp(at)),
+            locale.zone()
+        );
+        println!("# Command: {}", raw.command.as_deref().unwrap_or("-").replace('\n', "; "));
+        println!("# SHA-256: {}", raw.sha256);
+        print!("{}", raw.output);
+        if !raw.output.ends_with('\n') {
+            println!();
+        }
+        return Ok(());
     }
 
-    async fn get_devices(&self) -> Result<Vec<serde_json::Value>> {
-        let token = self
-            .access_token
-            .as_ref()
-            .context("Not authenticated. Please login first.")?;
+    // Notes are local to the database, so they don't need API credentials
+    if let Command::NoteAdd { device, text } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let annotation = db.add_annotation(device, text).await?;
+        println!(
+            "Added note {} to {}",
+            annotation.id,
+            annotation.hostname.as_deref().unwrap_or(device)
+        );
+        return Ok(());
+    }
+    if let Command::NoteList { device } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        annotations::print_annotations(&db.annotations(device.as_deref()).await?);
+        return Ok(());
+    }
 
-        let mut all_devices = Vec::new();
-        let mut page = 1;
-        let limit = 100;
+    // The health report only reads the stored inventory
+    if let Command::Health { rebooted_within, stale_after } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let rebooted = db.rebooted_devices(*rebooted_within).await?;
+        let stale = db.stale_devices(*stale_after).await?;
+        health::print_health_report(&rebooted, &stale, *rebooted_within, *stale_after, &config.locale.locale()?);
+        return Ok(());
+    }
 
-        loop {
-            println!("Fetching page {} with limit {}...", page, limit);
+    // Device queries only read the stored inventory
+    if let Command::QueryDevices { query } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        query::print_devices(&db.query_devices(query).await?);
+        return Ok(());
+    }
 
-            let devices_url = format!(
-                "{}/devices?page={}&limit={}&deviceTypes=REAL&async=false",
-                self.base_url, page, limit
-            );
+    // The calendar only reads the run history
+    if let Command::CalendarExport { output, runs } = &cli.command {
+        let feed = config.calendar.feed()?;
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let runs = db.calendar_runs(runs.unwrap_or(feed.runs)).await?;
+        let path = output.clone().unwrap_or_else(|| PathBuf::from(calendar::DEFAULT_CALENDAR_FILE));
+        calendar::write_calendar(&feed.windows, &runs, &path)?;
+        return Ok(());
+    }
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                AUTHORIZATION,
-                HeaderValue::from_str(&format!("Bearer {}", token))
-                    .context("Failed to create authorization header")?,
-            );
+    // The changelog only reads the events table
+    if let Command::EventsTail { limit } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        events::print_events(&db.events_tail(*limit).await?);
+        return Ok(());
+    }
+    if let Command::EventsSince { since } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        events::print_events(&db.events_since(since).await?);
+        return Ok(());
+    }
 
-            let response = self
-                .client
-                .get(&devices_url)
-                .headers(headers)
-                .send()
-                .await
-                .context("Failed to send devices request")?;
+    // BSSID lookups only read the database
+    if let Command::Lookup { mac } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        lookup::print_mac_lookup(mac, &db.lookup_mac(mac).await?, &config.locale.locale()?);
+        return Ok(());
+    }
+    if let Command::LookupFile { path, output } = &cli.command {
+        let macs = lookup::read_macs(path)?;
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let matches = db.lookup_macs(&macs).await?;
+        let report = output.clone().unwrap_or_else(|| PathBuf::from(lookup::DEFAULT_LOOKUP_REPORT));
+        lookup::write_lookup_report(&macs, &matches, &report, LineEnding::Lf, config.csv_profile(None)?)?;
+        lookup::print_lookup_summary(&macs, &matches, &report);
+        return Ok(());
+    }
+
+    // Imported exports stand in for the API, so no credentials are needed
+    if let Command::ImportDevices { path } = &cli.command {
+        let devices = import::load_devices(path)?;
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        save_devices_to_db(&db, &devices).await?;
+        let run_id = db.record_run("import devices", &devices).await?;
+        println!("Imported {} devices from {} as run {}", devices.len(), path.display(), run_id);
+        dump_database(&db, cli.global.db_dump.as_deref()).await?;
+        return Ok(());
+    }
 
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response.text().await.unwrap_or_default();
-                anyhow::bail!("Failed to fetch devices with status {}: {}", status, error_text);
+    // Run diffs only read the run history
+    if let Command::ReportDiff { from, to, format, output } = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let from_run = db.run(*from).await?.with_context(|| format!("Run {} not found", from))?;
+        let to_run = db.run(*to).await?.with_context(|| format!("Run {} not found", to))?;
+        let diff = run_diff::RunDiff::new(
+            from_run,
+            to_run,
+            &db.run_devices(*from).await?,
+            &db.run_devices(*to).await?,
+            &db.run_bssids(*from).await?,
+            &db.run_bssids(*to).await?,
+        );
+        match format {
+            DiffFormat::Text => run_diff::print_run_diff(&diff, &config.locale.locale()?),
+            DiffFormat::Html => {
+                let path = output.clone().unwrap_or_else(|| run_diff::default_output(*from, *to));
+                run_diff::write_html(&diff, &path, &config.locale.locale()?)?;
             }
+        }
+        return Ok(());
+    }
 
-            let devices_response: DevicesResponse = response
-                .json()
-                .await
-                .context("Failed to parse devices response")?;
+    // The web UI only reads collected data, so it doesn't need API credentials
+    if let Command::Serve { listen, allow_anonymous_read } = &cli.command {
+        println!("Connecting to database {}...", db_path.display());
+        let db = Database::new(&db_path, db_pool_size()?).await?;
 
-            let devices_in_page = devices_response.data.len();
-            println!("Retrieved {} devices from page {}", devices_in_page, page);
+        // Runs started from the server use the same database and config
+        let mut run_args = vec!["--db-path".to_string(), db_path.display().to_string()];
+        if let Some(config) = &cli.global.config {
+            run_args.extend(["--config".to_string(), config.display().to_string()]);
+        }
+        if let Some(name) = &profile_name {
+            run_args.extend(["--profile".to_string(), name.clone()]);
+        }
+        let program = env::current_exe().context("Failed to locate the running executable")?;
+        let runs = server::RunLauncher::new(program, run_args);
 
-            all_devices.extend(devices_response.data);
+        return server::serve(db, listen, *allow_anonymous_read, &config.server, config.calendar.feed()?, runs).await;
+    }
 
-            // Check if we have more pages to fetch
-            if let Some(total_pages) = devices_response.total_pages {
-                if page >= total_pages {
-                    println!("Reached last page ({}/{})", page, total_pages);
-                    break;
-                }
-            } else if devices_in_page < limit {
-                // If no total_pages info, stop when we get fewer devices than the limit
-                println!("Reached last page (received {} devices, less than limit of {})", devices_in_page, limit);
-                break;
+    let db_path = enter_output_dir(profile, db_path, &mut cli)?;
+
+    // Exports are written from a run stored by `collect`, without the API, to
+    // the profile's output directory like the run's own files
+    if let Command::Export(options) = &cli.command {
+        let db = Database::new(&db_path, db_pool_size()?).await?;
+        let snapshot = db.run_snapshot(options.run).await?.with_context(|| match options.run {
+            Some(id) => format!("Run {} has no stored results; only runs made with `collect` can be exported", id),
+            None => "No collected runs in the database; run `collect` first".to_string(),
+        })?;
+        let run = snapshot.run;
+        let command = run.command.clone().unwrap_or_else(|| cli::DEFAULT_CLI_COMMAND.to_string());
+        // The raw output is kept apart from the snapshot, and only for the
+        // latest [raw_outputs] keep_runs runs
+        let raw_outputs = db.run_raw_outputs(run.id).await?;
+        let results = if options.reparse {
+            if raw_outputs.is_empty() {
+                anyhow::bail!("Run {} has no stored raw output to reparse; keep more runs with [raw_outputs] keep_runs", run.id);
+            }
+            // Details from the follow-up commands ([radio], [ssid] command) aren't kept
+            let parsers = ParserRegistry::from_config(&config.parsers, &config.external_parsers)?;
+            let outputs = raw_outputs
+                .into_iter()
+                .map(|raw| CliResult { device_id: raw.device_id, status: CliStatus::Success, output: raw.output })
+                .collect();
+            let mut results = api::parse_cli_results(&snapshot.devices, outputs, &command, &parsers)?;
+            postprocess::apply(&mut results, &config.postprocess);
+            annotations::apply_notes(&mut results, &db.device_notes().await?);
+            mac_check::print_invalid_bssids(&mac_check::take_invalid_bssids(&mut results));
+            results
+        } else {
+            let mut outputs: HashMap<i64, String> = raw_outputs.into_iter().map(|raw| (raw.device_id, raw.output)).collect();
+            let mut results = snapshot.results;
+            for result in &mut results {
+                result.output = outputs.remove(&result.device_id).unwrap_or_default();
             }
+            results
+        };
 
-            page += 1;
+        let mut sink_names: Vec<String> = config.output.sinks.iter().filter(|name| name.as_str() != "db").cloned().collect();
+        for format in &options.formats {
+            if !sink_names.contains(format) {
+                sink_names.push(format.clone());
+            }
         }
-
-        println!("Successfully retrieved {} total devices across all pages", all_devices.len());
-
-        Ok(all_devices)
+        let sinks = sink::build_sinks(&sink_names, &config, None)?;
+        let export_context = ExportContext {
+            command: &command,
+            devices: &snapshot.devices,
+            results: &results,
+            line_ending: options.line_ending,
+            csv_profile: config.csv_profile(options.csv_profile.as_deref())?,
+            split_by: options.split_by,
+            incremental: false,
+            txt_layout: options.txt_layout,
+            collected_at: run.started_at.as_deref().and_then(locale::parse_utc).context("Run has no start time")?,
+        };
+        let locale = config.locale.locale()?;
+        println!(
+            "Exporting run {} (started {} {}, {} APs) from {}...",
+            run.id,
+            run.started_at.as_deref().map_or("-".to_string(), |at| locale.timestamp(at)),
+            locale.zone(),
+            results.len(),
+            db_path.display()
+        );
+        for sink in &sinks {
+            sink.export(&export_context)
+                .await
+                .with_context(|| format!("Output sink '{}' failed", sink.name()))?;
+        }
+        println!("\nDone!");
+        return Ok(());
     }
 
-    async fn save_devices_to_file(&self, filename: &str) -> Result<()> {
-        let devices = self.get_devices().await?;
+    // Collection runs are estimated from the stored inventory before logging
+    // in, and refused when they would go over the org's API budget
+    if let Command::Run(options) = &cli.command {
+        let api_budget = config.budget.budget();
+        if options.dry_run || api_budget.max_calls.is_some() {
+            let db = Database::new(&db_path, db_pool_size()?).await?;
+            let managed_by = if config.ssh.enabled { config.ssh.managed_by.clone() } else { Vec::new() };
+            let mesh_commands = if config.mesh.enabled { 2 } else { 0 };
+            let plan = budget::RunPlan {
+                inventory: db.inventory_counts(&managed_by).await?,
+                detail_commands: config.radio.commands.len() + usize::from(config.ssid.command.is_some()) + mesh_commands,
+                spread: options.spread.map(|window| (window, options.chunk_size)),
+            };
+            let estimate = budget::estimate(&plan, &api_budget);
+            let refused = budget::over_budget(&estimate, &api_budget);
+            if options.dry_run || refused.is_some() {
+                budget::print_estimate(&plan, &estimate, &api_budget);
+            }
+            if let Some(reason) = refused {
+                anyhow::bail!(reason);
+            }
+            if options.dry_run {
+                println!("\nDry run: nothing was sent to the API");
+                return Ok(());
+            }
+        }
+    }
 
-        let json_data = serde_json::to_string_pretty(&devices)
-            .context("Failed to serialize devices to JSON")?;
+    let base_url = match profile.and_then(|p| p.base_url.clone()) {
+        Some(url) => url,
+        None => env::var("XIQ_BASE_URL").unwrap_or_else(|_| client::DEFAULT_BASE_URL.to_string()),
+    };
+    let base_url = client::normalize_base_url(&base_url)?;
 
-        let mut file = File::create(filename)
-            .context(format!("Failed to create file: {}", filename))?;
+    let username = match profile.and_then(|p| p.username.clone()) {
+        Some(username) => username,
+        None => env::var("XIQ_USERNAME").context("XIQ_USERNAME environment variable not set")?,
+    };
 
-        file.write_all(json_data.as_bytes())
-            .context("Failed to write data to file")?;
+    let password = match profile.map(ProfileConfig::password).transpose()?.flatten() {
+        Some(password) => password,
+        None => env::var("XIQ_PASSWORD").context("XIQ_PASSWORD environment variable not set")?,
+    };
+    println!("Using API {} as {}", base_url, username);
+
+    // Tried in order when the one before is rejected, e.g. while a rotated
+    // password hasn't propagated yet
+    let mut credentials = vec![Credential::Password { username: username.clone(), password }];
+    let fallback_password = match profile.map(ProfileConfig::fallback_password).transpose()?.flatten() {
+        Some(password) => Some(password),
+        None => env::var("XIQ_FALLBACK_PASSWORD").ok(),
+    };
+    if let Some(password) = fallback_password {
+        let username = profile
+            .and_then(|p| p.fallback_username.clone())
+            .or_else(|| env::var("XIQ_FALLBACK_USERNAME").ok())
+            .unwrap_or_else(|| username.clone());
+        credentials.push(Credential::Password { username, password });
+    }
+    let fallback_token = match profile.map(ProfileConfig::fallback_token).transpose()?.flatten() {
+        Some(token) => Some(token),
+        None => env::var("XIQ_API_TOKEN").ok(),
+    };
+    credentials.extend(fallback_token.map(Credential::Token));
 
-        println!("Devices saved to {}", filename);
+    let mut client = CloudIQClient::new(base_url.clone())
+        .with_http_config(&config.http)?
+        .with_device_projection(config.devices.clone());
+    let started_at = chrono::Utc::now();
+    let mut timer = manifest::StageTimer::new();
 
-        Ok(())
-    }
+    client.check_reachable().await?;
+    println!("Authenticating with Extreme CloudIQ...");
+    client.authenticate(&credentials).await?;
+    timer.mark("login");
+    // Rows written from here on belong to the logged-in account
+    let owner_id = client.owner_id();
+
+    // Everything after login runs in one block so the token is revoked on
+    // every exit path, including errors
+    let result: Result<()> = async {
+        let options = match cli.command {
+            Command::Locate { hostname } => {
+                client.locate_by_hostname(&hostname).await?;
+                println!("\nDone!");
+                return Ok(());
+            }
+            Command::DeviceReboot { targets } => {
+                let db = Database::new(&db_path, db_pool_size()?).await?.with_owner(owner_id);
+                client.reboot_targets(&db, &targets).await?;
+                dump_database(&db, cli.global.db_dump.as_deref()).await?;
+                println!("\nDone!");
+                return Ok(());
+            }
+            Command::ConfigMismatch { push } => {
+                let db = Database::new(&db_path, db_pool_size()?).await?.with_owner(owner_id);
 
-    async fn save_devices_to_db(&self, db: &Database) -> Result<()> {
-        let devices = self.get_devices().await?;
-
-        // Count devices by device_function
-        let total_devices = devices.len();
-        let ap_devices = devices.iter()
-            .filter(|device| {
-                device.get("device_function")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s == "AP")
-                    .unwrap_or(false)
-            })
-            .count();
+                println!("Fetching devices...");
+                let devices = client.get_devices().await?;
+                save_devices_to_db(&db, &devices).await?;
+                let run_id = db.record_run("config mismatch", &devices).await?;
+                alerts::check_ap_changes(&db, run_id, &config.alerts, &config.notify).await?;
 
-        println!("\n=== Device Import Summary ===");
-        println!("Total devices imported: {}", total_devices);
-        println!("Devices with device_function 'AP': {}", ap_devices);
-        println!("============================\n");
+                let mismatched = db.config_mismatches().await?;
+                mismatch::print_mismatch_report(&mismatched, &config.locale.locale()?);
 
-        db.insert_devices(&devices).await?;
-        Ok(())
-    }
+                if let Some(targets) = push {
+                    client.push_config_targets(&db, &devices, &mismatched, &targets).await?;
+                }
 
-    async fn send_cli_command(&self, device_ids: &[i64], command: &str) -> Result<Vec<(i64, String)>> {
-        let token = self
-            .access_token
-            .as_ref()
-            .context("Not authenticated. Please login first.")?;
+                dump_database(&db, cli.global.db_dump.as_deref()).await?;
+                println!("\nDone!");
+                return Ok(());
+            }
+            Command::ConfigBackup => {
+                let db = Database::new(&db_path, db_pool_size()?).await?.with_owner(owner_id);
+
+                println!("Fetching devices...");
+                let devices = client.get_devices().await?;
+                save_devices_to_db(&db, &devices).await?;
+                let run_id = db.record_run("config backup", &devices).await?;
+                alerts::check_ap_changes(&db, run_id, &config.alerts, &config.notify).await?;
+
+                let previous = db.latest_config_hashes().await?;
+                let backups = client.backup_configs(&devices, &config.backup).await?;
+                let changed = backups
+                    .iter()
+                    .filter(|b| previous.get(&b.device_id) != Some(&backup::config_hash(&b.config)))
+                    .count();
+                db.insert_configs(run_id, &backups).await?;
+
+                println!(
+                    "Backed up {} configuration(s) in run {} ({} changed since the previous backup)",
+                    backups.len(),
+                    run_id,
+                    changed
+                );
+
+                dump_database(&db, cli.global.db_dump.as_deref()).await?;
+                println!("\nDone!");
+                return Ok(());
+            }
+            Command::Stats => {
+                let db = Database::new(&db_path, db_pool_size()?).await?.with_owner(owner_id);
+                let targets = db.access_interfaces().await?;
+                if targets.is_empty() {
+                    anyhow::bail!("No access interfaces in the database; run a collection first");
+                }
 
-        let cli_url = format!("{}/devices/:cli", self.base_url);
+                println!("Fetching devices...");
+                let devices = client.get_devices().await?;
+                save_devices_to_db(&db, &devices).await?;
+                let run_id = db.record_run("stats", &devices).await?;
+                alerts::check_ap_changes(&db, run_id, &config.alerts, &config.notify).await?;
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", token))
-                .context("Failed to create authorization header")?,
-        );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            HeaderValue::from_static("application/json"),
-        );
+                let counters = client.collect_interface_stats(&devices, &targets, &config.stats.command).await?;
+                db.insert_interface_stats(run_id, &counters).await?;
+                stats::print_stats_summary(&counters);
 
-        let payload = serde_json::json!({
-            "devices": {
-                "ids": device_ids
-            },
-            "clis": [command]
-        });
-
-        let response = self
-            .client
-            .post(&cli_url)
-            .headers(headers)
-            .json(&payload)
-            .send()
-            .await
-            .context("Failed to send CLI command request")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("CLI command failed with status {}: {}", status, error_text);
-        }
-
-        let response_text = response.text().await.context("Failed to get response text")?;
-
-        let cli_response: serde_json::Value = serde_json::from_str(&response_text)
-            .context("Failed to parse CLI response as JSON")?;
-
-        let mut results = Vec::new();
-        if let Some(outputs) = cli_response.get("device_cli_outputs").and_then(|v| v.as_object()) {
-            for (device_id_str, output_value) in outputs {
-                if let Ok(device_id) = device_id_str.parse::<i64>() {
-                    // Handle different possible output formats
-                    let output = if let Some(arr) = output_value.as_array() {
-                        // Array of objects with "output" field
-                        arr.iter()
-                            .filter_map(|item| {
-                                item.get("output").and_then(|o| o.as_str())
-                            })
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    } else if let Some(s) = output_value.as_str() {
-                        s.to_string()
-                    } else {
-                        output_value.to_string()
+                dump_database(&db, cli.global.db_dump.as_deref()).await?;
+                println!("\nDone!");
+                return Ok(());
+            }
+            Command::Subscribe { interval } => {
+                let db = Database::new(&db_path, db_pool_size()?).await?.with_owner(owner_id);
+                let parsers = ParserRegistry::from_config(&config.parsers, &config.external_parsers)?;
+                let interrupt = Interrupt::install();
+
+                // Compared against the last stored inventory, so changes since
+                // the previous run are picked up on the first poll
+                let mut previous = db.connection_states().await?;
+                println!("Polling devices every {}s for connection changes (Ctrl-C to stop)...", interval.as_secs());
+                loop {
+                    let Some(devices) = interrupt.guard(client.get_devices()).await else {
+                        break;
                     };
-                    results.push((device_id, output));
+                    let devices = devices?;
+                    let current = subscribe::connection_states(&devices);
+                    let events = subscribe::connection_events(&previous, &current);
+                    subscribe::print_events(&events);
+
+                    if !events.is_empty() {
+                        save_devices_to_db(&db, &devices).await?;
+                        let targets = subscribe::refresh_targets(&events, &devices);
+                        if !targets.is_empty() {
+                            let dispatch = client.dispatch_to_connected_aps(&targets, cli::DEFAULT_CLI_COMMAND);
+                            let Some(outputs) = interrupt.guard(dispatch).await else {
+                                break;
+                            };
+                            let outputs = outputs?;
+                            cli_status::print_failure_summary(&cli_status::device_statuses(&targets, &outputs));
+                            let mut aps = api::parse_cli_results(&targets, outputs, cli::DEFAULT_CLI_COMMAND, &parsers)?;
+                            postprocess::apply(&mut aps, &config.postprocess);
+                            mac_check::print_invalid_bssids(&mac_check::take_invalid_bssids(&mut aps));
+                            db.update_interfaces(&aps).await?;
+                        }
+                    }
+
+                    previous = current;
+                    if interrupt.guard(tokio::time::sleep(interval)).await.is_none() {
+                        break;
+                    }
                 }
+
+                println!("\nStopped polling for device changes");
+                return Ok(());
+            }
+            Command::ConfigDiff { .. }
+            | Command::RawShow { .. }
+            | Command::Schema { .. }
+            | Command::DbMerge { .. }
+            | Command::NoteAdd { .. }
+            | Command::NoteList { .. }
+            | Command::Health { .. }
+            | Command::ReportDiff { .. }
+            | Command::ImportDevices { .. }
+            | Command::Lookup { .. }
+            | Command::LookupFile { .. }
+            | Command::GrafanaExport { .. }
+            | Command::QueryDevices { .. }
+            | Command::CalendarExport { .. }
+            | Command::EventsTail { .. }
+            | Command::EventsSince { .. }
+            | Command::Export(_)
+            | Command::Serve { .. } => {
+                unreachable!("handled before login")
+            }
+            Command::Run(options) => options,
+        };
+
+        progress::emit(ProgressEvent::RunStarted { command: options.cli_command.clone() });
+        let run_started = std::time::SystemTime::now();
+        let csv_profile = config.csv_profile(options.csv_profile.as_deref())?;
+        if options.stream {
+            // Streamed rows are final once written, so nothing may change them afterwards
+            if !config.radio.commands.is_empty() || config.ssid.command.is_some() {
+                anyhow::bail!("--stream can't be combined with [radio] commands or [ssid] command");
+            }
+            if options.split_by.is_some() {
+                anyhow::bail!("--stream can't be combined with --split-by");
             }
         }
+        let interrupt = Interrupt::install();
+        if let Some(max_duration) = options.max_duration {
+            // Counted from the start of the command, so login time is included
+            let elapsed = (chrono::Utc::now() - started_at).to_std().unwrap_or_default();
+            interrupt.set_deadline(max_duration.saturating_sub(elapsed));
+        }
+        let stopped = |interrupt: &Interrupt| Interrupted(interrupt.reason().unwrap_or(StopReason::Interrupted));
+
+        println!("Fetching devices...");
+        let mut devices = interrupt.guard(client.get_devices()).await.ok_or_else(|| stopped(&interrupt))??;
+        timer.mark("fetch_devices");
+
+        if config.dns.enabled {
+            println!("Resolving device IPs via reverse DNS...");
+            let timeout = std::time::Duration::from_secs(config.dns.timeout_secs);
+            let lookup = dns::reverse_lookup(&devices, config.dns.concurrency, timeout);
+            let names = interrupt.guard(lookup).await.ok_or_else(|| stopped(&interrupt))?;
+            dns::apply_fqdns(&mut devices, &names);
+            timer.mark("reverse_dns");
+        }
 
-        Ok(results)
-    }
+        if options.device_export && options.files {
+            save_devices_to_file(&devices, Path::new("devices.json"), options.line_ending)?;
+        }
 
-    fn get_connected_aps(devices: &[serde_json::Value]) -> Vec<(i64, String)> {
-        devices
-            .iter()
-            .filter(|device| {
-                let connected = device.get("connected")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                let is_ap = device.get("device_function")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s == "AP")
-                    .unwrap_or(false);
-                connected && is_ap
-            })
-            .filter_map(|device| {
-                let id = device.get("id")?.as_i64()?;
-                let hostname = device.get("hostname")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                Some((id, hostname))
-            })
-            .collect()
-    }
+        let mut run_id = None;
+        let mut known_ids = None;
+        let db = if options.db {
+            println!("Connecting to database {}...", db_path.display());
+            let db = Database::new(&db_path, db_pool_size()?).await?.with_owner(owner_id);
 
-    async fn run_command_on_connected_aps(&self, command: &str) -> Result<()> {
-        let devices = self.get_devices().await?;
-        let connected_aps = Self::get_connected_aps(&devices);
-
-        if connected_aps.is_empty() {
-            println!("No connected APs found.");
-            return Ok(());
-        }
-
-        println!("\n=== Found {} connected APs ===", connected_aps.len());
-        for (id, hostname) in &connected_aps {
-            println!("  - {} (ID: {})", hostname, id);
-        }
-        println!();
-
-        let device_ids: Vec<i64> = connected_aps.iter().map(|(id, _)| *id).collect();
-
-        println!("Sending command '{}' to all connected APs...\n", command);
-
-        let results = self.send_cli_command(&device_ids, command).await?;
-
-        // Create a map of device_id -> hostname for output
-        let hostname_map: std::collections::HashMap<i64, String> = connected_aps.into_iter().collect();
-
-        // Open bssids.txt for writing - will contain normalized BSSIDs
-        let mut bssid_file = File::create("bssids.txt")
-            .context("Failed to create bssids.txt")?;
-
-        // Open wifi-bssids.txt for writing - will contain only access mode interfaces
-        let mut wifi_bssid_file = File::create("wifi-bssids.txt")
-            .context("Failed to create wifi-bssids.txt")?;
-
-        // Open wifi-bssids.csv for writing - CSV format of access mode interfaces
-        let mut wifi_bssid_csv = File::create("wifi-bssids.csv")
-            .context("Failed to create wifi-bssids.csv")?;
-
-        // Write header for wifi-bssids.txt once at the top
-        writeln!(wifi_bssid_file, "{:<20} {:<20} {:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<12} {:<12} {}",
-            "Device", "DeviceID", "Name", "MAC", "Mode", "State", "Channel", "VLAN", "Radio", "Hive", "SSID")
-            .context("Failed to write column header to wifi-bssids.txt")?;
-        writeln!(wifi_bssid_file, "{}", "-".repeat(140))
-            .context("Failed to write separator to wifi-bssids.txt")?;
-
-        // Write CSV header
-        writeln!(wifi_bssid_csv, "Device,DeviceID,Name,MAC,Mode,State,Channel,VLAN,Radio,Hive,SSID")
-            .context("Failed to write CSV header to wifi-bssids.csv")?;
-
-        // Build JSON output for saving to file
-        let mut json_results = Vec::new();
-        let mut total_bssids = 0;
-        let mut total_wifi_bssids = 0;
-
-        println!("=== CLI Command Results ===\n");
-        for (device_id, output) in &results {
-            let hostname = hostname_map.get(device_id).map(|s| s.as_str()).unwrap_or("unknown");
-
-            // Extract and normalize interface entries using the parser module
-            let interfaces = extract_interfaces(output);
-            if !interfaces.is_empty() {
-                println!("  {} (ID: {}): Found {} interface(s)", hostname, device_id, interfaces.len());
-                total_bssids += interfaces.len();
-
-                // Write full interface data to file with device context
-                writeln!(bssid_file, "--- {} (ID: {}) ---", hostname, device_id)
-                    .context("Failed to write header to bssids.txt")?;
-                writeln!(bssid_file, "{:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<8} {:<12} {}",
-                    "Name", "MAC", "Mode", "State", "Channel", "VLAN", "Radio", "Hive", "SSID")
-                    .context("Failed to write column header to bssids.txt")?;
-                writeln!(bssid_file, "{}", "-".repeat(100))
-                    .context("Failed to write separator to bssids.txt")?;
-                for iface in &interfaces {
-                    writeln!(bssid_file, "{:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<8} {:<12} {}",
-                        iface.name, iface.mac, iface.mode, iface.state,
-                        iface.channel, iface.vlan, iface.radio, iface.hive, iface.ssid)
-                        .context("Failed to write interface to bssids.txt")?;
-                }
-                writeln!(bssid_file).context("Failed to write newline to bssids.txt")?;
+            // Read before the save below, which would add this run's devices
+            if options.only_new {
+                known_ids = Some(db.device_ids().await?);
+            }
+
+            println!("Saving devices to database...");
+            save_devices_to_db(&db, &devices).await?;
+            let id = db.record_run(&options.cli_command, &devices).await?;
+            alerts::check_ap_changes(&db, id, &config.alerts, &config.notify).await?;
+            run_id = Some(id);
+
+            let count = db.count_devices().await?;
+            println!("Database now contains {} devices", count);
+
+            timer.mark("save_devices");
+            Some(db)
+        } else {
+            None
+        };
+        let notes = match &db {
+            Some(db) => db.device_notes().await?,
+            None => Default::default(),
+        };
 
-                // Filter and write access-mode interfaces to wifi-bssids.txt
-                let access_interfaces: Vec<_> = interfaces.iter()
-                    .filter(|iface| iface.mode.to_lowercase() == "access")
+        // With --only-new, devices already in the database keep their stored interfaces
+        let mut targets: Vec<serde_json::Value> = match &known_ids {
+            Some(known) => {
+                let new: Vec<_> = devices
+                    .iter()
+                    .filter(|d| d.get("id").and_then(|v| v.as_i64()).is_some_and(|id| !known.contains(&id)))
+                    .cloned()
                     .collect();
+                println!("{} of {} devices are new since the last run", new.len(), devices.len());
+                new
+            }
+            None => devices.clone(),
+        };
+        if !options.targets.is_empty() {
+            let listed: Vec<i64> = api::resolve_targets(&devices, &options.targets)?.into_iter().map(|(id, _)| id).collect();
+            targets.retain(|d| d.get("id").and_then(|v| v.as_i64()).is_some_and(|id| listed.contains(&id)));
+            println!("Collecting from {} of {} listed devices", targets.len(), listed.len());
+        }
 
-                if !access_interfaces.is_empty() {
-                    total_wifi_bssids += access_interfaces.len();
-                    for iface in &access_interfaces {
-                        // Write to txt file (fixed-width format)
-                        writeln!(wifi_bssid_file, "{:<20} {:<20} {:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<12} {:<12} {}",
-                            hostname, device_id, iface.name, iface.mac, iface.mode, iface.state,
-                            iface.channel, iface.vlan, iface.radio, iface.hive, iface.ssid)
-                            .context("Failed to write interface to wifi-bssids.txt")?;
-
-                        // Write to CSV file (with proper escaping)
-                        writeln!(wifi_bssid_csv, "{},{},{},{},{},{},{},{},{},{},{}",
-                            csv_escape(hostname),
-                            device_id,
-                            csv_escape(&iface.name),
-                            csv_escape(&iface.mac),
-                            csv_escape(&iface.mode),
-                            csv_escape(&iface.state),
-                            csv_escape(&iface.channel),
-                            csv_escape(&iface.vlan),
-                            csv_escape(&iface.radio),
-                            csv_escape(&iface.hive),
-                            csv_escape(&iface.ssid))
-                            .context("Failed to write interface to wifi-bssids.csv")?;
-                    }
+        // Run CLI command on connected APs
+        println!("\nRunning CLI command on connected APs...");
+        let parsers = ParserRegistry::from_config(&config.parsers, &config.external_parsers)?;
+
+        // Locally managed APs can't take cloud CLI; they're collected over SSH below
+        let (ssh_aps, ssh_collector) = if config.ssh.enabled {
+            (ssh::locally_managed_aps(&targets, &config.ssh.managed_by), Some(config.ssh.collector()?))
+        } else {
+            (Vec::new(), None)
+        };
+        let cloud_targets: Vec<serde_json::Value> = targets
+            .iter()
+            .filter(|d| !d.get("id").and_then(|v| v.as_i64()).is_some_and(|id| ssh_aps.contains(&id)))
+            .cloned()
+            .collect();
+
+        let dispatch = async {
+            match (options.spread, &db) {
+                (Some(window), Some(db)) => {
+                    spread::dispatch_spread(&client, db, &cloud_targets, &options.cli_command, window, options.chunk_size).await
+                }
+                _ => client.dispatch_to_connected_aps(&cloud_targets, &options.cli_command).await,
+            }
+        };
+        let outputs = interrupt.guard(dispatch).await.transpose()?;
+        timer.mark("cli_dispatch");
+        // Stored before parsing, so it's exactly what the APs returned
+        let keep_raw = match (&db, run_id) {
+            (Some(db), Some(run_id)) if config.raw_outputs.keep_runs > 0 => Some((db, run_id)),
+            _ => None,
+        };
+        if let (Some((db, run_id)), Some(outputs)) = (keep_raw, &outputs) {
+            db.record_raw_outputs(run_id, &options.cli_command, &devices, outputs).await?;
+            db.prune_raw_outputs(config.raw_outputs.keep_runs).await?;
+        }
+        let cli_statuses = outputs
+            .as_ref()
+            .map(|outputs| cli_status::device_statuses(&cloud_targets, outputs))
+            .unwrap_or_default();
+        let mut invalid_bssids = Vec::new();
+        let mut stream_writer = None;
+        let command_results = match outputs {
+            Some(outputs) if options.stream => {
+                let streamed: Vec<String> = config
+                    .output
+                    .sinks
+                    .iter()
+                    .chain(&options.formats)
+                    .filter(|name| options.files && stream::STREAMED_SINKS.contains(&name.as_str()))
+                    .cloned()
+                    .collect();
+                let mut writer = StreamWriter::create(&streamed, &options.cli_command, options.line_ending, csv_profile, options.txt_layout)?;
+                let results = api::parse_cli_results_with(&devices, outputs, &options.cli_command, &parsers, |ap| {
+                    postprocess::apply(std::slice::from_mut(ap), &config.postprocess);
+                    annotations::apply_notes(std::slice::from_mut(ap), &notes);
+                    invalid_bssids.extend(mac_check::take_invalid_bssids(std::slice::from_mut(ap)));
+                    writer.write_device(ap)
+                })?;
+                stream_writer = Some(writer);
+                Some(results)
+            }
+            Some(outputs) => Some(api::parse_cli_results(&devices, outputs, &options.cli_command, &parsers)?),
+            None => None,
+        };
+        timer.mark("parse");
+
+        // Interrupted before any CLI output arrived: keep the device inventory
+        // saved above, but don't replace the previous interface outputs with nothing
+        let collected = command_results.is_some();
+        let mut results = command_results.unwrap_or_default();
+        let streamed = results.len();
+
+        // APs that didn't answer the CLI command are walked directly instead
+        if config.snmp_fallback.enabled && collected {
+            if let Some(walked) = interrupt
+                .guard(snmp::collect_fallback(&targets, &cli_statuses, &config.snmp_fallback.fallback()))
+                .await
+            {
+                results.extend(walked);
+                timer.mark("snmp_fallback");
+            }
+        }
+        if let (Some(collector), true) = (&ssh_collector, collected && !ssh_aps.is_empty()) {
+            let command = &options.cli_command;
+            if let Some(outputs) = interrupt.guard(ssh::collect_over_ssh(&targets, &ssh_aps, command, collector)).await {
+                if let Some((db, run_id)) = keep_raw {
+                    db.record_raw_outputs(run_id, command, &devices, &outputs).await?;
+                }
+                results.extend(api::parse_cli_results(&devices, outputs, command, &parsers)?);
+                timer.mark("ssh");
+            }
+        }
+        // The fallbacks' APs go through the same steps as the streamed CLI results
+        // before the streamed files are closed
+        let cleaned = match stream_writer {
+            Some(mut writer) => {
+                for ap in &mut results[streamed..] {
+                    postprocess::apply(std::slice::from_mut(ap), &config.postprocess);
+                    annotations::apply_notes(std::slice::from_mut(ap), &notes);
+                    invalid_bssids.extend(mac_check::take_invalid_bssids(std::slice::from_mut(ap)));
+                    writer.write_device(ap)?;
                 }
+                writer.finish();
+                results.len()
             }
+            None => 0,
+        };
+        annotations::apply_notes(&mut results, &notes);
+
+        let radio_details = if config.radio.commands.is_empty() {
+            Vec::new()
+        } else if let Some(details) = interrupt.guard(client.collect_radio_details(&results, &config.radio.commands)).await {
+            let details = details?;
+            radio::apply_radio_details(&mut results, &details);
+            timer.mark("radio_details");
+            details
+        } else {
+            Vec::new()
+        };
 
-            json_results.push(serde_json::json!({
-                "device_id": device_id,
-                "hostname": hostname,
-                "command": command,
-                "output": output
-            }));
+        if let Some(command) = &config.ssid.command {
+            if let Some(security) = interrupt.guard(client.collect_ssid_security(&results, command)).await {
+                ssid::apply_ssid_security(&mut results, &security?);
+                timer.mark("ssid_security");
+            }
         }
 
-        // Save to full_cli.json
-        let json_data = serde_json::to_string_pretty(&json_results)
-            .context("Failed to serialize CLI results to JSON")?;
+        let mesh = if config.mesh.enabled {
+            interrupt
+                .guard(client.collect_mesh(&results, &config.mesh.hive_command, &config.mesh.neighbor_command))
+                .await
+                .transpose()?
+        } else {
+            None
+        };
+        let mesh_links = match mesh {
+            Some(mesh) => {
+                let links = mesh::mesh_links(&mesh, &mesh::mac_index(&devices, &results));
+                if options.files {
+                    mesh::write_mesh_exports(&mesh, &links, options.line_ending, csv_profile)?;
+                }
+                timer.mark("mesh");
+                links
+            }
+            None => Vec::new(),
+        };
 
-        let mut file = File::create("full_cli.json")
-            .context("Failed to create full_cli.json")?;
+        let unreachable_aps = if config.reachability.enabled && collected {
+            println!("Checking that connected APs are reachable...");
+            let flagged = interrupt
+                .guard(reachability::check_connected_aps(&targets, &config.reachability.checks()))
+                .await;
+            if let Some(flagged) = &flagged {
+                if options.files {
+                    reachability::write_unreachable_aps(
+                        flagged,
+                        Path::new(reachability::UNREACHABLE_APS_FILE),
+                        options.line_ending,
+                        csv_profile,
+                    )?;
+                }
+                timer.mark("reachability");
+            }
+            flagged.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
 
-        file.write_all(json_data.as_bytes())
-            .context("Failed to write CLI results to file")?;
+        // After the SSID security lookup, which matches the SSIDs as the APs report them
+        let dropped = postprocess::apply(&mut results[cleaned..], &config.postprocess);
+        if dropped > 0 {
+            println!("Dropped {} interfaces on VLANs listed in [postprocess] drop_vlans", dropped);
+        }
 
-        println!("CLI results saved to full_cli.json");
-        println!("CLI output saved to bssids.txt ({} BSSIDs found)", total_bssids);
-        println!("Access mode BSSIDs saved to wifi-bssids.txt ({} entries)", total_wifi_bssids);
-        println!("Access mode BSSIDs saved to wifi-bssids.csv ({} entries)", total_wifi_bssids);
+        let stop_reason = interrupt.reason();
+        let partial = stop_reason.is_some();
+        // Stored rows of the devices a stopped or partial run didn't reach are kept
+        let incomplete = options.is_incomplete(stop_reason);
+        if partial {
+            println!("\nRun stopped early; saving the {} devices and {} APs collected so far", devices.len(), results.len());
+        }
 
-        Ok(())
-    }
-}
+        // Keep malformed, multicast and locally administered MACs out of the exports
+        invalid_bssids.extend(mac_check::take_invalid_bssids(&mut results));
+        if options.files && collected {
+            mac_check::write_invalid_bssids(
+                &invalid_bssids,
+                Path::new(mac_check::INVALID_BSSIDS_FILE),
+                options.line_ending,
+                csv_profile,
+            )?;
+        }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    dotenv::dotenv().ok();
+        let ssid_violations = ssid::ssid_compliance(&devices, &results, &config.ssid);
+        if options.files && collected && config.ssid.has_compliance_rules() {
+            ssid::write_ssid_compliance(
+                &ssid_violations,
+                Path::new(ssid::SSID_COMPLIANCE_FILE),
+                options.line_ending,
+                csv_profile,
+            )?;
+        }
 
-    println!("Developed by Jeff Buddington www.linkedin.com/in/jeff-buddington-5178ba4");
-    println!();
+        let validation_findings = if config.validation.enabled && collected {
+            let findings = validation::validate(&results, &config.validation);
+            if options.files {
+                validation::write_validation_report(
+                    &findings,
+                    Path::new(validation::VALIDATION_REPORT_FILE),
+                    options.line_ending,
+                    csv_profile,
+                )?;
+            }
+            findings
+        } else {
+            Vec::new()
+        };
 
-    let args: Vec<String> = env::args().collect();
+        let bssid_count = results
+            .iter()
+            .flat_map(|r| &r.interfaces)
+            .filter(|i| i.is_access())
+            .count();
+        let site_summary = summary::site_summary(&devices, &results);
+        let (previous_bssids, new_bssids) = match (&db, run_id) {
+            (Some(db), Some(run_id)) if collected => {
+                // A partial or stopped run's count would look like a drop to the next full run
+                let previous = db.previous_run_bssids(run_id).await?;
+                if !incomplete {
+                    db.set_run_bssids(run_id, bssid_count).await?;
+                }
+                let new_bssids = db.record_bssids(run_id, &results).await?;
+                db.record_bssid_events(run_id).await?;
+                db.record_radio_details(run_id, &radio_details).await?;
+                db.record_mesh_links(run_id, &mesh_links).await?;
+                db.record_cli_statuses(run_id, &cli_statuses).await?;
+                if options.snapshot {
+                    db.record_run_snapshot(run_id, &devices, &results).await?;
+                    db.prune_run_snapshots(config.collect.keep_runs.max(1)).await?;
+                    println!("Results of run {} stored; write the output files with `export --run {}`", run_id, run_id);
+                }
+                if !incomplete {
+                    db.record_site_summary(run_id, &site_summary).await?;
+                }
+                alerts::check_bssid_churn(db, run_id, &config.alerts, &config.notify).await?;
+                timer.mark("record_history");
+                (previous, new_bssids)
+            }
+            _ => (None, Vec::new()),
+        };
 
-    let base_url = env::var("XIQ_BASE_URL")
-        .unwrap_or_else(|_| "https://api.extremecloudiq.com".to_string());
+        let mut requested_sinks = config.output.sinks.clone();
+        for format in &options.formats {
+            if !requested_sinks.contains(format) {
+                requested_sinks.push(format.clone());
+            }
+        }
+        let sink_names: Vec<String> = requested_sinks
+            .iter()
+            .filter(|name| options.files || !sink::FILE_SINKS.contains(&name.as_str()))
+            .filter(|name| options.db || name.as_str() != "db")
+            .filter(|name| !options.stream || !stream::STREAMED_SINKS.contains(&name.as_str()))
+            .filter(|_| collected)
+            .cloned()
+            .collect();
+        let sinks = sink::build_sinks(&sink_names, &config, db.as_ref())?;
+
+        let export_context = ExportContext {
+            command: &options.cli_command,
+            devices: &devices,
+            results: &results,
+            line_ending: options.line_ending,
+            csv_profile,
+            split_by: options.split_by,
+            incremental: incomplete,
+            txt_layout: options.txt_layout,
+            collected_at: started_at,
+        };
+        for sink in &sinks {
+            sink.export(&export_context)
+                .await
+                .with_context(|| format!("Output sink '{}' failed", sink.name()))?;
+            timer.mark(&format!("export_{}", sink.name()));
+        }
 
-    let username = env::var("XIQ_USERNAME")
-        .context("XIQ_USERNAME environment variable not set")?;
+        summary::print_new_bssids(&new_bssids);
+        summary::print_ssid_summary(&summary::ssid_summary(&results));
+        summary::print_site_summary(&site_summary);
+        summary::print_policy_summary(&summary::policy_summary(&devices, &results), &config.ssid.required_by_policy);
+        if !config.vendors.allowed.is_empty() {
+            let ouis = oui::OuiDatabase::load(config.vendors.oui_file.as_deref())?;
+            oui::print_unexpected_vendors(&oui::unexpected_vendors(&results, &ouis, &config.vendors.allowed));
+        }
+        mac_check::print_invalid_bssids(&invalid_bssids);
+        ssid::print_ssid_compliance(&ssid_violations);
+        validation::print_validation_summary(&validation_findings);
+        reachability::print_unreachable_aps(&unreachable_aps);
+        cli_status::print_failure_summary(&cli_statuses);
+        rf::print_rf_summary(
+            &rf::channel_histogram(&devices, &results),
+            &rf::co_channel_conflicts(&devices, &results),
+            &rf::non_psc_radios(&results),
+            &rf::radio_bssid_counts(&results),
+            config.rf.max_bssids_per_radio,
+        );
 
-    let password = env::var("XIQ_PASSWORD")
-        .context("XIQ_PASSWORD environment variable not set")?;
+        let mut archived_files: Vec<PathBuf> = archive::OUTPUT_FILES.iter().map(PathBuf::from).collect();
+        archived_files.extend(sink::split_output_files(&export_context));
+
+        if let Some(template) = options.template.as_ref().filter(|_| collected) {
+            let output = options
+                .template_output
+                .clone()
+                .unwrap_or_else(|| report::default_template_output(template));
+            archived_files.push(output.clone());
+            let context = TemplateContext {
+                command: &options.cli_command,
+                devices: &devices,
+                aps: &results,
+            };
+            report::render_template_file(template, &output, &context, options.line_ending)?;
+            timer.mark("template");
+        }
 
-    let mut client = CloudIQClient::new(base_url);
+        let throughput = manifest::Throughput::new(devices.len(), bssid_count, timer.total_millis());
+        if options.files {
+            let manifest = manifest::Manifest {
+                tool_version: env!("CARGO_PKG_VERSION"),
+                started_at: started_at.to_rfc3339(),
+                finished_at: chrono::Utc::now().to_rfc3339(),
+                base_url,
+                account: username,
+                org_ids: manifest::org_ids(&devices),
+                commands: options.cli_command.lines().map(String::from).collect(),
+                filters: manifest::RunFilters {
+                    db: options.db,
+                    files: options.files,
+                    device_export: options.device_export,
+                    crlf: options.line_ending == LineEnding::Crlf,
+                    stream: options.stream,
+                    only_new: options.only_new,
+                    sinks: sink_names.clone(),
+                    template: options.template.clone(),
+                },
+                devices: devices.len(),
+                aps: results.len(),
+                partial,
+                stop_reason,
+                stages: timer.stages().to_vec(),
+                throughput,
+                outputs: manifest::checksum_outputs(&archived_files, run_started)?,
+            };
+            manifest::write_manifest(&manifest, Path::new("manifest.json"), options.line_ending)?;
+        }
 
-    println!("Authenticating with Extreme CloudIQ...");
-    client.login(&username, &password).await?;
+        manifest::print_timings(timer.stages(), &throughput);
+        if let (Some(db), Some(run_id)) = (&db, run_id) {
+            db.record_run_stages(run_id, timer.stages()).await?;
+        }
 
-    // Determine the CLI command to run
-    let command = if args.len() > 1 {
-        args[1..].join(" ")
-    } else {
-        "show interface".to_string()
-    };
+        if options.files && config.archive.keep > 0 {
+            if let Some(dir) = archive::archive_outputs(&config.archive.dir, &archived_files, run_started, config.archive.keep)? {
+                println!("Output files archived to {}", dir.display());
+            }
+        }
 
-    // Save devices to file and database
-    println!("Fetching devices...");
-    client.save_devices_to_file("devices.json").await?;
+        if let Some(db) = &db {
+            dump_database(db, cli.global.db_dump.as_deref()).await?;
+        }
 
-    println!("Connecting to database...");
-    let db = Database::new("xiq-db").await?;
+        if let Some(reason) = stop_reason {
+            return Err(Interrupted(reason).into());
+        }
 
-    println!("Saving devices to database...");
-    client.save_devices_to_db(&db).await?;
+        // Checked last so every output is still written for troubleshooting
+        if let Some(problem) = validation::threshold_violation(&validation_findings, config.validation.max_errors) {
+            anyhow::bail!("{}", problem);
+        }
+        if let Some(problem) = notify::bssid_count_violation(
+            bssid_count,
+            previous_bssids,
+            options.min_bssids,
+            options.max_bssid_drop,
+        ) {
+            notify::send_notifications(
+                &config.notify,
+                "low_bssid_count",
+                &problem,
+                serde_json::json!({
+                    "command": options.cli_command,
+                    "bssids": bssid_count,
+                    "previous_bssids": previous_bssids,
+                    "aps": results.len(),
+                }),
+            )
+            .await;
+            anyhow::bail!("{}", problem);
+        }
 
-    let count = db.count_devices().await?;
-    println!("Database now contains {} devices", count);
+        progress::emit(ProgressEvent::RunFinished { bssids: bssid_count });
+        println!("\nDone!");
 
-    // Run CLI command on connected APs
-    println!("\nRunning CLI command on connected APs...");
-    client.run_command_on_connected_aps(&command).await?;
+        Ok(())
+    }
+    .await;
 
-    println!("\nDone!");
+    if let Err(e) = client.logout().await {
+        eprintln!("Warning: {:#}", e);
+    }
+    client.latency().print_summary();
 
-    Ok(())
+    result
 }
@@ -0,0 +1,226 @@
+use crate::interrupt::StopReason;
+use crate::output::{create_output_file, LineEnding};
+use crate::progress::{self, ProgressEvent};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Hex SHA-256 of a byte slice
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// How long one pipeline stage took
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub millis: u128,
+}
+
+/// Records the duration of consecutive pipeline stages
+pub struct StageTimer {
+    last: Instant,
+    stages: Vec<StageTiming>,
+}
+
+impl StageTimer {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Close the current stage, timing it from the previous mark
+    pub fn mark(&mut self, stage: &str) {
+        let now = Instant::now();
+        let millis = now.duration_since(self.last).as_millis();
+        progress::emit(ProgressEvent::StageCompleted { stage: stage.to_string(), millis });
+        self.stages.push(StageTiming { stage: stage.to_string(), millis });
+        self.last = now;
+    }
+
+    pub fn stages(&self) -> &[StageTiming] {
+        &self.stages
+    }
+
+    /// Sum of all recorded stages
+    pub fn total_millis(&self) -> u128 {
+        self.stages.iter().map(|s| s.millis).sum()
+    }
+}
+
+/// Devices and BSSIDs processed per second over the whole run
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Throughput {
+    pub devices_per_sec: f64,
+    pub bssids_per_sec: f64,
+}
+
+impl Throughput {
+    pub fn new(devices: usize, bssids: usize, millis: u128) -> Self {
+        let secs = (millis as f64 / 1000.0).max(0.001);
+        Self {
+            devices_per_sec: devices as f64 / secs,
+            bssids_per_sec: bssids as f64 / secs,
+        }
+    }
+}
+
+/// Print each stage's duration and share of the run, then the throughput
+pub fn print_timings(stages: &[StageTiming], throughput: &Throughput) {
+    let total: u128 = stages.iter().map(|s| s.millis).sum();
+    let width = stages.iter().map(|s| s.stage.len()).max().unwrap_or(0).max(5);
+
+    println!("\n=== Stage Timings ===");
+    for stage in stages {
+        let share = if total > 0 { stage.millis as f64 * 100.0 / total as f64 } else { 0.0 };
+        println!("  {:<width$}  {:>9.2}s  {:>5.1}%", stage.stage, stage.millis as f64 / 1000.0, share);
+    }
+    println!("  {:<width$}  {:>9.2}s", "total", total as f64 / 1000.0);
+    println!(
+        "Throughput: {:.1} devices/s, {:.1} BSSIDs/s",
+        throughput.devices_per_sec, throughput.bssids_per_sec
+    );
+}
+
+impl Default for StageTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Options that shaped the run's output
+#[derive(Debug, Serialize)]
+pub struct RunFilters {
+    pub db: bool,
+    pub files: bool,
+    pub device_export: bool,
+    pub crlf: bool,
+    /// csv/txt/json were written while parsing (`--stream`) and aren't in `sinks`
+    pub stream: bool,
+    /// Only devices new to the database were collected (`--only-new`)
+    pub only_new: bool,
+    pub sinks: Vec<String>,
+    pub template: Option<PathBuf>,
+}
+
+/// Size and checksum of one output file
+#[derive(Debug, Serialize)]
+pub struct OutputChecksum {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// Reproducibility record written to manifest.json after each run
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub tool_version: &'static str,
+    pub started_at: String,
+    pub finished_at: String,
+    pub base_url: String,
+    pub account: String,
+    pub org_ids: Vec<i64>,
+    pub commands: Vec<String>,
+    pub filters: RunFilters,
+    pub devices: usize,
+    pub aps: usize,
+    /// The run was stopped early and only saved what it had collected
+    pub partial: bool,
+    /// Why it stopped: `interrupted` (Ctrl-C) or `timed_out` (`--max-duration`)
+    pub stop_reason: Option<StopReason>,
+    pub stages: Vec<StageTiming>,
+    pub throughput: Throughput,
+    pub outputs: Vec<OutputChecksum>,
+}
+
+/// Distinct organization IDs in the device inventory
+pub fn org_ids(devices: &[serde_json::Value]) -> Vec<i64> {
+    let mut ids: Vec<i64> = devices
+        .iter()
+        .filter_map(|d| d.get("org_id").and_then(|v| v.as_i64()))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Checksums of the files in `paths` that exist and were written at or after
+/// `since`, so files left over from earlier runs aren't attributed to this one
+pub fn checksum_outputs(paths: &[PathBuf], since: std::time::SystemTime) -> Result<Vec<OutputChecksum>> {
+    let mut outputs = Vec::new();
+    for path in paths {
+        let Ok(metadata) = fs::metadata(path) else {
+            continue;
+        };
+        if metadata.modified().map(|m| m < since).unwrap_or(true) {
+            continue;
+        }
+
+        let data = fs::read(path).context(format!("Failed to read {} for checksum", path.display()))?;
+        outputs.push(OutputChecksum {
+            path: path.clone(),
+            bytes: data.len() as u64,
+            sha256: sha256_hex(&data),
+        });
+    }
+
+    Ok(outputs)
+}
+
+pub fn write_manifest(manifest: &Manifest, path: &Path, line_ending: LineEnding) -> Result<()> {
+    let json = serde_json::to_string_pretty(manifest).context("Failed to serialize manifest")?;
+
+    let mut file = create_output_file(path, line_ending)?;
+    writeln!(file, "{}", json)
+        .and_then(|_| file.flush())
+        .context(format!("Failed to write {}", path.display()))?;
+
+    println!("Run manifest saved to {}", path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_outputs() {
+        let dir = std::env::temp_dir().join(format!("xiq-manifest-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let since = std::time::SystemTime::now() - std::time::Duration::from_secs(2);
+        let file = dir.join("wifi-bssids.csv");
+        fs::write(&file, "abc").unwrap();
+
+        let outputs = checksum_outputs(&[file, dir.join("missing.txt")], since).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].bytes, 3);
+        assert_eq!(outputs[0].sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_throughput() {
+        let throughput = Throughput::new(200, 1000, 4000);
+        assert_eq!(throughput, Throughput { devices_per_sec: 50.0, bssids_per_sec: 250.0 });
+        assert!(Throughput::new(1, 1, 0).devices_per_sec.is_finite());
+    }
+
+    #[test]
+    fn test_org_ids() {
+        let devices = vec![
+            serde_json::json!({ "org_id": 2 }),
+            serde_json::json!({ "org_id": 1 }),
+            serde_json::json!({ "org_id": 2 }),
+            serde_json::json!({}),
+        ];
+        assert_eq!(org_ids(&devices), vec![1, 2]);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::csv_output::{write_csv, CsvProfile};
+use crate::oui::oui;
+use crate::output::{create_output_file, LineEnding};
+use crate::parser::{normalize_mac, table_cells, table_columns};
+use crate::report::DeviceInterfaces;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+/// Hive membership command
+pub const DEFAULT_HIVE_COMMAND: &str = "show hive";
+
+/// AMRP neighbor table command
+pub const DEFAULT_NEIGHBOR_COMMAND: &str = "show amrp neighbor";
+
+/// Graphviz export of the mesh
+pub const MESH_DOT_FILE: &str = "mesh-topology.dot";
+
+/// CSV export of the mesh links
+pub const MESH_CSV_FILE: &str = "mesh-links.csv";
+
+/// One row of an AMRP neighbor table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AmrpNeighbor {
+    pub mac: String,
+    pub interface: String,
+    pub hostname: String,
+    pub state: String,
+    pub metric: Option<i64>,
+    pub rssi: Option<i64>,
+}
+
+/// Hive and AMRP neighbors reported by one AP
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshDevice {
+    pub device_id: i64,
+    pub hostname: String,
+    pub hive: Option<String>,
+    pub neighbors: Vec<AmrpNeighbor>,
+}
+
+/// Mesh relationship between an AP and an AMRP neighbor
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MeshLink {
+    pub device_id: i64,
+    pub hostname: String,
+    pub hive: String,
+    pub interface: String,
+    pub neighbor_mac: String,
+    /// Set when the neighbor MAC belongs to a known device
+    pub neighbor_device_id: Option<i64>,
+    /// Known device hostname, else the one AMRP reported, else empty
+    pub neighbor_hostname: String,
+    pub state: String,
+    pub metric: Option<i64>,
+    pub rssi: Option<i64>,
+}
+
+impl MeshLink {
+    /// Graph node name of the neighbor
+    fn neighbor_node(&self) -> &str {
+        if self.neighbor_hostname.is_empty() {
+            &self.neighbor_mac
+        } else {
+            &self.neighbor_hostname
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].parse().ok()
+}
+
+/// Hive name from `show hive` output, as `Hive name=...` / `Hive: ...` or
+/// the first row of a table with a `Hive` or `Name` column
+pub fn parse_hive_name(output: &str) -> Option<String> {
+    let fields = output
+        .lines()
+        .flat_map(|line| line.split(';'))
+        .filter_map(|field| field.split_once('=').or_else(|| field.split_once(':')));
+    for (key, value) in fields {
+        if matches!(key.trim().to_lowercase().as_str(), "hive" | "hive name" | "hive id") && !value.trim().is_empty() {
+            return Some(value.trim().to_string());
+        }
+    }
+
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.by_ref().find(|line| {
+        table_columns(line)
+            .iter()
+            .any(|(_, h)| matches!(h.to_lowercase().as_str(), "hive" | "hive name" | "name"))
+    })?;
+    let columns = table_columns(header);
+    let index = columns
+        .iter()
+        .position(|(_, h)| matches!(h.to_lowercase().as_str(), "hive" | "hive name" | "name"))?;
+    let row = lines.find(|line| !line.trim_start().starts_with('-'))?;
+    table_cells(row, &columns).get(index).filter(|v| !v.is_empty()).map(|v| v.to_string())
+}
+
+/// Neighbors from an AMRP neighbor table. Columns are found by header name
+/// (`Neighbor ID`/`MAC`, `Interface`, `Hostname`, `State`, `Metric`/`Cost`,
+/// `RSSI`); rows without a valid neighbor MAC are skipped.
+pub fn parse_amrp_neighbors(output: &str) -> Vec<AmrpNeighbor> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Field {
+        Mac,
+        Interface,
+        Hostname,
+        State,
+        Metric,
+        Rssi,
+        Other,
+    }
+
+    fn field(header: &str) -> Field {
+        let header = header.to_lowercase();
+        if header.contains("neighbor") || header.contains("mac") {
+            Field::Mac
+        } else if header.starts_with("interface") || header == "ifname" {
+            Field::Interface
+        } else if header.contains("host") {
+            Field::Hostname
+        } else if header.contains("state") {
+            Field::State
+        } else if header.contains("metric") || header.contains("cost") {
+            Field::Metric
+        } else if header.contains("rssi") {
+            Field::Rssi
+        } else {
+            Field::Other
+        }
+    }
+
+    let mut lines = output.lines();
+    let Some(header) = lines
+        .by_ref()
+        .find(|line| table_columns(line).iter().any(|(_, h)| field(h) == Field::Mac))
+    else {
+        return Vec::new();
+    };
+    let columns = table_columns(header);
+    let fields: Vec<Field> = columns.iter().map(|(_, h)| field(h)).collect();
+
+    let mut neighbors = Vec::new();
+    for line in lines {
+        let mut neighbor = AmrpNeighbor::default();
+        for (field, value) in fields.iter().zip(table_cells(line, &columns)) {
+            match field {
+                Field::Mac if neighbor.mac.is_empty() => neighbor.mac = normalize_mac(value),
+                Field::Interface => neighbor.interface = value.to_string(),
+                Field::Hostname => neighbor.hostname = value.to_string(),
+                Field::State => neighbor.state = value.to_string(),
+                Field::Metric => neighbor.metric = parse_number(value),
+                Field::Rssi => neighbor.rssi = parse_number(value),
+                _ => {}
+            }
+        }
+        if oui(&neighbor.mac).is_some() {
+            neighbors.push(neighbor);
+        }
+    }
+
+    neighbors
+}
+
+/// Device ID and hostname by MAC: each device's own MAC plus every
+/// interface MAC collected from it
+pub fn mac_index(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> HashMap<String, (i64, String)> {
+    let mut index = HashMap::new();
+    for device in devices {
+        let (Some(id), Some(mac)) = (
+            device.get("id").and_then(|v| v.as_i64()),
+            device.get("mac_address").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let hostname = device.get("hostname").and_then(|v| v.as_str()).unwrap_or("unknown");
+        index.insert(normalize_mac(mac), (id, hostname.to_string()));
+    }
+    for result in results {
+        for iface in &result.interfaces {
+            index
+                .entry(iface.mac.clone())
+                .or_insert_with(|| (result.device_id, result.hostname.clone()));
+        }
+    }
+    index
+}
+
+/// Links from every AP to its AMRP neighbors, with neighbors resolved to
+/// known devices where their MAC is recognized
+pub fn mesh_links(mesh: &[MeshDevice], macs: &HashMap<String, (i64, String)>) -> Vec<MeshLink> {
+    mesh.iter()
+        .flat_map(|device| {
+            device.neighbors.iter().map(move |neighbor| {
+                let known = macs.get(&neighbor.mac);
+                MeshLink {
+                    device_id: device.device_id,
+                    hostname: device.hostname.clone(),
+                    hive: device.hive.clone().unwrap_or_default(),
+                    interface: neighbor.interface.clone(),
+                    neighbor_mac: neighbor.mac.clone(),
+                    neighbor_device_id: known.map(|(id, _)| *id),
+                    neighbor_hostname: known.map_or_else(|| neighbor.hostname.clone(), |(_, h)| h.clone()),
+                    state: neighbor.state.clone(),
+                    metric: neighbor.metric,
+                    rssi: neighbor.rssi,
+                }
+            })
+        })
+        .collect()
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Graphviz graph with one cluster per hive and one edge per neighbor pair
+pub fn topology_dot(mesh: &[MeshDevice], links: &[MeshLink]) -> String {
+    let mut hives: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for device in mesh {
+        hives
+            .entry(device.hive.as_deref().unwrap_or(""))
+            .or_default()
+            .insert(&device.hostname);
+    }
+
+    let mut dot = String::from("graph mesh {\n");
+    for (hive, members) in &hives {
+        let indent = if hive.is_empty() {
+            "  "
+        } else {
+            dot.push_str(&format!("  subgraph {} {{\n    label={};\n", quote(&format!("cluster_{}", hive)), quote(hive)));
+            "    "
+        };
+        for member in members {
+            dot.push_str(&format!("{}{};\n", indent, quote(member)));
+        }
+        if !hive.is_empty() {
+            dot.push_str("  }\n");
+        }
+    }
+
+    let mut seen = BTreeSet::new();
+    for link in links {
+        let (a, b) = (link.hostname.as_str(), link.neighbor_node());
+        if !seen.insert(if a <= b { (a, b) } else { (b, a) }) {
+            continue;
+        }
+        let mut label = link.interface.clone();
+        if let Some(metric) = link.metric {
+            label = format!("{} metric {}", label, metric).trim().to_string();
+        }
+        dot.push_str(&format!("  {} -- {} [label={}];\n", quote(a), quote(b), quote(&label)));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Write the Graphviz and CSV mesh exports
+pub fn write_mesh_exports(
+    mesh: &[MeshDevice],
+    links: &[MeshLink],
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let mut file = create_output_file(Path::new(MESH_DOT_FILE), line_ending)?;
+    file.write_all(topology_dot(mesh, links).as_bytes())
+        .and_then(|_| file.flush())
+        .with_context(|| format!("Failed to write {}", MESH_DOT_FILE))?;
+
+    let show = |value: Option<i64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let rows: Vec<Vec<String>> = links
+        .iter()
+        .map(|link| {
+            vec![
+                link.hostname.clone(),
+                link.hive.clone(),
+                link.interface.clone(),
+                link.neighbor_mac.clone(),
+                link.neighbor_hostname.clone(),
+                link.state.clone(),
+                show(link.metric),
+                show(link.rssi),
+            ]
+        })
+        .collect();
+    write_csv(
+        Path::new(MESH_CSV_FILE),
+        &["Device", "Hive", "Interface", "NeighborMAC", "Neighbor", "State", "Metric", "RSSI"],
+        &rows,
+        line_ending,
+        profile,
+    )?;
+
+    println!("Mesh topology saved to {} and {} ({} links)", MESH_DOT_FILE, MESH_CSV_FILE, links.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hive_and_neighbors() {
+        assert_eq!(parse_hive_name("Hive name=MainHive; Native VLAN=1;").as_deref(), Some("MainHive"));
+        let table = "\
+Hive        Native-VLAN  Password
+----------  -----------  --------
+Lab Hive    1            ***
+";
+        assert_eq!(parse_hive_name(table).as_deref(), Some("Lab Hive"));
+        assert_eq!(parse_hive_name("ERROR"), None);
+
+        let output = "\
+Neighbor ID     Interface  Hostname     State    Metric  RSSI(dBm)
+--------------  ---------  -----------  -------  ------  ---------
+0019:7700:0002  wifi1.1    AP-2         TWO-WAY  66      -55
+0019:7700:0099  wifi1.1                 INIT     -       -90
+Total neighbors: 2
+";
+        let neighbors = parse_amrp_neighbors(output);
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].mac, "00:19:77:00:00:02");
+        assert_eq!(neighbors[0].hostname, "AP-2");
+        assert_eq!((neighbors[0].metric, neighbors[0].rssi), (Some(66), Some(-55)));
+        assert_eq!((neighbors[1].metric, neighbors[1].rssi), (None, Some(-90)));
+    }
+
+    #[test]
+    fn test_mesh_links_and_dot() {
+        let neighbor = |mac: &str| AmrpNeighbor {
+            mac: mac.to_string(),
+            interface: "wifi1.1".to_string(),
+            metric: Some(66),
+            ..Default::default()
+        };
+        let mesh = vec![
+            MeshDevice {
+                device_id: 1,
+                hostname: "AP-1".into(),
+                hive: Some("MainHive".into()),
+                neighbors: vec![neighbor("00:19:77:00:00:02"), neighbor("00:19:77:00:00:99")],
+            },
+            MeshDevice {
+                device_id: 2,
+                hostname: "AP-2".into(),
+                hive: Some("MainHive".into()),
+                neighbors: vec![neighbor("00:19:77:00:00:01")],
+            },
+        ];
+        let devices = [
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "mac_address": "001977000001" }),
+            serde_json::json!({ "id": 2, "hostname": "AP-2", "mac_address": "001977000002" }),
+        ];
+
+        let links = mesh_links(&mesh, &mac_index(&devices, &[]));
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].neighbor_device_id, Some(2));
+        assert_eq!(links[0].neighbor_hostname, "AP-2");
+        assert_eq!(links[1].neighbor_device_id, None);
+
+        let dot = topology_dot(&mesh, &links);
+        assert!(dot.contains("subgraph \"cluster_MainHive\""));
+        assert_eq!(dot.matches("\"AP-1\" -- \"AP-2\"").count(), 1);
+        assert!(!dot.contains("\"AP-2\" -- \"AP-1\""));
+        assert!(dot.contains("\"AP-1\" -- \"00:19:77:00:00:99\" [label=\"wifi1.1 metric 66\"]"));
+    }
+}
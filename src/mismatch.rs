@@ -0,0 +1,62 @@
+use crate::db::MismatchedDevice;
+use crate::locale::Locale;
+
+/// Compact age like `3d 4h`, `2h 15m` or `40s`
+pub fn format_age(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+pub fn print_mismatch_report(devices: &[MismatchedDevice], locale: &Locale) {
+    println!("\n=== Config Mismatch ===");
+    if devices.is_empty() {
+        println!("No devices with config mismatch");
+        println!("=======================");
+        return;
+    }
+
+    println!(
+        "{:<30} {:>12}  {:<20} {:>10} {:>5}",
+        "Device",
+        "ID",
+        format!("Mismatched Since ({})", locale.zone()),
+        "For",
+        "Runs"
+    );
+    for device in devices {
+        println!(
+            "{:<30} {:>12}  {:<20} {:>10} {:>5}",
+            device.hostname,
+            device.id,
+            device.since.as_deref().map_or("-".to_string(), |since| locale.timestamp(since)),
+            device.age_secs.map(format_age).unwrap_or_else(|| "-".to_string()),
+            locale.integer(device.runs)
+        );
+    }
+    println!("{} device(s) with config mismatch", locale.integer(devices.len() as i64));
+    println!("=======================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(40), "40s");
+        assert_eq!(format_age(125), "2m");
+        assert_eq!(format_age(2 * 3600 + 15 * 60), "2h 15m");
+        assert_eq!(format_age(3 * 86400 + 4 * 3600 + 59), "3d 4h");
+        assert_eq!(format_age(-5), "0s");
+    }
+}
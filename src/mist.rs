@@ -0,0 +1,101 @@
+use crate::location;
+use crate::report::DeviceInterfaces;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// AP list in the layout of the Mist inventory import
+pub const MIST_APS_FILE: &str = "mist-aps.csv";
+
+/// Fixed leading columns; one `wlan:<SSID>` column per WLAN follows
+const MIST_COLUMNS: [&str; 4] = ["name", "mac", "site", "model"];
+
+/// MAC as Mist writes it: lowercase hex without separators, e.g. `5c5b35000001`
+pub fn mist_mac(mac: &str) -> String {
+    mac.chars().filter(|c| c.is_ascii_hexdigit()).collect::<String>().to_lowercase()
+}
+
+/// Header and one row per collected AP: its name, base MAC, site and model,
+/// then the BSSIDs of each WLAN it broadcasts (space-separated, in radio order)
+pub fn mist_rows(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> (Vec<String>, Vec<Vec<String>>) {
+    let wlans: BTreeSet<&str> = results
+        .iter()
+        .flat_map(|r| &r.interfaces)
+        .filter(|i| i.is_access() && !i.ssid.is_empty())
+        .map(|i| i.ssid.as_str())
+        .collect();
+
+    let mut header: Vec<String> = MIST_COLUMNS.iter().map(|c| c.to_string()).collect();
+    header.extend(wlans.iter().map(|ssid| format!("wlan:{}", ssid)));
+
+    let mut rows = Vec::new();
+    for result in results {
+        let device = devices
+            .iter()
+            .find(|d| d.get("id").and_then(|id| id.as_i64()) == Some(result.device_id));
+        let field = |name: &str| {
+            device
+                .and_then(|d| d.get(name))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let mut bssids: BTreeMap<&str, Vec<(&str, String)>> = BTreeMap::new();
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            bssids.entry(iface.ssid.as_str()).or_default().push((iface.radio.as_str(), mist_mac(&iface.mac)));
+        }
+
+        let mut row = vec![
+            result.hostname.clone(),
+            mist_mac(&field("mac_address")),
+            device.map(location::device_site).unwrap_or_else(|| location::UNASSIGNED.to_string()),
+            field("product_type"),
+        ];
+        for ssid in &wlans {
+            let mut macs = bssids.remove(ssid).unwrap_or_default();
+            macs.sort();
+            row.push(macs.into_iter().map(|(_, mac)| mac).collect::<Vec<_>>().join(" "));
+        }
+        rows.push(row);
+    }
+
+    (header, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_mist_rows() {
+        let access = |radio: &str, mac: &str, ssid: &str| InterfaceEntry {
+            mode: "access".into(),
+            radio: radio.into(),
+            mac: mac.into(),
+            ssid: ssid.into(),
+            ..Default::default()
+        };
+        let devices = vec![serde_json::json!({
+            "id": 1, "mac_address": "00:11:22:33:44:00", "product_type": "AP_305C",
+            "locations": [{ "name": "Global" }, { "name": "HQ" }]
+        })];
+        let results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                access("wifi1", "00:11:22:33:44:61", "Corp"),
+                access("wifi0", "00:11:22:33:44:51", "Corp"),
+                access("wifi0", "00:11:22:33:44:52", "Guest"),
+            ],
+        }];
+
+        let (header, rows) = mist_rows(&devices, &results);
+        assert_eq!(header, vec!["name", "mac", "site", "model", "wlan:Corp", "wlan:Guest"]);
+        assert_eq!(rows[0][1], "001122334400");
+        assert_eq!(rows[0][2], "HQ");
+        assert_eq!(rows[0][3], "AP_305C");
+        assert_eq!(rows[0][4], "001122334451 001122334461");
+        assert_eq!(rows[0][5], "001122334452");
+    }
+}
@@ -0,0 +1,89 @@
+use crate::config::NotifyConfig;
+use anyhow::{Context, Result};
+
+/// Why a run's BSSID count looks wrong, if it does.
+///
+/// `min` is an absolute floor; `max_drop_pct` is the largest allowed drop
+/// relative to the previous run's count.
+pub fn bssid_count_violation(
+    count: usize,
+    previous: Option<i64>,
+    min: Option<usize>,
+    max_drop_pct: Option<f64>,
+) -> Option<String> {
+    if let Some(min) = min {
+        if count < min {
+            return Some(format!("Collected {} BSSIDs, below the minimum of {}", count, min));
+        }
+    }
+
+    if let (Some(max_drop), Some(previous)) = (max_drop_pct, previous) {
+        if previous > 0 {
+            let drop = (previous as f64 - count as f64) / previous as f64 * 100.0;
+            if drop > max_drop {
+                return Some(format!(
+                    "Collected {} BSSIDs, {:.1}% fewer than the previous run ({}); limit is {}%",
+                    count, drop, previous, max_drop
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+/// Post an event to every configured notification webhook.
+/// Failures are reported but don't stop the remaining notifications.
+pub async fn send_notifications(config: &NotifyConfig, event: &str, message: &str, details: serde_json::Value) {
+    if config.webhooks.is_empty() {
+        return;
+    }
+
+    let client = reqwest::Client::new();
+    let payload = serde_json::json!({
+        "event": event,
+        "message": message,
+        "details": details,
+    });
+
+    for webhook in &config.webhooks {
+        match post(&client, &webhook.url, webhook.token.as_deref(), &payload).await {
+            Ok(()) => println!("Notification sent to {}", webhook.url),
+            Err(e) => eprintln!("Warning: notification to {} failed: {:#}", webhook.url, e),
+        }
+    }
+}
+
+async fn post(client: &reqwest::Client, url: &str, token: Option<&str>, payload: &serde_json::Value) -> Result<()> {
+    let mut request = client.post(url).json(payload);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request.send().await.context("Failed to send notification")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Notification failed with status {}: {}", status, error_text);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bssid_count_violation() {
+        assert_eq!(bssid_count_violation(100, None, None, None), None);
+        assert!(bssid_count_violation(10, None, Some(50), None).is_some());
+        assert_eq!(bssid_count_violation(50, None, Some(50), None), None);
+
+        assert!(bssid_count_violation(70, Some(100), None, Some(20.0)).is_some());
+        assert_eq!(bssid_count_violation(85, Some(100), None, Some(20.0)), None);
+        // No previous run to compare against
+        assert_eq!(bssid_count_violation(0, None, None, Some(20.0)), None);
+    }
+}
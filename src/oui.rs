@@ -0,0 +1,228 @@
+use crate::parser::normalize_mac;
+use crate::report::DeviceInterfaces;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Vendors whose BSSIDs are expected when no allow list is configured
+pub const DEFAULT_ALLOWED_VENDORS: &[&str] = &["Extreme", "Aerohive"];
+
+/// Vendor reported for an OUI missing from the lookup table
+pub const UNKNOWN_VENDOR: &str = "(unknown)";
+
+/// Aerohive and Extreme Networks OUIs seen on APs. Point `vendors.oui_file`
+/// at the IEEE registry for a complete table.
+const BUILTIN_OUIS: &[(&str, &str)] = &[
+    ("00:19:77", "Aerohive Networks"),
+    ("08:EA:44", "Aerohive Networks"),
+    ("34:85:84", "Aerohive Networks"),
+    ("40:18:B1", "Aerohive Networks"),
+    ("88:5B:DD", "Aerohive Networks"),
+    ("9C:5D:12", "Aerohive Networks"),
+    ("C4:13:E2", "Aerohive Networks"),
+    ("D8:54:A2", "Aerohive Networks"),
+    ("E0:1C:41", "Aerohive Networks"),
+    ("F0:9C:E9", "Aerohive Networks"),
+    ("00:01:30", "Extreme Networks"),
+    ("00:04:96", "Extreme Networks"),
+    ("00:E0:2B", "Extreme Networks"),
+    ("5C:0E:8B", "Extreme Networks"),
+    ("74:67:F7", "Extreme Networks"),
+    ("B4:C7:99", "Extreme Networks"),
+    ("D8:84:66", "Extreme Networks"),
+];
+
+/// First three octets of a MAC address as `XX:XX:XX`, if it is a valid MAC
+pub fn oui(mac: &str) -> Option<String> {
+    let mac = normalize_mac(mac);
+    // Input without 12 hex digits comes back unchanged, so check the octets too
+    let valid = mac.len() == 17
+        && mac.split(':').all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()));
+    valid.then(|| mac[..8].to_string())
+}
+
+/// Whether the locally administered bit is set; such MACs carry no vendor OUI
+pub fn is_locally_administered(mac: &str) -> bool {
+    oui(mac)
+        .and_then(|oui| u8::from_str_radix(&oui[..2], 16).ok())
+        .is_some_and(|first| first & 0x02 != 0)
+}
+
+/// OUI to vendor name lookup table
+#[derive(Debug, Clone, Default)]
+pub struct OuiDatabase {
+    vendors: HashMap<String, String>,
+}
+
+impl OuiDatabase {
+    /// The built-in Aerohive/Extreme table
+    pub fn builtin() -> Self {
+        let vendors = BUILTIN_OUIS
+            .iter()
+            .map(|(oui, vendor)| (oui.to_string(), vendor.to_string()))
+            .collect();
+        Self { vendors }
+    }
+
+    /// The built-in table extended with an IEEE `oui.csv` registry file
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut db = Self::builtin();
+        if let Some(path) = path {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read OUI file {}", path.display()))?;
+            db.extend_from_csv(&content);
+        }
+        Ok(db)
+    }
+
+    /// Add the entries of an IEEE registry CSV
+    /// (`Registry,Assignment,Organization Name,Organization Address`)
+    pub fn extend_from_csv(&mut self, content: &str) {
+        for line in content.lines() {
+            let mut fields = line.splitn(3, ',');
+            let (Some(_registry), Some(assignment), Some(rest)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let Some(oui) = oui(&format!("{}000000", assignment.trim())) else {
+                continue;
+            };
+            let name = match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                None => rest.split(',').next().unwrap_or_default(),
+            };
+            self.vendors.insert(oui, name.trim().to_string());
+        }
+    }
+
+    /// Vendor that owns the MAC's OUI
+    pub fn vendor(&self, mac: &str) -> Option<&str> {
+        oui(mac).and_then(|oui| self.vendors.get(&oui)).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vendors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vendors.is_empty()
+    }
+}
+
+/// Access-mode BSSID whose OUI isn't from an allowed vendor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnexpectedVendor {
+    pub device_id: i64,
+    pub hostname: String,
+    pub mac: String,
+    pub ssid: String,
+    pub vendor: String,
+}
+
+/// Whether `vendor` (or the MAC's OUI) matches an allow list entry. Entries
+/// are vendor name substrings (case-insensitive) or OUIs like `00:19:77`.
+fn is_allowed(mac: &str, vendor: Option<&str>, allowed: &[String]) -> bool {
+    allowed.iter().any(|entry| {
+        let hex: String = entry.chars().filter(|c| !matches!(c, ':' | '-' | '.')).collect();
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return oui(mac).is_some_and(|oui| oui.replace(':', "").eq_ignore_ascii_case(&hex));
+        }
+        vendor.is_some_and(|v| v.to_lowercase().contains(&entry.to_lowercase()))
+    })
+}
+
+/// Access-mode BSSIDs from vendors outside `allowed`, such as a consumer AP
+/// broadcasting a corporate SSID. Locally administered MACs are skipped.
+pub fn unexpected_vendors(results: &[DeviceInterfaces], ouis: &OuiDatabase, allowed: &[String]) -> Vec<UnexpectedVendor> {
+    results
+        .iter()
+        .flat_map(|r| r.interfaces.iter().filter(|i| i.is_access()).map(move |i| (r, i)))
+        .filter(|(_, iface)| oui(&iface.mac).is_some() && !is_locally_administered(&iface.mac))
+        .filter_map(|(device, iface)| {
+            let vendor = ouis.vendor(&iface.mac);
+            if is_allowed(&iface.mac, vendor, allowed) {
+                return None;
+            }
+            Some(UnexpectedVendor {
+                device_id: device.device_id,
+                hostname: device.hostname.clone(),
+                mac: iface.mac.clone(),
+                ssid: iface.ssid.clone(),
+                vendor: vendor.unwrap_or(UNKNOWN_VENDOR).to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn print_unexpected_vendors(flagged: &[UnexpectedVendor]) {
+    if flagged.is_empty() {
+        return;
+    }
+
+    println!("\n=== BSSIDs From Unexpected Vendors ({}) ===", flagged.len());
+    println!("{:<24} {:<20} {:<24} SSID", "Device", "MAC", "Vendor");
+    for bssid in flagged {
+        println!("{:<24} {:<20} {:<24} {}", bssid.hostname, bssid.mac, bssid.vendor, bssid.ssid);
+    }
+    println!("===========================================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    fn access(mac: &str) -> InterfaceEntry {
+        InterfaceEntry {
+            mac: mac.to_string(),
+            mode: "access".to_string(),
+            ssid: "Corp".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_oui_lookup() {
+        assert_eq!(oui("0019.7712.3456").as_deref(), Some("00:19:77"));
+        assert_eq!(oui("not a mac"), None);
+        assert!(is_locally_administered("02:11:22:33:44:55"));
+        assert!(!is_locally_administered("00:19:77:33:44:55"));
+
+        let mut db = OuiDatabase::builtin();
+        assert_eq!(db.vendor("00:19:77:AA:BB:CC"), Some("Aerohive Networks"));
+        db.extend_from_csv(
+            "Registry,Assignment,Organization Name,Organization Address\n\
+             MA-L,F4F26D,\"TP-LINK TECHNOLOGIES CO.,LTD.\",\"Shenzhen\"\n\
+             MA-L,001B63,Apple Inc,1 Infinite Loop\n",
+        );
+        assert_eq!(db.vendor("f4:f2:6d:00:00:01"), Some("TP-LINK TECHNOLOGIES CO.,LTD."));
+        assert_eq!(db.vendor("00:1B:63:00:00:01"), Some("Apple Inc"));
+    }
+
+    #[test]
+    fn test_unexpected_vendors() {
+        let mut ouis = OuiDatabase::builtin();
+        ouis.extend_from_csv("MA-L,F4F26D,TP-LINK,Shenzhen\n");
+        let results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                access("00:19:77:00:00:01"),
+                access("F4:F2:6D:00:00:01"),
+                access("00:AA:BB:00:00:01"),
+                access("02:34:56:00:00:01"),
+            ],
+        }];
+
+        let allowed: Vec<String> = DEFAULT_ALLOWED_VENDORS.iter().map(|s| s.to_string()).collect();
+        let flagged = unexpected_vendors(&results, &ouis, &allowed);
+        assert_eq!(flagged.len(), 2);
+        assert_eq!(flagged[0].vendor, "TP-LINK");
+        assert_eq!(flagged[1].vendor, UNKNOWN_VENDOR);
+
+        let allowed = vec!["aerohive".to_string(), "tp-link".to_string(), "00:AA:BB".to_string()];
+        assert!(unexpected_vendors(&results, &ouis, &allowed).is_empty());
+    }
+}
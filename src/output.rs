@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Line ending used for text output files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    /// Windows-style line endings, so Notepad shows the fixed-width files correctly
+    Crlf,
+}
+
+/// Writer that translates `\n` into the configured line ending
+pub struct LineEndingWriter<W: Write> {
+    inner: W,
+    ending: LineEnding,
+    last_byte: Option<u8>,
+}
+
+impl<W: Write> LineEndingWriter<W> {
+    pub fn new(inner: W, ending: LineEnding) -> Self {
+        Self {
+            inner,
+            ending,
+            last_byte: None,
+        }
+    }
+}
+
+impl<W: Write> Write for LineEndingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.ending == LineEnding::Lf {
+            return self.inner.write(buf);
+        }
+
+        let mut translated = Vec::with_capacity(buf.len() + buf.len() / 16);
+        for &byte in buf {
+            // Don't double up line endings that are already CRLF
+            if byte == b'\n' && self.last_byte != Some(b'\r') {
+                translated.push(b'\r');
+            }
+            translated.push(byte);
+            self.last_byte = Some(byte);
+        }
+
+        self.inner.write_all(&translated)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Buffered output file with line ending translation
+pub type OutputFile = LineEndingWriter<BufWriter<File>>;
+
+/// Create an output file, creating missing parent directories first
+pub fn create_output_file(path: &Path, ending: LineEnding) -> Result<OutputFile> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .context(format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let file = File::create(path)
+        .context(format!("Failed to create {}", path.display()))?;
+
+    Ok(LineEndingWriter::new(BufWriter::new(file), ending))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("xiq-output-{}-test-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_crlf_translation() {
+        let mut writer = LineEndingWriter::new(Vec::new(), LineEnding::Crlf);
+        write!(writer, "a\nb\r\nc").unwrap();
+        writeln!(writer).unwrap();
+        assert_eq!(writer.inner, b"a\r\nb\r\nc\r\n");
+
+        let mut writer = LineEndingWriter::new(Vec::new(), LineEnding::Lf);
+        writeln!(writer, "a").unwrap();
+        assert_eq!(writer.inner, b"a\n");
+    }
+
+    #[test]
+    fn test_create_output_file_in_nested_dir() {
+        let path = temp_dir("nested").join("sub dir").join("wifi-bssids.txt");
+
+        let mut file = create_output_file(&path, LineEnding::Crlf).unwrap();
+        writeln!(file, "Device").unwrap();
+        file.flush().unwrap();
+        drop(file);
+
+        assert_eq!(fs::read(&path).unwrap(), b"Device\r\n");
+        fs::remove_dir_all(temp_dir("nested")).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_create_output_file_with_backslash_paths() {
+        let dir = temp_dir("backslash");
+        let write = |path: &Path| {
+            let mut file = create_output_file(path, LineEnding::Crlf).unwrap();
+            writeln!(file, "Device").unwrap();
+            file.flush().unwrap();
+        };
+
+        // Typed as users do on the command line, with backslashes throughout
+        let typed = format!(r"{}\sub dir\wifi-bssids.txt", dir.display());
+        write(Path::new(&typed));
+        assert_eq!(fs::read(dir.join("sub dir").join("wifi-bssids.txt")).unwrap(), b"Device\r\n");
+
+        // Mixed separators resolve to the same directories
+        let mixed = format!(r"{}/sub dir\more/wifi-bssids.csv", dir.display());
+        write(Path::new(&mixed));
+        assert!(dir.join("sub dir").join("more").join("wifi-bssids.csv").is_file());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_create_output_file_with_drive_letter_paths() {
+        let dir = temp_dir("drive");
+        fs::create_dir_all(&dir).unwrap();
+
+        // canonicalize() gives the verbatim form, e.g. \\?\C:\Users\...\Temp
+        let verbatim = fs::canonicalize(&dir).unwrap();
+        assert!(verbatim.to_string_lossy().starts_with(r"\\?\"));
+        let path = verbatim.join("out").join("wifi-bssids.txt");
+        let mut file = create_output_file(&path, LineEnding::Lf).unwrap();
+        writeln!(file, "Device").unwrap();
+        file.flush().unwrap();
+        drop(file);
+        assert_eq!(fs::read(dir.join("out").join("wifi-bssids.txt")).unwrap(), b"Device\n");
+
+        // The plain drive-letter form, e.g. C:\Users\...\Temp
+        assert_eq!(dir.to_string_lossy().chars().nth(1), Some(':'));
+        create_output_file(Path::new(&format!(r"{}\wifi-bssids.txt", dir.display())), LineEnding::Crlf).unwrap();
+        assert!(dir.join("wifi-bssids.txt").is_file());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,37 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// One row of the HiveOS interface table, built from the TextFSM template patterns:
+/// NAME: \S+
+/// MAC: [a-fA-F0-9:\.]+
+/// MODE: \S+
+/// STATE: \w+
+/// CHANNEL: \S+
+/// VLAN: \S+
+/// RADIO: \S+
+/// HIVE: \S+
+/// SSID: rest of the line, so multi-word SSIDs like "Acme Guest WiFi" survive
+static INTERFACE_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\S+)\s+([a-fA-F0-9:\.]+)\s+(\S+)\s+(\w+)\s+(\S+)\s+(\S+)\s+(\S+)\s+(\S+)\s+(\S.*?)\s*$")
+        .expect("Failed to compile interface regex")
+});
+
+/// HiveOS SSID interface (`wifi1.2`) and its radio (`wifi1`)
+static WIFI_INTERFACE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b((wifi\d+)\.\d+)\b").expect("Failed to compile interface name regex"));
+
+/// `key: value` pairs on a BSSID line; `ssid` doesn't match inside `BSSID`
+static LINE_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b(ssid|channel|chan|radio|interface|ifname)\s*[:=]\s*("[^"]*"|[^\s,;]+)"#)
+        .expect("Failed to compile BSSID line field regex")
+});
+
+/// Colon-separated MAC address anywhere on a line
+static COLON_MAC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"([0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2})")
+        .expect("Failed to compile MAC regex")
+});
 
 /// Normalize a MAC address to colon-separated format (xx:xx:xx:xx:xx:xx)
 /// Handles various input formats:
@@ -7,7 +39,7 @@ use serde::{Deserialize, Serialize};
 /// - 001122334455 (no separators)
 /// - 00-11-22-33-44-55 (dash separated)
 /// - 00:11:22:33:44:55 (already colon separated)
-fn normalize_mac(mac: &str) -> String {
+pub fn normalize_mac(mac: &str) -> String {
     // Remove all separators (colons, dots, dashes)
     let hex_only: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
 
@@ -29,9 +61,51 @@ fn normalize_mac(mac: &str) -> String {
     bytes.join(":").to_uppercase()
 }
 
+/// Column headers of a fixed-width table and their start positions. Headers
+/// are separated by two or more spaces, so `Access Security` stays one header.
+pub fn table_columns(header: &str) -> Vec<(usize, String)> {
+    let mut columns = Vec::new();
+    let mut start = None;
+    let mut spaces = 0;
+
+    for (i, c) in header.char_indices() {
+        if c == ' ' {
+            spaces += 1;
+            continue;
+        }
+        match start {
+            None => start = Some(i),
+            Some(s) if spaces >= 2 => {
+                columns.push((s, header[s..i].trim_end().to_string()));
+                start = Some(i);
+            }
+            _ => {}
+        }
+        spaces = 0;
+    }
+    if let Some(s) = start {
+        columns.push((s, header[s..].trim_end().to_string()));
+    }
+    columns
+}
+
+/// Cells of a fixed-width table row, cut at the header column positions so
+/// values containing spaces are kept whole
+pub fn table_cells<'a>(line: &'a str, columns: &[(usize, String)]) -> Vec<&'a str> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(index, (start, _))| {
+            let end = columns.get(index + 1).map_or(line.len(), |(next, _)| *next).min(line.len());
+            line.get((*start).min(end)..end).unwrap_or_default().trim()
+        })
+        .collect()
+}
+
 /// Represents a parsed interface entry from CLI output
 /// Equivalent to the TextFSM template fields in hiveos.template
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InterfaceEntry {
     pub name: String,
     pub mac: String,
@@ -42,31 +116,57 @@ pub struct InterfaceEntry {
     pub radio: String,
     pub hive: String,
     pub ssid: String,
+    /// Radio transmit power in dBm, from the radio detail commands
+    pub tx_power: String,
+    /// Radio PHY mode (a/b/g/n/ac/ax/be), from the radio detail commands
+    pub phy_mode: String,
+    /// Radio band from the PHY mode of the radio detail commands (`11ax-6g`)
+    pub band: Option<crate::rf::Band>,
+    /// SSID security mode (Open/PSK/SAE/802.1X/OWE), from `show ssid`
+    pub security: String,
+    /// SSID cipher, from `show ssid`
+    pub encryption: String,
+    /// Whether the SSID is broadcast (`yes`/`no`), from `show ssid`
+    pub broadcast: String,
+    /// The device's local notes from the `annotations` table
+    pub notes: String,
+}
+
+impl InterfaceEntry {
+    /// Access-mode interfaces are the BSSIDs clients connect to
+    pub fn is_access(&self) -> bool {
+        self.mode.to_lowercase() == "access"
+    }
+
+    /// Fill the fields this entry is missing (empty or `-`) from another
+    /// entry for the same MAC, keeping every value it already has
+    pub fn fill_gaps(&mut self, other: &InterfaceEntry) {
+        for (field, value) in [
+            (&mut self.name, &other.name),
+            (&mut self.mode, &other.mode),
+            (&mut self.state, &other.state),
+            (&mut self.channel, &other.channel),
+            (&mut self.vlan, &other.vlan),
+            (&mut self.radio, &other.radio),
+            (&mut self.hive, &other.hive),
+            (&mut self.ssid, &other.ssid),
+        ] {
+            if (field.is_empty() || field == "-") && !value.is_empty() {
+                field.clone_from(value);
+            }
+        }
+    }
 }
 
 /// Parser for HiveOS-style interface output
 /// Replaces the TextFSM Python template with native Rust parsing
 pub struct InterfaceParser {
-    line_regex: Regex,
+    line_regex: &'static Regex,
 }
 
 impl InterfaceParser {
     pub fn new() -> Self {
-        // Build regex from the TextFSM template patterns:
-        // NAME: \S+
-        // MAC: [a-fA-F0-9:\.]+
-        // MODE: \S+
-        // STATE: \w+
-        // CHANNEL: \S+
-        // VLAN: \S+
-        // RADIO: \S+
-        // HIVE: \S+
-        // SSID: \S+
-        let line_regex = Regex::new(
-            r"^(\S+)\s+([a-fA-F0-9:\.]+)\s+(\S+)\s+(\w+)\s+(\S+)\s+(\S+)\s+(\S+)\s+(\S+)\s+(\S+)\s*$"
-        ).expect("Failed to compile interface regex");
-
-        Self { line_regex }
+        Self { line_regex: &INTERFACE_LINE }
     }
 
     /// Parse CLI output and extract interface entries
@@ -94,6 +194,7 @@ impl InterfaceParser {
                     radio: caps.get(7).map(|m| m.as_str().to_string()).unwrap_or_default(),
                     hive: caps.get(8).map(|m| m.as_str().to_string()).unwrap_or_default(),
                     ssid: caps.get(9).map(|m| m.as_str().to_string()).unwrap_or_default(),
+                    ..Default::default()
                 };
                 entries.push(entry);
             }
@@ -116,6 +217,7 @@ impl Default for InterfaceParser {
 }
 
 /// Extract BSSIDs from raw CLI output using multiple strategies
+#[allow(dead_code)]
 pub fn extract_bssids(output: &str) -> Vec<String> {
     // Use extract_interfaces and return just the MACs for backward compatibility
     extract_interfaces(output)
@@ -126,37 +228,45 @@ pub fn extract_bssids(output: &str) -> Vec<String> {
 
 /// Extract full interface entries from raw CLI output
 pub fn extract_interfaces(output: &str) -> Vec<InterfaceEntry> {
-    let mut entries = Vec::new();
-    let mac_regex = Regex::new(
-        r"([0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2}:[0-9a-fA-F]{2})"
-    ).expect("Failed to compile MAC regex");
-
     // Strategy 1: Try structured parsing with InterfaceParser
     let parser = InterfaceParser::new();
-    let parsed = parser.parse(output);
-    if !parsed.is_empty() {
-        entries.extend(parsed);
+    let mut entries = parser.parse(output);
+
+    // Strategy 2: BSSID-labeled MAC addresses fill the gaps of the table entry
+    // with the same MAC, and only become entries of their own when the table
+    // doesn't have them
+    for labeled in extract_bssid_lines(output) {
+        match entries.iter_mut().find(|e| e.mac == labeled.mac) {
+            Some(entry) => entry.fill_gaps(&labeled),
+            None => entries.push(labeled),
+        }
     }
 
-    // Strategy 2: Also extract any BSSID-labeled MAC addresses (as minimal entries)
+    entries
+}
+
+/// Extract MAC addresses from lines labeled "BSSID" as minimal interface entries,
+/// with the interface, radio, channel and SSID when the line names them
+pub fn extract_bssid_lines(output: &str) -> Vec<InterfaceEntry> {
+    let mut entries: Vec<InterfaceEntry> = Vec::new();
+
     for line in output.lines() {
-        let line_lower = line.to_lowercase();
-        if line_lower.contains("bssid") {
-            for cap in mac_regex.captures_iter(line) {
-                let mac = normalize_mac(&cap[1]);
-                if !entries.iter().any(|e| e.mac == mac) {
-                    entries.push(InterfaceEntry {
-                        name: String::new(),
-                        mac,
-                        mode: String::new(),
-                        state: String::new(),
-                        channel: String::new(),
-                        vlan: String::new(),
-                        radio: String::new(),
-                        hive: String::new(),
-                        ssid: String::new(),
-                    });
-                }
+        // Checked without lowercasing a copy of every line
+        if !line.as_bytes().windows(5).any(|w| w.eq_ignore_ascii_case(b"bssid")) {
+            continue;
+        }
+
+        let macs: Vec<String> = COLON_MAC.captures_iter(line).map(|cap| normalize_mac(&cap[1])).collect();
+        // Context can't be told apart when one line lists several BSSIDs
+        let context = match macs.len() {
+            1 => bssid_line_context(line),
+            _ => InterfaceEntry::default(),
+        };
+        for mac in macs {
+            let labeled = InterfaceEntry { mac, ..context.clone() };
+            match entries.iter_mut().find(|e| e.mac == labeled.mac) {
+                Some(entry) => entry.fill_gaps(&labeled),
+                None => entries.push(labeled),
             }
         }
     }
@@ -164,6 +274,26 @@ pub fn extract_interfaces(output: &str) -> Vec<InterfaceEntry> {
     entries
 }
 
+/// Interface, radio, channel and SSID named on a BSSID line, such as
+/// `wifi0.1 BSSID: 00:19:77:aa:bb:cc SSID: "Acme Guest" Channel: 36`
+fn bssid_line_context(line: &str) -> InterfaceEntry {
+    let mut context = InterfaceEntry::default();
+    if let Some(cap) = WIFI_INTERFACE.captures(line) {
+        context.name = cap[1].to_string();
+        context.radio = cap[2].to_string();
+    }
+    for cap in LINE_FIELD.captures_iter(line) {
+        let value = cap[2].trim_matches('"').to_string();
+        match cap[1].to_lowercase().as_str() {
+            "ssid" => context.ssid = value,
+            "channel" | "chan" => context.channel = value,
+            "radio" => context.radio = value,
+            _ => context.name = value,
+        }
+    }
+    context
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,6 +318,38 @@ wifi1    AA:BB:CC:DD:EE:FF  AP     up     36(80)      10    wifi1 hive2 Corp
         assert_eq!(entries[1].mac, "AA:BB:CC:DD:EE:FF");
     }
 
+    #[test]
+    fn test_parse_ssid_with_spaces() {
+        let output = "\
+Name     MAC addr           Mode   State  Chan(Width) VLAN  Radio Hive SSID
+wifi0.1  00:11:22:33:44:56  access up     11(20)      1     wifi0 hive1 Acme Guest WiFi   
+wifi0.2  00:11:22:33:44:57  access up     11(20)      1     wifi0 hive1 Corp  Two Spaces
+wifi1.1  00:11:22:33:44:58  access up     36(80)      1     wifi1 hive1 Corp
+";
+
+        let entries = InterfaceParser::new().parse(output);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].ssid, "Acme Guest WiFi");
+        assert_eq!(entries[0].hive, "hive1");
+        assert_eq!(entries[1].ssid, "Corp  Two Spaces");
+        assert_eq!(entries[2].ssid, "Corp");
+        assert_eq!(entries[2].channel, "36(80)");
+    }
+
+    #[test]
+    fn test_table_columns() {
+        let header = "No.  Name              Access Security    Hide SSID";
+        let columns = table_columns(header);
+        let names: Vec<_> = columns.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(names, ["No.", "Name", "Access Security", "Hide SSID"]);
+        assert_eq!(
+            table_cells("2    Acme Guest WiFi   open               No", &columns),
+            ["2", "Acme Guest WiFi", "open", "No"]
+        );
+        assert_eq!(table_cells("1    Corp", &columns), ["1", "Corp", "", ""]);
+    }
+
     #[test]
     fn test_extract_bssids() {
         let output = "BSSID: 00:11:22:33:44:55\nSome other line\nbssid AA:BB:CC:DD:EE:FF";
@@ -196,4 +358,35 @@ wifi1    AA:BB:CC:DD:EE:FF  AP     up     36(80)      10    wifi1 hive2 Corp
         assert!(bssids.contains(&"00:11:22:33:44:55".to_string()));
         assert!(bssids.contains(&"AA:BB:CC:DD:EE:FF".to_string()));
     }
+
+    #[test]
+    fn test_merge_bssid_lines() {
+        let output = r#"
+Name     MAC addr           Mode   State  Chan(Width) VLAN  Radio Hive SSID
+wifi0.1  00:11:22:33:44:55  access up     11(20)      1     -     hive1 Corp
+wifi1.1  00:11:22:33:44:66  access up     36(80)      1     wifi1 hive1 Guest
+wifi0.1 BSSID: 00:11:22:33:44:55 SSID: "Not Corp" Channel: 6
+wifi0.2 BSSID: 00:11:22:33:44:77, SSID: IoT, Channel: 11
+BSSID 00:11:22:33:44:77
+Neighbor BSSIDs: 02:00:00:00:00:01 02:00:00:00:00:02 SSID: Rogue
+"#;
+        let entries = extract_interfaces(output);
+        let macs: Vec<&str> = entries.iter().map(|e| e.mac.as_str()).collect();
+        assert_eq!(
+            macs,
+            ["00:11:22:33:44:55", "00:11:22:33:44:66", "00:11:22:33:44:77", "02:00:00:00:00:01", "02:00:00:00:00:02"]
+        );
+
+        // The table entry keeps its values and only takes the missing radio
+        let corp = &entries[0];
+        assert_eq!((corp.ssid.as_str(), corp.channel.as_str(), corp.radio.as_str()), ("Corp", "11(20)", "wifi0"));
+
+        // Found only on BSSID lines, merged into one entry with the line's context
+        let iot = &entries[2];
+        assert_eq!(
+            (iot.name.as_str(), iot.radio.as_str(), iot.ssid.as_str(), iot.channel.as_str()),
+            ("wifi0.2", "wifi0", "IoT", "11")
+        );
+        assert!(entries[3].ssid.is_empty());
+    }
 }
@@ -0,0 +1,350 @@
+use crate::error::{self, Error};
+use crate::parser::{extract_bssid_lines, extract_interfaces, normalize_mac, InterfaceEntry};
+use anyhow::Result;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default time an external parser may run before it is killed
+const DEFAULT_EXTERNAL_TIMEOUT_SECS: u64 = 30;
+
+/// Parses the raw output of one CLI command into interface entries
+pub trait CliOutputParser: Send + Sync {
+    /// Registry name, as used in `[[parsers]]` config rules
+    fn name(&self) -> &str;
+
+    /// Failures reading the output are [`Error::Parse`]
+    fn parse(&self, output: &str) -> error::Result<Vec<InterfaceEntry>>;
+}
+
+/// HiveOS `show interface` table plus any BSSID-labeled lines
+pub struct HiveOsInterfaceParser;
+
+impl CliOutputParser for HiveOsInterfaceParser {
+    fn name(&self) -> &str {
+        "hiveos-interface"
+    }
+
+    fn parse(&self, output: &str) -> error::Result<Vec<InterfaceEntry>> {
+        Ok(extract_interfaces(output))
+    }
+}
+
+/// Only MAC addresses on lines labeled "BSSID", for outputs without a table
+pub struct BssidLineParser;
+
+impl CliOutputParser for BssidLineParser {
+    fn name(&self) -> &str {
+        "bssid-lines"
+    }
+
+    fn parse(&self, output: &str) -> error::Result<Vec<InterfaceEntry>> {
+        Ok(extract_bssid_lines(output))
+    }
+}
+
+/// External parser program registered in the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalParserConfig {
+    /// Registry name used by `[[parsers]]` rules
+    pub name: String,
+    /// Program and arguments
+    pub command: Vec<String>,
+    /// Seconds before the program is killed
+    pub timeout_secs: Option<u64>,
+}
+
+/// Runs an external program that receives raw CLI output on stdin and
+/// prints a JSON array of interface entries on stdout
+pub struct ExternalParser {
+    config: ExternalParserConfig,
+}
+
+impl ExternalParser {
+    pub fn new(config: ExternalParserConfig) -> Result<Self> {
+        if config.command.is_empty() {
+            anyhow::bail!("External parser '{}' has an empty command", config.name);
+        }
+        Ok(Self { config })
+    }
+}
+
+impl CliOutputParser for ExternalParser {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn parse(&self, output: &str) -> error::Result<Vec<InterfaceEntry>> {
+        let program = &self.config.command[0];
+        let mut child = Command::new(program)
+            .args(&self.config.command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::parse(format!("Failed to start external parser: {}", program), e))?;
+
+        // Feed stdin and drain stdout/stderr on separate threads so a parser
+        // with large input or output can't deadlock against the pipes
+        let missing = |pipe: &str| Error::invalid(format!("External parser has no {}", pipe));
+        let mut stdin = child.stdin.take().ok_or_else(|| missing("stdin"))?;
+        let mut stdout = child.stdout.take().ok_or_else(|| missing("stdout"))?;
+        let mut stderr = child.stderr.take().ok_or_else(|| missing("stderr"))?;
+        let input = output.to_string();
+        let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let stdout_reader = thread::spawn(move || {
+            let mut buf = Vec::new();
+            stdout.read_to_end(&mut buf).map(|_| buf)
+        });
+        let stderr_reader = thread::spawn(move || {
+            let mut buf = Vec::new();
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        });
+
+        let timeout = Duration::from_secs(self.config.timeout_secs.unwrap_or(DEFAULT_EXTERNAL_TIMEOUT_SECS));
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| Error::parse("Failed to wait for external parser", e))?
+            {
+                break status;
+            }
+            if started.elapsed() > timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Error::invalid(format!(
+                    "External parser '{}' timed out after {:?}",
+                    self.config.name, timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        // A parser may exit without reading all of stdin; that's not an error
+        let _ = writer.join();
+        let stdout = stdout_reader
+            .join()
+            .map_err(|_| Error::invalid("External parser stdout reader panicked"))?
+            .map_err(|e| Error::parse("Failed to read external parser output", e))?;
+        let stderr = stderr_reader
+            .join()
+            .map_err(|_| Error::invalid("External parser stderr reader panicked"))?
+            .unwrap_or_default();
+
+        if !status.success() {
+            return Err(Error::invalid(format!(
+                "External parser '{}' exited with {}: {}",
+                self.config.name,
+                status,
+                String::from_utf8_lossy(&stderr).trim()
+            )));
+        }
+
+        let mut entries: Vec<InterfaceEntry> = serde_json::from_slice(&stdout)
+            .map_err(|e| Error::parse(format!("External parser '{}' returned invalid JSON", self.config.name), e))?;
+        for entry in &mut entries {
+            entry.mac = normalize_mac(&entry.mac);
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Config rule selecting a parser for a command and device family
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParserRule {
+    /// Command prefix, matched case-insensitively (`show interface` also matches `show interface wifi0`)
+    pub command: String,
+    /// Device family (lowercase `device_function`, e.g. `ap` or `switch`); any family when omitted
+    pub family: Option<String>,
+    /// Name of a registered parser
+    pub parser: String,
+}
+
+struct Registration {
+    command: String,
+    family: Option<String>,
+    parser: Arc<dyn CliOutputParser>,
+}
+
+/// Parsers keyed by command prefix and device family
+pub struct ParserRegistry {
+    parsers: Vec<Arc<dyn CliOutputParser>>,
+    registrations: Vec<Registration>,
+}
+
+/// Device family used for parser selection
+pub fn device_family(device: &serde_json::Value) -> Option<String> {
+    device
+        .get("device_function")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_lowercase())
+}
+
+impl ParserRegistry {
+    /// Built-in parsers, with the HiveOS interface parser handling every command
+    pub fn with_defaults() -> Self {
+        let mut registry = Self {
+            parsers: Vec::new(),
+            registrations: Vec::new(),
+        };
+        registry.add_parser(Arc::new(HiveOsInterfaceParser));
+        registry.add_parser(Arc::new(BssidLineParser));
+        registry
+            .register("", None, "hiveos-interface")
+            .expect("built-in parser is registered");
+        registry
+    }
+
+    /// Built-in parsers plus the external parsers and rules from the config file
+    pub fn from_config(rules: &[ParserRule], external: &[ExternalParserConfig]) -> Result<Self> {
+        let mut registry = Self::with_defaults();
+        for config in external {
+            registry.add_parser(Arc::new(ExternalParser::new(config.clone())?));
+        }
+        for rule in rules {
+            registry.register(&rule.command, rule.family.as_deref(), &rule.parser)?;
+        }
+        Ok(registry)
+    }
+
+    /// Make a parser available by name
+    pub fn add_parser(&mut self, parser: Arc<dyn CliOutputParser>) {
+        self.parsers.retain(|p| p.name() != parser.name());
+        self.parsers.push(parser);
+    }
+
+    /// Route a command prefix (and optionally a device family) to a named parser.
+    /// Later registrations override earlier ones for the same key.
+    pub fn register(&mut self, command: &str, family: Option<&str>, parser: &str) -> Result<()> {
+        let parser = self
+            .parsers
+            .iter()
+            .find(|p| p.name() == parser)
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<_> = self.parsers.iter().map(|p| p.name().to_string()).collect();
+                anyhow::anyhow!("Unknown parser '{}' (available: {})", parser, names.join(", "))
+            })?;
+
+        let command = command.trim().to_lowercase();
+        let family = family.map(|f| f.to_lowercase());
+        self.registrations
+            .retain(|r| !(r.command == command && r.family == family));
+        self.registrations.push(Registration { command, family, parser });
+
+        Ok(())
+    }
+
+    /// Most specific parser for a command: family-specific rules beat generic
+    /// ones, then the longest matching command prefix wins
+    pub fn lookup(&self, command: &str, family: Option<&str>) -> Option<&dyn CliOutputParser> {
+        let command = command.trim().to_lowercase();
+        let family = family.map(|f| f.to_lowercase());
+
+        self.registrations
+            .iter()
+            .filter(|r| command.starts_with(&r.command))
+            .filter(|r| r.family.is_none() || r.family == family)
+            .max_by_key(|r| (r.family.is_some(), r.command.len()))
+            .map(|r| r.parser.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_lookup() {
+        let registry = ParserRegistry::with_defaults();
+        let parser = registry.lookup("show interface", Some("ap")).unwrap();
+        assert_eq!(parser.name(), "hiveos-interface");
+    }
+
+    #[test]
+    fn test_rules_override_by_specificity() {
+        let rules = vec![
+            ParserRule { command: "show station".into(), family: None, parser: "bssid-lines".into() },
+            ParserRule { command: "show interface".into(), family: Some("SWITCH".into()), parser: "bssid-lines".into() },
+        ];
+        let registry = ParserRegistry::from_config(&rules, &[]).unwrap();
+
+        assert_eq!(registry.lookup("show station detail", Some("ap")).unwrap().name(), "bssid-lines");
+        assert_eq!(registry.lookup("show interface", Some("switch")).unwrap().name(), "bssid-lines");
+        assert_eq!(registry.lookup("show interface", Some("ap")).unwrap().name(), "hiveos-interface");
+    }
+
+    #[test]
+    fn test_unknown_parser() {
+        let rules = vec![ParserRule { command: "show".into(), family: None, parser: "wing".into() }];
+        assert!(ParserRegistry::from_config(&rules, &[]).is_err());
+    }
+
+    #[cfg(unix)]
+    fn external(name: &str, script: &str) -> ExternalParserConfig {
+        ExternalParserConfig {
+            name: name.to_string(),
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            timeout_secs: Some(5),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_parser() {
+        let parser = ExternalParser::new(external(
+            "acme",
+            r#"cat > /dev/null; echo '[{"name": "eth0", "mac": "0011.2233.4455", "ssid": "Corp"}]'"#,
+        ))
+        .unwrap();
+
+        let entries = parser.parse("raw output").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].mac, "00:11:22:33:44:55");
+        assert_eq!(entries[0].ssid, "Corp");
+        assert_eq!(entries[0].vlan, "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_parser_large_output() {
+        // Well past a pipe buffer in both directions
+        let input = "x".repeat(1 << 20);
+        let parser = ExternalParser::new(external(
+            "large",
+            r#"i=0; printf '['; while [ $i -lt 5000 ]; do printf '{"name":"wifi0.%d","mac":"00:11:22:33:44:55"},' $i; i=$((i+1)); done; printf '{}]'; cat > /dev/null"#,
+        ))
+        .unwrap();
+
+        assert_eq!(parser.parse(&input).unwrap().len(), 5001);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_parser_failures() {
+        let failing = ExternalParser::new(external("fail", "echo oops >&2; exit 3")).unwrap();
+        assert!(failing.parse("x").is_err());
+
+        let garbage = ExternalParser::new(external("garbage", "echo not json")).unwrap();
+        assert!(matches!(garbage.parse("x"), Err(Error::Parse { source: Some(_), .. })));
+
+        let mut slow = external("slow", "sleep 5");
+        slow.timeout_secs = Some(0);
+        assert!(ExternalParser::new(slow).unwrap().parse("x").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_parser_registered_from_config() {
+        let rules = vec![ParserRule { command: "show acme".into(), family: None, parser: "acme".into() }];
+        let registry = ParserRegistry::from_config(&rules, &[external("acme", "echo []")]).unwrap();
+        assert_eq!(registry.lookup("show acme", None).unwrap().name(), "acme");
+    }
+}
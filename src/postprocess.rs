@@ -0,0 +1,65 @@
+use crate::config::PostprocessConfig;
+use crate::report::DeviceInterfaces;
+
+/// Apply the `[postprocess]` cleanups to parsed entries: drop interfaces on
+/// the listed VLANs, then rename SSIDs and hives. Renames are applied once,
+/// so `A = "B"` and `B = "C"` don't turn A into C. Returns the number of
+/// interfaces dropped.
+pub fn apply(results: &mut [DeviceInterfaces], config: &PostprocessConfig) -> usize {
+    if config.is_empty() {
+        return 0;
+    }
+
+    let mut dropped = 0;
+    for device in results.iter_mut() {
+        let before = device.interfaces.len();
+        device.interfaces.retain(|iface| !config.drop_vlans.contains(&iface.vlan));
+        dropped += before - device.interfaces.len();
+
+        for iface in device.interfaces.iter_mut() {
+            if let Some(ssid) = config.ssid_renames.get(&iface.ssid) {
+                iface.ssid.clone_from(ssid);
+            }
+            if let Some(hive) = config.hive_renames.get(&iface.hive) {
+                iface.hive.clone_from(hive);
+            }
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_apply() {
+        let iface = |ssid: &str, vlan: &str, hive: &str| InterfaceEntry {
+            ssid: ssid.to_string(),
+            vlan: vlan.to_string(),
+            hive: hive.to_string(),
+            mode: "access".to_string(),
+            ..Default::default()
+        };
+        let mut results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![iface("Corp-Old", "10", "hive0"), iface("Lab", "999", "hive0"), iface("Guest", "20", "hq")],
+        }];
+        let config = PostprocessConfig {
+            ssid_renames: [("Corp-Old", "Corp"), ("Corp", "Corp-New")]
+                .into_iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+            hive_renames: [("hive0".to_string(), "hq".to_string())].into_iter().collect(),
+            drop_vlans: vec!["999".to_string()],
+        };
+
+        assert_eq!(apply(&mut results, &config), 1);
+        let kept: Vec<_> = results[0].interfaces.iter().map(|i| (i.ssid.as_str(), i.hive.as_str())).collect();
+        assert_eq!(kept, vec![("Corp", "hq"), ("Guest", "hq")]);
+        assert_eq!(apply(&mut results, &PostprocessConfig::default()), 0);
+    }
+}
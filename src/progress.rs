@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Environment variable that turns on structured progress: `XIQ_PROGRESS=json`
+/// prints one JSON event per line on stderr, as `--progress json` does
+pub const PROGRESS_ENV: &str = "XIQ_PROGRESS";
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Structured progress of a collection run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    RunStarted { command: String },
+    /// One page of the device inventory was fetched
+    PageFetched {
+        page: i32,
+        devices: usize,
+        total_pages: Option<i32>,
+        /// Share of the pages fetched, when the page count is known
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        percent: Option<u8>,
+    },
+    /// A batch of devices returned its CLI output
+    ChunkCompleted { chunk: usize, chunks: usize, devices: usize, percent: u8 },
+    /// `parsed` of the `total` devices with CLI output have been parsed
+    DeviceParsed { device_id: i64, hostname: String, interfaces: usize, parsed: usize, total: usize, percent: u8 },
+    /// A stage of the run finished (`login`, `fetch_devices`, `cli_dispatch`, ...)
+    StageCompleted { stage: String, millis: u128 },
+    RunFinished { bssids: usize },
+    Error { message: String },
+    /// Plain output line from a run started by `serve`
+    Log { line: String },
+    /// A run started by `serve` exited
+    RunExited { code: Option<i32> },
+}
+
+/// Whether progress events are printed
+pub fn enabled() -> bool {
+    *ENABLED.get_or_init(|| std::env::var(PROGRESS_ENV).is_ok_and(|v| v == "json"))
+}
+
+/// Print progress events regardless of the environment (`--progress json`).
+/// Call before the first event is emitted.
+pub fn enable() {
+    let _ = ENABLED.set(true);
+}
+
+/// `done` of `total` as a whole percentage, capped at 100
+pub fn percent(done: usize, total: usize) -> u8 {
+    match total {
+        0 => 100,
+        total => (done.min(total) * 100 / total) as u8,
+    }
+}
+
+/// Print `event` as a JSON line on stderr, when progress is enabled
+pub fn emit(event: ProgressEvent) {
+    if enabled() {
+        if let Ok(line) = serde_json::to_string(&event) {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event = ProgressEvent::PageFetched { page: 2, devices: 100, total_pages: Some(3), percent: Some(percent(2, 3)) };
+        let line = serde_json::to_string(&event).unwrap();
+        assert_eq!(line, r#"{"event":"page_fetched","page":2,"devices":100,"total_pages":3,"percent":66}"#);
+        assert_eq!(serde_json::from_str::<ProgressEvent>(&line).unwrap(), event);
+        assert!(serde_json::from_str::<ProgressEvent>("Fetching page 1 with limit 100...").is_err());
+
+        let stage = ProgressEvent::StageCompleted { stage: "login".to_string(), millis: 850 };
+        assert_eq!(serde_json::to_string(&stage).unwrap(), r#"{"event":"stage_completed","stage":"login","millis":850}"#);
+        assert_eq!((percent(5, 0), percent(7, 5)), (100, 100));
+    }
+}
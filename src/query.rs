@@ -0,0 +1,59 @@
+use crate::db::{DeviceRow, JsonFilter};
+use anyhow::Result;
+
+/// `--where` condition: `<path>=<value>`, or just `<path>` for "has the
+/// field". Paths are JSON paths into `devices.extra`; `$.` may be left out.
+pub fn parse_json_filter(arg: &str) -> Result<JsonFilter> {
+    let (path, value) = match arg.split_once('=') {
+        Some((path, value)) => (path.trim(), Some(value.to_string())),
+        None => (arg.trim(), None),
+    };
+    if path.is_empty() || path == "$" {
+        anyhow::bail!("Invalid --where '{}': expected <path>[=<value>], e.g. radios[0].channel=36", arg);
+    }
+
+    let path = if path.starts_with('$') {
+        path.to_string()
+    } else if path.starts_with('[') {
+        format!("${}", path)
+    } else {
+        format!("$.{}", path)
+    };
+
+    Ok(JsonFilter { path, value })
+}
+
+pub fn print_devices(devices: &[DeviceRow]) {
+    if devices.is_empty() {
+        println!("No matching devices");
+        return;
+    }
+
+    println!("{:<8} {:<24} {:<10} {:<16} Extra", "ID", "Hostname", "Function", "IP");
+    for device in devices {
+        println!(
+            "{:<8} {:<24} {:<10} {:<16} {}",
+            device.id,
+            device.hostname.as_deref().unwrap_or("-"),
+            device.device_function.as_deref().unwrap_or("-"),
+            device.ip_address.as_deref().unwrap_or("-"),
+            device.extra.as_deref().unwrap_or("")
+        );
+    }
+    println!("\n{} devices", devices.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_filter() {
+        let filter = |path: &str, value: Option<&str>| JsonFilter { path: path.to_string(), value: value.map(str::to_string) };
+        assert_eq!(parse_json_filter("radios[0].channel=36").unwrap(), filter("$.radios[0].channel", Some("36")));
+        assert_eq!(parse_json_filter("$.tags").unwrap(), filter("$.tags", None));
+        assert_eq!(parse_json_filter("[0]=a=b").unwrap(), filter("$[0]", Some("a=b")));
+        assert_eq!(parse_json_filter("site=").unwrap(), filter("$.site", Some("")));
+        assert!(parse_json_filter("=36").is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::report::DeviceInterfaces;
+use crate::rf::Band;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Transmit power and PHY mode of one AP radio
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RadioDetail {
+    pub device_id: i64,
+    pub hostname: String,
+    /// Radio interface, e.g. `wifi0`
+    pub radio: String,
+    pub tx_power_dbm: Option<f64>,
+    /// 802.11 generation letters: a, b, g, n, ac, ax or be
+    pub phy_mode: String,
+    /// Band from the PHY mode's suffix or its band-specific letters
+    pub band: Option<Band>,
+}
+
+/// Reduce a HiveOS PHY mode such as `11ax-5g` or `11ng` to its 802.11 letters
+pub fn normalize_phy(raw: &str) -> String {
+    let lower = raw.trim().to_lowercase();
+    let letters: String = lower
+        .trim_start_matches("802.")
+        .trim_start_matches("11")
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    match letters.as_str() {
+        "ng" | "na" => "n".to_string(),
+        "" => lower,
+        other => other.to_string(),
+    }
+}
+
+/// Band a HiveOS PHY mode runs on: the `-2g`/`-5g`/`-6g` suffix of `11ax-6g`,
+/// or the letters only used on one band (`11ng`, `11ac`)
+pub fn phy_band(raw: &str) -> Option<Band> {
+    let lower = raw.trim().to_lowercase();
+    if lower.ends_with("6g") || lower.ends_with("6e") {
+        return Some(Band::Six);
+    }
+    if lower.ends_with("5g") {
+        return Some(Band::Five);
+    }
+    if lower.ends_with("2g") {
+        return Some(Band::TwoPointFour);
+    }
+    match lower.trim_start_matches("802.").trim_start_matches("11") {
+        "b" | "g" | "ng" => Some(Band::TwoPointFour),
+        "a" | "na" | "ac" => Some(Band::Five),
+        _ => None,
+    }
+}
+
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-?\d+(\.\d+)?").expect("Failed to compile power regex"));
+
+/// First number in a power value such as `20 dBm` or `auto(18dBm)`
+fn parse_power(value: &str) -> Option<f64> {
+    NUMBER.find(value)?.as_str().parse().ok()
+}
+
+/// Radio name the command asks about, e.g. `wifi1` for `show interface wifi1`
+pub fn radio_from_command(command: &str) -> Option<String> {
+    command
+        .split_whitespace()
+        .last()
+        .filter(|word| word.to_lowercase().starts_with("wifi"))
+        .map(|word| word.to_lowercase())
+}
+
+/// Parse per-radio detail output (`key=value;` pairs or `key: value` lines).
+/// A `Radio name`/`Interface name` key starts a new radio; output without one
+/// is attributed to `default_radio`. The device is left for the caller to fill.
+pub fn parse_radio_details(output: &str, default_radio: Option<&str>) -> Vec<RadioDetail> {
+    let mut radios: Vec<RadioDetail> = Vec::new();
+    let mut current: Option<usize> = None;
+
+    let fields = output
+        .lines()
+        .flat_map(|line| line.split(';'))
+        .filter_map(|field| field.split_once('=').or_else(|| field.split_once(':')));
+
+    for (key, value) in fields {
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        if matches!(key.as_str(), "radio name" | "interface name" | "ifname") {
+            radios.push(RadioDetail { radio: value.to_lowercase(), ..Default::default() });
+            current = Some(radios.len() - 1);
+            continue;
+        }
+
+        let is_power = key.replace(' ', "").contains("txpower");
+        let is_phy = key.replace(' ', "") == "phymode";
+        if !is_power && !is_phy {
+            continue;
+        }
+
+        let index = match current {
+            Some(index) => index,
+            None => {
+                let Some(radio) = default_radio else {
+                    continue;
+                };
+                radios.push(RadioDetail { radio: radio.to_string(), ..Default::default() });
+                radios.len() - 1
+            }
+        };
+        current = Some(index);
+        let entry = &mut radios[index];
+        if is_power && entry.tx_power_dbm.is_none() {
+            entry.tx_power_dbm = parse_power(value);
+        } else if is_phy && entry.phy_mode.is_empty() {
+            entry.phy_mode = normalize_phy(value);
+            entry.band = phy_band(value);
+        }
+    }
+
+    radios.retain(|detail| detail.tx_power_dbm.is_some() || !detail.phy_mode.is_empty());
+    radios
+}
+
+/// Copy each radio's power, PHY mode and band onto the interfaces it carries
+pub fn apply_radio_details(results: &mut [DeviceInterfaces], details: &[RadioDetail]) {
+    let by_radio: HashMap<(i64, &str), &RadioDetail> = details
+        .iter()
+        .map(|d| ((d.device_id, d.radio.as_str()), d))
+        .collect();
+
+    for device in results.iter_mut() {
+        for iface in &mut device.interfaces {
+            let radio = iface.radio.to_lowercase();
+            if let Some(detail) = by_radio.get(&(device.device_id, radio.as_str())) {
+                iface.tx_power = detail.tx_power_dbm.map(|p| p.to_string()).unwrap_or_default();
+                iface.phy_mode = detail.phy_mode.clone();
+                iface.band = detail.band;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_parse_radio_details() {
+        assert_eq!(normalize_phy("11ax-5g"), "ax");
+        assert_eq!(normalize_phy("11ng"), "n");
+        assert_eq!(normalize_phy("11ac"), "ac");
+        assert_eq!(normalize_phy("11be-6g"), "be");
+        assert_eq!(phy_band("11be-6g"), Some(Band::Six));
+        assert_eq!(phy_band("11ng"), Some(Band::TwoPointFour));
+        assert_eq!(phy_band("11ax"), None);
+        assert_eq!(radio_from_command("show interface wifi1").as_deref(), Some("wifi1"));
+        assert_eq!(radio_from_command("show radio"), None);
+
+        let output = "\
+Interface name=wifi0; Radio name=wifi0;
+Mode=access; Admin state=enabled;
+Phymode=11ax-2g; Operational channel=6;
+Operational TX power=auto(18 dBm); Max TX power=20 dBm;
+";
+        let detail = |radio: &str, power: f64, phy: &str, band: Band| RadioDetail {
+            radio: radio.into(),
+            tx_power_dbm: Some(power),
+            phy_mode: phy.into(),
+            band: Some(band),
+            ..Default::default()
+        };
+        assert_eq!(parse_radio_details(output, None), vec![detail("wifi0", 18.0, "ax", Band::TwoPointFour)]);
+
+        let output = "Phy mode: 11ac\nTx power(dBm): 17.5\n";
+        assert_eq!(parse_radio_details(output, Some("wifi1")), vec![detail("wifi1", 17.5, "ac", Band::Five)]);
+        assert!(parse_radio_details(output, None).is_empty());
+    }
+
+    #[test]
+    fn test_apply_radio_details() {
+        let mut results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                InterfaceEntry { radio: "wifi0".into(), ..Default::default() },
+                InterfaceEntry { radio: "wifi1".into(), ..Default::default() },
+            ],
+        }];
+        let details = [RadioDetail {
+            device_id: 1,
+            hostname: "AP-1".into(),
+            radio: "wifi1".into(),
+            tx_power_dbm: Some(20.0),
+            phy_mode: "ax".into(),
+            band: Some(Band::Six),
+        }];
+
+        apply_radio_details(&mut results, &details);
+        assert_eq!(results[0].interfaces[0].tx_power, "");
+        assert_eq!(results[0].interfaces[1].tx_power, "20");
+        assert_eq!(results[0].interfaces[1].phy_mode, "ax");
+        assert_eq!(results[0].interfaces[1].band, Some(Band::Six));
+    }
+}
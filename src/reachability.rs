@@ -0,0 +1,237 @@
+use crate::csv_output::{write_csv, CsvProfile};
+use crate::output::LineEnding;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Report of the connected APs that failed a reachability check
+pub const UNREACHABLE_APS_FILE: &str = "unreachable-aps.csv";
+
+/// Default number of APs checked at once
+pub const DEFAULT_REACHABILITY_CONCURRENCY: usize = 16;
+
+/// Default seconds before a check program is killed
+pub const DEFAULT_REACHABILITY_TIMEOUT_SECS: u64 = 5;
+
+/// Placeholder replaced with the AP's IP address in check commands
+const IP_PLACEHOLDER: &str = "{ip}";
+
+/// Default ping, one echo request with a 2 second wait
+pub fn default_ping_command() -> Vec<String> {
+    let args: &[&str] = if cfg!(windows) {
+        &["ping", "-n", "1", "-w", "2000", IP_PLACEHOLDER]
+    } else {
+        &["ping", "-c", "1", "-W", "2", IP_PLACEHOLDER]
+    };
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+/// Programs run against each connected AP's IP; an empty command skips that check
+#[derive(Debug, Clone, Default)]
+pub struct ReachabilityChecks {
+    /// Succeeds when the AP answers
+    pub ping_command: Vec<String>,
+    /// Prints the AP's sysName on stdout
+    pub snmp_command: Vec<String>,
+    pub concurrency: usize,
+    pub timeout: Duration,
+}
+
+/// What the collector saw of an AP that XIQ reports as connected
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reachability {
+    pub device_id: i64,
+    pub hostname: String,
+    pub ip_address: Option<String>,
+    /// `None` when ping isn't configured
+    pub ping: Option<bool>,
+    /// sysName returned over SNMP, `None` when SNMP isn't configured or got no answer
+    pub sys_name: Option<String>,
+    /// Why the AP is flagged, `None` when every check passed
+    pub issue: Option<String>,
+}
+
+/// Run the configured checks against every connected AP in `devices`.
+/// Only the APs with an issue are returned.
+pub async fn check_connected_aps(devices: &[serde_json::Value], checks: &ReachabilityChecks) -> Vec<Reachability> {
+    let targets: Vec<(i64, String, Option<String>)> = devices
+        .iter()
+        .filter(|d| d.get("connected").and_then(|v| v.as_bool()).unwrap_or(false))
+        .filter(|d| d.get("device_function").and_then(|v| v.as_str()) == Some("AP"))
+        .filter_map(|d| {
+            let id = d.get("id")?.as_i64()?;
+            let hostname = d.get("hostname").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
+            let ip = d.get("ip_address").and_then(|v| v.as_str()).map(str::to_string);
+            Some((id, hostname, ip))
+        })
+        .collect();
+
+    let permits = Arc::new(Semaphore::new(checks.concurrency.max(1)));
+    let mut set = JoinSet::new();
+    for (device_id, hostname, ip_address) in targets.iter().cloned() {
+        let permits = permits.clone();
+        let checks = checks.clone();
+        set.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok();
+            check_ap(device_id, hostname, ip_address, &checks).await
+        });
+    }
+
+    let mut flagged = Vec::new();
+    while let Some(result) = set.join_next().await {
+        if let Ok(ap) = result {
+            if ap.issue.is_some() {
+                flagged.push(ap);
+            }
+        }
+    }
+    flagged.sort_by(|a, b| a.hostname.cmp(&b.hostname).then(a.device_id.cmp(&b.device_id)));
+
+    println!("Checked reachability of {} connected APs, {} flagged", targets.len(), flagged.len());
+    flagged
+}
+
+async fn check_ap(device_id: i64, hostname: String, ip_address: Option<String>, checks: &ReachabilityChecks) -> Reachability {
+    let Some(ip) = ip_address.clone() else {
+        return Reachability {
+            device_id,
+            hostname,
+            ip_address,
+            ping: None,
+            sys_name: None,
+            issue: Some("no IP address".to_string()),
+        };
+    };
+
+    let ping = if checks.ping_command.is_empty() {
+        None
+    } else {
+        Some(run_check(&checks.ping_command, &ip, checks.timeout).await.is_some())
+    };
+    let sys_name = if checks.snmp_command.is_empty() {
+        None
+    } else {
+        run_check(&checks.snmp_command, &ip, checks.timeout)
+            .await
+            .map(|out| out.trim().trim_matches('"').to_string())
+            .filter(|name| !name.is_empty())
+    };
+
+    let mut issues = Vec::new();
+    if ping == Some(false) {
+        issues.push("no ping reply".to_string());
+    }
+    if !checks.snmp_command.is_empty() {
+        match &sys_name {
+            None => issues.push("no SNMP response".to_string()),
+            Some(name) if !name.eq_ignore_ascii_case(&hostname) => {
+                issues.push(format!("sysName is {}", name));
+            }
+            Some(_) => {}
+        }
+    }
+
+    Reachability {
+        device_id,
+        hostname,
+        ip_address,
+        ping,
+        sys_name,
+        issue: (!issues.is_empty()).then(|| issues.join("; ")),
+    }
+}
+
+/// Stdout of `command` with `{ip}` filled in, or `None` if it can't be
+/// started, exits unsuccessfully or times out
+pub(crate) async fn run_check(command: &[String], ip: &str, timeout: Duration) -> Option<String> {
+    let args: Vec<String> = command.iter().map(|arg| arg.replace(IP_PLACEHOLDER, ip)).collect();
+    let output = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(timeout, output).await.ok()?.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn write_unreachable_aps(
+    flagged: &[Reachability],
+    path: &Path,
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let rows: Vec<Vec<String>> = flagged
+        .iter()
+        .map(|ap| {
+            vec![
+                ap.hostname.clone(),
+                ap.device_id.to_string(),
+                ap.ip_address.clone().unwrap_or_default(),
+                ap.ping.map(|ok| if ok { "ok" } else { "failed" }).unwrap_or_default().to_string(),
+                ap.sys_name.clone().unwrap_or_default(),
+                ap.issue.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    write_csv(path, &["Device", "Device ID", "IP", "Ping", "sysName", "Issue"], &rows, line_ending, profile)
+}
+
+pub fn print_unreachable_aps(flagged: &[Reachability]) {
+    if flagged.is_empty() {
+        return;
+    }
+
+    println!("\n=== Connected APs Not Reachable From Collector ({}) ===", flagged.len());
+    println!("{:<24} {:<16} Issue", "Device", "IP");
+    for ap in flagged {
+        println!(
+            "{:<24} {:<16} {}",
+            ap.hostname,
+            ap.ip_address.as_deref().unwrap_or("-"),
+            ap.issue.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_check_connected_aps() {
+        let devices = vec![
+            serde_json::json!({ "id": 1, "hostname": "AP-1", "device_function": "AP", "connected": true, "ip_address": "10.0.0.1" }),
+            serde_json::json!({ "id": 2, "hostname": "AP-2", "device_function": "AP", "connected": true, "ip_address": "10.0.0.2" }),
+            serde_json::json!({ "id": 3, "hostname": "AP-3", "device_function": "AP", "connected": true }),
+            serde_json::json!({ "id": 4, "hostname": "AP-4", "device_function": "AP", "connected": false }),
+        ];
+        // Only 10.0.0.1 "answers", and reports its sysName as AP-1
+        let checks = ReachabilityChecks {
+            ping_command: command(&["sh", "-c", "test {ip} = 10.0.0.1"]),
+            snmp_command: command(&["sh", "-c", "test {ip} = 10.0.0.1 && echo '\"ap-1\"'"]),
+            concurrency: 2,
+            timeout: Duration::from_secs(5),
+        };
+        let flagged = check_connected_aps(&devices, &checks).await;
+
+        let issues: Vec<(i64, Option<&str>)> = flagged.iter().map(|ap| (ap.device_id, ap.issue.as_deref())).collect();
+        assert_eq!(
+            issues,
+            vec![(2, Some("no ping reply; no SNMP response")), (3, Some("no IP address"))]
+        );
+    }
+}
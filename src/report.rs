@@ -0,0 +1,107 @@
+use crate::output::{create_output_file, LineEnding};
+use crate::parser::InterfaceEntry;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Parsed interfaces for a single AP, as produced by a collection run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceInterfaces {
+    pub device_id: i64,
+    pub hostname: String,
+    /// Raw CLI output returned by the AP
+    pub output: String,
+    pub interfaces: Vec<InterfaceEntry>,
+}
+
+/// Context handed to custom report templates
+#[derive(Debug, Serialize)]
+pub struct TemplateContext<'a> {
+    /// CLI command that produced the interface data
+    pub command: &'a str,
+    /// Full device inventory as returned by the API
+    pub devices: &'a [serde_json::Value],
+    /// Parsed interfaces grouped by AP
+    pub aps: &'a [DeviceInterfaces],
+}
+
+/// Render a template source string against the collected data
+pub fn render_template_str(source: &str, context: &TemplateContext) -> Result<String> {
+    let env = minijinja::Environment::new();
+    env.render_str(source, context)
+        .context("Failed to render report template")
+}
+
+/// Default output path for a template: `report.html.j2` renders to `report.html`
+pub fn default_template_output(template: &Path) -> PathBuf {
+    match template.extension().and_then(|e| e.to_str()) {
+        Some("j2" | "jinja" | "jinja2") => template.with_extension(""),
+        _ => {
+            let mut name = template.file_name().unwrap_or_default().to_os_string();
+            name.push(".out");
+            template.with_file_name(name)
+        }
+    }
+}
+
+/// Render a template file and write the result to `output`
+pub fn render_template_file(
+    template: &Path,
+    output: &Path,
+    context: &TemplateContext,
+    line_ending: LineEnding,
+) -> Result<()> {
+    let source = fs::read_to_string(template)
+        .context(format!("Failed to read template: {}", template.display()))?;
+
+    let rendered = render_template_str(&source, context)?;
+
+    let mut file = create_output_file(output, line_ending)?;
+    file.write_all(rendered.as_bytes())
+        .and_then(|_| file.flush())
+        .context(format!("Failed to write rendered report: {}", output.display()))?;
+
+    println!("Custom report rendered to {}", output.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let aps = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![InterfaceEntry {
+                name: "wifi0.1".to_string(),
+                mac: "00:11:22:33:44:55".to_string(),
+                mode: "access".to_string(),
+                state: "Up".to_string(),
+                channel: "36(80)".to_string(),
+                vlan: "10".to_string(),
+                radio: "wifi0".to_string(),
+                hive: "hive1".to_string(),
+                ssid: "Corp".to_string(),
+                ..Default::default()
+            }],
+        }];
+        let context = TemplateContext { command: "show interface", devices: &[], aps: &aps };
+
+        let source = "{% for ap in aps %}{% for i in ap.interfaces %}{{ ap.hostname }} {{ i.mac }} {{ i.ssid }}{% endfor %}{% endfor %}";
+        let rendered = render_template_str(source, &context).unwrap();
+
+        assert_eq!(rendered, "AP-1 00:11:22:33:44:55 Corp");
+    }
+
+    #[test]
+    fn test_default_template_output() {
+        assert_eq!(default_template_output(Path::new("report.html.j2")), PathBuf::from("report.html"));
+        assert_eq!(default_template_output(Path::new("report.txt")), PathBuf::from("report.txt.out"));
+    }
+}
@@ -0,0 +1,449 @@
+use crate::location::{device_location, device_site, DeviceLocation};
+use crate::parser::InterfaceEntry;
+use crate::report::DeviceInterfaces;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// WiFi frequency band
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Band {
+    #[serde(rename = "2.4GHz")]
+    TwoPointFour,
+    #[serde(rename = "5GHz")]
+    Five,
+    #[serde(rename = "6GHz")]
+    Six,
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Band::TwoPointFour => "2.4GHz",
+            Band::Five => "5GHz",
+            Band::Six => "6GHz",
+        };
+        f.write_str(label)
+    }
+}
+
+/// Primary channel number from a channel column like `36(80)`, `6` or `6g-37(160)`
+pub fn channel_number(channel: &str) -> Option<u32> {
+    let trimmed = channel.trim();
+    let lower = trimmed.to_lowercase();
+    let trimmed = if lower.starts_with("6g") || lower.starts_with("6e") {
+        &trimmed[2..]
+    } else {
+        trimmed
+    };
+
+    let digits: String = trimmed
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Band for a channel column.
+///
+/// Channel numbers overlap between 2.4/5 GHz and 6 GHz, so an explicit `6g`
+/// marker wins; otherwise 1-14 is 2.4 GHz, 32-177 is 5 GHz except the 6 GHz
+/// numbers no 5 GHz channel uses (37, 53, ... 145), and anything higher can
+/// only be 6 GHz. Use [`band_for_interface`] when the radio is known.
+pub fn band_for_channel(channel: &str) -> Option<Band> {
+    let lower = channel.to_lowercase();
+    if lower.contains("6g") || lower.contains("6e") {
+        return Some(Band::Six);
+    }
+
+    match channel_number(channel)? {
+        1..=14 => Some(Band::TwoPointFour),
+        number @ 33..=145 if is_6ghz_channel(number) => Some(Band::Six),
+        32..=177 => Some(Band::Five),
+        178..=233 => Some(Band::Six),
+        _ => None,
+    }
+}
+
+/// Whether a channel number exists on 6 GHz (1, 5, 9, ... 233)
+fn is_6ghz_channel(channel: u32) -> bool {
+    (1..=233).contains(&channel) && (channel - 1).is_multiple_of(4)
+}
+
+/// Band of an interface's radio.
+///
+/// `show interface` doesn't mark 6 GHz channels, so the radio decides before
+/// the channel number: the band its PHY mode reported in the radio detail
+/// commands (`11ax-6g`), then `wifi2`, the 6 GHz radio of tri-band APs, when
+/// the channel exists on 6 GHz.
+pub fn band_for_interface(iface: &InterfaceEntry) -> Option<Band> {
+    if iface.band.is_some() {
+        return iface.band;
+    }
+    if radio_name(iface).eq_ignore_ascii_case("wifi2") && channel_number(&iface.channel).is_some_and(is_6ghz_channel) {
+        return Some(Band::Six);
+    }
+    band_for_channel(&iface.channel)
+}
+
+/// Whether a 6 GHz channel is a Preferred Scanning Channel (5, 21, 37, ... 229).
+/// Clients doing passive discovery on 6 GHz only scan PSCs, so APs elsewhere
+/// are hard to find without 2.4/5 GHz discovery.
+pub fn is_psc(channel: u32) -> bool {
+    (5..=229).contains(&channel) && (channel - 5).is_multiple_of(16)
+}
+
+/// PSC status of an interface's channel; `None` when it isn't on 6 GHz
+pub fn psc_status(iface: &InterfaceEntry) -> Option<bool> {
+    if band_for_interface(iface) != Some(Band::Six) {
+        return None;
+    }
+    channel_number(&iface.channel).map(is_psc)
+}
+
+/// An AP broadcasting on a 6 GHz channel that isn't a PSC
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NonPscRadio {
+    pub hostname: String,
+    pub channel: u32,
+    pub bssids: usize,
+}
+
+/// Access-mode 6 GHz BSSIDs on non-PSC channels, grouped per AP and channel
+pub fn non_psc_radios(results: &[DeviceInterfaces]) -> Vec<NonPscRadio> {
+    let mut counts: BTreeMap<(String, u32), usize> = BTreeMap::new();
+    for result in results {
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            if psc_status(iface) == Some(false) {
+                if let Some(channel) = channel_number(&iface.channel) {
+                    *counts.entry((result.hostname.clone(), channel)).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|((hostname, channel), bssids)| NonPscRadio { hostname, channel, bssids })
+        .collect()
+}
+
+/// Best-practice ceiling for SSIDs/BSSIDs broadcast from one radio
+pub const DEFAULT_MAX_BSSIDS_PER_RADIO: usize = 7;
+
+/// Enabled access-mode BSSIDs on one AP radio
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RadioBssidCount {
+    pub hostname: String,
+    pub radio: String,
+    pub bssids: usize,
+}
+
+/// Radio an interface belongs to, falling back to the name prefix (`wifi0` for `wifi0.3`)
+fn radio_name(iface: &InterfaceEntry) -> String {
+    if !iface.radio.is_empty() && iface.radio != "-" {
+        return iface.radio.clone();
+    }
+    iface.name.split('.').next().unwrap_or_default().to_string()
+}
+
+/// Enabled access-mode BSSIDs per AP radio; interfaces in the `Down` state are skipped
+pub fn radio_bssid_counts(results: &[DeviceInterfaces]) -> Vec<RadioBssidCount> {
+    let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for result in results {
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            if iface.state.eq_ignore_ascii_case("down") {
+                continue;
+            }
+            *counts.entry((result.hostname.clone(), radio_name(iface))).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|((hostname, radio), bssids)| RadioBssidCount { hostname, radio, bssids })
+        .collect()
+}
+
+/// BSSIDs on one channel at one site
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelCount {
+    pub site: String,
+    pub band: Option<Band>,
+    pub channel: u32,
+    pub bssids: usize,
+    pub aps: usize,
+    /// PSC status for 6 GHz channels, `None` for other bands
+    pub psc: Option<bool>,
+}
+
+/// Two or more APs on the same floor using the same channel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoChannelConflict {
+    /// Full location path of the floor
+    pub floor: String,
+    pub band: Option<Band>,
+    pub channel: u32,
+    pub aps: Vec<String>,
+}
+
+/// Site or floor path, band and primary channel
+type ChannelKey = (String, Option<Band>, u32);
+
+fn locations_by_id(devices: &[serde_json::Value]) -> HashMap<i64, Option<DeviceLocation>> {
+    devices
+        .iter()
+        .filter_map(|d| Some((d.get("id")?.as_i64()?, device_location(d))))
+        .collect()
+}
+
+/// Access-mode BSSID counts per site and channel
+pub fn channel_histogram(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> Vec<ChannelCount> {
+    let sites: HashMap<i64, String> = devices
+        .iter()
+        .filter_map(|d| Some((d.get("id")?.as_i64()?, device_site(d))))
+        .collect();
+
+    let mut counts: BTreeMap<ChannelKey, (usize, BTreeSet<i64>)> = BTreeMap::new();
+    for result in results {
+        let site = sites
+            .get(&result.device_id)
+            .cloned()
+            .unwrap_or_else(|| crate::location::UNASSIGNED.to_string());
+
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            if let Some(channel) = channel_number(&iface.channel) {
+                let entry = counts
+                    .entry((site.clone(), band_for_interface(iface), channel))
+                    .or_default();
+                entry.0 += 1;
+                entry.1.insert(result.device_id);
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|((site, band, channel), (bssids, aps))| ChannelCount {
+            site,
+            band,
+            channel,
+            bssids,
+            aps: aps.len(),
+            psc: (band == Some(Band::Six)).then(|| is_psc(channel)),
+        })
+        .collect()
+}
+
+/// APs that share a channel with another AP on the same floor.
+/// Devices without floor-level location data are skipped.
+pub fn co_channel_conflicts(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> Vec<CoChannelConflict> {
+    let locations = locations_by_id(devices);
+
+    let mut by_channel: BTreeMap<ChannelKey, BTreeSet<String>> = BTreeMap::new();
+    for result in results {
+        let floor = match locations.get(&result.device_id) {
+            Some(Some(loc)) if loc.floor.is_some() => loc.path.clone(),
+            _ => continue,
+        };
+
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            if let Some(channel) = channel_number(&iface.channel) {
+                by_channel
+                    .entry((floor.clone(), band_for_interface(iface), channel))
+                    .or_default()
+                    .insert(result.hostname.clone());
+            }
+        }
+    }
+
+    by_channel
+        .into_iter()
+        .filter(|(_, aps)| aps.len() > 1)
+        .map(|((floor, band, channel), aps)| CoChannelConflict {
+            floor,
+            band,
+            channel,
+            aps: aps.into_iter().collect(),
+        })
+        .collect()
+}
+
+pub fn print_rf_summary(
+    histogram: &[ChannelCount],
+    conflicts: &[CoChannelConflict],
+    non_psc: &[NonPscRadio],
+    radios: &[RadioBssidCount],
+    max_bssids_per_radio: usize,
+) {
+    if histogram.is_empty() {
+        return;
+    }
+
+    println!("\n=== BSSIDs per Channel ===");
+    let mut current_site = None;
+    for row in histogram {
+        if current_site != Some(&row.site) {
+            println!("{}:", row.site);
+            current_site = Some(&row.site);
+        }
+        let band = row.band.map(|b| b.to_string()).unwrap_or_else(|| "?".to_string());
+        let flag = if row.psc == Some(false) { "  (non-PSC)" } else { "" };
+        println!("  {:<7} ch {:<4} {:>5} BSSIDs on {} AP(s){}", band, row.channel, row.bssids, row.aps, flag);
+    }
+
+    if !conflicts.is_empty() {
+        println!("\n=== Co-channel APs (same floor) ===");
+        for conflict in conflicts {
+            println!("  {} ch {}: {}", conflict.floor, conflict.channel, conflict.aps.join(", "));
+        }
+    }
+    if !non_psc.is_empty() {
+        println!("\n=== 6 GHz non-PSC channels ===");
+        for radio in non_psc {
+            println!("  WARNING: {} ch {} is not a PSC ({} BSSIDs)", radio.hostname, radio.channel, radio.bssids);
+        }
+    }
+    let overloaded: Vec<_> = radios.iter().filter(|r| r.bssids > max_bssids_per_radio).collect();
+    if !overloaded.is_empty() {
+        println!("\n=== Radios over {} BSSIDs ===", max_bssids_per_radio);
+        for radio in overloaded {
+            println!("  WARNING: {} {} broadcasts {} BSSIDs", radio.hostname, radio.radio, radio.bssids);
+        }
+    }
+    println!("==========================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_band_for_channel() {
+        assert_eq!(band_for_channel("6(20)"), Some(Band::TwoPointFour));
+        assert_eq!(band_for_channel("36(80)"), Some(Band::Five));
+        assert_eq!(band_for_channel("149"), Some(Band::Five));
+        assert_eq!(band_for_channel("197(160)"), Some(Band::Six));
+        assert_eq!(band_for_channel("6g-37(160)"), Some(Band::Six));
+        assert_eq!(band_for_channel("37(160)"), Some(Band::Six));
+        assert_eq!(band_for_channel("-"), None);
+        assert_eq!(channel_number("6g-37(160)"), Some(37));
+        assert_eq!(band_for_channel(""), None);
+    }
+
+    #[test]
+    fn test_psc() {
+        assert!(is_psc(5));
+        assert!(is_psc(37));
+        assert!(is_psc(229));
+        assert!(!is_psc(1));
+        assert!(!is_psc(33));
+        let channel = |channel: &str| InterfaceEntry { channel: channel.into(), ..Default::default() };
+        assert_eq!(psc_status(&channel("6g-37(160)")), Some(true));
+        assert_eq!(psc_status(&channel("193(160)")), Some(false));
+        assert_eq!(psc_status(&channel("36(80)")), None);
+
+        let results = vec![ap(1, "AP-1", &["6g-33(160)", "6g-33(160)", "6g-37(160)", "36(80)"])];
+        assert_eq!(
+            non_psc_radios(&results),
+            vec![NonPscRadio { hostname: "AP-1".into(), channel: 33, bssids: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_unmarked_6ghz_channels() {
+        let wifi2 = |channel: &str| InterfaceEntry {
+            name: "wifi2.1".into(),
+            mode: "access".into(),
+            channel: channel.into(),
+            ..Default::default()
+        };
+        assert_eq!(band_for_interface(&wifi2("73(160)")), Some(Band::Six));
+        assert_eq!(band_for_interface(&wifi2("5(20)")), Some(Band::Six));
+        assert_eq!(band_for_interface(&wifi2("36(80)")), Some(Band::Five));
+
+        let detailed = InterfaceEntry { band: Some(Band::Six), ..wifi2("5(20)") };
+        assert_eq!(band_for_interface(&detailed), Some(Band::Six));
+        let five = InterfaceEntry { band: Some(Band::Five), ..wifi2("149") };
+        assert_eq!(band_for_interface(&five), Some(Band::Five));
+
+        let results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".into(),
+            output: String::new(),
+            interfaces: vec![wifi2("73(160)"), wifi2("73(160)"), wifi2("21(160)")],
+        }];
+        assert_eq!(
+            non_psc_radios(&results),
+            vec![NonPscRadio { hostname: "AP-1".into(), channel: 73, bssids: 2 }]
+        );
+        let histogram = channel_histogram(&[], &results);
+        assert_eq!(histogram.iter().map(|c| (c.channel, c.psc)).collect::<Vec<_>>(), vec![(21, Some(true)), (73, Some(false))]);
+    }
+
+    #[test]
+    fn test_radio_bssid_counts() {
+        let mut result = ap(1, "AP-1", &["36(80)", "36(80)", "36(80)", "6(20)"]);
+        result.interfaces[0].name = "wifi1.1".into();
+        result.interfaces[1].name = "wifi1.2".into();
+        result.interfaces[2].name = "wifi1.3".into();
+        result.interfaces[2].state = "Down".into();
+        result.interfaces[3].radio = "wifi0".into();
+
+        let counts = radio_bssid_counts(&[result]);
+        assert_eq!(
+            counts,
+            vec![
+                RadioBssidCount { hostname: "AP-1".into(), radio: "wifi0".into(), bssids: 1 },
+                RadioBssidCount { hostname: "AP-1".into(), radio: "wifi1".into(), bssids: 2 },
+            ]
+        );
+    }
+
+    fn ap(device_id: i64, hostname: &str, channels: &[&str]) -> DeviceInterfaces {
+        DeviceInterfaces {
+            device_id,
+            hostname: hostname.to_string(),
+            output: String::new(),
+            interfaces: channels
+                .iter()
+                .map(|c| InterfaceEntry {
+                    mode: "access".to_string(),
+                    channel: c.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn device(id: i64, floor: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "locations": [{ "name": "Global" }, { "name": "HQ" }, { "name": "B1" }, { "name": floor }]
+        })
+    }
+
+    #[test]
+    fn test_channel_histogram_and_conflicts() {
+        let devices = vec![device(1, "F1"), device(2, "F1"), device(3, "F2"), serde_json::json!({ "id": 4 })];
+        let results = vec![
+            ap(1, "AP-1", &["36(80)", "36(80)", "6(20)"]),
+            ap(2, "AP-2", &["36(80)", "1(20)"]),
+            ap(3, "AP-3", &["36(80)"]),
+            ap(4, "AP-4", &["36(80)"]),
+        ];
+
+        let histogram = channel_histogram(&devices, &results);
+        let hq36 = histogram.iter().find(|r| r.site == "HQ" && r.channel == 36).unwrap();
+        assert_eq!((hq36.bssids, hq36.aps), (4, 3));
+        assert!(histogram.iter().any(|r| r.site == "Unassigned" && r.channel == 36));
+
+        let conflicts = co_channel_conflicts(&devices, &results);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].floor, "Global/HQ/B1/F1");
+        assert_eq!(conflicts[0].channel, 36);
+        assert_eq!(conflicts[0].aps, vec!["AP-1", "AP-2"]);
+    }
+}
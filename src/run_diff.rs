@@ -0,0 +1,358 @@
+use crate::db::{RunBssid, RunDevice, RunRow};
+use crate::locale::Locale;
+use crate::output::{create_output_file, LineEnding};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const RUN_DIFF_TEMPLATE: &str = include_str!("../static/run-diff.html.j2");
+
+/// Output of `report diff`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// Printed to the terminal
+    #[default]
+    Text,
+    /// Standalone HTML page with color-coded rows
+    Html,
+}
+
+impl DiffFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "text" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            other => anyhow::bail!("Unknown --format '{}' (available: text, html)", other),
+        }
+    }
+}
+
+/// Default file for an HTML diff of two runs
+pub fn default_output(from: i64, to: i64) -> PathBuf {
+    PathBuf::from(format!("run-diff-{}-{}.html", from, to))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Changed => "changed",
+        }
+    }
+}
+
+/// BSSID added, removed, or broadcasting other SSIDs between two runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BssidChange {
+    pub kind: ChangeKind,
+    pub device_id: i64,
+    pub hostname: String,
+    pub mac: String,
+    /// SSIDs in the later run, joined with `, `; empty when removed
+    pub ssid: String,
+    /// SSIDs in the earlier run; empty when added
+    pub previous_ssid: String,
+}
+
+/// Device added to, removed from, or changed in the inventory between two runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceChange {
+    pub kind: ChangeKind,
+    pub device_id: i64,
+    pub hostname: String,
+    /// What changed, e.g. `disconnected; renamed from AP-1`
+    pub details: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunDiff {
+    pub from: RunRow,
+    pub to: RunRow,
+    pub devices: Vec<DeviceChange>,
+    pub bssids: Vec<BssidChange>,
+}
+
+impl RunDiff {
+    pub fn new(
+        from: RunRow,
+        to: RunRow,
+        from_devices: &[RunDevice],
+        to_devices: &[RunDevice],
+        from_bssids: &[RunBssid],
+        to_bssids: &[RunBssid],
+    ) -> Self {
+        Self {
+            from,
+            to,
+            devices: device_changes(from_devices, to_devices),
+            bssids: bssid_changes(from_bssids, to_bssids),
+        }
+    }
+}
+
+fn device_changes(from: &[RunDevice], to: &[RunDevice]) -> Vec<DeviceChange> {
+    let before: BTreeMap<i64, &RunDevice> = from.iter().map(|d| (d.device_id, d)).collect();
+    let after: BTreeMap<i64, &RunDevice> = to.iter().map(|d| (d.device_id, d)).collect();
+    let flag = |value: Option<bool>| value.unwrap_or(false);
+    let change = |kind, device: &RunDevice, details: String| DeviceChange {
+        kind,
+        device_id: device.device_id,
+        hostname: device.hostname.clone(),
+        details,
+    };
+
+    let mut changes = Vec::new();
+    for (id, new) in &after {
+        let Some(old) = before.get(id) else {
+            changes.push(change(ChangeKind::Added, new, String::new()));
+            continue;
+        };
+
+        let mut details = Vec::new();
+        if old.hostname != new.hostname {
+            details.push(format!("renamed from {}", old.hostname));
+        }
+        match (flag(old.connected), flag(new.connected)) {
+            (false, true) => details.push("connected".to_string()),
+            (true, false) => details.push("disconnected".to_string()),
+            _ => {}
+        }
+        match (flag(old.config_mismatch), flag(new.config_mismatch)) {
+            (false, true) => details.push("config mismatch".to_string()),
+            (true, false) => details.push("config back in sync".to_string()),
+            _ => {}
+        }
+        if !details.is_empty() {
+            changes.push(change(ChangeKind::Changed, new, details.join("; ")));
+        }
+    }
+    for (id, old) in &before {
+        if !after.contains_key(id) {
+            changes.push(change(ChangeKind::Removed, old, String::new()));
+        }
+    }
+
+    changes.sort_by(|a, b| a.hostname.cmp(&b.hostname).then(a.device_id.cmp(&b.device_id)));
+    changes
+}
+
+/// SSIDs of each BSSID by device ID and MAC, with the device's hostname
+fn ssids_by_bssid(rows: &[RunBssid]) -> BTreeMap<(i64, &str), (&str, Vec<&str>)> {
+    let mut bssids: BTreeMap<(i64, &str), (&str, Vec<&str>)> = BTreeMap::new();
+    for row in rows {
+        let entry = bssids.entry((row.device_id, row.mac.as_str())).or_insert((row.hostname.as_str(), Vec::new()));
+        entry.1.push(row.ssid.as_str());
+    }
+    for (_, ssids) in bssids.values_mut() {
+        ssids.sort_unstable();
+    }
+    bssids
+}
+
+fn bssid_changes(from: &[RunBssid], to: &[RunBssid]) -> Vec<BssidChange> {
+    let before = ssids_by_bssid(from);
+    let after = ssids_by_bssid(to);
+
+    let mut changes = Vec::new();
+    for ((id, mac), (hostname, ssids)) in &after {
+        let kind = match before.get(&(*id, *mac)) {
+            None => ChangeKind::Added,
+            Some((_, old)) if old != ssids => ChangeKind::Changed,
+            Some(_) => continue,
+        };
+        changes.push(BssidChange {
+            kind,
+            device_id: *id,
+            hostname: hostname.to_string(),
+            mac: mac.to_string(),
+            ssid: ssids.join(", "),
+            previous_ssid: before.get(&(*id, *mac)).map(|(_, old)| old.join(", ")).unwrap_or_default(),
+        });
+    }
+    for ((id, mac), (hostname, ssids)) in &before {
+        if !after.contains_key(&(*id, *mac)) {
+            changes.push(BssidChange {
+                kind: ChangeKind::Removed,
+                device_id: *id,
+                hostname: hostname.to_string(),
+                mac: mac.to_string(),
+                ssid: String::new(),
+                previous_ssid: ssids.join(", "),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.hostname.cmp(&b.hostname).then(a.mac.cmp(&b.mac)));
+    changes
+}
+
+/// One of the two compared runs, with its start time and BSSID count localized
+#[derive(Debug, Serialize)]
+struct RunLine<'a> {
+    id: i64,
+    started_at: String,
+    command: &'a str,
+    bssids: String,
+}
+
+impl<'a> RunLine<'a> {
+    fn new(run: &'a RunRow, locale: &Locale) -> Self {
+        Self {
+            id: run.id,
+            started_at: run.started_at.as_deref().map_or("-".to_string(), |at| locale.timestamp(at)),
+            command: run.command.as_deref().unwrap_or("-"),
+            bssids: run.bssids.map_or("-".to_string(), |bssids| locale.integer(bssids)),
+        }
+    }
+}
+
+/// Template context of the HTML diff
+#[derive(Serialize)]
+struct HtmlDiff<'a> {
+    #[serde(flatten)]
+    diff: &'a RunDiff,
+    zone: &'static str,
+    runs: [RunLine<'a>; 2],
+}
+
+pub fn print_run_diff(diff: &RunDiff, locale: &Locale) {
+    println!("\n=== Changes from run {} to run {} ===", diff.from.id, diff.to.id);
+    for run in [&diff.from, &diff.to].map(|run| RunLine::new(run, locale)) {
+        println!("Run {}: started {} ({}), {} BSSIDs, {}", run.id, run.started_at, locale.zone(), run.bssids, run.command);
+    }
+    println!();
+
+    if diff.devices.is_empty() {
+        println!("No device changes");
+    } else {
+        println!("{:<30} {:>12}  {:<8} Details", "Device", "ID", "Change");
+        for change in &diff.devices {
+            println!("{:<30} {:>12}  {:<8} {}", change.hostname, change.device_id, change.kind.label(), change.details);
+        }
+    }
+
+    println!();
+    if diff.bssids.is_empty() {
+        println!("No BSSID changes");
+    } else {
+        println!("{:<30} {:<20} {:<8} SSID", "Device", "BSSID", "Change");
+        for change in &diff.bssids {
+            let ssid = match change.kind {
+                ChangeKind::Added => change.ssid.clone(),
+                ChangeKind::Removed => change.previous_ssid.clone(),
+                ChangeKind::Changed => format!("{} -> {}", change.previous_ssid, change.ssid),
+            };
+            println!("{:<30} {:<20} {:<8} {}", change.hostname, change.mac, change.kind.label(), ssid);
+        }
+    }
+    println!("{} device change(s), {} BSSID change(s)", diff.devices.len(), diff.bssids.len());
+    println!("=====================================");
+}
+
+/// Render the diff as a standalone HTML page
+pub fn render_html(diff: &RunDiff, locale: &Locale) -> Result<String> {
+    // The `.html` name turns on HTML escaping of hostnames and SSIDs
+    let mut env = minijinja::Environment::new();
+    env.add_template("run-diff.html", RUN_DIFF_TEMPLATE)
+        .context("Failed to load run diff template")?;
+    env.get_template("run-diff.html")?
+        .render(HtmlDiff {
+            diff,
+            zone: locale.zone(),
+            runs: [RunLine::new(&diff.from, locale), RunLine::new(&diff.to, locale)],
+        })
+        .context("Failed to render run diff")
+}
+
+pub fn write_html(diff: &RunDiff, path: &Path, locale: &Locale) -> Result<()> {
+    let html = render_html(diff, locale)?;
+    let mut file = create_output_file(path, LineEnding::Lf)?;
+    file.write_all(html.as_bytes())
+        .and_then(|_| file.flush())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!(
+        "Diff of runs {} and {} saved to {} ({} device change(s), {} BSSID change(s))",
+        diff.from.id,
+        diff.to.id,
+        path.display(),
+        diff.devices.len(),
+        diff.bssids.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: i64) -> RunRow {
+        RunRow { id, command: Some("show interface".to_string()), bssids: None, started_at: None }
+    }
+
+    fn device(id: i64, hostname: &str, connected: bool) -> RunDevice {
+        RunDevice { device_id: id, hostname: hostname.to_string(), connected: Some(connected), config_mismatch: Some(false) }
+    }
+
+    fn bssid(id: i64, mac: &str, ssid: &str) -> RunBssid {
+        RunBssid { device_id: id, hostname: format!("AP-{}", id), mac: mac.to_string(), ssid: ssid.to_string() }
+    }
+
+    #[test]
+    fn test_run_diff() {
+        let diff = RunDiff::new(
+            run(1),
+            run(2),
+            &[device(1, "AP-1", true), device(2, "AP-2", true), device(3, "AP-3", true)],
+            &[device(1, "AP-1", true), device(2, "AP-2b", false), device(4, "<AP-4>", true)],
+            &[bssid(1, "aa", "Corp"), bssid(1, "bb", "Guest"), bssid(2, "cc", "Corp")],
+            &[bssid(1, "aa", "Corp"), bssid(1, "bb", "Corp"), bssid(4, "dd", "Corp")],
+        );
+
+        let devices: Vec<_> = diff.devices.iter().map(|d| (d.device_id, d.kind, d.details.as_str())).collect();
+        assert_eq!(
+            devices,
+            vec![
+                (4, ChangeKind::Added, ""),
+                (2, ChangeKind::Changed, "renamed from AP-2; disconnected"),
+                (3, ChangeKind::Removed, ""),
+            ]
+        );
+
+        let bssids: Vec<_> = diff.bssids.iter().map(|b| (b.mac.as_str(), b.kind, b.previous_ssid.as_str(), b.ssid.as_str())).collect();
+        assert_eq!(
+            bssids,
+            vec![
+                ("bb", ChangeKind::Changed, "Guest", "Corp"),
+                ("cc", ChangeKind::Removed, "Corp", ""),
+                ("dd", ChangeKind::Added, "", "Corp"),
+            ]
+        );
+
+        let html = render_html(&diff, &Locale::default()).unwrap();
+        assert!(html.contains(r#"<tr class="added">"#));
+        assert!(html.contains("&lt;AP-4&gt;"));
+
+        let mut later = run(3);
+        later.started_at = Some("2024-07-01 14:30:00".to_string());
+        later.bssids = Some(12345);
+        let diff = RunDiff::new(run(2), later, &[], &[], &[], &[]);
+        let locale = Locale::new("America/Chicago", crate::locale::NumberStyle::En).unwrap();
+        let html = render_html(&diff, &locale).unwrap();
+        assert!(html.contains("<th>Started (America&#x2f;Chicago)</th>"));
+        assert!(html.contains("<td>2024-07-01 09:30:00</td>"));
+        assert!(html.contains("<td>12,345</td>"));
+    }
+}
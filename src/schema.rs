@@ -0,0 +1,140 @@
+use crate::parser::InterfaceEntry;
+use crate::report::DeviceInterfaces;
+use anyhow::Result;
+use schemars::{schema_for, JsonSchema, Schema};
+use serde::Serialize;
+
+/// Output files with a published schema, as accepted by the `schema` subcommand
+pub const SCHEMA_NAMES: &[&str] = &["devices", "full_cli", "wifi-bssids"];
+
+/// One entry in devices.json.
+///
+/// devices.json is written straight from the XIQ API response, so only the
+/// fields the tool relies on are described; any others are passed through.
+#[derive(Debug, JsonSchema)]
+#[allow(dead_code)]
+pub struct DeviceRecord {
+    pub id: i64,
+    pub hostname: Option<String>,
+    pub config_mismatch: Option<bool>,
+    pub connected: Option<bool>,
+    pub description: Option<String>,
+    pub device_admin_state: Option<String>,
+    /// `AP`, `SWITCH`, ...
+    pub device_function: Option<String>,
+    pub ip_address: Option<String>,
+    pub mac_address: Option<String>,
+    pub managed_by: Option<String>,
+    pub org_id: Option<i64>,
+    pub product_type: Option<String>,
+    pub serial_number: Option<String>,
+    pub simulated: Option<bool>,
+    pub software_version: Option<String>,
+    pub system_up_time: Option<i64>,
+    pub location_id: Option<i64>,
+    pub network_policy_name: Option<String>,
+    pub hive_name: Option<String>,
+    pub device_model: Option<String>,
+    /// Epoch milliseconds
+    pub last_connect_time: Option<i64>,
+    /// Location hierarchy, outermost first
+    pub locations: Option<Vec<LocationRecord>>,
+    /// Values of the configured `devices.extra_fields`
+    pub extra: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, JsonSchema)]
+#[allow(dead_code)]
+pub struct LocationRecord {
+    pub id: Option<i64>,
+    pub name: String,
+}
+
+/// Raw CLI output for one device (full_cli.json)
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CliResultRecord<'a> {
+    pub device_id: i64,
+    pub hostname: &'a str,
+    pub command: &'a str,
+    pub output: &'a str,
+}
+
+/// One access-mode BSSID (wifi-bssids.json)
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BssidRecord<'a> {
+    pub device: &'a str,
+    pub device_id: i64,
+    pub name: &'a str,
+    /// Colon-separated uppercase MAC
+    pub mac: &'a str,
+    pub mode: &'a str,
+    pub state: &'a str,
+    pub channel: &'a str,
+    pub vlan: &'a str,
+    pub radio: &'a str,
+    pub hive: &'a str,
+    pub ssid: &'a str,
+    /// Radio transmit power in dBm; empty unless `[radio] commands` are set
+    pub tx_power: &'a str,
+    /// Radio PHY mode (a/b/g/n/ac/ax/be); empty unless `[radio] commands` are set
+    pub phy_mode: &'a str,
+    /// SSID security mode (Open/PSK/SAE/802.1X/OWE); empty unless `[ssid] command` is set
+    pub security: &'a str,
+    /// SSID cipher (AES/TKIP/GCMP/none); empty unless `[ssid] command` is set
+    pub encryption: &'a str,
+    /// `yes` when the SSID is broadcast, `no` when hidden; empty unless `[ssid] command` is set
+    pub broadcast: &'a str,
+    /// The device's local notes (`note add`), joined with `; `
+    pub notes: &'a str,
+}
+
+impl<'a> BssidRecord<'a> {
+    pub fn new(device: &'a DeviceInterfaces, iface: &'a InterfaceEntry) -> Self {
+        Self {
+            device: &device.hostname,
+            device_id: device.device_id,
+            name: &iface.name,
+            mac: &iface.mac,
+            mode: &iface.mode,
+            state: &iface.state,
+            channel: &iface.channel,
+            vlan: &iface.vlan,
+            radio: &iface.radio,
+            hive: &iface.hive,
+            ssid: &iface.ssid,
+            tx_power: &iface.tx_power,
+            phy_mode: &iface.phy_mode,
+            security: &iface.security,
+            encryption: &iface.encryption,
+            broadcast: &iface.broadcast,
+            notes: &iface.notes,
+        }
+    }
+}
+
+/// JSON Schema for an output file; every file is an array of records
+pub fn schema(name: &str) -> Result<Schema> {
+    match name {
+        "devices" => Ok(schema_for!(Vec<DeviceRecord>)),
+        "full_cli" => Ok(schema_for!(Vec<CliResultRecord>)),
+        "wifi-bssids" => Ok(schema_for!(Vec<BssidRecord>)),
+        other => anyhow::bail!("Unknown schema '{}' (available: {})", other, SCHEMA_NAMES.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas() {
+        for name in SCHEMA_NAMES {
+            let schema = serde_json::to_value(schema(name).unwrap()).unwrap();
+            assert_eq!(schema["type"], "array", "{}", name);
+        }
+
+        let bssids = serde_json::to_value(schema("wifi-bssids").unwrap()).unwrap();
+        assert!(bssids.to_string().contains("\"ssid\""));
+        assert!(schema("bssids").is_err());
+    }
+}
@@ -0,0 +1,595 @@
+use crate::calendar::{self, CalendarFeed};
+use crate::cli::{self, Command, DEFAULT_CLI_COMMAND};
+use crate::config::{ApiToken, Role, ServerConfig};
+use crate::db::{BssidMatch, Database, DeviceSummary, LabelCount};
+use crate::progress::{ProgressEvent, PROGRESS_ENV};
+use anyhow::{Context, Result};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Address `serve` listens on when `--listen` isn't given
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+/// Most rows returned by one BSSID search
+const SEARCH_LIMIT: i64 = 500;
+
+/// Single-page UI, embedded so the binary serves it without extra files
+const INDEX_HTML: &str = include_str!("../static/index.html");
+
+/// Error returned to API clients with a JSON body
+struct ApiError {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: &str) -> Self {
+        Self {
+            status,
+            error: anyhow::anyhow!("{}", message),
+        }
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(e: E) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: e.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status.is_server_error() {
+            eprintln!("Request failed: {:#}", self.error);
+        }
+        let body = serde_json::json!({ "error": format!("{:#}", self.error) });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Progress events buffered for subscribers that fall behind
+const EVENT_BUFFER: usize = 1024;
+
+/// Starts collection runs for `serve` by running this tool's own binary
+/// against the server's database and config, one run at a time, and relays
+/// its progress events and output to subscribers
+pub struct RunLauncher {
+    program: PathBuf,
+    global_args: Vec<String>,
+    running: Arc<AtomicBool>,
+    events: broadcast::Sender<ProgressEvent>,
+}
+
+impl RunLauncher {
+    pub fn new(program: PathBuf, global_args: Vec<String>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            program,
+            global_args,
+            running: Arc::new(AtomicBool::new(false)),
+            events,
+        }
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<ProgressEvent> {
+        self.events.subscribe()
+    }
+
+    /// Start a run of `words` (the CLI command for the APs); returns the process ID
+    async fn start(&self, words: &[String]) -> Result<Option<u32>, ApiError> {
+        if self.running.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return Err(ApiError::new(StatusCode::CONFLICT, "A run is already in progress"));
+        }
+
+        let spawned = tokio::process::Command::new(&self.program)
+            .args(&self.global_args)
+            .args(words)
+            .env(PROGRESS_ENV, "json")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                self.running.store(false, Ordering::SeqCst);
+                return Err(anyhow::Error::new(e)
+                    .context(format!("Failed to start run: {}", self.program.display()))
+                    .into());
+            }
+        };
+        let pid = child.id();
+
+        let readers = [
+            forward_lines(child.stdout.take(), self.events.clone()),
+            forward_lines(child.stderr.take(), self.events.clone()),
+        ];
+        let running = self.running.clone();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            for reader in readers {
+                let _ = reader.await;
+            }
+            let code = child.wait().await.ok().and_then(|status| status.code());
+            let _ = events.send(ProgressEvent::RunExited { code });
+            running.store(false, Ordering::SeqCst);
+        });
+
+        Ok(pid)
+    }
+}
+
+/// Relay a run's output lines: progress events as they are, anything else as
+/// a log line (also echoed to the server's console)
+fn forward_lines<R>(reader: Option<R>, events: broadcast::Sender<ProgressEvent>) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let Some(reader) = reader else {
+            return;
+        };
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let event = match serde_json::from_str(&line) {
+                Ok(event) => event,
+                Err(_) => {
+                    println!("{}", line);
+                    ProgressEvent::Log { line }
+                }
+            };
+            // Nobody listening is fine
+            let _ = events.send(event);
+        }
+    })
+}
+
+#[derive(Clone)]
+struct AppState {
+    db: Database,
+    #[cfg(feature = "graphql")]
+    graphql: crate::graphql::XiqSchema,
+    tokens: Arc<Vec<ApiToken>>,
+    runs: Arc<RunLauncher>,
+    calendar: Arc<CalendarFeed>,
+}
+
+/// Compare without returning early, so response times don't reveal how much of a token matched
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Check the request's bearer token against `required`. Returns the token
+/// holder, or `None` when no tokens are configured and read access is open.
+fn authorize<'a>(state: &'a AppState, headers: &HeaderMap, required: Role) -> Result<Option<&'a ApiToken>, ApiError> {
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    authorize_token(state, given, required)
+}
+
+/// Check a token given in the request, by header or otherwise, against `required`
+fn authorize_token<'a>(state: &'a AppState, given: Option<&str>, required: Role) -> Result<Option<&'a ApiToken>, ApiError> {
+    if state.tokens.is_empty() {
+        if required == Role::Viewer {
+            return Ok(None);
+        }
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "No operator tokens are configured in [server]",
+        ));
+    }
+
+    let given = given.ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Missing bearer token"))?;
+
+    let token = state
+        .tokens
+        .iter()
+        .find(|t| tokens_match(given.trim(), &t.token))
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Invalid token"))?;
+
+    if token.role < required {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "This token may not do that"));
+    }
+
+    Ok(Some(token))
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    q: String,
+}
+
+#[derive(Deserialize)]
+struct CalendarParams {
+    /// Calendar apps subscribe by URL and can't send headers
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RunRequest {
+    /// CLI command for the APs; the default command when omitted
+    command: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Session {
+    name: Option<String>,
+    role: Role,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    ssids: Vec<LabelCount>,
+    channels: Vec<LabelCount>,
+}
+
+async fn index() -> Html<&'static str> {
+    Html(INDEX_HTML)
+}
+
+async fn session(State(state): State<AppState>, headers: HeaderMap) -> Result<Json<Session>, ApiError> {
+    let token = authorize(&state, &headers, Role::Viewer)?;
+    Ok(Json(Session {
+        name: token.map(|t| t.name.clone()),
+        role: token.map_or(Role::Viewer, |t| t.role),
+    }))
+}
+
+async fn devices(State(state): State<AppState>, headers: HeaderMap) -> Result<Json<Vec<DeviceSummary>>, ApiError> {
+    authorize(&state, &headers, Role::Viewer)?;
+    Ok(Json(state.db.device_summaries().await?))
+}
+
+async fn bssids(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Vec<BssidMatch>>, ApiError> {
+    authorize(&state, &headers, Role::Viewer)?;
+    Ok(Json(state.db.search_bssids(&params.q, SEARCH_LIMIT).await?))
+}
+
+async fn stats(State(state): State<AppState>, headers: HeaderMap) -> Result<Json<Stats>, ApiError> {
+    authorize(&state, &headers, Role::Viewer)?;
+    Ok(Json(Stats {
+        ssids: state.db.ssid_counts().await?,
+        channels: state.db.channel_counts().await?,
+    }))
+}
+
+/// Scheduled collection windows and completed runs as an iCalendar feed
+async fn calendar_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<CalendarParams>,
+) -> Result<Response, ApiError> {
+    match params.token.as_deref() {
+        Some(token) => authorize_token(&state, Some(token), Role::Viewer)?,
+        None => authorize(&state, &headers, Role::Viewer)?,
+    };
+    let runs = state.db.calendar_runs(state.calendar.runs).await?;
+    let ics = calendar::render(&state.calendar.windows, &runs, chrono::Utc::now());
+    Ok(([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], ics).into_response())
+}
+
+async fn start_run(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Option<Json<RunRequest>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let token = authorize(&state, &headers, Role::Operator)?;
+    let command = request
+        .and_then(|Json(r)| r.command)
+        .unwrap_or_else(|| DEFAULT_CLI_COMMAND.to_string());
+
+    // Only AP CLI commands: no flags, and nothing the CLI would treat as a subcommand
+    let words: Vec<String> = command.split_whitespace().map(String::from).collect();
+    let is_run = matches!(cli::parse_args(&words), Ok(cli::Cli { command: Command::Run(_), .. }));
+    if words.is_empty() || words.iter().any(|w| w.starts_with('-')) || !is_run {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Only CLI commands for the APs can be run"));
+    }
+
+    let pid = state.runs.start(&words).await?;
+    let started_by = token.map_or("anonymous", |t| t.name.as_str());
+    state
+        .db
+        .log_audit("run", &command, &format!("started by {}", started_by))
+        .await?;
+    println!("Run '{}' started by {}", command, started_by);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "command": command, "pid": pid })),
+    ))
+}
+
+/// Live progress of runs started from the server, as server-sent events
+async fn run_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    authorize(&state, &headers, Role::Viewer)?;
+
+    // Subscribers that lag behind the buffer skip the events they missed
+    let events = BroadcastStream::new(state.runs.subscribe())
+        .filter_map(|event| event.ok())
+        .map(|event| Event::default().json_data(event));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(feature = "graphql")]
+async fn graphql(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, ApiError> {
+    authorize(&state, &headers, Role::Viewer)?;
+    Ok(Json(state.graphql.execute(request).await))
+}
+
+/// Routes for the web UI and its JSON API
+pub fn router(db: Database, config: &ServerConfig, calendar: CalendarFeed, runs: RunLauncher) -> Router {
+    let state = AppState {
+        #[cfg(feature = "graphql")]
+        graphql: crate::graphql::schema(db.clone()),
+        db,
+        tokens: Arc::new(config.tokens.clone()),
+        runs: Arc::new(runs),
+        calendar: Arc::new(calendar),
+    };
+
+    let router = Router::new()
+        .route("/", get(index))
+        .route("/api/session", get(session))
+        .route("/api/devices", get(devices))
+        .route("/api/bssids", get(bssids))
+        .route("/api/stats", get(stats))
+        .route("/api/runs", post(start_run))
+        .route("/api/runs/events", get(run_events))
+        .route("/calendar.ics", get(calendar_feed));
+    #[cfg(feature = "graphql")]
+    let router = router.route("/api/graphql", post(graphql));
+
+    router.with_state(state)
+}
+
+/// Serve the web UI on `listen` until the process is stopped
+/// Refuse to serve the data without tokens, unless `allow_anonymous_read`
+/// opts in to that, and never to more than the local machine
+fn check_anonymous_access(config: &ServerConfig, addr: SocketAddr, allow_anonymous_read: bool) -> Result<()> {
+    if !config.tokens.is_empty() {
+        return Ok(());
+    }
+    if !addr.ip().is_loopback() {
+        anyhow::bail!("No [server] tokens are configured; configure tokens before listening on {}", addr);
+    }
+    if !allow_anonymous_read {
+        anyhow::bail!("No [server] tokens are configured; configure tokens, or pass --allow-anonymous-read to serve the data without one");
+    }
+    Ok(())
+}
+
+pub async fn serve(
+    db: Database,
+    listen: &str,
+    allow_anonymous_read: bool,
+    config: &ServerConfig,
+    calendar: CalendarFeed,
+    runs: RunLauncher,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .context(format!("Failed to listen on {}", listen))?;
+    check_anonymous_access(config, listener.local_addr()?, allow_anonymous_read)?;
+
+    if config.tokens.is_empty() {
+        println!("WARNING: no [server] tokens configured; the data is readable without a token and runs can't be started");
+    }
+    println!("Serving web UI on http://{}", listener.local_addr()?);
+
+    axum::serve(listener, router(db, config, calendar, runs))
+        .await
+        .context("Web server failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MEMORY_DB;
+    use crate::parser::InterfaceEntry;
+    use crate::report::DeviceInterfaces;
+    use std::path::Path;
+
+    async fn start(config: ServerConfig) -> String {
+        let db = Database::new(Path::new(MEMORY_DB), 1).await.unwrap();
+        db.insert_devices(&[serde_json::json!({ "id": 1, "hostname": "AP-1" })]).await.unwrap();
+        db.insert_interfaces(&[DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![InterfaceEntry {
+                mac: "00:11:22:33:44:55".to_string(),
+                mode: "access".to_string(),
+                ssid: "Corp".to_string(),
+                ..Default::default()
+            }],
+        }])
+        .await
+        .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = router(db, &config, CalendarFeed::default(), RunLauncher::new(PathBuf::from("true"), Vec::new()));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        base
+    }
+
+    fn token(name: &str, role: Role) -> ApiToken {
+        ApiToken {
+            name: name.to_string(),
+            token: format!("{}-token", name),
+            role,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_routes() {
+        let base = start(ServerConfig::default()).await;
+
+        let page = reqwest::get(&base).await.unwrap().text().await.unwrap();
+        assert!(page.contains("<html"));
+
+        let found: serde_json::Value = reqwest::get(format!("{}/api/bssids?q=001122334455", base))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(found[0]["hostname"], "AP-1");
+
+        let devices: serde_json::Value = reqwest::get(format!("{}/api/devices", base)).await.unwrap().json().await.unwrap();
+        assert_eq!(devices[0]["bssids"], 1);
+
+        let stats: serde_json::Value = reqwest::get(format!("{}/api/stats", base)).await.unwrap().json().await.unwrap();
+        assert_eq!(stats["ssids"][0]["label"], "Corp");
+
+        // Without tokens nobody may start a run
+        let client = reqwest::Client::new();
+        let response = client.post(format!("{}/api/runs", base)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_token_roles() {
+        let base = start(ServerConfig {
+            tokens: vec![token("viewer", Role::Viewer), token("operator", Role::Operator)],
+        })
+        .await;
+        let client = reqwest::Client::new();
+        let get = |path: &str, token: Option<&str>| {
+            let request = client.get(format!("{}{}", base, path));
+            match token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+            .send()
+        };
+
+        assert_eq!(get("/", None).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(get("/api/devices", None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(get("/api/devices", Some("wrong")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(get("/api/devices", Some("viewer-token")).await.unwrap().status(), StatusCode::OK);
+
+        let session: serde_json::Value = get("/api/session", Some("operator-token")).await.unwrap().json().await.unwrap();
+        assert_eq!(session["role"], "operator");
+
+        // Calendar apps pass the token in the URL
+        assert_eq!(get("/calendar.ics", None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let feed = get("/calendar.ics?token=viewer-token", None).await.unwrap();
+        assert_eq!(feed.headers()[header::CONTENT_TYPE], "text/calendar; charset=utf-8");
+        assert!(feed.text().await.unwrap().starts_with("BEGIN:VCALENDAR\r\n"));
+
+        let run = |token: &str, command: &str| {
+            client
+                .post(format!("{}/api/runs", base))
+                .bearer_auth(token)
+                .json(&serde_json::json!({ "command": command }))
+                .send()
+        };
+        assert_eq!(run("viewer-token", "show interface").await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(run("operator-token", "device reboot 1 --yes").await.unwrap().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(run("operator-token", "show --db x.db").await.unwrap().status(), StatusCode::BAD_REQUEST);
+        #[cfg(unix)]
+        assert_eq!(run("operator-token", "show interface").await.unwrap().status(), StatusCode::ACCEPTED);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_events() {
+        let db = Database::new(Path::new(MEMORY_DB), 1).await.unwrap();
+        let script = r#"echo "Fetching devices..."; echo '{"event":"page_fetched","page":1,"devices":3,"total_pages":1}' >&2"#;
+        let runs = RunLauncher::new(PathBuf::from("sh"), vec!["-c".into(), script.into(), "sh".into()]);
+        let config = ServerConfig { tokens: vec![token("operator", Role::Operator)] };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = router(db, &config, CalendarFeed::default(), runs);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = reqwest::Client::new();
+        let mut events = client
+            .get(format!("{}/api/runs/events", base))
+            .bearer_auth("operator-token")
+            .send()
+            .await
+            .unwrap();
+        let started = client
+            .post(format!("{}/api/runs", base))
+            .bearer_auth("operator-token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(started.status(), StatusCode::ACCEPTED);
+
+        let mut received = String::new();
+        while !received.contains("run_exited") {
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(10), events.chunk())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert!(received.contains(r#""event":"log","line":"Fetching devices...""#));
+        assert!(received.contains(r#""event":"page_fetched""#));
+        assert!(received.contains(r#""event":"run_exited","code":0"#));
+    }
+
+    #[test]
+    fn test_check_anonymous_access() {
+        let open = ServerConfig::default();
+        let loopback: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let public: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+
+        assert!(check_anonymous_access(&open, loopback, false).is_err());
+        assert!(check_anonymous_access(&open, loopback, true).is_ok());
+        assert!(check_anonymous_access(&open, public, true).is_err());
+        assert!(check_anonymous_access(&open, "[::1]:8080".parse().unwrap(), true).is_ok());
+
+        let secured = ServerConfig { tokens: vec![token("viewer", Role::Viewer)] };
+        assert!(check_anonymous_access(&secured, public, false).is_ok());
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("abc", "abc"));
+        assert!(!tokens_match("abd", "abc"));
+        assert!(!tokens_match("ab", "abc"));
+    }
+}
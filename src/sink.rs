@@ -0,0 +1,988 @@
+use crate::ansible;
+use crate::config::{Config, WebhookConfig};
+use crate::csv_output::{write_csv, write_csv_as, CsvProfile};
+use crate::db::Database;
+use crate::locale::Locale;
+use crate::location;
+use crate::mist;
+use crate::output::{create_output_file, LineEnding};
+use crate::parser::InterfaceEntry;
+use crate::report::DeviceInterfaces;
+use crate::schema::{BssidRecord, CliResultRecord};
+use crate::rf::{channel_histogram, co_channel_conflicts, radio_bssid_counts};
+use crate::summary::{device_policy, policy_summary, site_summary, ssid_summary};
+use crate::terraform;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Every sink name that can appear in `output.sinks`
+pub const SINK_NAMES: &[&str] =
+    &["json", "txt", "csv", "xlsx", "ssid-summary", "site-summary", "policy-summary", "rf-summary", "mist", "ansible-inventory", "terraform", "db", "webhook"];
+
+/// Sinks that write local files, skipped by `--no-files`
+pub const FILE_SINKS: &[&str] =
+    &["json", "txt", "csv", "xlsx", "ssid-summary", "site-summary", "policy-summary", "rf-summary", "mist", "ansible-inventory", "terraform"];
+
+/// Sinks that are only compiled in with a cargo feature, and that feature
+pub const FEATURE_SINKS: &[(&str, &str)] = &[("xlsx", "xlsx")];
+
+/// Cargo feature a sink needs that this build was compiled without
+pub fn missing_feature(sink: &str) -> Option<&'static str> {
+    let enabled = |feature: &str| feature != "xlsx" || cfg!(feature = "xlsx");
+    FEATURE_SINKS
+        .iter()
+        .find(|(name, feature)| *name == sink && !enabled(feature))
+        .map(|(_, feature)| *feature)
+}
+
+/// How the BSSID exports (wifi-bssids.csv/.json) are additionally split
+/// into one file per group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// Site from the device's location hierarchy
+    Site,
+    /// Network policy assigned to the device
+    Policy,
+    /// Hostname up to the first `-`, e.g. `BLDG1` for `BLDG1-AP03`
+    Prefix,
+    /// One file per AP, named after its hostname
+    Device,
+}
+
+impl SplitBy {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "site" => Ok(Self::Site),
+            "policy" => Ok(Self::Policy),
+            "prefix" => Ok(Self::Prefix),
+            "device" => Ok(Self::Device),
+            other => anyhow::bail!("Unknown --split-by '{}' (available: site, policy, prefix, device)", other),
+        }
+    }
+
+    /// Group name of one AP
+    fn group(self, result: &DeviceInterfaces, devices: &[serde_json::Value]) -> String {
+        let device = || devices.iter().find(|d| d.get("id").and_then(|id| id.as_i64()) == Some(result.device_id));
+        match self {
+            Self::Site => device().map(location::device_site).unwrap_or_else(|| location::UNASSIGNED.to_string()),
+            Self::Policy => device().map(device_policy).unwrap_or_else(|| location::UNASSIGNED.to_string()),
+            Self::Prefix => result.hostname.split('-').next().unwrap_or_default().to_string(),
+            Self::Device => result.hostname.clone(),
+        }
+    }
+}
+
+/// File name stem for one split group, with characters that aren't safe in
+/// file names replaced, e.g. `wifi-bssids-HQ_East`
+fn split_file_stem(group: &str) -> String {
+    let slug: String = group
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    let slug = if slug.is_empty() { location::UNASSIGNED.to_string() } else { slug };
+    format!("wifi-bssids-{}", slug)
+}
+
+/// Access-mode rows grouped by split file stem, or nothing without `--split-by`
+fn split_rows<'a>(ctx: &ExportContext<'a>) -> BTreeMap<String, Vec<(&'a DeviceInterfaces, &'a InterfaceEntry)>> {
+    let mut groups: BTreeMap<String, Vec<_>> = BTreeMap::new();
+    if let Some(split_by) = ctx.split_by {
+        for (result, iface) in access_rows(ctx.results) {
+            groups
+                .entry(split_file_stem(&split_by.group(result, ctx.devices)))
+                .or_default()
+                .push((result, iface));
+        }
+    }
+    groups
+}
+
+/// Per-group CSV and JSON files the csv and json sinks write for `--split-by`
+pub fn split_output_files(ctx: &ExportContext<'_>) -> Vec<PathBuf> {
+    split_rows(ctx)
+        .keys()
+        .flat_map(|stem| [PathBuf::from(format!("{}.csv", stem)), PathBuf::from(format!("{}.json", stem))])
+        .collect()
+}
+
+/// Layout of wifi-bssids.txt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TxtLayout {
+    /// Device, then radio, then the radio's BSSIDs, with a subtotal per radio
+    #[default]
+    Grouped,
+    /// One line per BSSID with its device on every line (`--flat-txt`)
+    Flat,
+}
+
+/// Data handed to every sink after a CLI run
+pub struct ExportContext<'a> {
+    pub command: &'a str,
+    /// Full device inventory from the API
+    pub devices: &'a [serde_json::Value],
+    pub results: &'a [DeviceInterfaces],
+    pub line_ending: LineEnding,
+    /// Header mapping profile for CSV files
+    pub csv_profile: Option<&'a CsvProfile>,
+    /// Also write the BSSID exports split into one file per group
+    pub split_by: Option<SplitBy>,
+    /// Only some APs were collected (`--only-new`, `--targets`), so the stored interfaces
+    /// of the others are kept
+    pub incremental: bool,
+    pub txt_layout: TxtLayout,
+    /// When the results were collected: the start of the run, also when a
+    /// stored run is exported later
+    pub collected_at: DateTime<Utc>,
+}
+
+/// Destination for parsed CLI results
+#[async_trait]
+pub trait OutputSink: Send + Sync {
+    /// Registry name, as used in `output.sinks`
+    fn name(&self) -> &'static str;
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()>;
+}
+
+/// Access-mode interfaces paired with their device, in output order
+fn access_rows(results: &[DeviceInterfaces]) -> impl Iterator<Item = (&DeviceInterfaces, &InterfaceEntry)> {
+    results
+        .iter()
+        .flat_map(|r| r.interfaces.iter().filter(|i| i.is_access()).map(move |i| (r, i)))
+}
+
+/// Build the sinks named in the config, in order
+pub fn build_sinks(names: &[String], config: &Config, db: Option<&Database>) -> Result<Vec<Box<dyn OutputSink>>> {
+    let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+
+    for name in names {
+        if let Some(feature) = missing_feature(name) {
+            anyhow::bail!(
+                "The '{}' sink isn't included in this build; rebuild with `cargo build --features {}`",
+                name,
+                feature
+            );
+        }
+
+        let sink: Box<dyn OutputSink> = match name.as_str() {
+            "json" => Box::new(JsonSink),
+            "txt" => Box::new(TxtSink {
+                locale: config.locale.locale()?,
+            }),
+            "csv" => Box::new(CsvSink),
+            #[cfg(feature = "xlsx")]
+            "xlsx" => Box::new(XlsxSink {
+                locale: config.locale.locale()?,
+            }),
+            "ssid-summary" => Box::new(SsidSummarySink),
+            "site-summary" => Box::new(SiteSummarySink),
+            "policy-summary" => Box::new(PolicySummarySink),
+            "mist" => Box::new(MistSink),
+            "ansible-inventory" => Box::new(AnsibleInventorySink),
+            "terraform" => Box::new(TerraformSink),
+            "rf-summary" => Box::new(RfSummarySink {
+                max_bssids_per_radio: config.rf.max_bssids_per_radio,
+            }),
+            "db" => Box::new(DbSink {
+                db: db.context("The db sink requires a database")?.clone(),
+            }),
+            "webhook" => Box::new(WebhookSink {
+                client: reqwest::Client::new(),
+                config: config
+                    .output
+                    .webhook
+                    .clone()
+                    .context("The webhook sink requires an [output.webhook] section")?,
+            }),
+            other => anyhow::bail!(
+                "Unknown output sink '{}' (available: {})",
+                other,
+                SINK_NAMES.join(", ")
+            ),
+        };
+        sinks.push(sink);
+    }
+
+    Ok(sinks)
+}
+
+/// Raw CLI output per device (full_cli.json) and structured access-mode
+/// BSSIDs (wifi-bssids.json); see `schema` for their JSON Schemas
+pub struct JsonSink;
+
+#[async_trait]
+impl OutputSink for JsonSink {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let json_results: Vec<_> = ctx
+            .results
+            .iter()
+            .map(|result| CliResultRecord {
+                device_id: result.device_id,
+                hostname: &result.hostname,
+                command: ctx.command,
+                output: &result.output,
+            })
+            .collect();
+
+        let json_data = serde_json::to_string_pretty(&json_results)
+            .context("Failed to serialize CLI results to JSON")?;
+
+        let mut file = create_output_file(Path::new("full_cli.json"), ctx.line_ending)?;
+
+        file.write_all(json_data.as_bytes())
+            .and_then(|_| file.flush())
+            .context("Failed to write CLI results to file")?;
+
+        println!("CLI results saved to full_cli.json");
+
+        let bssids: Vec<_> = access_rows(ctx.results)
+            .map(|(device, iface)| BssidRecord::new(device, iface))
+            .collect();
+
+        let json_data = serde_json::to_string_pretty(&bssids)
+            .context("Failed to serialize BSSIDs to JSON")?;
+
+        let mut file = create_output_file(Path::new("wifi-bssids.json"), ctx.line_ending)?;
+
+        file.write_all(json_data.as_bytes())
+            .and_then(|_| file.flush())
+            .context("Failed to write BSSIDs to file")?;
+
+        println!("Access mode BSSIDs saved to wifi-bssids.json ({} entries)", bssids.len());
+
+        let split = split_rows(ctx);
+        for (stem, group) in &split {
+            let bssids: Vec<_> = group.iter().map(|(device, iface)| BssidRecord::new(device, iface)).collect();
+            let path = format!("{}.json", stem);
+            let json_data = serde_json::to_string_pretty(&bssids)
+                .context("Failed to serialize BSSIDs to JSON")?;
+            let mut file = create_output_file(Path::new(&path), ctx.line_ending)?;
+            file.write_all(json_data.as_bytes())
+                .and_then(|_| file.flush())
+                .context(format!("Failed to write BSSIDs to {}", path))?;
+        }
+        if !split.is_empty() {
+            println!("Access mode BSSIDs split into {} files (wifi-bssids-*.json)", split.len());
+        }
+
+        Ok(())
+    }
+}
+
+/// Fixed-width text reports (bssids.txt and wifi-bssids.txt)
+pub struct TxtSink {
+    /// Time zone and number style of the grouped wifi-bssids.txt report
+    pub locale: Locale,
+}
+
+#[async_trait]
+impl OutputSink for TxtSink {
+    fn name(&self) -> &'static str {
+        "txt"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        // bssids.txt - all parsed interfaces grouped by device
+        let mut bssid_file = create_output_file(Path::new("bssids.txt"), ctx.line_ending)?;
+        let mut total_bssids = 0;
+
+        for result in ctx.results.iter().filter(|r| !r.interfaces.is_empty()) {
+            total_bssids += result.interfaces.len();
+            write_device_block(&mut bssid_file, result)?;
+        }
+
+        bssid_file.flush().context("Failed to flush bssids.txt")?;
+        println!("CLI output saved to bssids.txt ({} BSSIDs found)", total_bssids);
+
+        // wifi-bssids.txt - access mode interfaces only
+        let mut wifi_bssid_file = create_output_file(Path::new("wifi-bssids.txt"), ctx.line_ending)?;
+
+        let total_wifi_bssids = access_rows(ctx.results).count();
+        match ctx.txt_layout {
+            TxtLayout::Grouped => {
+                writeln!(wifi_bssid_file, "Collected {} ({}), {} BSSIDs\n",
+                    self.locale.datetime(ctx.collected_at), self.locale.zone(), self.locale.integer(total_wifi_bssids as i64))
+                    .context("Failed to write summary to wifi-bssids.txt")?;
+                for result in ctx.results {
+                    write_radio_groups(&mut wifi_bssid_file, result)?;
+                }
+            }
+            TxtLayout::Flat => {
+                write_access_header(&mut wifi_bssid_file)?;
+                for (result, iface) in access_rows(ctx.results) {
+                    write_access_line(&mut wifi_bssid_file, result, iface)?;
+                }
+            }
+        }
+
+        wifi_bssid_file.flush().context("Failed to flush wifi-bssids.txt")?;
+        println!("Access mode BSSIDs saved to wifi-bssids.txt ({} entries)", total_wifi_bssids);
+
+        Ok(())
+    }
+}
+
+/// One device's interfaces in bssids.txt, with a header naming the device
+pub(crate) fn write_device_block(file: &mut impl Write, result: &DeviceInterfaces) -> Result<()> {
+    writeln!(file, "--- {} (ID: {}) ---", result.hostname, result.device_id)
+        .context("Failed to write header to bssids.txt")?;
+    writeln!(file, "{:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<8} {:<12} SSID",
+        "Name", "MAC", "Mode", "State", "Channel", "VLAN", "Radio", "Hive")
+        .context("Failed to write column header to bssids.txt")?;
+    writeln!(file, "{}", "-".repeat(100))
+        .context("Failed to write separator to bssids.txt")?;
+    for iface in &result.interfaces {
+        writeln!(file, "{:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<8} {:<12} {}",
+            iface.name, iface.mac, iface.mode, iface.state,
+            iface.channel, iface.vlan, iface.radio, iface.hive, iface.ssid)
+            .context("Failed to write interface to bssids.txt")?;
+    }
+    writeln!(file).context("Failed to write newline to bssids.txt")
+}
+
+/// One AP's access-mode interfaces in the grouped wifi-bssids.txt: a block
+/// per radio with its BSSID and SSID subtotal. APs without any are skipped.
+pub(crate) fn write_radio_groups(file: &mut impl Write, result: &DeviceInterfaces) -> Result<()> {
+    let mut radios: BTreeMap<&str, Vec<&InterfaceEntry>> = BTreeMap::new();
+    for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+        radios.entry(iface.radio.as_str()).or_default().push(iface);
+    }
+    if radios.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(file, "=== {} (ID: {}) ===", result.hostname, result.device_id)
+        .context("Failed to write device header to wifi-bssids.txt")?;
+    for (radio, ifaces) in &radios {
+        let ssids: BTreeSet<&str> = ifaces.iter().map(|i| i.ssid.as_str()).collect();
+        writeln!(file, "  Radio {}: {} BSSIDs, {} SSIDs",
+            if radio.is_empty() { "-" } else { radio }, ifaces.len(), ssids.len())
+            .context("Failed to write radio header to wifi-bssids.txt")?;
+        writeln!(file, "    {:<12} {:<20} {:<8} {:<12} {:<6} {:<12} {:<8} {:<4} {:<8} SSID",
+            "Name", "MAC", "State", "Channel", "VLAN", "Hive", "TxPower", "PHY", "Security")
+            .context("Failed to write column header to wifi-bssids.txt")?;
+        for iface in ifaces {
+            writeln!(file, "    {:<12} {:<20} {:<8} {:<12} {:<6} {:<12} {:<8} {:<4} {:<8} {}",
+                iface.name, iface.mac, iface.state, iface.channel, iface.vlan,
+                iface.hive, iface.tx_power, iface.phy_mode, iface.security, iface.ssid)
+                .context("Failed to write interface to wifi-bssids.txt")?;
+        }
+    }
+    writeln!(file).context("Failed to write newline to wifi-bssids.txt")
+}
+
+/// Column header and separator of the flat wifi-bssids.txt
+pub(crate) fn write_access_header(file: &mut impl Write) -> Result<()> {
+    writeln!(file, "{:<20} {:<20} {:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<12} {:<12} {:<8} {:<4} {:<8} SSID",
+        "Device", "DeviceID", "Name", "MAC", "Mode", "State", "Channel", "VLAN", "Radio", "Hive", "TxPower", "PHY", "Security")
+        .context("Failed to write column header to wifi-bssids.txt")?;
+    writeln!(file, "{}", "-".repeat(163))
+        .context("Failed to write separator to wifi-bssids.txt")
+}
+
+/// One access-mode interface in the flat wifi-bssids.txt
+pub(crate) fn write_access_line(file: &mut impl Write, result: &DeviceInterfaces, iface: &InterfaceEntry) -> Result<()> {
+    writeln!(file, "{:<20} {:<20} {:<12} {:<20} {:<8} {:<8} {:<12} {:<6} {:<12} {:<12} {:<8} {:<4} {:<8} {}",
+        result.hostname, result.device_id, iface.name, iface.mac, iface.mode, iface.state,
+        iface.channel, iface.vlan, iface.radio, iface.hive, iface.tx_power, iface.phy_mode, iface.security, iface.ssid)
+        .context("Failed to write interface to wifi-bssids.txt")
+}
+
+/// Columns of the access-mode BSSID exports
+pub(crate) const BSSID_COLUMNS: [&str; 17] = [
+    "Device", "DeviceID", "Name", "MAC", "Mode", "State", "Channel", "VLAN", "Radio", "Hive", "SSID", "TxPower", "PHY",
+    "Security", "Encryption", "Broadcast", "Notes",
+];
+
+/// Access mode interfaces as CSV (wifi-bssids.csv)
+pub struct CsvSink;
+
+#[async_trait]
+impl OutputSink for CsvSink {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let rows: Vec<Vec<String>> = access_rows(ctx.results).map(|(result, iface)| csv_row(result, iface)).collect();
+
+        write_csv(Path::new("wifi-bssids.csv"), &BSSID_COLUMNS, &rows, ctx.line_ending, ctx.csv_profile)?;
+        let total = rows.len();
+        println!("Access mode BSSIDs saved to wifi-bssids.csv ({} entries)", total);
+
+        let split = split_rows(ctx);
+        for (stem, group) in &split {
+            let rows: Vec<Vec<String>> = group.iter().map(|(result, iface)| csv_row(result, iface)).collect();
+            let path = format!("{}.csv", stem);
+            write_csv_as(Path::new(&path), "wifi-bssids.csv", &BSSID_COLUMNS, &rows, ctx.line_ending, ctx.csv_profile)?;
+        }
+        if !split.is_empty() {
+            println!("Access mode BSSIDs split into {} files (wifi-bssids-*.csv)", split.len());
+        }
+
+        Ok(())
+    }
+}
+
+/// One wifi-bssids.csv row, in `BSSID_COLUMNS` order
+pub(crate) fn csv_row(result: &DeviceInterfaces, iface: &InterfaceEntry) -> Vec<String> {
+    vec![
+        result.hostname.clone(),
+        result.device_id.to_string(),
+        iface.name.clone(),
+        iface.mac.clone(),
+        iface.mode.clone(),
+        iface.state.clone(),
+        iface.channel.clone(),
+        iface.vlan.clone(),
+        iface.radio.clone(),
+        iface.hive.clone(),
+        iface.ssid.clone(),
+        iface.tx_power.clone(),
+        iface.phy_mode.clone(),
+        iface.security.clone(),
+        iface.encryption.clone(),
+        iface.broadcast.clone(),
+        iface.notes.clone(),
+    ]
+}
+
+/// Access mode interfaces as an Excel workbook (wifi-bssids.xlsx)
+#[cfg(feature = "xlsx")]
+pub struct XlsxSink {
+    /// Time zone and number style of the Run sheet
+    pub locale: Locale,
+}
+
+#[cfg(feature = "xlsx")]
+#[async_trait]
+impl OutputSink for XlsxSink {
+    fn name(&self) -> &'static str {
+        "xlsx"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("BSSIDs").context("Failed to name worksheet")?;
+
+        for (col, header) in BSSID_COLUMNS.iter().enumerate() {
+            sheet.write_string(0, col as u16, *header)
+                .context("Failed to write xlsx header")?;
+        }
+
+        let mut row = 0;
+        for (result, iface) in access_rows(ctx.results) {
+            row += 1;
+            let values = [
+                result.hostname.as_str(), &result.device_id.to_string(), &iface.name, &iface.mac,
+                &iface.mode, &iface.state, &iface.channel, &iface.vlan, &iface.radio, &iface.hive, &iface.ssid,
+                &iface.tx_power, &iface.phy_mode, &iface.security, &iface.encryption, &iface.broadcast, &iface.notes,
+            ];
+            for (col, value) in values.iter().enumerate() {
+                sheet.write_string(row, col as u16, *value)
+                    .context("Failed to write xlsx row")?;
+            }
+        }
+
+        sheet.autofit();
+
+        let run = workbook.add_worksheet();
+        run.set_name("Run").context("Failed to name worksheet")?;
+        let collected = format!("Collected ({})", self.locale.zone());
+        let details = [
+            ("Command", ctx.command.to_string()),
+            (collected.as_str(), self.locale.datetime(ctx.collected_at)),
+        ];
+        for (index, (label, value)) in details.iter().enumerate() {
+            run.write_string(index as u32, 0, *label)
+                .and_then(|run| run.write_string(index as u32, 1, value))
+                .context("Failed to write xlsx run details")?;
+        }
+
+        // Counts stay numbers; Excel groups them with the reader's own separators
+        let grouping = match self.locale.numbers {
+            crate::locale::NumberStyle::Plain => "0",
+            _ => "#,##0",
+        };
+        let count_format = rust_xlsxwriter::Format::new().set_num_format(grouping);
+        let counts = [("APs", ctx.results.len()), ("BSSIDs", row as usize)];
+        for (index, (label, count)) in counts.iter().enumerate() {
+            let index = (details.len() + index) as u32;
+            run.write_string(index, 0, *label)
+                .and_then(|run| run.write_number_with_format(index, 1, *count as f64, &count_format))
+                .context("Failed to write xlsx run details")?;
+        }
+        run.autofit();
+
+        workbook.save("wifi-bssids.xlsx")
+            .context("Failed to save wifi-bssids.xlsx")?;
+
+        println!("Access mode BSSIDs saved to wifi-bssids.xlsx ({} entries)", row);
+
+        Ok(())
+    }
+}
+
+/// Per-SSID BSSID/AP/band rollup (ssid-summary.csv)
+pub struct SsidSummarySink;
+
+#[async_trait]
+impl OutputSink for SsidSummarySink {
+    fn name(&self) -> &'static str {
+        "ssid-summary"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let summary = ssid_summary(ctx.results);
+        let rows: Vec<Vec<String>> = summary
+            .iter()
+            .map(|row| vec![row.ssid.clone(), row.bssids.to_string(), row.aps.to_string(), row.bands_label()])
+            .collect();
+
+        write_csv(Path::new("ssid-summary.csv"), &["SSID", "BSSIDs", "APs", "Bands"], &rows, ctx.line_ending, ctx.csv_profile)?;
+        println!("SSID summary saved to ssid-summary.csv ({} SSIDs)", summary.len());
+
+        Ok(())
+    }
+}
+
+/// Per-site AP/BSSID/SSID rollup (site-summary.csv)
+pub struct SiteSummarySink;
+
+#[async_trait]
+impl OutputSink for SiteSummarySink {
+    fn name(&self) -> &'static str {
+        "site-summary"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let summary = site_summary(ctx.devices, ctx.results);
+        let rows: Vec<Vec<String>> = summary
+            .iter()
+            .map(|row| {
+                vec![
+                    row.site.clone(),
+                    row.aps.to_string(),
+                    row.connected_aps.to_string(),
+                    row.bssids.to_string(),
+                    row.bssids_24ghz.to_string(),
+                    row.bssids_5ghz.to_string(),
+                    row.bssids_6ghz.to_string(),
+                    row.ssids.to_string(),
+                ]
+            })
+            .collect();
+
+        write_csv(
+            Path::new("site-summary.csv"),
+            &["Site", "APs", "Connected APs", "BSSIDs", "2.4GHz BSSIDs", "5GHz BSSIDs", "6GHz BSSIDs", "SSIDs"],
+            &rows,
+            ctx.line_ending,
+            ctx.csv_profile,
+        )?;
+        println!("Site summary saved to site-summary.csv ({} sites)", summary.len());
+
+        Ok(())
+    }
+}
+
+/// Per-network-policy SSID rollup with a total row per policy (policy-summary.csv)
+pub struct PolicySummarySink;
+
+#[async_trait]
+impl OutputSink for PolicySummarySink {
+    fn name(&self) -> &'static str {
+        "policy-summary"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let summary = policy_summary(ctx.devices, ctx.results);
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for policy in &summary {
+            for ssid in &policy.ssids {
+                rows.push(vec![policy.policy.clone(), ssid.ssid.clone(), ssid.bssids.to_string(), ssid.aps.to_string()]);
+            }
+            rows.push(vec![policy.policy.clone(), "(total)".to_string(), policy.bssids.to_string(), policy.aps.to_string()]);
+        }
+
+        write_csv(
+            Path::new("policy-summary.csv"),
+            &["Policy", "SSID", "BSSIDs", "APs"],
+            &rows,
+            ctx.line_ending,
+            ctx.csv_profile,
+        )?;
+        println!("Policy summary saved to policy-summary.csv ({} policies)", summary.len());
+
+        Ok(())
+    }
+}
+
+/// One row per AP with its base MAC and per-WLAN BSSIDs, in the layout of
+/// the Mist inventory import (mist-aps.csv)
+pub struct MistSink;
+
+#[async_trait]
+impl OutputSink for MistSink {
+    fn name(&self) -> &'static str {
+        "mist"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let (header, rows) = mist::mist_rows(ctx.devices, ctx.results);
+        let header: Vec<&str> = header.iter().map(String::as_str).collect();
+        write_csv(Path::new(mist::MIST_APS_FILE), &header, &rows, ctx.line_ending, ctx.csv_profile)?;
+        println!("Mist AP import saved to {} ({} APs)", mist::MIST_APS_FILE, rows.len());
+
+        Ok(())
+    }
+}
+
+/// Collected APs as Ansible inventories grouped by site and device function
+/// (ansible-inventory.yml and ansible-inventory.ini)
+pub struct AnsibleInventorySink;
+
+#[async_trait]
+impl OutputSink for AnsibleInventorySink {
+    fn name(&self) -> &'static str {
+        "ansible-inventory"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        ansible::write_inventories(ctx.devices, ctx.results, ctx.line_ending)
+    }
+}
+
+/// BSSID inventory for a Terraform `external` data source (terraform-bssids.json)
+pub struct TerraformSink;
+
+#[async_trait]
+impl OutputSink for TerraformSink {
+    fn name(&self) -> &'static str {
+        "terraform"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        terraform::write_terraform_bssids(ctx.devices, ctx.results, ctx.line_ending)
+    }
+}
+
+/// Channel histogram, co-channel and per-radio BSSID reports
+/// (channel-histogram.csv, co-channel-conflicts.csv and radio-bssids.csv)
+pub struct RfSummarySink {
+    max_bssids_per_radio: usize,
+}
+
+#[async_trait]
+impl OutputSink for RfSummarySink {
+    fn name(&self) -> &'static str {
+        "rf-summary"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let histogram = channel_histogram(ctx.devices, ctx.results);
+        let rows: Vec<Vec<String>> = histogram
+            .iter()
+            .map(|row| {
+                let psc = match row.psc {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "",
+                };
+                vec![
+                    row.site.clone(),
+                    row.band.map(|b| b.to_string()).unwrap_or_default(),
+                    row.channel.to_string(),
+                    row.bssids.to_string(),
+                    row.aps.to_string(),
+                    psc.to_string(),
+                ]
+            })
+            .collect();
+        write_csv(
+            Path::new("channel-histogram.csv"),
+            &["Site", "Band", "Channel", "BSSIDs", "APs", "PSC"],
+            &rows,
+            ctx.line_ending,
+            ctx.csv_profile,
+        )?;
+
+        let conflicts = co_channel_conflicts(ctx.devices, ctx.results);
+        let rows: Vec<Vec<String>> = conflicts
+            .iter()
+            .map(|conflict| {
+                vec![
+                    conflict.floor.clone(),
+                    conflict.band.map(|b| b.to_string()).unwrap_or_default(),
+                    conflict.channel.to_string(),
+                    conflict.aps.join(";"),
+                ]
+            })
+            .collect();
+        write_csv(
+            Path::new("co-channel-conflicts.csv"),
+            &["Floor", "Band", "Channel", "APs"],
+            &rows,
+            ctx.line_ending,
+            ctx.csv_profile,
+        )?;
+
+        let radios = radio_bssid_counts(ctx.results);
+        let rows: Vec<Vec<String>> = radios
+            .iter()
+            .map(|radio| {
+                let over = if radio.bssids > self.max_bssids_per_radio { "yes" } else { "no" };
+                vec![radio.hostname.clone(), radio.radio.clone(), radio.bssids.to_string(), over.to_string()]
+            })
+            .collect();
+        write_csv(
+            Path::new("radio-bssids.csv"),
+            &["Device", "Radio", "BSSIDs", "OverLimit"],
+            &rows,
+            ctx.line_ending,
+            ctx.csv_profile,
+        )?;
+
+        let overloaded = radios.iter().filter(|r| r.bssids > self.max_bssids_per_radio).count();
+        println!(
+            "RF summary saved to channel-histogram.csv, co-channel-conflicts.csv and radio-bssids.csv \
+             ({} conflicts, {} radios over {} BSSIDs)",
+            conflicts.len(),
+            overloaded,
+            self.max_bssids_per_radio
+        );
+
+        Ok(())
+    }
+}
+
+/// Parsed interfaces in the `interfaces` table
+pub struct DbSink {
+    db: Database,
+}
+
+#[async_trait]
+impl OutputSink for DbSink {
+    fn name(&self) -> &'static str {
+        "db"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let changes = if ctx.incremental {
+            self.db.update_interfaces(ctx.results).await?
+        } else {
+            self.db.insert_interfaces(ctx.results).await?
+        };
+        if changes.unchanged > 0 && !changes.changed.is_empty() {
+            println!("Devices with changed interfaces: {}", changes.changed.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// POST the parsed interfaces as JSON to a configured URL
+pub struct WebhookSink {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+#[async_trait]
+impl OutputSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn export(&self, ctx: &ExportContext<'_>) -> Result<()> {
+        let payload = serde_json::json!({
+            "command": ctx.command,
+            "devices": ctx.results.iter().map(|r| serde_json::json!({
+                "device_id": r.device_id,
+                "hostname": r.hostname,
+                "interfaces": r.interfaces,
+            })).collect::<Vec<_>>(),
+        });
+
+        let mut request = self.client.post(&self.config.url).json(&payload);
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request
+            .send()
+            .await
+            .context("Failed to send webhook request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Webhook failed with status {}: {}", status, error_text);
+        }
+
+        println!("CLI results posted to webhook {}", self.config.url);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_sinks() {
+        let config = Config::default();
+        let names: Vec<String> = vec!["json".into(), "csv".into()];
+        let sinks = build_sinks(&names, &config, None).unwrap();
+        assert_eq!(sinks.iter().map(|s| s.name()).collect::<Vec<_>>(), vec!["json", "csv"]);
+
+        assert!(build_sinks(&["db".to_string()], &config, None).is_err());
+        assert!(build_sinks(&["webhook".to_string()], &config, None).is_err());
+        assert!(build_sinks(&["pdf".to_string()], &config, None).is_err());
+    }
+
+    #[test]
+    fn test_write_radio_groups() {
+        let iface = |name: &str, radio: &str, ssid: &str| InterfaceEntry {
+            name: name.into(),
+            mode: "access".into(),
+            radio: radio.into(),
+            ssid: ssid.into(),
+            ..Default::default()
+        };
+        let ap = DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                iface("wifi1.1", "wifi1", "Corp"),
+                iface("wifi0.1", "wifi0", "Corp"),
+                iface("wifi0.2", "wifi0", "Guest"),
+                InterfaceEntry { name: "mgt0".into(), mode: "backhaul".into(), ..Default::default() },
+            ],
+        };
+
+        let mut out = Vec::new();
+        write_radio_groups(&mut out, &ap).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "=== AP-1 (ID: 1) ===");
+        assert_eq!(lines[1], "  Radio wifi0: 2 BSSIDs, 2 SSIDs");
+        assert!(lines[3].trim_start().starts_with("wifi0.1"));
+        assert_eq!(lines[5], "  Radio wifi1: 1 BSSIDs, 1 SSIDs");
+        assert!(!text.contains("mgt0"));
+
+        let mut out = Vec::new();
+        write_radio_groups(&mut out, &DeviceInterfaces { interfaces: Vec::new(), ..ap }).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_feature_sinks() {
+        let config = Config::default();
+        let xlsx = build_sinks(&["xlsx".to_string()], &config, None);
+        if cfg!(feature = "xlsx") {
+            assert_eq!(missing_feature("xlsx"), None);
+            assert!(xlsx.is_ok());
+        } else {
+            assert_eq!(missing_feature("xlsx"), Some("xlsx"));
+            assert!(xlsx.err().unwrap().to_string().contains("--features xlsx"));
+        }
+        assert_eq!(missing_feature("csv"), None);
+    }
+
+    #[test]
+    fn test_split_output_files() {
+        let access = InterfaceEntry { mode: "access".into(), ..Default::default() };
+        let ap = |device_id, hostname: &str| DeviceInterfaces {
+            device_id,
+            hostname: hostname.to_string(),
+            output: String::new(),
+            interfaces: vec![access.clone()],
+        };
+        let results = vec![ap(1, "BLDG1-AP01"), ap(2, "BLDG1-AP02"), ap(3, "Annex AP")];
+        let devices = vec![
+            serde_json::json!({ "id": 1, "locations": [{ "name": "Global" }, { "name": "HQ East" }], "network_policy_name": "Corp" }),
+            serde_json::json!({ "id": 2, "locations": [{ "name": "Global" }, { "name": "HQ East" }] }),
+        ];
+        let mut ctx = ExportContext {
+            command: "show interface",
+            devices: &devices,
+            results: &results,
+            line_ending: LineEnding::Lf,
+            csv_profile: None,
+            split_by: None,
+            incremental: false,
+            txt_layout: TxtLayout::Grouped,
+            collected_at: Utc::now(),
+        };
+        let stems = |ctx: &ExportContext<'_>| split_rows(ctx).into_keys().collect::<Vec<_>>();
+        assert!(split_output_files(&ctx).is_empty());
+
+        ctx.split_by = Some(SplitBy::Site);
+        assert_eq!(stems(&ctx), vec!["wifi-bssids-HQ_East", "wifi-bssids-Unassigned"]);
+        assert_eq!(split_rows(&ctx)["wifi-bssids-HQ_East"].len(), 2);
+
+        ctx.split_by = Some(SplitBy::Policy);
+        assert_eq!(stems(&ctx), vec!["wifi-bssids-Corp", "wifi-bssids-Unassigned"]);
+        assert_eq!(split_rows(&ctx)["wifi-bssids-Unassigned"].len(), 2);
+
+        ctx.split_by = Some(SplitBy::Prefix);
+        assert_eq!(stems(&ctx), vec!["wifi-bssids-Annex_AP", "wifi-bssids-BLDG1"]);
+
+        ctx.split_by = Some(SplitBy::Device);
+        assert_eq!(stems(&ctx), vec!["wifi-bssids-Annex_AP", "wifi-bssids-BLDG1-AP01", "wifi-bssids-BLDG1-AP02"]);
+        assert_eq!(
+            split_output_files(&ctx)[..2],
+            [PathBuf::from("wifi-bssids-Annex_AP.csv"), PathBuf::from("wifi-bssids-Annex_AP.json")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_db_sink_keeps_unreached_aps_of_interrupted_run() {
+        use crate::cli::RunOptions;
+        use crate::db::{DEFAULT_POOL_SIZE, MEMORY_DB};
+        use crate::interrupt::StopReason;
+
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let devices = vec![serde_json::json!({ "id": 1, "hostname": "AP-1" }), serde_json::json!({ "id": 2, "hostname": "AP-2" })];
+        db.insert_devices(&devices).await.unwrap();
+        let ap = |device_id, mac: &str| DeviceInterfaces {
+            device_id,
+            hostname: format!("AP-{}", device_id),
+            output: String::new(),
+            interfaces: vec![InterfaceEntry { name: "wifi0.1".into(), mac: mac.into(), mode: "access".into(), ..Default::default() }],
+        };
+        db.insert_interfaces(&[ap(1, "00:11:22:33:44:01"), ap(2, "00:11:22:33:44:02")]).await.unwrap();
+
+        // Ctrl-C after only AP-1 answered
+        let options = RunOptions::default();
+        let results = vec![ap(1, "00:11:22:33:44:11")];
+        let ctx = ExportContext {
+            command: "show interface",
+            devices: &devices,
+            results: &results,
+            line_ending: LineEnding::Lf,
+            csv_profile: None,
+            split_by: None,
+            incremental: options.is_incomplete(Some(StopReason::Interrupted)),
+            txt_layout: TxtLayout::Grouped,
+            collected_at: Utc::now(),
+        };
+        DbSink { db: db.clone() }.export(&ctx).await.unwrap();
+
+        let mut macs: Vec<String> = db.access_interfaces().await.unwrap().into_iter().map(|i| i.mac).collect();
+        macs.sort();
+        assert_eq!(macs, vec!["00:11:22:33:44:02", "00:11:22:33:44:11"]);
+        assert!(!options.is_incomplete(None));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::cli_status::{CliStatus, DeviceStatus};
+use crate::parser::{normalize_mac, InterfaceEntry};
+use crate::reachability::run_check;
+use crate::report::DeviceInterfaces;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Default number of APs walked at once
+pub const DEFAULT_SNMP_CONCURRENCY: usize = 8;
+
+/// Default seconds before a walk is killed
+pub const DEFAULT_SNMP_TIMEOUT_SECS: u64 = 15;
+
+/// IF-MIB ifDescr: interface names (`wifi0.1`, ...)
+pub const IF_DESCR_OID: &str = ".1.3.6.1.2.1.2.2.1.2";
+
+/// IF-MIB ifPhysAddress: interface MACs, the BSSIDs of the wifi interfaces
+pub const IF_PHYS_ADDRESS_OID: &str = ".1.3.6.1.2.1.2.2.1.6";
+
+/// Placeholder replaced with the walked column in the walk command
+const OID_PLACEHOLDER: &str = "{oid}";
+
+/// Default walk, SNMPv2c with numeric OIDs and bare values
+pub fn default_walk_command() -> Vec<String> {
+    ["snmpwalk", "-v2c", "-c", "public", "-On", "-Oq", "-t", "2", "-r", "1", "{ip}", OID_PLACEHOLDER]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Direct SNMP collection from APs the CLI command failed on
+#[derive(Debug, Clone, Default)]
+pub struct SnmpFallback {
+    /// Program and arguments printing one `<oid> <value>` line per row
+    pub walk_command: Vec<String>,
+    /// Vendor column with each interface's SSID, indexed by ifIndex
+    pub ssid_oid: Option<String>,
+    pub concurrency: usize,
+    pub timeout: Duration,
+}
+
+/// Values of a walked table column by ifIndex, the last component of each OID
+pub fn parse_walk(output: &str) -> BTreeMap<u32, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (oid, value) = line.trim().split_once(char::is_whitespace).unwrap_or((line.trim(), ""));
+            let index = oid.rsplit('.').next()?.parse().ok()?;
+            Some((index, value.trim().trim_matches('"').trim().to_string()))
+        })
+        .collect()
+}
+
+/// ifPhysAddress as printed by net-snmp (`0:19:77:aa:bb:1`, or hex octets
+/// `00 19 77 AA BB 01`), in the same form as the CLI parsers' MACs
+pub fn snmp_mac(value: &str) -> String {
+    let octets: Vec<&str> = value.split([':', ' ', '-']).filter(|o| !o.is_empty()).collect();
+    if octets.len() == 6 && octets.iter().all(|o| o.len() <= 2 && o.chars().all(|c| c.is_ascii_hexdigit())) {
+        let padded: String = octets.iter().map(|o| format!("{:0>2}", o)).collect();
+        return normalize_mac(&padded);
+    }
+    normalize_mac(value)
+}
+
+/// Wifi interfaces (`wifiN.M`, or any with an SSID) from the walked columns,
+/// as access-mode entries like the CLI parsers produce
+pub fn interfaces_from_columns(
+    names: &BTreeMap<u32, String>,
+    macs: &BTreeMap<u32, String>,
+    ssids: &BTreeMap<u32, String>,
+) -> Vec<InterfaceEntry> {
+    names
+        .iter()
+        .filter_map(|(index, name)| {
+            let mac = snmp_mac(macs.get(index)?);
+            let ssid = ssids.get(index).cloned().unwrap_or_default();
+            let is_vap = name.starts_with("wifi") && name.contains('.');
+            if mac.is_empty() || mac == "00:00:00:00:00:00" || (ssid.is_empty() && !is_vap) {
+                return None;
+            }
+            Some(InterfaceEntry {
+                name: name.clone(),
+                mac,
+                mode: "access".to_string(),
+                radio: name.split('.').next().filter(|_| is_vap).unwrap_or_default().to_string(),
+                ssid,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Walk the interfaces of every AP whose CLI command failed. APs without an
+/// IP, or that don't answer over SNMP either, are left out.
+pub async fn collect_fallback(
+    devices: &[serde_json::Value],
+    statuses: &[DeviceStatus],
+    fallback: &SnmpFallback,
+) -> Vec<DeviceInterfaces> {
+    let targets: Vec<(i64, String, String)> = statuses
+        .iter()
+        .filter(|s| s.status != CliStatus::Success)
+        .filter_map(|s| {
+            let device = devices.iter().find(|d| d.get("id").and_then(|v| v.as_i64()) == Some(s.device_id))?;
+            let ip = device.get("ip_address").and_then(|v| v.as_str())?;
+            Some((s.device_id, s.hostname.clone(), ip.to_string()))
+        })
+        .collect();
+    if targets.is_empty() {
+        return Vec::new();
+    }
+
+    let permits = Arc::new(Semaphore::new(fallback.concurrency.max(1)));
+    let mut set = JoinSet::new();
+    for (device_id, hostname, ip) in targets.iter().cloned() {
+        let permits = permits.clone();
+        let fallback = fallback.clone();
+        set.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok();
+            walk_ap(device_id, hostname, &ip, &fallback).await
+        });
+    }
+
+    let mut collected = Vec::new();
+    while let Some(result) = set.join_next().await {
+        if let Ok(Some(ap)) = result {
+            collected.push(ap);
+        }
+    }
+    collected.sort_by(|a, b| a.hostname.cmp(&b.hostname).then(a.device_id.cmp(&b.device_id)));
+
+    println!(
+        "Collected {} BSSIDs over SNMP from {} of {} APs the CLI command failed on",
+        collected.iter().map(|ap| ap.interfaces.len()).sum::<usize>(),
+        collected.len(),
+        targets.len()
+    );
+    collected
+}
+
+async fn walk_ap(device_id: i64, hostname: String, ip: &str, fallback: &SnmpFallback) -> Option<DeviceInterfaces> {
+    let walk = |oid: &str| {
+        let command: Vec<String> = fallback.walk_command.iter().map(|arg| arg.replace(OID_PLACEHOLDER, oid)).collect();
+        async move { run_check(&command, ip, fallback.timeout).await }
+    };
+
+    let names = walk(IF_DESCR_OID).await?;
+    let macs = walk(IF_PHYS_ADDRESS_OID).await?;
+    let ssids = match &fallback.ssid_oid {
+        Some(oid) => walk(oid).await.unwrap_or_default(),
+        None => String::new(),
+    };
+
+    let interfaces = interfaces_from_columns(&parse_walk(&names), &parse_walk(&macs), &parse_walk(&ssids));
+    if interfaces.is_empty() {
+        return None;
+    }
+    Some(DeviceInterfaces {
+        device_id,
+        hostname,
+        output: [names, macs, ssids].join("\n"),
+        interfaces,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interfaces_from_walk() {
+        let names = parse_walk(
+            ".1.3.6.1.2.1.2.2.1.2.1 mgt0\n\
+             .1.3.6.1.2.1.2.2.1.2.7 wifi0.1\n\
+             .1.3.6.1.2.1.2.2.1.2.8 wifi1.1\n\
+             .1.3.6.1.2.1.2.2.1.2.9 wifi1.2\n",
+        );
+        let macs = parse_walk(
+            ".1.3.6.1.2.1.2.2.1.6.1 0:19:77:aa:bb:0\n\
+             .1.3.6.1.2.1.2.2.1.6.7 0:19:77:aa:bb:1\n\
+             .1.3.6.1.2.1.2.2.1.6.8 \"00 19 77 AA BB 11 \"\n\
+             .1.3.6.1.2.1.2.2.1.6.9 \n",
+        );
+        let ssids = parse_walk("AH-INTERFACE-MIB::ahSSIDName.7 \"Corp\"\nAH-INTERFACE-MIB::ahSSIDName.8 \"Guest\"\n");
+
+        let interfaces = interfaces_from_columns(&names, &macs, &ssids);
+        let summary: Vec<_> = interfaces
+            .iter()
+            .map(|i| (i.name.as_str(), i.mac.as_str(), i.ssid.as_str(), i.radio.as_str(), i.is_access()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("wifi0.1", "00:19:77:AA:BB:01", "Corp", "wifi0", true),
+                ("wifi1.1", "00:19:77:AA:BB:11", "Guest", "wifi1", true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::api::{get_connected_aps, XiqApi};
+use crate::cli_status::{CliResult, CliStatus};
+use crate::db::Database;
+use crate::progress::{self, ProgressEvent};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// APs sent the command together in each chunk of a `--spread` collection
+pub const DEFAULT_CHUNK_SIZE: usize = 100;
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Start of each chunk after the first, evenly spaced over `remaining` so
+/// the last chunk starts before the window ends
+pub fn chunk_offsets(chunks: usize, remaining: Duration) -> Vec<Duration> {
+    let interval = remaining.checked_div(chunks as u32).unwrap_or_default();
+    (0..chunks).map(|chunk| interval * chunk as u32).collect()
+}
+
+/// Send a CLI command to every connected AP in chunks spread over `window`,
+/// saving each chunk's output to the database as it arrives. An unfinished
+/// collection of the same command is resumed: APs that already answered are
+/// skipped and the rest are spread over what is left of its window. One
+/// whose window has already ended is abandoned for a new collection, so
+/// stale output isn't mixed into a fresh one.
+pub async fn dispatch_spread(
+    api: &(impl XiqApi + ?Sized),
+    db: &Database,
+    devices: &[serde_json::Value],
+    command: &str,
+    window: Duration,
+    chunk_size: usize,
+) -> Result<Vec<CliResult>> {
+    let connected_aps = get_connected_aps(devices);
+    if connected_aps.is_empty() {
+        println!("No connected APs found.");
+        return Ok(Vec::new());
+    }
+
+    let collection = match db.unfinished_spread(command).await? {
+        Some(collection) if collection.deadline >= now_secs() => {
+            println!("Resuming spread collection {} of '{}'", collection.id, command);
+            collection
+        }
+        expired => {
+            if let Some(collection) = expired {
+                println!("Spread collection {} of '{}' ran past its window; starting a new one", collection.id, command);
+                db.finish_spread(collection.id).await?;
+            }
+            db.create_spread(command, now_secs() + window.as_secs() as i64).await?
+        }
+    };
+
+    let collected: HashMap<i64, String> = db.spread_outputs(collection.id).await?.into_iter().collect();
+    let mut results: Vec<CliResult> = connected_aps
+        .iter()
+        .filter_map(|(id, _)| {
+            let output = collected.get(id)?.clone();
+            Some(CliResult { device_id: *id, status: CliStatus::Success, output })
+        })
+        .collect();
+    let pending: Vec<i64> = connected_aps
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| !collected.contains_key(id))
+        .collect();
+
+    let remaining = Duration::from_secs(collection.deadline.saturating_sub(now_secs()).max(0) as u64);
+    let chunks: Vec<&[i64]> = pending.chunks(chunk_size.max(1)).collect();
+    let offsets = chunk_offsets(chunks.len(), remaining);
+
+    println!(
+        "\n=== Spreading '{}' over {} chunks of up to {} APs ({} of {} APs already collected, {}s left) ===\n",
+        command,
+        chunks.len(),
+        chunk_size,
+        results.len(),
+        connected_aps.len(),
+        remaining.as_secs()
+    );
+
+    let started = tokio::time::Instant::now();
+    for (index, (chunk, offset)) in chunks.iter().zip(offsets).enumerate() {
+        tokio::time::sleep_until(started + offset).await;
+
+        println!("Chunk {}/{}: sending command to {} APs", index + 1, chunks.len(), chunk.len());
+        let chunk_results = api.send_cli_command_with_status(chunk, command).await?;
+        db.record_spread_outputs(collection.id, &chunk_results).await?;
+        progress::emit(ProgressEvent::ChunkCompleted {
+            chunk: index + 1,
+            chunks: chunks.len(),
+            devices: chunk_results.len(),
+            percent: progress::percent(index + 1, chunks.len()),
+        });
+        results.extend(chunk_results);
+    }
+
+    db.finish_spread(collection.id).await?;
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{FakeApi, FakeCall};
+    use crate::db::{DEFAULT_POOL_SIZE, MEMORY_DB};
+    use std::path::Path;
+
+    #[test]
+    fn test_chunk_offsets() {
+        let offsets = chunk_offsets(4, Duration::from_secs(6 * 3600));
+        assert_eq!(offsets.iter().map(Duration::as_secs).collect::<Vec<_>>(), vec![0, 5400, 10800, 16200]);
+        assert!(chunk_offsets(0, Duration::from_secs(60)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_spread_resumes() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let devices: Vec<serde_json::Value> = (1..=5)
+            .map(|id| serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": true }))
+            .collect();
+        let api = (1..=5)
+            .filter(|id| *id != 4)
+            .fold(FakeApi::new(devices.clone()), |api, id| api.with_output("show interface", id, &format!("out-{}", id)))
+            .with_status("show interface", 4, CliStatus::Timeout, "Command timed out");
+
+        // A previous invocation got through AP-1 and AP-2 before it was stopped
+        let collection = db.create_spread("show interface", now_secs() + 1).await.unwrap();
+        let earlier = [1, 2].map(|id| CliResult { device_id: id, status: CliStatus::Success, output: format!("old-{}", id) });
+        db.record_spread_outputs(collection.id, &earlier).await.unwrap();
+
+        let results = dispatch_spread(&api, &db, &devices, "show interface", Duration::ZERO, 2).await.unwrap();
+        let sent: Vec<Vec<i64>> = api
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                FakeCall::Cli { device_ids, .. } => Some(device_ids),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sent, vec![vec![3, 4], vec![5]]);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].output, "old-1");
+        assert_eq!(results.iter().filter(|r| r.succeeded()).count(), 4);
+
+        // Finished, with the failed AP left out of the saved progress
+        assert_eq!(db.unfinished_spread("show interface").await.unwrap(), None);
+        assert_eq!(db.spread_outputs(collection.id).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_spread_restarts_expired() {
+        let db = Database::new(Path::new(MEMORY_DB), DEFAULT_POOL_SIZE).await.unwrap();
+        let devices: Vec<serde_json::Value> = (1..=3)
+            .map(|id| serde_json::json!({ "id": id, "hostname": format!("AP-{}", id), "device_function": "AP", "connected": true }))
+            .collect();
+        let api = (1..=3).fold(FakeApi::new(devices.clone()), |api, id| api.with_output("show interface", id, &format!("out-{}", id)));
+
+        // Yesterday's collection got through AP-1; its output is out of date
+        let expired = db.create_spread("show interface", now_secs() - 86400).await.unwrap();
+        db.record_spread_outputs(expired.id, &[CliResult { device_id: 1, status: CliStatus::Success, output: "old-1".to_string() }])
+            .await
+            .unwrap();
+
+        // Another account's unfinished collection isn't resumed either
+        let other = db.clone().with_owner(Some(200));
+        other.create_spread("show interface", now_secs() + 3600).await.unwrap();
+
+        let results = dispatch_spread(&api, &db, &devices, "show interface", Duration::ZERO, 3).await.unwrap();
+        assert_eq!(results.iter().map(|r| r.output.as_str()).collect::<Vec<_>>(), vec!["out-1", "out-2", "out-3"]);
+        assert_eq!(db.unfinished_spread("show interface").await.unwrap(), None);
+        assert!(other.unfinished_spread("show interface").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::cli_status::{CliResult, CliStatus};
+use anyhow::Context;
+use russh::client;
+use russh::keys::{known_hosts, load_secret_key, PrivateKeyWithHashAlg, PublicKey, PublicKeyOrCertificate};
+use russh::{ChannelMsg, Disconnect};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Default number of APs logged into at once
+pub const DEFAULT_SSH_CONCURRENCY: usize = 8;
+
+/// Default seconds before an SSH session is killed
+pub const DEFAULT_SSH_TIMEOUT_SECS: u64 = 30;
+
+/// `managed_by` values of APs collected over SSH by default
+pub const DEFAULT_SSH_MANAGED_BY: &[&str] = &["LOCAL"];
+
+/// Default SSH port of the APs
+pub const DEFAULT_SSH_PORT: u16 = 22;
+
+/// Direct SSH collection from locally managed APs
+#[derive(Debug, Clone, Default)]
+pub struct SshCollector {
+    pub username: String,
+    pub password: Option<String>,
+    /// Private key for public key logins, tried before the password
+    pub key_file: Option<PathBuf>,
+    pub port: u16,
+    /// known_hosts file the APs' host keys are checked against; the user's
+    /// `~/.ssh/known_hosts` when unset
+    pub known_hosts: Option<PathBuf>,
+    /// Record the host keys of APs missing from known_hosts instead of
+    /// refusing to log in
+    pub accept_new_hosts: bool,
+    pub concurrency: usize,
+    pub timeout: Duration,
+}
+
+/// APs in `devices` whose `managed_by` is one of `managed_by`
+/// (case-insensitive), which XIQ can't send CLI commands to
+pub fn locally_managed_aps(devices: &[serde_json::Value], managed_by: &[String]) -> Vec<i64> {
+    devices
+        .iter()
+        .filter(|d| d.get("device_function").and_then(|v| v.as_str()) == Some("AP"))
+        .filter(|d| {
+            d.get("managed_by")
+                .and_then(|v| v.as_str())
+                .is_some_and(|m| managed_by.iter().any(|wanted| wanted.eq_ignore_ascii_case(m)))
+        })
+        .filter_map(|d| d.get("id")?.as_i64())
+        .collect()
+}
+
+/// Run `command` on each of `device_ids` over SSH, in the same shape as the
+/// `:cli` results so the output goes through the same parsers. APs without
+/// an IP address are unreachable.
+pub async fn collect_over_ssh(
+    devices: &[serde_json::Value],
+    device_ids: &[i64],
+    command: &str,
+    collector: &SshCollector,
+) -> Vec<CliResult> {
+    let permits = Arc::new(Semaphore::new(collector.concurrency.max(1)));
+    let mut set = JoinSet::new();
+    for device_id in device_ids.iter().copied() {
+        let ip = devices
+            .iter()
+            .find(|d| d.get("id").and_then(|v| v.as_i64()) == Some(device_id))
+            .and_then(|d| d.get("ip_address")?.as_str())
+            .map(str::to_string);
+        let permits = permits.clone();
+        let collector = collector.clone();
+        let command = command.to_string();
+        set.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok();
+            match ip {
+                Some(ip) => run_ssh(device_id, &ip, &command, &collector).await,
+                None => CliResult { device_id, status: CliStatus::Unreachable, output: "no IP address".to_string() },
+            }
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(result) = set.join_next().await {
+        if let Ok(result) = result {
+            results.push(result);
+        }
+    }
+    results.sort_by_key(|r| r.device_id);
+
+    for failed in results.iter().filter(|r| !r.succeeded()) {
+        let message = failed.output.lines().next().unwrap_or_default();
+        eprintln!("Warning: SSH collection from AP {} failed: {} {}", failed.device_id, failed.status.label(), message);
+    }
+
+    println!(
+        "Collected '{}' over SSH from {} of {} locally managed APs",
+        command,
+        results.iter().filter(|r| r.succeeded()).count(),
+        device_ids.len()
+    );
+    results
+}
+
+async fn run_ssh(device_id: i64, ip: &str, command: &str, collector: &SshCollector) -> CliResult {
+    let (status, output) = match tokio::time::timeout(collector.timeout, exec(ip, command, collector)).await {
+        Err(_) => (CliStatus::Timeout, format!("SSH session to {} timed out", ip)),
+        Ok(Ok((0, output))) => (CliStatus::Success, output),
+        Ok(Ok((code, output))) => (CliStatus::Error, format!("exited with status {}: {}", code, output.trim())),
+        Ok(Err(SshError::Login(message))) => (CliStatus::Unreachable, message),
+        Ok(Err(SshError::Session(message))) => (CliStatus::Error, message),
+    };
+    CliResult { device_id, status, output }
+}
+
+/// Why an SSH collection failed
+enum SshError {
+    /// Connecting, the host key check or authentication failed
+    Login(String),
+    /// Logged in, but the command couldn't be run
+    Session(String),
+}
+
+/// Log into `ip`, run `command` and return its exit status and standard output
+async fn exec(ip: &str, command: &str, collector: &SshCollector) -> Result<(u32, String), SshError> {
+    let handler = HostKeyCheck {
+        host: ip.to_string(),
+        port: collector.port,
+        known_hosts: collector.known_hosts.clone(),
+        accept_new: collector.accept_new_hosts,
+    };
+    let config = Arc::new(client::Config::default());
+    let mut session = client::connect(config, (ip, collector.port), handler)
+        .await
+        .map_err(|e| SshError::Login(format!("{:#}", e)))?;
+    authenticate(&mut session, collector).await?;
+
+    let session_error = |e: russh::Error| SshError::Session(e.to_string());
+    let mut channel = session.channel_open_session().await.map_err(session_error)?;
+    channel.exec(true, command).await.map_err(session_error)?;
+    let mut stdout = Vec::new();
+    let mut exit_status = None;
+    // The exit status can arrive before the last data, so read until the channel closes
+    while let Some(message) = channel.wait().await {
+        match message {
+            ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+            ChannelMsg::ExitStatus { exit_status: code } => exit_status = Some(code),
+            _ => {}
+        }
+    }
+    let _ = session.disconnect(Disconnect::ByApplication, "", "en").await;
+
+    let exit_status = exit_status.ok_or_else(|| SshError::Session("command ended without an exit status".to_string()))?;
+    Ok((exit_status, String::from_utf8_lossy(&stdout).into_owned()))
+}
+
+/// Try the key file, then the password
+async fn authenticate(session: &mut client::Handle<HostKeyCheck>, collector: &SshCollector) -> Result<(), SshError> {
+    let login_error = |e: russh::Error| SshError::Login(e.to_string());
+    if let Some(path) = &collector.key_file {
+        let key = load_secret_key(path, None)
+            .map_err(|e| SshError::Login(format!("Failed to load SSH key {}: {}", path.display(), e)))?;
+        let hash_alg = session.best_supported_rsa_hash().await.map_err(login_error)?.flatten();
+        let key = PrivateKeyWithHashAlg::new(Arc::new(key), hash_alg);
+        if session.authenticate_publickey(&collector.username, key).await.map_err(login_error)?.success() {
+            return Ok(());
+        }
+    }
+    if let Some(password) = &collector.password {
+        if session.authenticate_password(&collector.username, password).await.map_err(login_error)?.success() {
+            return Ok(());
+        }
+    }
+    Err(SshError::Login(format!("authentication as {} failed", collector.username)))
+}
+
+/// Accepts an AP's host key only if known_hosts has it, or records it when
+/// new hosts are accepted. A changed key is always refused.
+struct HostKeyCheck {
+    host: String,
+    port: u16,
+    known_hosts: Option<PathBuf>,
+    accept_new: bool,
+}
+
+impl client::Handler for HostKeyCheck {
+    type Error = anyhow::Error;
+
+    async fn check_server_key(&mut self, server_public_key: &PublicKeyOrCertificate) -> anyhow::Result<bool> {
+        let PublicKeyOrCertificate::PublicKey { key, .. } = server_public_key else {
+            anyhow::bail!("{} offered a host certificate, which isn't supported", self.host);
+        };
+        check_host_key(&self.host, self.port, key, self.known_hosts.as_deref(), self.accept_new)?;
+        Ok(true)
+    }
+}
+
+/// Check `key` against known_hosts (the user's when `known_hosts` is None)
+fn check_host_key(host: &str, port: u16, key: &PublicKey, known_hosts: Option<&Path>, accept_new: bool) -> anyhow::Result<()> {
+    let known = match known_hosts {
+        Some(path) => known_hosts::check_known_hosts_path(host, port, key, path),
+        None => known_hosts::check_known_hosts(host, port, key),
+    };
+    match known {
+        Ok(true) => Ok(()),
+        Ok(false) if accept_new => {
+            match known_hosts {
+                Some(path) => known_hosts::learn_known_hosts_path(host, port, key, path),
+                None => known_hosts::learn_known_hosts(host, port, key),
+            }
+            .with_context(|| format!("Failed to record the host key of {}", host))?;
+            Ok(())
+        }
+        Ok(false) => anyhow::bail!("host key of {} isn't in known_hosts (set accept_new_hosts to record it)", host),
+        Err(russh::keys::Error::KeyChanged { line }) => {
+            anyhow::bail!("host key of {} doesn't match known_hosts line {}", host, line)
+        }
+        Err(e) => Err(e).context("Failed to read known_hosts"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collect_over_ssh() {
+        let devices = vec![
+            serde_json::json!({ "id": 1, "device_function": "AP", "managed_by": "LOCAL", "ip_address": "127.0.0.1" }),
+            serde_json::json!({ "id": 2, "device_function": "AP", "managed_by": "XIQ", "ip_address": "10.0.0.2" }),
+            serde_json::json!({ "id": 3, "device_function": "AP", "managed_by": "local" }),
+        ];
+        let local = locally_managed_aps(&devices, &["LOCAL".to_string()]);
+        assert_eq!(local, vec![1, 3]);
+
+        // Nothing listens on the port of a listener that was just closed
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let collector = SshCollector {
+            username: "admin".to_string(),
+            password: Some("secret".to_string()),
+            port,
+            concurrency: 2,
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        };
+        let results = collect_over_ssh(&devices, &local, "show interface", &collector).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status, CliStatus::Unreachable);
+        assert_eq!(results[1], CliResult { device_id: 3, status: CliStatus::Unreachable, output: "no IP address".to_string() });
+    }
+
+    #[test]
+    fn test_check_host_key() {
+        let key = |base64| russh::keys::parse_public_key_base64(base64).unwrap();
+        let ap = key("AAAAC3NzaC1lZDI1NTE5AAAAIF5tuEXWiQbPCAwiAkF6/m1PYF/jRbSRkZIp78S2neJ+");
+        let other = key("AAAAC3NzaC1lZDI1NTE5AAAAIIaNOnErBuuz7H/ZganLaaEXWpLkgn6BHuua6D8m00Zz");
+        let path = std::env::temp_dir().join(format!("xiq-known-hosts-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Unknown hosts are refused unless new hosts are accepted, which records the key
+        assert!(check_host_key("10.0.0.1", 22, &ap, Some(&path), false).is_err());
+        check_host_key("10.0.0.1", 22, &ap, Some(&path), true).unwrap();
+        check_host_key("10.0.0.1", 22, &ap, Some(&path), false).unwrap();
+        // A changed key is refused even when new hosts are accepted
+        let changed = check_host_key("10.0.0.1", 22, &other, Some(&path), true).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(changed.to_string().contains("doesn't match known_hosts"), "{}", changed);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::config::SsidConfig;
+use crate::csv_output::{write_csv, CsvProfile};
+use crate::output::LineEnding;
+use crate::parser::{table_cells, table_columns};
+use crate::report::DeviceInterfaces;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Report of the BSSIDs and APs failing the SSID allowlist
+pub const SSID_COMPLIANCE_FILE: &str = "ssid-compliance.csv";
+
+/// Security settings of one SSID on an AP
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SsidSecurity {
+    pub ssid: String,
+    /// `Open`, `PSK`, `SAE`, `802.1X` or `OWE`; the raw value when unrecognized
+    pub security: String,
+    /// `AES`, `TKIP`, `GCMP`, `none`, or empty when not reported
+    pub encryption: String,
+    /// `yes` when the SSID is broadcast in beacons, `no` when hidden
+    pub broadcast: String,
+}
+
+/// Security mode from an access security value such as `wpa2-aes-8021x`
+pub fn security_mode(raw: &str) -> String {
+    let lower = raw.trim().to_lowercase();
+    let mode = if lower.contains("owe") {
+        "OWE"
+    } else if lower.contains("8021x") || lower.contains("802.1x") || lower.contains("eap") || lower.contains("enterprise") {
+        "802.1X"
+    } else if lower.contains("sae") {
+        "SAE"
+    } else if lower.contains("psk") || lower.contains("personal") {
+        "PSK"
+    } else if lower.is_empty() || lower == "open" || lower == "none" {
+        "Open"
+    } else {
+        return raw.trim().to_string();
+    };
+    mode.to_string()
+}
+
+/// Cipher named in a security or encryption value
+pub fn encryption(raw: &str) -> Option<&'static str> {
+    let lower = raw.to_lowercase();
+    if lower.contains("gcmp") {
+        Some("GCMP")
+    } else if lower.contains("aes") || lower.contains("ccmp") {
+        Some("AES")
+    } else if lower.contains("tkip") {
+        Some("TKIP")
+    } else if lower == "none" || lower == "open" {
+        Some("none")
+    } else {
+        None
+    }
+}
+
+/// What a `show ssid` table column holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Name,
+    Security,
+    Encryption,
+    Broadcast,
+    Hidden,
+    Other,
+}
+
+fn column_kind(header: &str) -> Column {
+    let header = header.to_lowercase();
+    if header == "name" || header == "ssid" || header == "ssid name" {
+        Column::Name
+    } else if header.contains("security") || header.contains("auth") || header.contains("key mgmt") {
+        Column::Security
+    } else if header.contains("encrypt") || header.contains("cipher") {
+        Column::Encryption
+    } else if header.contains("broadcast") {
+        Column::Broadcast
+    } else if header.contains("hide") || header.contains("hidden") {
+        Column::Hidden
+    } else {
+        Column::Other
+    }
+}
+
+fn is_yes(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "yes" | "y" | "enabled" | "enable" | "true" | "on")
+}
+
+/// Parse a `show ssid` table. Columns are located by the header positions,
+/// so SSIDs with spaces are kept whole.
+pub fn parse_show_ssid(output: &str) -> Vec<SsidSecurity> {
+    let mut lines = output.lines();
+    let Some(header) = lines.by_ref().find(|line| {
+        let kinds: Vec<_> = table_columns(line).iter().map(|(_, h)| column_kind(h)).collect();
+        kinds.contains(&Column::Name) && kinds.contains(&Column::Security)
+    }) else {
+        return Vec::new();
+    };
+    let columns = table_columns(header);
+    let kinds: Vec<_> = columns.iter().map(|(_, h)| column_kind(h)).collect();
+
+    let mut entries = Vec::new();
+    for line in lines {
+        if line.trim().is_empty() || line.trim_start().starts_with('-') {
+            continue;
+        }
+
+        let mut entry = SsidSecurity::default();
+        for (kind, value) in kinds.iter().zip(table_cells(line, &columns)) {
+            match kind {
+                Column::Name => entry.ssid = value.to_string(),
+                Column::Security => {
+                    entry.security = security_mode(value);
+                    if entry.encryption.is_empty() {
+                        entry.encryption = encryption(value).unwrap_or_default().to_string();
+                    }
+                }
+                Column::Encryption => {
+                    if let Some(cipher) = encryption(value) {
+                        entry.encryption = cipher.to_string();
+                    }
+                }
+                Column::Broadcast => entry.broadcast = if is_yes(value) { "yes" } else { "no" }.to_string(),
+                Column::Hidden => entry.broadcast = if is_yes(value) { "no" } else { "yes" }.to_string(),
+                Column::Other => {}
+            }
+        }
+        if entry.security == "Open" && entry.encryption.is_empty() {
+            entry.encryption = "none".to_string();
+        }
+        if !entry.ssid.is_empty() {
+            entries.push(entry);
+        }
+    }
+
+    entries
+}
+
+/// Copy each SSID's security settings onto the BSSIDs broadcasting it
+pub fn apply_ssid_security(results: &mut [DeviceInterfaces], security: &[(i64, Vec<SsidSecurity>)]) {
+    let by_device: HashMap<i64, &Vec<SsidSecurity>> = security.iter().map(|(id, ssids)| (*id, ssids)).collect();
+
+    for device in results.iter_mut() {
+        let Some(ssids) = by_device.get(&device.device_id) else {
+            continue;
+        };
+        for iface in &mut device.interfaces {
+            if let Some(ssid) = ssids.iter().find(|s| s.ssid == iface.ssid) {
+                iface.security = ssid.security.clone();
+                iface.encryption = ssid.encryption.clone();
+                iface.broadcast = ssid.broadcast.clone();
+            }
+        }
+    }
+}
+
+/// How an AP breaks the configured SSID list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SsidIssue {
+    /// A BSSID broadcasts an SSID that isn't in `allowed`
+    NotAllowed,
+    /// A required SSID isn't broadcast by the AP at all
+    Missing,
+}
+
+impl SsidIssue {
+    pub fn label(self) -> &'static str {
+        match self {
+            SsidIssue::NotAllowed => "not allowed",
+            SsidIssue::Missing => "missing",
+        }
+    }
+}
+
+/// One SSID compliance finding; `interface` and `mac` are empty for missing SSIDs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SsidViolation {
+    pub device_id: i64,
+    pub hostname: String,
+    pub ssid: String,
+    pub interface: String,
+    pub mac: String,
+    pub issue: SsidIssue,
+}
+
+/// Check every collected AP's access-mode SSIDs against `[ssid] allowed`,
+/// `required` and `required_by_policy`
+pub fn ssid_compliance(devices: &[serde_json::Value], results: &[DeviceInterfaces], config: &SsidConfig) -> Vec<SsidViolation> {
+    let mut violations = Vec::new();
+    for device in results {
+        let access: Vec<_> = device.interfaces.iter().filter(|i| i.is_access()).collect();
+
+        if !config.allowed.is_empty() {
+            for iface in access.iter().filter(|i| !config.allowed.contains(&i.ssid)) {
+                violations.push(SsidViolation {
+                    device_id: device.device_id,
+                    hostname: device.hostname.clone(),
+                    ssid: iface.ssid.clone(),
+                    interface: iface.name.clone(),
+                    mac: iface.mac.clone(),
+                    issue: SsidIssue::NotAllowed,
+                });
+            }
+        }
+
+        let policy = devices
+            .iter()
+            .find(|d| d.get("id").and_then(|id| id.as_i64()) == Some(device.device_id))
+            .and_then(|d| d.get("network_policy_name"))
+            .and_then(|p| p.as_str());
+        let mut required: BTreeSet<&String> = config.required.iter().collect();
+        if let Some(ssids) = policy.and_then(|p| config.required_by_policy.get(p)) {
+            required.extend(ssids);
+        }
+        for ssid in required.into_iter().filter(|ssid| !access.iter().any(|i| &i.ssid == *ssid)) {
+            violations.push(SsidViolation {
+                device_id: device.device_id,
+                hostname: device.hostname.clone(),
+                ssid: ssid.clone(),
+                interface: String::new(),
+                mac: String::new(),
+                issue: SsidIssue::Missing,
+            });
+        }
+    }
+    violations
+}
+
+pub fn write_ssid_compliance(
+    violations: &[SsidViolation],
+    path: &Path,
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let rows: Vec<Vec<String>> = violations
+        .iter()
+        .map(|v| {
+            vec![
+                v.hostname.clone(),
+                v.device_id.to_string(),
+                v.ssid.clone(),
+                v.interface.clone(),
+                v.mac.clone(),
+                v.issue.label().to_string(),
+            ]
+        })
+        .collect();
+    write_csv(path, &["Device", "DeviceID", "SSID", "Interface", "MAC", "Issue"], &rows, line_ending, profile)
+}
+
+pub fn print_ssid_compliance(violations: &[SsidViolation]) {
+    if violations.is_empty() {
+        return;
+    }
+
+    println!("\n=== SSID Compliance Violations ({}) ===", violations.len());
+    println!("{:<24} {:<12} {:<20} {:<12} SSID", "Device", "Interface", "MAC", "Issue");
+    for v in violations {
+        println!(
+            "{:<24} {:<12} {:<20} {:<12} {}",
+            v.hostname,
+            if v.interface.is_empty() { "-" } else { &v.interface },
+            if v.mac.is_empty() { "-" } else { &v.mac },
+            v.issue.label(),
+            v.ssid
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_parse_show_ssid() {
+        let output = "\
+Total SSID num=3
+No.  Name              Access Security    Hide SSID  Frequency
+---  ----------------  -----------------  ---------  ---------
+1    Corp              wpa2-aes-8021x     No         dual
+2    Acme Guest WiFi   open               No         dual
+3    IoT               wpa3-sae           Yes        5g
+";
+        let ssids = parse_show_ssid(output);
+        assert_eq!(ssids.len(), 3);
+        assert_eq!(
+            ssids[0],
+            SsidSecurity {
+                ssid: "Corp".into(),
+                security: "802.1X".into(),
+                encryption: "AES".into(),
+                broadcast: "yes".into()
+            }
+        );
+        assert_eq!(ssids[1].ssid, "Acme Guest WiFi");
+        assert_eq!((ssids[1].security.as_str(), ssids[1].encryption.as_str()), ("Open", "none"));
+        assert_eq!((ssids[2].security.as_str(), ssids[2].broadcast.as_str()), ("SAE", "no"));
+
+        assert_eq!(security_mode("wpa2-tkip-psk"), "PSK");
+        assert_eq!(security_mode("enhanced-open-owe"), "OWE");
+        assert!(parse_show_ssid("ERROR: unknown command").is_empty());
+    }
+
+    #[test]
+    fn test_apply_ssid_security() {
+        let mut results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                InterfaceEntry { ssid: "Corp".into(), ..Default::default() },
+                InterfaceEntry { ssid: "Other".into(), ..Default::default() },
+            ],
+        }];
+        let security = vec![(
+            1,
+            vec![SsidSecurity { ssid: "Corp".into(), security: "PSK".into(), encryption: "AES".into(), broadcast: "yes".into() }],
+        )];
+
+        apply_ssid_security(&mut results, &security);
+        assert_eq!(results[0].interfaces[0].security, "PSK");
+        assert_eq!(results[0].interfaces[0].broadcast, "yes");
+        assert_eq!(results[0].interfaces[1].security, "");
+    }
+
+    #[test]
+    fn test_ssid_compliance() {
+        let access = |ssid: &str| InterfaceEntry { name: "wifi0.1".into(), mode: "access".into(), ssid: ssid.into(), ..Default::default() };
+        let results = vec![
+            DeviceInterfaces { device_id: 1, hostname: "AP-1".to_string(), output: String::new(), interfaces: vec![access("Corp"), access("Rogue")] },
+            DeviceInterfaces { device_id: 2, hostname: "AP-2".to_string(), output: String::new(), interfaces: vec![access("Corp")] },
+        ];
+        let devices = vec![serde_json::json!({ "id": 2, "network_policy_name": "HQ" })];
+        let config = SsidConfig {
+            allowed: vec!["Corp".into(), "Guest".into()],
+            required: vec!["Corp".into()],
+            required_by_policy: HashMap::from([("HQ".to_string(), vec!["Guest".to_string()])]),
+            ..Default::default()
+        };
+
+        let violations = ssid_compliance(&devices, &results, &config);
+        let found: Vec<(i64, &str, SsidIssue)> = violations.iter().map(|v| (v.device_id, v.ssid.as_str(), v.issue)).collect();
+        assert_eq!(found, vec![(1, "Rogue", SsidIssue::NotAllowed), (2, "Guest", SsidIssue::Missing)]);
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+
+/// Per-interface detail command used by `stats`; `{interface}` is replaced
+/// with each access interface's name
+pub const DEFAULT_STATS_COMMAND: &str = "show interface {interface}";
+
+/// Traffic and client counters of one BSSID at the time it was polled
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InterfaceCounters {
+    pub device_id: i64,
+    pub hostname: String,
+    pub interface: String,
+    pub mac: String,
+    pub rx_bytes: Option<i64>,
+    pub tx_bytes: Option<i64>,
+    pub clients: Option<i64>,
+}
+
+/// Leading integer of a counter value such as `1234567` or `1,234,567 bytes`
+fn parse_count(value: &str) -> Option<i64> {
+    let digits: String = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == ',')
+        .filter(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// RX bytes, TX bytes and associated clients from interface detail output,
+/// read from `key=value;` pairs or `key: value` lines
+pub fn parse_counters(output: &str) -> (Option<i64>, Option<i64>, Option<i64>) {
+    let (mut rx, mut tx, mut clients) = (None, None, None);
+
+    let fields = output
+        .lines()
+        .flat_map(|line| line.split(';'))
+        .filter_map(|field| field.split_once('=').or_else(|| field.split_once(':')));
+
+    for (key, value) in fields {
+        let key = key.trim().to_lowercase().replace([' ', '_'], "");
+        let slot = match key.as_str() {
+            "rxbytes" | "receivedbytes" => &mut rx,
+            "txbytes" | "transmittedbytes" => &mut tx,
+            "clients" | "associatedclients" | "stations" | "associatedstations" | "numberofassociatedstations" => {
+                &mut clients
+            }
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = parse_count(value);
+        }
+    }
+
+    (rx, tx, clients)
+}
+
+fn total(counters: &[InterfaceCounters], field: impl Fn(&InterfaceCounters) -> Option<i64>) -> i64 {
+    counters.iter().filter_map(field).sum()
+}
+
+pub fn print_stats_summary(counters: &[InterfaceCounters]) {
+    if counters.is_empty() {
+        return;
+    }
+
+    println!("\n=== Interface Counters ({}) ===", counters.len());
+    println!("{:<24} {:<12} {:<20} {:>16} {:>16} {:>8}", "Device", "Interface", "MAC", "RX bytes", "TX bytes", "Clients");
+    let show = |value: Option<i64>| value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+    for row in counters {
+        println!(
+            "{:<24} {:<12} {:<20} {:>16} {:>16} {:>8}",
+            row.hostname,
+            row.interface,
+            row.mac,
+            show(row.rx_bytes),
+            show(row.tx_bytes),
+            show(row.clients)
+        );
+    }
+    println!(
+        "Total: {} RX bytes, {} TX bytes, {} clients",
+        total(counters, |c| c.rx_bytes),
+        total(counters, |c| c.tx_bytes),
+        total(counters, |c| c.clients)
+    );
+    println!("===============================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_counters() {
+        let output = "\
+Interface name=wifi0.1; Mode=access;
+Rx bytes=1234567; Rx unicast data frames=900;
+Tx bytes=7,654,321; Tx dropped frames=3;
+Number of associated stations=12;
+";
+        assert_eq!(parse_counters(output), (Some(1234567), Some(7654321), Some(12)));
+
+        let output = "RX bytes: 10\nTX bytes: 20\n";
+        assert_eq!(parse_counters(output), (Some(10), Some(20), None));
+        assert_eq!(parse_counters("ERROR: unknown interface"), (None, None, None));
+    }
+}
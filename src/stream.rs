@@ -0,0 +1,203 @@
+use crate::csv_output::{CsvProfile, CsvWriter};
+use crate::output::{create_output_file, LineEnding, OutputFile};
+use crate::report::DeviceInterfaces;
+use crate::schema::{BssidRecord, CliResultRecord};
+use crate::sink::{csv_row, write_access_header, write_access_line, write_device_block, write_radio_groups, TxtLayout, BSSID_COLUMNS};
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+/// Sinks whose files `--stream` writes while parsing instead of at export time
+pub const STREAMED_SINKS: &[&str] = &["json", "txt", "csv"];
+
+/// Export files written device by device as CLI output is parsed (`--stream`),
+/// so memory use doesn't grow with every device's raw output held until export.
+/// Only the formats of the enabled sinks are written:
+/// - csv: wifi-bssids.csv
+/// - txt: bssids.txt and wifi-bssids.txt
+/// - json: wifi-bssids.jsonl and full_cli.jsonl, one record per line
+pub struct StreamWriter {
+    command: String,
+    csv: Option<CsvWriter>,
+    txt: Option<(OutputFile, OutputFile)>,
+    txt_layout: TxtLayout,
+    jsonl: Option<(OutputFile, OutputFile)>,
+    devices: usize,
+    bssids: usize,
+}
+
+impl StreamWriter {
+    /// Create the files of the streamed sinks among `sinks` in the working
+    /// directory and write their headers
+    pub fn create(
+        sinks: &[String],
+        command: &str,
+        line_ending: LineEnding,
+        csv_profile: Option<&CsvProfile>,
+        txt_layout: TxtLayout,
+    ) -> Result<Self> {
+        Self::create_in(Path::new(""), sinks, command, line_ending, csv_profile, txt_layout)
+    }
+
+    /// Like `create`, with the files in `dir`
+    pub fn create_in(
+        dir: &Path,
+        sinks: &[String],
+        command: &str,
+        line_ending: LineEnding,
+        csv_profile: Option<&CsvProfile>,
+        txt_layout: TxtLayout,
+    ) -> Result<Self> {
+        let enabled = |name: &str| sinks.iter().any(|s| s == name);
+
+        let csv = if enabled("csv") {
+            let path = dir.join("wifi-bssids.csv");
+            Some(CsvWriter::create_as(&path, "wifi-bssids.csv", &BSSID_COLUMNS, line_ending, csv_profile)?)
+        } else {
+            None
+        };
+
+        let txt = if enabled("txt") {
+            let bssids = create_output_file(&dir.join("bssids.txt"), line_ending)?;
+            let mut access = create_output_file(&dir.join("wifi-bssids.txt"), line_ending)?;
+            if txt_layout == TxtLayout::Flat {
+                write_access_header(&mut access)?;
+            }
+            Some((bssids, access))
+        } else {
+            None
+        };
+
+        let jsonl = if enabled("json") {
+            Some((
+                create_output_file(&dir.join("wifi-bssids.jsonl"), line_ending)?,
+                create_output_file(&dir.join("full_cli.jsonl"), line_ending)?,
+            ))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            command: command.to_string(),
+            csv,
+            txt,
+            txt_layout,
+            jsonl,
+            devices: 0,
+            bssids: 0,
+        })
+    }
+
+    /// Append one device's rows and flush them, then drop its raw CLI output,
+    /// which is only kept in full_cli.jsonl
+    pub fn write_device(&mut self, result: &mut DeviceInterfaces) -> Result<()> {
+        let access: Vec<_> = result.interfaces.iter().filter(|i| i.is_access()).collect();
+
+        if let Some(csv) = &mut self.csv {
+            for iface in &access {
+                csv.write_row(&csv_row(result, iface))?;
+            }
+            csv.flush()?;
+        }
+
+        if let Some((bssids, wifi_bssids)) = &mut self.txt {
+            if !result.interfaces.is_empty() {
+                write_device_block(bssids, result)?;
+            }
+            match self.txt_layout {
+                TxtLayout::Grouped => write_radio_groups(wifi_bssids, result)?,
+                TxtLayout::Flat => {
+                    for iface in &access {
+                        write_access_line(wifi_bssids, result, iface)?;
+                    }
+                }
+            }
+            bssids.flush().context("Failed to flush bssids.txt")?;
+            wifi_bssids.flush().context("Failed to flush wifi-bssids.txt")?;
+        }
+
+        if let Some((bssid_lines, cli_lines)) = &mut self.jsonl {
+            for iface in &access {
+                let line = serde_json::to_string(&BssidRecord::new(result, iface))
+                    .context("Failed to serialize BSSID to JSON")?;
+                writeln!(bssid_lines, "{}", line).context("Failed to write wifi-bssids.jsonl")?;
+            }
+            let record = CliResultRecord {
+                device_id: result.device_id,
+                hostname: &result.hostname,
+                command: &self.command,
+                output: &result.output,
+            };
+            let line = serde_json::to_string(&record).context("Failed to serialize CLI result to JSON")?;
+            writeln!(cli_lines, "{}", line).context("Failed to write full_cli.jsonl")?;
+            bssid_lines.flush().context("Failed to flush wifi-bssids.jsonl")?;
+            cli_lines.flush().context("Failed to flush full_cli.jsonl")?;
+        }
+
+        self.devices += 1;
+        self.bssids += access.len();
+        result.output = String::new();
+
+        Ok(())
+    }
+
+    /// Report what was written; the files are already flushed
+    pub fn finish(self) {
+        let mut files = Vec::new();
+        if self.csv.is_some() {
+            files.push("wifi-bssids.csv");
+        }
+        if self.txt.is_some() {
+            files.extend(["bssids.txt", "wifi-bssids.txt"]);
+        }
+        if self.jsonl.is_some() {
+            files.extend(["wifi-bssids.jsonl", "full_cli.jsonl"]);
+        }
+        if !files.is_empty() {
+            println!(
+                "Streamed {} access mode BSSIDs from {} devices to {}",
+                self.bssids,
+                self.devices,
+                files.join(", ")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_stream_writer() {
+        let dir = std::env::temp_dir().join(format!("xiq-stream-test-{}", std::process::id()));
+        let sinks = vec!["csv".to_string(), "json".to_string()];
+        let mut writer = StreamWriter::create_in(&dir, &sinks, "show interface", LineEnding::Lf, None, TxtLayout::Grouped).unwrap();
+        let mut ap = DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: "raw".to_string(),
+            interfaces: vec![
+                InterfaceEntry { name: "wifi0.1".into(), mac: "00:11:22:33:44:55".into(), mode: "access".into(), ..Default::default() },
+                InterfaceEntry { name: "mgt0".into(), mode: "backhaul".into(), ..Default::default() },
+            ],
+        };
+        writer.write_device(&mut ap).unwrap();
+        assert!(ap.output.is_empty());
+
+        // Rows are on disk before the writer is finished
+        let csv = std::fs::read_to_string(dir.join("wifi-bssids.csv")).unwrap();
+        let cli = std::fs::read_to_string(dir.join("full_cli.jsonl")).unwrap();
+        let bssids = std::fs::read_to_string(dir.join("wifi-bssids.jsonl")).unwrap();
+        writer.finish();
+        let exists = dir.join("wifi-bssids.txt").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("AP-1,1,wifi0.1,00:11:22:33:44:55,access"));
+        assert_eq!(bssids.lines().count(), 1);
+        assert!(cli.contains(r#""output":"raw""#));
+        assert!(!exists);
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Default time between device list polls in `subscribe`
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Change in a device's connection seen between two polls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceEventKind {
+    /// New to the inventory
+    Added,
+    /// Left the inventory
+    Removed,
+    Connected,
+    Disconnected,
+}
+
+impl DeviceEventKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Connected => "connected",
+            Self::Disconnected => "disconnected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceEvent {
+    pub device_id: i64,
+    pub hostname: String,
+    pub kind: DeviceEventKind,
+}
+
+/// Last known hostname and `connected` flag of each device, by ID
+pub type ConnectionStates = HashMap<i64, (String, bool)>;
+
+/// Hostname and `connected` flag of every device in an API device list
+pub fn connection_states(devices: &[serde_json::Value]) -> ConnectionStates {
+    devices
+        .iter()
+        .filter_map(|d| {
+            let id = d.get("id")?.as_i64()?;
+            let hostname = d.get("hostname").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
+            let connected = d.get("connected").and_then(|v| v.as_bool()).unwrap_or(false);
+            Some((id, (hostname, connected)))
+        })
+        .collect()
+}
+
+/// Events between the `previous` states and the `current` ones, ordered by hostname
+pub fn connection_events(previous: &ConnectionStates, current: &ConnectionStates) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+    for (id, (hostname, connected)) in current {
+        let kind = match previous.get(id) {
+            None => Some(DeviceEventKind::Added),
+            Some((_, was)) if !was && *connected => Some(DeviceEventKind::Connected),
+            Some((_, was)) if *was && !connected => Some(DeviceEventKind::Disconnected),
+            Some(_) => None,
+        };
+        if let Some(kind) = kind {
+            events.push(DeviceEvent { device_id: *id, hostname: hostname.clone(), kind });
+        }
+    }
+    for (id, (hostname, _)) in previous {
+        if !current.contains_key(id) {
+            events.push(DeviceEvent { device_id: *id, hostname: hostname.clone(), kind: DeviceEventKind::Removed });
+        }
+    }
+    events.sort_by(|a, b| a.hostname.cmp(&b.hostname).then(a.device_id.cmp(&b.device_id)));
+    events
+}
+
+/// Devices whose BSSIDs need refreshing: connected APs that just connected
+/// or were just added
+pub fn refresh_targets(events: &[DeviceEvent], devices: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    let ids: Vec<i64> = events
+        .iter()
+        .filter(|e| matches!(e.kind, DeviceEventKind::Added | DeviceEventKind::Connected))
+        .map(|e| e.device_id)
+        .collect();
+    devices
+        .iter()
+        .filter(|d| d.get("id").and_then(|v| v.as_i64()).is_some_and(|id| ids.contains(&id)))
+        .cloned()
+        .collect()
+}
+
+pub fn print_events(events: &[DeviceEvent]) {
+    for event in events {
+        println!(
+            "[{}] {} (ID: {}) {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            event.hostname,
+            event.device_id,
+            event.kind.label()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(entries: &[(i64, &str, bool)]) -> ConnectionStates {
+        entries.iter().map(|(id, host, up)| (*id, (host.to_string(), *up))).collect()
+    }
+
+    #[test]
+    fn test_connection_events() {
+        let previous = states(&[(1, "AP-1", false), (2, "AP-2", true), (3, "AP-3", true), (4, "AP-4", true)]);
+        let current = states(&[(1, "AP-1", true), (2, "AP-2", false), (3, "AP-3", true), (5, "AP-5", true)]);
+        let kinds: Vec<(i64, DeviceEventKind)> =
+            connection_events(&previous, &current).iter().map(|e| (e.device_id, e.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, DeviceEventKind::Connected),
+                (2, DeviceEventKind::Disconnected),
+                (4, DeviceEventKind::Removed),
+                (5, DeviceEventKind::Added),
+            ]
+        );
+
+        let devices = vec![serde_json::json!({ "id": 1 }), serde_json::json!({ "id": 2 }), serde_json::json!({ "id": 5 })];
+        let events = connection_events(&previous, &current);
+        let targets: Vec<i64> = refresh_targets(&events, &devices).iter().filter_map(|d| d["id"].as_i64()).collect();
+        assert_eq!(targets, vec![1, 5]);
+    }
+}
@@ -0,0 +1,453 @@
+use crate::db::NewBssid;
+use crate::location;
+use crate::report::DeviceInterfaces;
+use crate::rf::{band_for_interface, Band};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// BSSID rollup for one SSID
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SsidSummary {
+    pub ssid: String,
+    /// Access-mode interfaces broadcasting this SSID
+    pub bssids: usize,
+    /// Distinct APs broadcasting this SSID
+    pub aps: usize,
+    pub bands: Vec<Band>,
+}
+
+impl SsidSummary {
+    /// Bands joined for display, e.g. `2.4GHz/5GHz`
+    pub fn bands_label(&self) -> String {
+        self.bands
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Per-SSID BSSID, AP and band counts, sorted by SSID
+pub fn ssid_summary(results: &[DeviceInterfaces]) -> Vec<SsidSummary> {
+    struct Acc {
+        bssids: usize,
+        aps: HashSet<i64>,
+        bands: BTreeSet<Band>,
+    }
+
+    let mut by_ssid: BTreeMap<&str, Acc> = BTreeMap::new();
+
+    for result in results {
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            let ssid = iface.ssid.trim();
+            if ssid.is_empty() || ssid == "-" {
+                continue;
+            }
+
+            let acc = by_ssid.entry(ssid).or_insert_with(|| Acc {
+                bssids: 0,
+                aps: HashSet::new(),
+                bands: BTreeSet::new(),
+            });
+            acc.bssids += 1;
+            acc.aps.insert(result.device_id);
+            if let Some(band) = band_for_interface(iface) {
+                acc.bands.insert(band);
+            }
+        }
+    }
+
+    by_ssid
+        .into_iter()
+        .map(|(ssid, acc)| SsidSummary {
+            ssid: ssid.to_string(),
+            bssids: acc.bssids,
+            aps: acc.aps.len(),
+            bands: acc.bands.into_iter().collect(),
+        })
+        .collect()
+}
+
+/// Per-site rollup (site-summary.csv, `site_summary` table)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SiteSummary {
+    pub site: String,
+    pub aps: usize,
+    pub connected_aps: usize,
+    /// Access-mode BSSIDs, including those whose band is unknown
+    pub bssids: usize,
+    pub bssids_24ghz: usize,
+    pub bssids_5ghz: usize,
+    pub bssids_6ghz: usize,
+    /// Distinct SSIDs broadcast at the site
+    pub ssids: usize,
+}
+
+/// AP, BSSID and SSID counts per site, sorted by site. Every AP in
+/// `devices` is counted; BSSIDs come from the collected `results`.
+pub fn site_summary(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> Vec<SiteSummary> {
+    let mut by_site: BTreeMap<String, (SiteSummary, HashSet<&str>)> = BTreeMap::new();
+    let mut device_sites: HashMap<i64, String> = HashMap::new();
+
+    for device in devices {
+        let site = location::device_site(device);
+        if let Some(id) = device.get("id").and_then(|v| v.as_i64()) {
+            device_sites.insert(id, site.clone());
+        }
+        if device.get("device_function").and_then(|v| v.as_str()) != Some("AP") {
+            continue;
+        }
+        let (row, _) = by_site.entry(site).or_default();
+        row.aps += 1;
+        if device.get("connected").and_then(|v| v.as_bool()).unwrap_or(false) {
+            row.connected_aps += 1;
+        }
+    }
+
+    for result in results {
+        let site = device_sites
+            .get(&result.device_id)
+            .cloned()
+            .unwrap_or_else(|| location::UNASSIGNED.to_string());
+        let (row, ssids) = by_site.entry(site).or_default();
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            row.bssids += 1;
+            match band_for_interface(iface) {
+                Some(Band::TwoPointFour) => row.bssids_24ghz += 1,
+                Some(Band::Five) => row.bssids_5ghz += 1,
+                Some(Band::Six) => row.bssids_6ghz += 1,
+                None => {}
+            }
+            let ssid = iface.ssid.trim();
+            if !ssid.is_empty() && ssid != "-" {
+                ssids.insert(ssid);
+            }
+        }
+    }
+
+    by_site
+        .into_iter()
+        .map(|(site, (row, ssids))| SiteSummary { site, ssids: ssids.len(), ..row })
+        .collect()
+}
+
+/// SSID broadcast under one network policy
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicySsid {
+    pub ssid: String,
+    pub bssids: usize,
+    pub aps: usize,
+}
+
+/// Per-network-policy rollup (policy-summary.csv)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PolicySummary {
+    pub policy: String,
+    pub aps: usize,
+    /// Access-mode BSSIDs, including those without an SSID
+    pub bssids: usize,
+    /// SSIDs broadcast by the policy's APs, sorted by SSID
+    pub ssids: Vec<PolicySsid>,
+}
+
+/// Network policy assigned to a device (FULL view), or `Unassigned`
+pub fn device_policy(device: &serde_json::Value) -> String {
+    device
+        .get("network_policy_name")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|policy| !policy.is_empty())
+        .unwrap_or(location::UNASSIGNED)
+        .to_string()
+}
+
+/// AP, BSSID and per-SSID counts per network policy, sorted by policy.
+/// Every AP in `devices` is counted; BSSIDs come from the collected `results`.
+pub fn policy_summary(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> Vec<PolicySummary> {
+    #[derive(Default)]
+    struct Acc<'a> {
+        aps: usize,
+        bssids: usize,
+        ssids: BTreeMap<&'a str, (usize, HashSet<i64>)>,
+    }
+
+    let mut by_policy: BTreeMap<String, Acc> = BTreeMap::new();
+    let mut device_policies: HashMap<i64, String> = HashMap::new();
+
+    for device in devices {
+        let policy = device_policy(device);
+        if let Some(id) = device.get("id").and_then(|v| v.as_i64()) {
+            device_policies.insert(id, policy.clone());
+        }
+        if device.get("device_function").and_then(|v| v.as_str()) == Some("AP") {
+            by_policy.entry(policy).or_default().aps += 1;
+        }
+    }
+
+    for result in results {
+        let policy = device_policies
+            .get(&result.device_id)
+            .cloned()
+            .unwrap_or_else(|| location::UNASSIGNED.to_string());
+        let acc = by_policy.entry(policy).or_default();
+        for iface in result.interfaces.iter().filter(|i| i.is_access()) {
+            acc.bssids += 1;
+            let ssid = iface.ssid.trim();
+            if ssid.is_empty() || ssid == "-" {
+                continue;
+            }
+            let (count, aps) = acc.ssids.entry(ssid).or_default();
+            *count += 1;
+            aps.insert(result.device_id);
+        }
+    }
+
+    by_policy
+        .into_iter()
+        .map(|(policy, acc)| PolicySummary {
+            policy,
+            aps: acc.aps,
+            bssids: acc.bssids,
+            ssids: acc
+                .ssids
+                .into_iter()
+                .map(|(ssid, (bssids, aps))| PolicySsid { ssid: ssid.to_string(), bssids, aps: aps.len() })
+                .collect(),
+        })
+        .collect()
+}
+
+pub fn print_new_bssids(new: &[NewBssid]) {
+    if new.is_empty() {
+        return;
+    }
+
+    println!("\n=== Newly Seen BSSIDs ({}) ===", new.len());
+    println!("{:<24} {:<20} SSID", "Device", "MAC");
+    for bssid in new {
+        println!("{:<24} {:<20} {}", bssid.hostname, bssid.mac, bssid.ssid);
+    }
+    println!("==============================");
+}
+
+pub fn print_ssid_summary(summary: &[SsidSummary]) {
+    if summary.is_empty() {
+        return;
+    }
+
+    println!("\n=== SSID Summary ===");
+    println!("{:<32} {:>8} {:>6}  Bands", "SSID", "BSSIDs", "APs");
+    for row in summary {
+        println!("{:<32} {:>8} {:>6}  {}", row.ssid, row.bssids, row.aps, row.bands_label());
+    }
+    println!("====================");
+}
+
+pub fn print_site_summary(summary: &[SiteSummary]) {
+    if summary.is_empty() {
+        return;
+    }
+
+    println!("\n=== Site Summary ===");
+    println!(
+        "{:<24} {:>5} {:>9} {:>7} {:>7} {:>7} {:>7} {:>6}",
+        "Site", "APs", "Connected", "BSSIDs", "2.4GHz", "5GHz", "6GHz", "SSIDs"
+    );
+    for row in summary {
+        println!(
+            "{:<24} {:>5} {:>9} {:>7} {:>7} {:>7} {:>7} {:>6}",
+            row.site, row.aps, row.connected_aps, row.bssids, row.bssids_24ghz, row.bssids_5ghz, row.bssids_6ghz, row.ssids
+        );
+    }
+    println!("====================");
+}
+
+/// Per-policy SSIDs and totals. SSIDs the policy's `[ssid] required_by_policy`
+/// list doesn't name are marked `unexpected`, and listed SSIDs no AP
+/// broadcasts are shown as missing, so each policy can be checked against
+/// exactly the SSIDs it should carry.
+pub fn print_policy_summary(summary: &[PolicySummary], required_by_policy: &HashMap<String, Vec<String>>) {
+    // Without policies in the inventory every AP would land in one group
+    if summary.iter().all(|row| row.policy == location::UNASSIGNED) {
+        return;
+    }
+
+    println!("\n=== Network Policy Summary ===");
+    for row in summary {
+        println!("{}: {} APs, {} BSSIDs, {} SSIDs", row.policy, row.aps, row.bssids, row.ssids.len());
+        let required = required_by_policy.get(&row.policy);
+        for ssid in &row.ssids {
+            let unexpected = required.is_some_and(|required| !required.contains(&ssid.ssid));
+            println!(
+                "  {:<32} {:>8} BSSIDs {:>6} APs{}",
+                ssid.ssid,
+                ssid.bssids,
+                ssid.aps,
+                if unexpected { "  unexpected" } else { "" }
+            );
+        }
+        for missing in required.into_iter().flatten().filter(|r| !row.ssids.iter().any(|s| &s.ssid == *r)) {
+            println!("  {:<32} missing", missing);
+        }
+    }
+    println!("==============================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    fn iface(mode: &str, channel: &str, ssid: &str) -> InterfaceEntry {
+        InterfaceEntry {
+            mode: mode.to_string(),
+            channel: channel.to_string(),
+            ssid: ssid.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ssid_summary() {
+        let results = vec![
+            DeviceInterfaces {
+                device_id: 1,
+                hostname: "AP-1".to_string(),
+                output: String::new(),
+                interfaces: vec![
+                    iface("AP", "36(80)", "-"),
+                    iface("access", "36(80)", "Corp"),
+                    iface("access", "6(20)", "Corp"),
+                    iface("access", "36(80)", "Guest"),
+                ],
+            },
+            DeviceInterfaces {
+                device_id: 2,
+                hostname: "AP-2".to_string(),
+                output: String::new(),
+                interfaces: vec![iface("access", "149(80)", "Corp")],
+            },
+        ];
+
+        let summary = ssid_summary(&results);
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].ssid, "Corp");
+        assert_eq!(summary[0].bssids, 3);
+        assert_eq!(summary[0].aps, 2);
+        assert_eq!(summary[0].bands_label(), "2.4GHz/5GHz");
+        assert_eq!(summary[1].ssid, "Guest");
+        assert_eq!(summary[1].aps, 1);
+    }
+
+    #[test]
+    fn test_policy_summary() {
+        let devices = vec![
+            serde_json::json!({ "id": 1, "device_function": "AP", "network_policy_name": "HQ" }),
+            serde_json::json!({ "id": 2, "device_function": "AP", "network_policy_name": "HQ" }),
+            serde_json::json!({ "id": 3, "device_function": "AP", "network_policy_name": " " }),
+            serde_json::json!({ "id": 4, "device_function": "SWITCH", "network_policy_name": "HQ" }),
+        ];
+        let results = vec![
+            DeviceInterfaces {
+                device_id: 1,
+                hostname: "AP-1".to_string(),
+                output: String::new(),
+                interfaces: vec![
+                    iface("AP", "36(80)", "-"),
+                    iface("access", "6(20)", "Corp"),
+                    iface("access", "36(80)", "Corp"),
+                    iface("access", "36(80)", "-"),
+                ],
+            },
+            DeviceInterfaces {
+                device_id: 2,
+                hostname: "AP-2".to_string(),
+                output: String::new(),
+                interfaces: vec![iface("access", "36(80)", "Corp"), iface("access", "36(80)", "Guest")],
+            },
+            DeviceInterfaces {
+                device_id: 3,
+                hostname: "AP-3".to_string(),
+                output: String::new(),
+                interfaces: vec![iface("access", "149(80)", "Corp")],
+            },
+        ];
+
+        let summary = policy_summary(&devices, &results);
+        let ssid = |ssid: &str, bssids, aps| PolicySsid { ssid: ssid.to_string(), bssids, aps };
+        assert_eq!(
+            summary,
+            vec![
+                PolicySummary {
+                    policy: "HQ".to_string(),
+                    aps: 2,
+                    bssids: 5,
+                    ssids: vec![ssid("Corp", 3, 2), ssid("Guest", 1, 1)],
+                },
+                PolicySummary {
+                    policy: "Unassigned".to_string(),
+                    aps: 1,
+                    bssids: 1,
+                    ssids: vec![ssid("Corp", 1, 1)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_site_summary() {
+        let hq = serde_json::json!([{ "name": "Global" }, { "name": "HQ" }]);
+        let devices = vec![
+            serde_json::json!({ "id": 1, "device_function": "AP", "connected": true, "locations": hq }),
+            serde_json::json!({ "id": 2, "device_function": "AP", "connected": false, "locations": hq }),
+            serde_json::json!({ "id": 3, "device_function": "AP", "connected": true }),
+            serde_json::json!({ "id": 4, "device_function": "SWITCH", "connected": true, "locations": hq }),
+        ];
+        let results = vec![
+            DeviceInterfaces {
+                device_id: 1,
+                hostname: "AP-1".to_string(),
+                output: String::new(),
+                interfaces: vec![
+                    iface("AP", "36(80)", "-"),
+                    iface("access", "6(20)", "Corp"),
+                    iface("access", "36(80)", "Corp"),
+                    iface("access", "6g-37(160)", "Guest"),
+                    iface("access", "", "Guest"),
+                ],
+            },
+            DeviceInterfaces {
+                device_id: 3,
+                hostname: "AP-3".to_string(),
+                output: String::new(),
+                interfaces: vec![iface("access", "149(80)", "Corp")],
+            },
+        ];
+
+        let summary = site_summary(&devices, &results);
+        assert_eq!(
+            summary,
+            vec![
+                SiteSummary {
+                    site: "HQ".to_string(),
+                    aps: 2,
+                    connected_aps: 1,
+                    bssids: 4,
+                    bssids_24ghz: 1,
+                    bssids_5ghz: 1,
+                    bssids_6ghz: 1,
+                    ssids: 2,
+                },
+                SiteSummary {
+                    site: "Unassigned".to_string(),
+                    aps: 1,
+                    connected_aps: 1,
+                    bssids: 1,
+                    bssids_5ghz: 1,
+                    ssids: 1,
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+}
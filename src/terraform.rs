@@ -0,0 +1,94 @@
+use crate::location;
+use crate::output::{create_output_file, LineEnding};
+use crate::report::DeviceInterfaces;
+use crate::rf::band_for_interface;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// BSSID inventory in the shape of a Terraform `external` data source result
+pub const TERRAFORM_BSSIDS_FILE: &str = "terraform-bssids.json";
+
+/// Result map of the `external` data source: one key per BSSID MAC, whose
+/// value is a JSON-encoded object of strings (decode with `jsondecode`).
+/// Terraform only accepts string values, so missing fields are empty strings.
+pub fn terraform_bssids(devices: &[serde_json::Value], results: &[DeviceInterfaces]) -> BTreeMap<String, String> {
+    let mut bssids = BTreeMap::new();
+    for result in results {
+        let site = devices
+            .iter()
+            .find(|d| d.get("id").and_then(|id| id.as_i64()) == Some(result.device_id))
+            .map(location::device_site)
+            .unwrap_or_else(|| location::UNASSIGNED.to_string());
+
+        for iface in result.interfaces.iter().filter(|i| i.is_access() && !i.mac.is_empty()) {
+            let attributes: BTreeMap<&str, String> = BTreeMap::from([
+                ("hostname", result.hostname.clone()),
+                ("device_id", result.device_id.to_string()),
+                ("interface", iface.name.clone()),
+                ("ssid", iface.ssid.clone()),
+                ("radio", iface.radio.clone()),
+                ("channel", iface.channel.clone()),
+                ("band", band_for_interface(iface).map(|b| b.to_string()).unwrap_or_default()),
+                ("site", site.clone()),
+            ]);
+            bssids
+                .entry(iface.mac.clone())
+                .or_insert_with(|| serde_json::to_string(&attributes).unwrap_or_default());
+        }
+    }
+    bssids
+}
+
+pub fn write_terraform_bssids(devices: &[serde_json::Value], results: &[DeviceInterfaces], line_ending: LineEnding) -> Result<()> {
+    let bssids = terraform_bssids(devices, results);
+    let json_data = serde_json::to_string_pretty(&bssids).context("Failed to serialize Terraform BSSIDs")?;
+
+    let path = Path::new(TERRAFORM_BSSIDS_FILE);
+    let mut file = create_output_file(path, line_ending)?;
+    file.write_all(json_data.as_bytes())
+        .and_then(|_| file.flush())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!("Terraform BSSID data saved to {} ({} BSSIDs)", TERRAFORM_BSSIDS_FILE, bssids.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_terraform_bssids() {
+        let devices = vec![serde_json::json!({ "id": 1, "locations": [{ "name": "Global" }, { "name": "HQ" }] })];
+        let results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                InterfaceEntry {
+                    name: "wifi1.1".into(),
+                    mode: "access".into(),
+                    mac: "00:11:22:33:44:61".into(),
+                    ssid: "Corp".into(),
+                    radio: "wifi1".into(),
+                    channel: "36(80)".into(),
+                    ..Default::default()
+                },
+                InterfaceEntry { mode: "backhaul".into(), mac: "00:11:22:33:44:00".into(), ..Default::default() },
+            ],
+        }];
+
+        let bssids = terraform_bssids(&devices, &results);
+        assert_eq!(bssids.keys().collect::<Vec<_>>(), vec!["00:11:22:33:44:61"]);
+
+        let attributes: BTreeMap<String, String> = serde_json::from_str(&bssids["00:11:22:33:44:61"]).unwrap();
+        assert_eq!(attributes["hostname"], "AP-1");
+        assert_eq!(attributes["band"], "5GHz");
+        assert_eq!(attributes["site"], "HQ");
+        assert_eq!(attributes["device_id"], "1");
+    }
+}
@@ -0,0 +1,222 @@
+use crate::config::ValidationConfig;
+use crate::csv_output::{write_csv, CsvProfile};
+use crate::output::LineEnding;
+use crate::report::DeviceInterfaces;
+use crate::rf::band_for_channel;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Findings of the data quality rules
+pub const VALIDATION_REPORT_FILE: &str = "validation-report.csv";
+
+/// How a validation rule's findings count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule isn't checked
+    Off,
+    /// Reported only
+    Warning,
+    /// Reported, and counted against `max_errors`
+    Error,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Data quality rule over an access-mode interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// SSID empty or `-`
+    MissingSsid,
+    /// VLAN 0
+    VlanZero,
+    /// Hive empty or `-`
+    EmptyHive,
+    /// Channel that isn't a 2.4, 5 or 6 GHz channel
+    MalformedChannel,
+}
+
+impl Rule {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::MissingSsid => "missing ssid",
+            Self::VlanZero => "vlan 0",
+            Self::EmptyHive => "empty hive",
+            Self::MalformedChannel => "malformed channel",
+        }
+    }
+
+    fn severity(self, config: &ValidationConfig) -> Severity {
+        match self {
+            Self::MissingSsid => config.missing_ssid,
+            Self::VlanZero => config.vlan_zero,
+            Self::EmptyHive => config.empty_hive,
+            Self::MalformedChannel => config.malformed_channel,
+        }
+    }
+}
+
+const RULES: [Rule; 4] = [Rule::MissingSsid, Rule::VlanZero, Rule::EmptyHive, Rule::MalformedChannel];
+
+fn is_blank(value: &str) -> bool {
+    matches!(value.trim(), "" | "-")
+}
+
+/// Access-mode interface that broke a validation rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub device_id: i64,
+    pub hostname: String,
+    pub interface: String,
+    pub mac: String,
+    pub rule: Rule,
+    pub severity: Severity,
+    /// The offending value
+    pub value: String,
+}
+
+/// Check every access-mode interface against the enabled rules
+pub fn validate(results: &[DeviceInterfaces], config: &ValidationConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for device in results {
+        for iface in device.interfaces.iter().filter(|i| i.is_access()) {
+            for rule in RULES {
+                let severity = rule.severity(config);
+                if severity == Severity::Off {
+                    continue;
+                }
+                let (broken, value) = match rule {
+                    Rule::MissingSsid => (is_blank(&iface.ssid), &iface.ssid),
+                    Rule::VlanZero => (iface.vlan.trim() == "0", &iface.vlan),
+                    Rule::EmptyHive => (is_blank(&iface.hive), &iface.hive),
+                    Rule::MalformedChannel => (band_for_channel(&iface.channel).is_none(), &iface.channel),
+                };
+                if broken {
+                    findings.push(Finding {
+                        device_id: device.device_id,
+                        hostname: device.hostname.clone(),
+                        interface: iface.name.clone(),
+                        mac: iface.mac.clone(),
+                        rule,
+                        severity,
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+    }
+    findings
+}
+
+pub fn error_count(findings: &[Finding]) -> usize {
+    findings.iter().filter(|f| f.severity == Severity::Error).count()
+}
+
+/// Why the run fails, when error findings exceed `max_errors`
+pub fn threshold_violation(findings: &[Finding], max_errors: usize) -> Option<String> {
+    let errors = error_count(findings);
+    (errors > max_errors).then(|| format!("{} validation errors, more than the allowed {}", errors, max_errors))
+}
+
+pub fn write_validation_report(
+    findings: &[Finding],
+    path: &Path,
+    line_ending: LineEnding,
+    profile: Option<&CsvProfile>,
+) -> Result<()> {
+    let rows: Vec<Vec<String>> = findings
+        .iter()
+        .map(|f| {
+            vec![
+                f.hostname.clone(),
+                f.device_id.to_string(),
+                f.interface.clone(),
+                f.mac.clone(),
+                f.rule.label().to_string(),
+                f.severity.label().to_string(),
+                f.value.clone(),
+            ]
+        })
+        .collect();
+    write_csv(
+        path,
+        &["Device", "DeviceID", "Interface", "MAC", "Rule", "Severity", "Value"],
+        &rows,
+        line_ending,
+        profile,
+    )
+}
+
+pub fn print_validation_summary(findings: &[Finding]) {
+    if findings.is_empty() {
+        return;
+    }
+
+    let errors = error_count(findings);
+    println!("\n=== Data Validation ({} errors, {} warnings) ===", errors, findings.len() - errors);
+    for rule in RULES {
+        let count = findings.iter().filter(|f| f.rule == rule).count();
+        if count > 0 {
+            println!("  {:<20} {}", rule.label(), count);
+        }
+    }
+    println!("==============================================");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::InterfaceEntry;
+
+    #[test]
+    fn test_validate() {
+        let iface = |name: &str, ssid: &str, vlan: &str, hive: &str, channel: &str| InterfaceEntry {
+            name: name.into(),
+            mode: "access".into(),
+            ssid: ssid.into(),
+            vlan: vlan.into(),
+            hive: hive.into(),
+            channel: channel.into(),
+            ..Default::default()
+        };
+        let results = vec![DeviceInterfaces {
+            device_id: 1,
+            hostname: "AP-1".to_string(),
+            output: String::new(),
+            interfaces: vec![
+                iface("wifi0.1", "Corp", "10", "MainHive", "36(80)"),
+                iface("wifi0.2", "-", "0", "MainHive", "36(80)"),
+                iface("wifi1.1", "Corp", "10", "", "auto"),
+                InterfaceEntry { name: "mgt0".into(), mode: "backhaul".into(), ..Default::default() },
+            ],
+        }];
+
+        let config = ValidationConfig::default();
+        let findings = validate(&results, &config);
+        let rules: Vec<_> = findings.iter().map(|f| (f.interface.as_str(), f.rule, f.severity)).collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("wifi0.2", Rule::MissingSsid, Severity::Error),
+                ("wifi0.2", Rule::VlanZero, Severity::Warning),
+                ("wifi1.1", Rule::EmptyHive, Severity::Warning),
+                ("wifi1.1", Rule::MalformedChannel, Severity::Error),
+            ]
+        );
+        assert_eq!(threshold_violation(&findings, 2), None);
+        assert!(threshold_violation(&findings, 1).is_some());
+
+        let config = ValidationConfig { vlan_zero: Severity::Off, empty_hive: Severity::Off, ..Default::default() };
+        assert_eq!(validate(&results, &config).len(), 2);
+    }
+}
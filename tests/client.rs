@@ -0,0 +1,343 @@
+//! CloudIQ client tests against a mock XIQ server, with sanitized API
+//! responses from `tests/fixtures`
+
+use serde_json::json;
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockBuilder, MockServer, ResponseTemplate};
+use xiq_cli_tool::api::XiqApi;
+use xiq_cli_tool::client::{CloudIQClient, Credential};
+use xiq_cli_tool::config::Config;
+use xiq_cli_tool::error::Error;
+use xiq_cli_tool::parser_registry::ParserRegistry;
+
+fn fixture(name: &str) -> serde_json::Value {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+    serde_json::from_str(&text).unwrap()
+}
+
+/// Mock server that accepts `user`/`secret`, and a client logged in to it
+async fn logged_in() -> (MockServer, CloudIQClient) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .and(body_json(json!({ "username": "user", "password": "secret" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("login.json")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut client = CloudIQClient::new(server.uri());
+    client.login("user", "secret").await.unwrap();
+    (server, client)
+}
+
+fn devices_page(page: &str) -> MockBuilder {
+    Mock::given(method("GET"))
+        .and(path("/devices"))
+        .and(query_param("page", page))
+        .and(query_param("limit", "100"))
+        .and(query_param("views", "FULL"))
+        .and(header("Authorization", "Bearer test-token"))
+}
+
+#[tokio::test]
+async fn test_logout_revokes_token() {
+    let (server, mut client) = logged_in().await;
+    Mock::given(method("POST"))
+        .and(path("/logout"))
+        .and(header("Authorization", "Bearer test-token"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    client.logout().await.unwrap();
+
+    // The token is gone, and logging out again sends nothing
+    assert!(client.get_devices().await.is_err());
+    client.logout().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_check_reachable() {
+    // Any answer counts, even a 404 for the bare base URL
+    let server = MockServer::start().await;
+    CloudIQClient::new(server.uri()).check_reachable().await.unwrap();
+
+    // A port nothing listens on
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let client = CloudIQClient::new(format!("http://127.0.0.1:{}", port));
+    let error = client.check_reachable().await.unwrap_err();
+    assert!(matches!(error, Error::Network { .. }));
+    let error = format!("{:#}", anyhow::Error::from(error));
+    assert!(error.contains("Can't reach the XIQ API"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_login_failure() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("invalid credentials"))
+        .mount(&server)
+        .await;
+
+    let mut client = CloudIQClient::new(server.uri());
+    let error = client.login("user", "wrong").await.unwrap_err();
+    assert_eq!(error.status(), Some(reqwest::StatusCode::UNAUTHORIZED));
+    let error = error.to_string();
+    assert!(error.contains("401") && error.contains("invalid credentials"), "{}", error);
+
+    // Nothing is sent without a token
+    let error = client.get_devices().await.unwrap_err();
+    assert!(matches!(error, Error::Auth { status: None, .. }));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_authenticate_falls_back() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .and(body_json(json!({ "username": "user", "password": "secret" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("login.json")))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("invalid credentials"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/devices"))
+        .and(query_param("page", "1"))
+        .and(query_param("limit", "1"))
+        .and(header("Authorization", "Bearer api-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "page": 1, "count": 0, "data": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let password = |password: &str| Credential::Password { username: "user".to_string(), password: password.to_string() };
+
+    // The rotated password hasn't propagated; the previous one still works
+    let mut client = CloudIQClient::new(server.uri());
+    assert_eq!(client.authenticate(&[password("rotated"), password("secret")]).await.unwrap(), 1);
+
+    // Both passwords rejected: the API token is checked and used, and isn't
+    // revoked on logout
+    let mut client = CloudIQClient::new(server.uri());
+    let credentials = [password("rotated"), password("old"), Credential::Token("api-token".to_string())];
+    assert_eq!(client.authenticate(&credentials).await.unwrap(), 2);
+    client.logout().await.unwrap();
+    assert_eq!(server.received_requests().await.unwrap().len(), 5);
+
+    let error = client.authenticate(&[password("rotated")]).await.unwrap_err();
+    assert!(matches!(error, Error::Auth { .. }));
+}
+
+#[tokio::test]
+async fn test_authenticate_rejected_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("invalid credentials"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/devices"))
+        .and(query_param("page", "1"))
+        .and(query_param("limit", "1"))
+        .and(header("Authorization", "Bearer revoked-token"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("token revoked"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // The revoked token fails the login rather than the first real request
+    let mut client = CloudIQClient::new(server.uri());
+    let credentials = [
+        Credential::Password { username: "user".to_string(), password: "rotated".to_string() },
+        Credential::Token("revoked-token".to_string()),
+    ];
+    let error = client.authenticate(&credentials).await.unwrap_err();
+    assert!(matches!(error, Error::Auth { .. }));
+    assert!(error.to_string().contains("token revoked"), "{}", error);
+
+    // The rejected token isn't kept
+    let error = client.get_devices().await.unwrap_err();
+    assert!(matches!(error, Error::Auth { status: None, .. }));
+}
+
+#[tokio::test]
+async fn test_get_devices_follows_total_pages() {
+    let (server, client) = logged_in().await;
+    devices_page("1")
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("devices_page1.json")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    devices_page("2")
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("devices_page2.json")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let devices = client.get_devices().await.unwrap();
+    let ids: Vec<_> = devices.iter().map(|d| d["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, [101, 102, 103]);
+}
+
+#[tokio::test]
+async fn test_get_devices_without_total_pages() {
+    let (server, client) = logged_in().await;
+    let full_page: Vec<_> = (0..100).map(|id| json!({ "id": id })).collect();
+    devices_page("1")
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": full_page })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // A short page is the last one
+    devices_page("2")
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [{ "id": 100 }] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(client.get_devices().await.unwrap().len(), 101);
+}
+
+#[tokio::test]
+async fn test_http_config_gzip_devices() {
+    use std::io::Write;
+
+    let server = MockServer::start().await;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(json!({ "data": [{ "id": 1 }], "total_pages": 1 }).to_string().as_bytes()).unwrap();
+    Mock::given(method("GET"))
+        .and(path("/devices"))
+        .and(header("Accept-Encoding", "gzip"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Encoding", "gzip")
+                .insert_header("Content-Type", "application/json")
+                .set_body_bytes(encoder.finish().unwrap()),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = Config::parse("[http.bulk]\nhttp2 = false\npool_max_idle = 2\ntimeout_secs = 10").unwrap();
+    let mut client = CloudIQClient::new(server.uri()).with_http_config(&config.http).unwrap();
+    client.use_token("api-token".to_string());
+    assert_eq!(client.get_devices().await.unwrap(), vec![json!({ "id": 1 })]);
+}
+
+#[tokio::test]
+async fn test_get_devices_empty_and_errors() {
+    let (server, client) = logged_in().await;
+    devices_page("1")
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [], "total_pages": 0 })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    assert!(client.get_devices().await.unwrap().is_empty());
+
+    devices_page("1")
+        .respond_with(ResponseTemplate::new(503).set_body_string("maintenance"))
+        .mount(&server)
+        .await;
+    let error = client.get_devices().await.unwrap_err();
+    assert_eq!(error.status(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+    let error = error.to_string();
+    assert!(error.contains("503") && error.contains("maintenance"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_get_devices_malformed_body() {
+    let (server, client) = logged_in().await;
+    devices_page("1")
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>gateway timeout</html>"))
+        .mount(&server)
+        .await;
+
+    let error = client.get_devices().await.unwrap_err();
+    assert!(matches!(error, Error::Parse { .. }));
+    let error = error.to_string();
+    assert!(error.contains("Failed to parse devices response"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_run_command_on_connected_aps() {
+    let (server, client) = logged_in().await;
+    devices_page("1")
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("devices_page1.json")))
+        .mount(&server)
+        .await;
+    devices_page("2")
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("devices_page2.json")))
+        .mount(&server)
+        .await;
+    // Only the connected AP is sent the command
+    Mock::given(method("POST"))
+        .and(path("/devices/:cli"))
+        .and(header("Authorization", "Bearer test-token"))
+        .and(body_json(json!({ "devices": { "ids": [101] }, "clis": ["show interface"] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("cli_show_interface.json")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let devices = client.get_devices().await.unwrap();
+    let results = client
+        .run_command_on_connected_aps(&devices, "show interface", &ParserRegistry::with_defaults())
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].hostname, "AP-Lobby");
+    let ssids: Vec<_> = results[0].interfaces.iter().map(|i| i.ssid.as_str()).collect();
+    assert_eq!(ssids, ["Corp", "Guest WiFi"]);
+}
+
+#[tokio::test]
+async fn test_cli_command_errors() {
+    let (server, client) = logged_in().await;
+    Mock::given(method("POST"))
+        .and(path("/devices/:cli"))
+        .respond_with(ResponseTemplate::new(400).set_body_string("unknown device"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    let error = client.send_cli_command(&[1], "show interface").await.unwrap_err().to_string();
+    assert!(error.contains("400") && error.contains("unknown device"), "{}", error);
+
+    Mock::given(method("POST"))
+        .and(path("/devices/:cli"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{\"device_cli_outputs\": {\"1\": "))
+        .mount(&server)
+        .await;
+    assert!(client.send_cli_command(&[1], "show interface").await.is_err());
+}
+
+#[tokio::test]
+async fn test_device_actions() {
+    let (server, client) = logged_in().await;
+    Mock::given(method("POST"))
+        .and(path("/devices/:reboot"))
+        .and(body_json(json!({ "ids": [101, 102] })))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/devices/101/:locate"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("device offline"))
+        .mount(&server)
+        .await;
+
+    client.reboot_devices(&[101, 102]).await.unwrap();
+    let error = client.locate_device(101).await.unwrap_err().to_string();
+    assert!(error.contains("device offline"), "{}", error);
+}
@@ -0,0 +1,121 @@
+//! Property tests for the parsers and the MAC normalizer, which take
+//! untrusted output from every device: they must never panic, and every MAC
+//! they return is either canonical or caught by the MAC check
+
+use proptest::prelude::*;
+use xiq_cli_tool::mac_check::{mac_issue, MacIssue};
+use xiq_cli_tool::oui::oui;
+use xiq_cli_tool::parser::{extract_bssid_lines, extract_interfaces, normalize_mac, InterfaceParser};
+
+/// `XX:XX:XX:XX:XX:XX` with uppercase hex digits
+fn is_canonical(mac: &str) -> bool {
+    mac.len() == 17
+        && mac.split(':').all(|octet| {
+            octet.len() == 2 && octet.chars().all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c))
+        })
+}
+
+/// Canonical, or held back from the exports as malformed
+fn is_usable_or_flagged(mac: &str) -> bool {
+    is_canonical(mac) || mac_issue(mac) == Some(MacIssue::Malformed)
+}
+
+/// The same six octets as colon, dash, Cisco dotted or bare hex
+fn any_notation(octets: [u8; 6], notation: usize, upper: bool) -> String {
+    let hex: Vec<String> = octets.iter().map(|b| format!("{:02x}", b)).collect();
+    let mac = match notation {
+        0 => hex.join(":"),
+        1 => hex.join("-"),
+        2 => hex.chunks(2).map(|pair| pair.concat()).collect::<Vec<_>>().join("."),
+        _ => hex.concat(),
+    };
+    if upper {
+        mac.to_uppercase()
+    } else {
+        mac
+    }
+}
+
+/// Lines a device might print: table rows, BSSID lines, headers and noise
+fn output_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z0-9.]{1,10} +[a-fA-F0-9:.]{1,20} +[a-z]{1,8} +[a-z]{1,5} +[0-9()]{1,8} +[0-9]{1,4} +wifi[01] +[a-z0-9]{1,6} +[ -~]{1,32}",
+        "[ -~]{0,20}[Bb][Ss][Ss][Ii][Dd][ -~]{0,20}([0-9a-fA-F]{2}:){5}[0-9a-fA-F]{2}[ -~]{0,20}",
+        Just("Name     MAC addr           Mode   State  Chan(Width) VLAN  Radio Hive SSID".to_string()),
+        Just("------   ---------------    -----  -----  ----------- ----  ----- ---- ----".to_string()),
+        "[ -~]{0,120}",
+        any::<String>(),
+    ]
+}
+
+fn device_output() -> impl Strategy<Value = String> {
+    prop::collection::vec(output_line(), 0..40).prop_map(|lines| lines.join("\n"))
+}
+
+proptest! {
+    #[test]
+    fn normalize_mac_canonicalizes_twelve_hex_digits(input in any::<String>()) {
+        let normalized = normalize_mac(&input);
+        if input.chars().filter(|c| c.is_ascii_hexdigit()).count() == 12 {
+            prop_assert!(is_canonical(&normalized), "{:?} -> {:?}", input, normalized);
+        } else {
+            prop_assert_eq!(normalized, input.to_uppercase());
+        }
+    }
+
+    #[test]
+    fn normalize_mac_accepts_every_notation(octets in any::<[u8; 6]>(), notation in 0..4usize, upper in any::<bool>()) {
+        let canonical = normalize_mac(&any_notation(octets, 0, true));
+        prop_assert!(is_canonical(&canonical));
+        prop_assert_eq!(normalize_mac(&any_notation(octets, notation, upper)), canonical.clone());
+        prop_assert_eq!(normalize_mac(&canonical), canonical);
+    }
+
+    #[test]
+    fn oui_only_for_canonical_macs(input in any::<String>()) {
+        if oui(&input).is_some() {
+            prop_assert!(is_canonical(&normalize_mac(&input)), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn interface_parser_never_panics(output in device_output()) {
+        for entry in InterfaceParser::new().parse(&output) {
+            prop_assert!(is_usable_or_flagged(&entry.mac), "{:?}", entry.mac);
+            prop_assert!(!entry.ssid.is_empty());
+        }
+    }
+
+    #[test]
+    fn extract_interfaces_returns_usable_or_flagged_macs(output in device_output()) {
+        let entries = extract_interfaces(&output);
+        for entry in &entries {
+            prop_assert!(is_usable_or_flagged(&entry.mac), "{:?}", entry.mac);
+        }
+
+        // BSSID-labeled lines only yield six full octets, each once
+        let labeled = extract_bssid_lines(&output);
+        for (i, entry) in labeled.iter().enumerate() {
+            prop_assert!(is_canonical(&entry.mac), "{:?}", entry.mac);
+            prop_assert!(!labeled[..i].iter().any(|e| e.mac == entry.mac));
+            prop_assert!(entries.iter().any(|e| e.mac == entry.mac));
+        }
+    }
+
+    #[test]
+    fn interface_rows_round_trip(
+        octets in any::<[u8; 6]>(),
+        notation in prop_oneof![Just(0usize), Just(2), Just(3)],
+        ssid in "[A-Za-z0-9][A-Za-z0-9 _-]{0,30}[A-Za-z0-9]",
+        channel in 1..165u16,
+    ) {
+        let mac = any_notation(octets, notation, false);
+        let output = format!("wifi0.1  {}  access up  {}(20)  1  wifi0  hive0  {}", mac, channel, ssid);
+
+        let entries = InterfaceParser::new().parse(&output);
+        prop_assert_eq!(entries.len(), 1);
+        prop_assert_eq!(&entries[0].mac, &normalize_mac(&any_notation(octets, 0, true)));
+        prop_assert_eq!(&entries[0].ssid, &ssid);
+        prop_assert_eq!(&entries[0].channel, &format!("{}(20)", channel));
+    }
+}